@@ -0,0 +1,186 @@
+//! Timing the decoding and the scaling of a folder of images, for comparing builds.
+//!
+//! The images go through the same code as `headless`, so the numbers reflect the decoders of the
+//! viewer. Uploading the textures is not included, because that needs a window.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::headless::{decode_image, shrink_to_fit};
+use crate::image_cache::{
+	directory::list_images,
+	image_loader::{ImageLoaderError, Result},
+};
+
+#[derive(Clone, Copy)]
+pub struct BenchmarkOptions {
+	/// How many times each image is decoded
+	pub iterations: u32,
+	/// The images are scaled down to fit this size, like with `--render`
+	pub size: u32,
+	/// Print the results as JSON instead of a table
+	pub json: bool,
+}
+
+/// The timings of the images that have the same extension
+#[derive(Default)]
+struct FormatTimes {
+	decode: Vec<Duration>,
+	scale: Vec<Duration>,
+	/// The number of decoded pixels
+	pixels: u64,
+	failed: usize,
+}
+
+impl FormatTimes {
+	/// The time that the given fraction of the decodes took at most
+	fn decode_percentile(&self, fraction: f64) -> f64 {
+		match self.decode.len() {
+			0 => 0.0,
+			len => millis(self.decode[((len - 1) as f64 * fraction).round() as usize]),
+		}
+	}
+
+	fn scale_mean(&self) -> f64 {
+		match self.scale.len() {
+			0 => 0.0,
+			len => millis(self.scale.iter().sum::<Duration>()) / len as f64,
+		}
+	}
+
+	fn megapixels_per_second(&self) -> f64 {
+		let seconds = self.decode.iter().sum::<Duration>().as_secs_f64();
+		if seconds > 0.0 {
+			self.pixels as f64 / seconds / 1e6
+		} else {
+			0.0
+		}
+	}
+}
+
+fn millis(duration: Duration) -> f64 {
+	duration.as_secs_f64() * 1000.0
+}
+
+pub struct Report {
+	formats: BTreeMap<String, FormatTimes>,
+	runs: usize,
+	total: Duration,
+}
+
+impl Report {
+	fn runs_per_second(&self) -> f64 {
+		self.runs as f64 / self.total.as_secs_f64().max(f64::EPSILON)
+	}
+
+	pub fn to_table(&self) -> String {
+		let mut table = format!(
+			"{:<8} {:>6} {:>6} {:>10} {:>10} {:>10} {:>10} {:>8}\n",
+			"format", "runs", "failed", "p50 ms", "p90 ms", "p99 ms", "scale ms", "MP/s"
+		);
+		for (format, times) in &self.formats {
+			let _ = writeln!(
+				table,
+				"{:<8} {:>6} {:>6} {:>10.2} {:>10.2} {:>10.2} {:>10.2} {:>8.1}",
+				format,
+				times.decode.len() + times.failed,
+				times.failed,
+				times.decode_percentile(0.5),
+				times.decode_percentile(0.9),
+				times.decode_percentile(0.99),
+				times.scale_mean(),
+				times.megapixels_per_second(),
+			);
+		}
+		let _ = write!(
+			table,
+			"\n{} runs in {:.2} s, {:.1} images per second",
+			self.runs,
+			self.total.as_secs_f64(),
+			self.runs_per_second(),
+		);
+		table
+	}
+
+	pub fn to_json(&self) -> String {
+		let formats = self
+			.formats
+			.iter()
+			.map(|(format, times)| {
+				format!(
+					"{:?}:{{\"runs\":{},\"failed\":{},\"decode_ms\":{{\"p50\":{:.3},\"p90\":{:.3},\
+					\"p99\":{:.3}}},\"scale_ms_mean\":{:.3},\"megapixels_per_second\":{:.3}}}",
+					format,
+					times.decode.len() + times.failed,
+					times.failed,
+					times.decode_percentile(0.5),
+					times.decode_percentile(0.9),
+					times.decode_percentile(0.99),
+					times.scale_mean(),
+					times.megapixels_per_second(),
+				)
+			})
+			.collect::<Vec<_>>()
+			.join(",");
+		format!(
+			"{{\"formats\":{{{}}},\"runs\":{},\"total_seconds\":{:.3},\"images_per_second\":{:.3}}}",
+			formats,
+			self.runs,
+			self.total.as_secs_f64(),
+			self.runs_per_second(),
+		)
+	}
+}
+
+/// Decodes and scales every image of `folder` `options.iterations` times.
+///
+/// `progress` is called with the number of decodes done and the number of all decodes.
+pub fn run_benchmark(
+	folder: &Path,
+	options: BenchmarkOptions,
+	mut progress: impl FnMut(usize, usize),
+) -> Result<Report> {
+	let paths =
+		list_images(folder).map_err(|e| ImageLoaderError { description: e.to_string().into() })?;
+	if paths.is_empty() {
+		let description = format!("There are no images in {:?}", folder);
+		return Err(ImageLoaderError { description: description.into() });
+	}
+	let iterations = options.iterations.max(1) as usize;
+	let runs = paths.len() * iterations;
+	let mut formats = BTreeMap::<String, FormatTimes>::new();
+	let start = Instant::now();
+	for iteration in 0..iterations {
+		for (i, path) in paths.iter().enumerate() {
+			progress(iteration * paths.len() + i, runs);
+			let format = path
+				.extension()
+				.map_or_else(|| "none".to_string(), |ext| ext.to_string_lossy().to_lowercase());
+			let times = formats.entry(format).or_default();
+			let decode_start = Instant::now();
+			let image = match decode_image(path) {
+				Ok(image) => image,
+				Err(e) => {
+					if iteration == 0 {
+						eprintln!("\nCould not load {:?}: {}", path, e);
+					}
+					times.failed += 1;
+					continue;
+				}
+			};
+			times.decode.push(decode_start.elapsed());
+			times.pixels += image.width() as u64 * image.height() as u64;
+			let scale_start = Instant::now();
+			drop(shrink_to_fit(image, options.size, options.size));
+			times.scale.push(scale_start.elapsed());
+		}
+	}
+	let total = start.elapsed();
+	progress(runs, runs);
+	for times in formats.values_mut() {
+		times.decode.sort();
+	}
+	Ok(Report { formats, runs, total })
+}
@@ -4,17 +4,23 @@ use std::sync::{
 	Arc, Condvar, Mutex,
 };
 
-use gelatin::image::imageops::{
-	flip_horizontal_in_place, flip_vertical_in_place, rotate180_in_place, rotate270, rotate90,
-};
+use gelatin::image::RgbaImage;
 
 use crate::image_cache::image_loader::{
-	complex_load_image, ImageLoaderError, LoadResult, Orientation,
+	apply_orientation, complex_load_image, ImageLoaderError, LoadResult,
 };
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+/// What is copied to the clipboard
+enum ClipboardRequest {
+	/// The image in the file
+	File(PathBuf),
+	Image(RgbaImage),
+}
+
 enum ClipboardState {
-	Pending(PathBuf),
+	Requested(ClipboardRequest),
+	/// The request was taken by the thread
+	Copying,
 	Succeeded,
 	Failed,
 }
@@ -50,12 +56,21 @@ impl ClipboardHandler {
 	}
 
 	pub fn request_copy(&mut self, target: PathBuf) -> bool {
+		self.request(ClipboardRequest::File(target))
+	}
+
+	/// Copies an image that is already decoded, like the pixels of the view
+	pub fn request_copy_image(&mut self, image: RgbaImage) -> bool {
+		self.request(ClipboardRequest::Image(image))
+	}
+
+	fn request(&mut self, request: ClipboardRequest) -> bool {
 		{
 			let mut state = self.request_handle.state.lock().unwrap();
-			if let ClipboardState::Pending(..) = &*state {
+			if let ClipboardState::Requested(..) | ClipboardState::Copying = &*state {
 				return false;
 			} else {
-				*state = ClipboardState::Pending(target);
+				*state = ClipboardState::Requested(request);
 			}
 		}
 		// Notify the condvar after releasing the mutex
@@ -71,7 +86,7 @@ impl ClipboardHandler {
 	pub fn try_get_result(&self) -> Option<bool> {
 		let state = self.request_handle.state.lock().unwrap();
 		match &*state {
-			ClipboardState::Pending(..) => None,
+			ClipboardState::Requested(..) | ClipboardState::Copying => None,
 			ClipboardState::Succeeded => Some(true),
 			ClipboardState::Failed => Some(false),
 		}
@@ -84,12 +99,14 @@ impl ClipboardHandler {
 			eprintln!("The clipboard could not be created, error was: {}", e);
 		}
 		while request_handle.run_thread.load(Ordering::Acquire) {
-			let request_path;
+			let request;
 			{
 				let mut state_guard = request_handle.state.lock().unwrap();
 				'wait_for_request: loop {
-					if let ClipboardState::Pending(path) = state_guard.clone() {
-						request_path = path;
+					if let ClipboardState::Requested(..) = &*state_guard {
+						let state = std::mem::replace(&mut *state_guard, ClipboardState::Copying);
+						let ClipboardState::Requested(taken) = state else { unreachable!() };
+						request = taken;
 						break 'wait_for_request;
 					} else {
 						if !request_handle.run_thread.load(Ordering::Acquire) {
@@ -106,54 +123,33 @@ impl ClipboardHandler {
 					}
 				}
 			}
-			let result = complex_load_image(&request_path, false, 0, |frame| {
-				if let LoadResult::Frame { mut image, orientation, .. } = frame {
-					if let Ok(clipboard) = &mut clipboard {
-						// Note: the imageops functions use clockwise rotation whereas the
-						// `Orientation` type describes counter-clockwise rotation.
-						image = match orientation {
-							Orientation::Deg0 => image,
-							Orientation::Deg0HorFlip => {
-								flip_horizontal_in_place(&mut image);
-								image
-							}
-							Orientation::Deg90 => rotate270(&image),
-							Orientation::Deg90VerFlip => {
-								let mut result = rotate270(&image);
-								flip_vertical_in_place(&mut result);
-								result
-							}
-							Orientation::Deg180 => {
-								rotate180_in_place(&mut image);
-								image
-							}
-							Orientation::Deg180HorFlip => {
-								// This is identical to just a vertical flip with no rotation.
-								flip_vertical_in_place(&mut image);
-								image
-							}
-							Orientation::Deg270 => rotate90(&image),
-							Orientation::Deg270VerFlip => {
-								let mut result = rotate90(&image);
-								flip_vertical_in_place(&mut result);
-								result
-							}
-						};
-						let (w, h) = image.dimensions();
-						let cb_image = arboard::ImageData {
-							width: w as usize,
-							height: h as usize,
-							bytes: image.into_raw().into(),
-						};
-						if let Err(e) = clipboard.set_image(cb_image) {
-							eprintln!("Could not set the clipboard image, error was: {}", e);
-						} else {
-							return Ok(());
-						}
+			let mut set_image = |image: RgbaImage| {
+				if let Ok(clipboard) = &mut clipboard {
+					let (w, h) = image.dimensions();
+					let cb_image = arboard::ImageData {
+						width: w as usize,
+						height: h as usize,
+						bytes: image.into_raw().into(),
+					};
+					if let Err(e) = clipboard.set_image(cb_image) {
+						eprintln!("Could not set the clipboard image, error was: {}", e);
+					} else {
+						return Ok(());
 					}
 				}
 				Err(ImageLoaderError { description: "Could not set the clipboard image.".into() })
-			});
+			};
+			let result = match request {
+				ClipboardRequest::File(path) => complex_load_image(&path, 1, 0, |frame| {
+					if let LoadResult::Frame { image, orientation, .. } = frame {
+						return set_image(apply_orientation(image, orientation));
+					}
+					Err(ImageLoaderError {
+						description: "Could not set the clipboard image.".into(),
+					})
+				}),
+				ClipboardRequest::Image(image) => set_image(image),
+			};
 			let mut state = request_handle.state.lock().unwrap();
 			*state =
 				if result.is_ok() { ClipboardState::Succeeded } else { ClipboardState::Failed };
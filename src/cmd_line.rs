@@ -1,11 +1,103 @@
+use crate::benchmark::BenchmarkOptions;
+use crate::configuration::{
+	LetterboxColor, NamedLetterboxColor, OpenBehavior, SortOrder, StartupFit,
+};
+use crate::export::{ContactSheetOptions, SlideshowOptions};
 use crate::Version;
-use clap::{parser::ValueSource, value_parser, Arg, Command};
+use clap::{parser::ValueSource, value_parser, Arg, ArgGroup, Command};
 use std::path::Path;
 
 #[derive(Clone)]
 pub struct Args {
 	pub file_path: Option<String>,
 	pub displayed_folders: Option<u32>,
+	pub render: Option<RenderArgs>,
+	pub export: Option<ExportArgs>,
+	pub benchmark: Option<BenchmarkArgs>,
+	/// A pattern for the file name of the image to show first, from the folder given by `file_path`
+	pub start: Option<String>,
+	/// Overrides the `on_open_directory` and `on_open_archive` options of the config
+	pub open_behavior: Option<OpenBehavior>,
+	/// Add emulsion to the applications that can open images, or remove it, and exit
+	pub register: bool,
+	pub unregister: bool,
+	/// Turns on the `read_only` option of the config
+	pub safe: bool,
+	/// Sets the `on_open_file` option of the config to `single_image`
+	pub single: bool,
+	/// Overrides the `startup_fit` option of the config
+	pub startup_fit: Option<StartupFit>,
+	/// Overrides the `antialiasing` option of the config
+	pub antialiasing: Option<String>,
+	/// Overrides the `letterbox_color` option of the config
+	pub letterbox_color: Option<LetterboxColor>,
+	/// Overrides the `sort_order` option of the config
+	pub sort_order: Option<SortOrder>,
+}
+
+/// Arguments for rendering an image to a file without opening a window
+#[derive(Clone)]
+pub struct RenderArgs {
+	pub input: String,
+	pub output: String,
+	pub size: u32,
+}
+
+/// Arguments for exporting a folder without opening a window
+#[derive(Clone)]
+pub enum ExportArgs {
+	ContactSheet { folder: String, output: String, options: ContactSheetOptions },
+	Slideshow { folder: String, output: String, options: SlideshowOptions },
+}
+
+/// Arguments for timing the decoding of a folder without opening a window
+#[derive(Clone)]
+pub struct BenchmarkArgs {
+	pub folder: String,
+	pub options: BenchmarkOptions,
+}
+
+/// Parses `WIDTHxHEIGHT`
+fn parse_resolution(value: &str) -> Result<(u32, u32), String> {
+	let error = || format!("{:?} is not a resolution like 1280x720", value);
+	let (w, h) = value.split_once(['x', 'X']).ok_or_else(error)?;
+	let w = w.trim().parse::<u32>().map_err(|_| error())?;
+	let h = h.trim().parse::<u32>().map_err(|_| error())?;
+	if w == 0 || h == 0 {
+		return Err(error());
+	}
+	Ok((w, h))
+}
+
+/// Parses a zoom level in percent
+fn parse_zoom(value: &str) -> Result<f32, String> {
+	match value.trim().trim_end_matches('%').parse::<f32>() {
+		Ok(percent) if percent > 0.0 && percent.is_finite() => Ok(percent),
+		_ => Err(format!("{:?} is not a zoom level like 100 or 250%", value)),
+	}
+}
+
+/// Parses the name of a `letterbox_color` or a color like `#1e1e1e`
+fn parse_background(value: &str) -> Result<LetterboxColor, String> {
+	let named = match value {
+		"theme" => Some(NamedLetterboxColor::Theme),
+		"average_edge" => Some(NamedLetterboxColor::AverageEdge),
+		"blur" => Some(NamedLetterboxColor::Blur),
+		_ => None,
+	};
+	if let Some(named) = named {
+		return Ok(LetterboxColor::Named(named));
+	}
+	let error = || {
+		format!("{:?} is not \"theme\", \"average_edge\", \"blur\" or a color like #1e1e1e", value)
+	};
+	let hex = value.strip_prefix('#').filter(|hex| hex.len() == 6 && hex.is_ascii());
+	let hex = hex.ok_or_else(error)?;
+	let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+	match (channel(0), channel(2), channel(4)) {
+		(Ok(r), Ok(g), Ok(b)) => Ok(LetterboxColor::Rgb([r, g, b])),
+		_ => Err(error()),
+	}
 }
 
 /// Parses the command-line arguments and returns the file path
@@ -46,6 +138,226 @@ pub fn parse_args(config_path: &Path, cache_path: &Path) -> Args {
 				.num_args(0)
 				.conflicts_with("FOLDER_COUNT"),
 		)
+		.arg(
+			Arg::new("RENDER")
+				.long("render")
+				.help("Render the image to the file given by --out and exit without opening a window")
+				.num_args(1)
+				.requires("OUT")
+				.conflicts_with("PATH"),
+		)
+		.arg(
+			Arg::new("OUT")
+				.long("out")
+				.help("The file to write the rendered image or the export to. The format is determined by the file extension")
+				.num_args(1)
+				.requires("HEADLESS"),
+		)
+		.arg(
+			Arg::new("CONTACT_SHEET")
+				.long("contact-sheet")
+				.value_name("FOLDER")
+				.help("Place the images of the folder into a grid, write it to the file given by --out and exit")
+				.num_args(1)
+				.requires("OUT")
+				.conflicts_with("PATH"),
+		)
+		.arg(
+			Arg::new("COLUMNS")
+				.long("columns")
+				.help("The number of images in a row of the contact sheet")
+				.num_args(1)
+				.default_value("6")
+				.value_parser(value_parser!(u32).range(1..)),
+		)
+		.arg(
+			Arg::new("CELL_SIZE")
+				.long("cell-size")
+				.help("The maximum width and height of an image on the contact sheet")
+				.num_args(1)
+				.default_value("256")
+				.value_parser(value_parser!(u32).range(1..)),
+		)
+		.arg(
+			Arg::new("LABELS")
+				.long("labels")
+				.help("Write the file names below the images of the contact sheet")
+				.num_args(0)
+				.requires("CONTACT_SHEET"),
+		)
+		.arg(
+			Arg::new("SLIDESHOW_VIDEO")
+				.long("slideshow-video")
+				.value_name("FOLDER")
+				.help(
+					"Encode the images of the folder into a video with ffmpeg, write it to the file \
+					given by --out and exit. Writes a GIF if --out ends with .gif or ffmpeg is missing",
+				)
+				.num_args(1)
+				.requires("OUT")
+				.conflicts_with("PATH"),
+		)
+		.arg(
+			Arg::new("DURATION")
+				.long("duration")
+				.help("How many seconds each image of the slideshow video is shown")
+				.num_args(1)
+				.default_value("3")
+				.value_parser(value_parser!(f32))
+				.requires("SLIDESHOW_VIDEO"),
+		)
+		.arg(
+			Arg::new("RESOLUTION")
+				.long("resolution")
+				.help("The width and height of the slideshow video, like 1280x720")
+				.num_args(1)
+				.default_value("1280x720")
+				.value_parser(parse_resolution)
+				.requires("SLIDESHOW_VIDEO"),
+		)
+		.arg(
+			Arg::new("BENCHMARK")
+				.long("benchmark")
+				.value_name("FOLDER")
+				.help(
+					"Decode and scale every image of the folder a few times, print how long it 					took for each format and exit",
+				)
+				.num_args(1)
+				.conflicts_with_all(["PATH", "HEADLESS"]),
+		)
+		.arg(
+			Arg::new("ITERATIONS")
+				.long("iterations")
+				.help("How many times the benchmark decodes each image")
+				.num_args(1)
+				.default_value("3")
+				.value_parser(value_parser!(u32).range(1..))
+				.requires("BENCHMARK"),
+		)
+		.arg(
+			Arg::new("JSON")
+				.long("json")
+				.help("Print the results of the benchmark as JSON")
+				.num_args(0)
+				.requires("BENCHMARK"),
+		)
+		.group(
+			ArgGroup::new("HEADLESS")
+				.args(["RENDER", "CONTACT_SHEET", "SLIDESHOW_VIDEO"])
+				.multiple(false),
+		)
+		.arg(
+			Arg::new("SIZE")
+				.long("size")
+				.help("The maximum width and height of the rendered image, or of the scaled images of the benchmark")
+				.num_args(1)
+				.default_value("256")
+				.value_parser(value_parser!(u32).range(1..)),
+		)
+		.arg(
+			Arg::new("START")
+				.long("start")
+				.help(
+					"When PATH is a folder, start at the first image (in the order of navigation) \
+					whose name contains this text. Can also be a pattern with * and ? wildcards",
+				)
+				.num_args(1)
+				.requires("PATH"),
+		)
+		.arg(
+			Arg::new("SLIDESHOW")
+				.long("slideshow")
+				.help("When PATH is a folder or an archive, start the presentation after opening it")
+				.num_args(0)
+				.requires("PATH"),
+		)
+		.arg(
+			Arg::new("NO_SLIDESHOW")
+				.long("no-slideshow")
+				.help("When PATH is a folder or an archive, only show its first image")
+				.num_args(0)
+				.requires("PATH")
+				.conflicts_with("SLIDESHOW"),
+		)
+		.arg(
+			Arg::new("REGISTER")
+				.long("register")
+				.help("Add emulsion to the applications that the system offers for opening images, and exit")
+				.num_args(0)
+				.conflicts_with_all(["PATH", "RENDER"]),
+		)
+		.arg(
+			Arg::new("UNREGISTER")
+				.long("unregister")
+				.help("Undo --register and exit")
+				.num_args(0)
+				.conflicts_with_all(["PATH", "RENDER", "REGISTER"]),
+		)
+		.arg(
+			Arg::new("SAFE")
+				.long("safe")
+				.help("Don't delete, move or otherwise change any files, for browsing files that must stay untouched")
+				.num_args(0),
+		)
+		.arg(
+			Arg::new("SINGLE")
+				.long("single")
+				.help("Show only the image that is opened, without the other images of its folder")
+				.num_args(0),
+		)
+		.arg(
+			Arg::new("FIT")
+				.long("fit")
+				.help("Show every image this way, overriding startup_fit of the config")
+				.num_args(1)
+				.value_parser(["fit", "fit_width", "fit_height", "one_to_one"])
+				.conflicts_with_all(["HEADLESS", "BENCHMARK"]),
+		)
+		.arg(
+			Arg::new("ZOOM")
+				.long("zoom")
+				.value_name("PERCENT")
+				.help("Show every image at this zoom level, overriding startup_fit of the config")
+				.num_args(1)
+				.value_parser(parse_zoom)
+				.conflicts_with_all(["FIT", "HEADLESS", "BENCHMARK"]),
+		)
+		.arg(
+			Arg::new("SCALING")
+				.long("scaling")
+				.help(
+					"Draw the images smoothly or with sharp pixels, overriding antialiasing of \
+					the config. \"auto\" shows sharp pixels when zoomed in far enough",
+				)
+				.num_args(1)
+				.value_parser(["auto", "smooth", "nearest"])
+				.conflicts_with_all(["HEADLESS", "BENCHMARK"]),
+		)
+		.arg(
+			Arg::new("BG")
+				.long("bg")
+				.help(
+					"What is shown around the image, overriding letterbox_color of the config. \
+					Either \"theme\", \"average_edge\", \"blur\" or a color like #1e1e1e",
+				)
+				.num_args(1)
+				.value_parser(parse_background)
+				.conflicts_with_all(["HEADLESS", "BENCHMARK"]),
+		)
+		.arg(
+			Arg::new("SORT")
+				.long("sort")
+				.help("The order to navigate the images in, overriding sort_order of the config")
+				.num_args(1)
+				.value_parser([
+					"name",
+					"exif_aperture",
+					"exif_iso",
+					"exif_focal_length",
+					"exif_shutter_speed",
+				])
+				.conflicts_with_all(["HEADLESS", "BENCHMARK"]),
+		)
 		.arg(Arg::new("PATH").help("The file path of the image").index(1))
 		.get_matches();
 
@@ -60,5 +372,93 @@ pub fn parse_args(config_path: &Path, cache_path: &Path) -> Args {
 		matches.get_one::<u32>("FOLDER_COUNT").copied()
 	};
 
-	Args { file_path, displayed_folders }
+	let render = matches.get_one::<String>("RENDER").map(|input| RenderArgs {
+		input: input.clone(),
+		output: matches.get_one::<String>("OUT").unwrap().clone(),
+		size: *matches.get_one::<u32>("SIZE").unwrap(),
+	});
+
+	let output = || matches.get_one::<String>("OUT").unwrap().clone();
+	let export = if let Some(folder) = matches.get_one::<String>("CONTACT_SHEET") {
+		let options = ContactSheetOptions {
+			columns: *matches.get_one::<u32>("COLUMNS").unwrap(),
+			cell_size: *matches.get_one::<u32>("CELL_SIZE").unwrap(),
+			labels: matches.get_flag("LABELS"),
+		};
+		Some(ExportArgs::ContactSheet { folder: folder.clone(), output: output(), options })
+	} else if let Some(folder) = matches.get_one::<String>("SLIDESHOW_VIDEO") {
+		let (width, height) = *matches.get_one::<(u32, u32)>("RESOLUTION").unwrap();
+		let duration = *matches.get_one::<f32>("DURATION").unwrap();
+		let options = SlideshowOptions { duration: duration.max(0.01), width, height };
+		Some(ExportArgs::Slideshow { folder: folder.clone(), output: output(), options })
+	} else {
+		None
+	};
+
+	let benchmark = matches.get_one::<String>("BENCHMARK").map(|folder| BenchmarkArgs {
+		folder: folder.clone(),
+		options: BenchmarkOptions {
+			iterations: *matches.get_one::<u32>("ITERATIONS").unwrap(),
+			size: *matches.get_one::<u32>("SIZE").unwrap(),
+			json: matches.get_flag("JSON"),
+		},
+	});
+
+	let start = matches.get_one::<String>("START").cloned();
+
+	let open_behavior = if matches.get_flag("SLIDESHOW") {
+		Some(OpenBehavior::Slideshow)
+	} else if matches.get_flag("NO_SLIDESHOW") {
+		Some(OpenBehavior::FirstImage)
+	} else {
+		None
+	};
+
+	let register = matches.get_flag("REGISTER");
+	let unregister = matches.get_flag("UNREGISTER");
+	let safe = matches.get_flag("SAFE");
+	let single = matches.get_flag("SINGLE");
+
+	let fit = matches.get_one::<String>("FIT").map(|fit| match fit.as_str() {
+		"fit" => StartupFit::Fit,
+		"fit_width" => StartupFit::FitWidth,
+		"fit_height" => StartupFit::FitHeight,
+		_ => StartupFit::OneToOne,
+	});
+	let zoom = matches.get_one::<f32>("ZOOM").map(|percent| StartupFit::Percent(*percent));
+	let startup_fit = fit.or(zoom);
+	let antialiasing = matches.get_one::<String>("SCALING").map(|scaling| {
+		let antialiasing = match scaling.as_str() {
+			"smooth" => "always",
+			"nearest" => "never",
+			_ => "auto",
+		};
+		antialiasing.to_string()
+	});
+	let letterbox_color = matches.get_one::<LetterboxColor>("BG").copied();
+	let sort_order = matches.get_one::<String>("SORT").map(|order| match order.as_str() {
+		"exif_aperture" => SortOrder::ExifAperture,
+		"exif_iso" => SortOrder::ExifIso,
+		"exif_focal_length" => SortOrder::ExifFocalLength,
+		"exif_shutter_speed" => SortOrder::ExifShutterSpeed,
+		_ => SortOrder::Name,
+	});
+
+	Args {
+		file_path,
+		displayed_folders,
+		render,
+		export,
+		benchmark,
+		start,
+		open_behavior,
+		register,
+		unregister,
+		safe,
+		single,
+		startup_fit,
+		antialiasing,
+		letterbox_color,
+		sort_order,
+	}
 }
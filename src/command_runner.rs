@@ -0,0 +1,123 @@
+//! Runs the custom commands from the configuration on background threads.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use crate::configuration::Command as ConfigCommand;
+
+/// Reported when a command exits
+pub struct CommandOutcome {
+	pub name: String,
+	/// The image that the command was run on
+	pub img_path: PathBuf,
+	pub reload: bool,
+	/// A description of the error if the command couldn't be started or it failed
+	pub error: Option<String>,
+}
+
+pub struct CommandRunner {
+	sender: Sender<CommandOutcome>,
+	receiver: Receiver<CommandOutcome>,
+	running_count: usize,
+}
+
+/// Replaces the variables of `var_map` in `string` from left to right. The substituted text
+/// isn't searched for variables again, so a file named `${img}` is passed as is.
+fn substitute_command_parameters(string: &str, var_map: &HashMap<&str, &str>) -> String {
+	let mut result = String::with_capacity(string.len());
+	let mut rest = string;
+	while let Some(start) = rest.find("${") {
+		result.push_str(&rest[..start]);
+		rest = &rest[start..];
+		let var = rest.find('}').and_then(|end| var_map.get_key_value(&rest[..=end]));
+		match var {
+			Some((var_name, substitute)) => {
+				result.push_str(substitute);
+				rest = &rest[var_name.len()..];
+			}
+			None => {
+				result.push_str("${");
+				rest = &rest[2..];
+			}
+		}
+	}
+	result.push_str(rest);
+	result
+}
+
+impl CommandRunner {
+	pub fn new() -> CommandRunner {
+		let (sender, receiver) = channel();
+		CommandRunner { sender, receiver, running_count: 0 }
+	}
+
+	/// Starts the command on `img_path`. The paths have to be valid UTF-8 to be substituted
+	/// into the arguments, so they are taken as `str`.
+	pub fn run(&mut self, command: &ConfigCommand, img_path: &str, folder_path: &str) {
+		let file_name = Path::new(img_path).file_name().and_then(|n| n.to_str()).unwrap_or("");
+		let mut var_map = HashMap::with_capacity(3);
+		var_map.insert("${img}", img_path);
+		var_map.insert("${folder}", folder_path);
+		var_map.insert("${name}", file_name);
+
+		let mut cmd = Command::new(&command.program);
+		if let Some(ref args) = command.args {
+			cmd.args(args.iter().map(|arg| substitute_command_parameters(arg, &var_map)));
+		}
+		if let Some(ref envs) = command.envs {
+			cmd.envs(envs.iter().map(|env_var| (env_var.name.as_str(), env_var.value.as_str())));
+		}
+
+		let mut outcome = CommandOutcome {
+			name: command.display_name().to_owned(),
+			img_path: PathBuf::from(img_path),
+			reload: command.reload.unwrap_or(false),
+			error: None,
+		};
+		let sender = self.sender.clone();
+		let command = command.clone();
+		self.running_count += 1;
+		std::thread::spawn(move || {
+			outcome.error = match cmd.status() {
+				Ok(status) if status.success() => None,
+				Ok(status) => Some(format!("it exited with {}", status)),
+				Err(e) => {
+					eprintln!("Error while executing the following user command. See the error below.\n{:?}\nError: {:?}", command, e);
+					Some(format!("it could not be started: {}", e))
+				}
+			};
+			let _ = sender.send(outcome);
+		});
+	}
+
+	/// Returns the outcome of a command that exited since the last call
+	pub fn try_get_outcome(&mut self) -> Option<CommandOutcome> {
+		let outcome = self.receiver.try_recv().ok()?;
+		self.running_count -= 1;
+		Some(outcome)
+	}
+
+	pub fn running(&self) -> bool {
+		self.running_count > 0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn substituted_paths_are_not_substituted_again() {
+		let mut var_map = HashMap::new();
+		var_map.insert("${img}", "/photos/${folder} ${name}.jpg");
+		var_map.insert("${folder}", "/photos");
+		var_map.insert("${name}", "${folder} ${name}.jpg");
+
+		let substituted = substitute_command_parameters("${name}|${img}|${folder}", &var_map);
+		assert_eq!(substituted, "${folder} ${name}.jpg|/photos/${folder} ${name}.jpg|/photos");
+		let substituted = substitute_command_parameters("${${img}} ${unknown} ${", &var_map);
+		assert_eq!(substituted, "${/photos/${folder} ${name}.jpg} ${unknown} ${");
+	}
+}
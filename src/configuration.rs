@@ -10,11 +10,13 @@
 use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
 
+use crate::image_cache::archive::is_archive;
+
 #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum Theme {
@@ -45,9 +47,342 @@ pub struct CacheImageSection {
 	pub antialiasing: Antialias,
 }
 
-#[derive(Debug, Default, Eq, PartialEq, Clone, Deserialize)]
+/// How the image is scaled when it's first shown from a folder with saved view settings.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FolderScaling {
+	/// Show at the original size. Zoomed in or out views are also saved as this.
+	Orig,
+	Fit,
+	FitBest,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct FolderViewSettings {
+	pub scaling: FolderScaling,
+	pub antialiasing: Antialias,
+	/// `None` for the folders that were saved before the sort order was remembered
+	#[serde(default)]
+	pub sort_order: Option<SortOrder>,
+	/// Applied to each image of the folder, like `startup_fit`
+	#[serde(default)]
+	pub fit: Option<StartupFit>,
+	/// Seconds since the unix epoch, used to decide which entries to forget
+	/// once there are too many of them.
+	pub last_used: u64,
+}
+
+/// View settings that the user explicitly saved for individual folders.
+/// These override the global defaults while an image from the folder is shown.
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct CacheFoldersSection {
+	pub settings: BTreeMap<String, FolderViewSettings>,
+}
+
+impl CacheFoldersSection {
+	pub const MAX_REMEMBERED_FOLDERS: usize = 256;
+
+	pub fn get(&mut self, folder: &Path) -> Option<&FolderViewSettings> {
+		let settings = self.settings.get_mut(folder.to_str()?)?;
+		settings.last_used = secs_since_epoch();
+		Some(settings)
+	}
+
+	/// Saves `settings` for `folder`, their `last_used` is set to now
+	pub fn insert(&mut self, folder: &Path, mut settings: FolderViewSettings) {
+		let Some(key) = folder.to_str() else {
+			log::error!("Could not convert the folder path to utf8. Path: '{:?}'", folder);
+			return;
+		};
+		settings.last_used = secs_since_epoch();
+		self.settings.insert(key.to_owned(), settings);
+		self.prune();
+	}
+
+	pub fn remove(&mut self, folder: &Path) {
+		if let Some(key) = folder.to_str() {
+			self.settings.remove(key);
+		}
+	}
+
+	/// The folders that have saved settings
+	pub fn folders(&self) -> Vec<PathBuf> {
+		self.settings.keys().map(PathBuf::from).collect()
+	}
+
+	/// Forgets the least recently used folders if there are more than `MAX_REMEMBERED_FOLDERS`.
+	/// The folders that were deleted are forgotten by `forget_deleted_folders`, because looking
+	/// them up can stall on network drives.
+	pub fn prune(&mut self) {
+		while self.settings.len() > Self::MAX_REMEMBERED_FOLDERS {
+			let oldest = self.settings.iter().min_by_key(|(_, s)| s.last_used);
+			let oldest = oldest.map(|(k, _)| k.clone()).unwrap();
+			self.settings.remove(&oldest);
+		}
+	}
+}
+
+fn secs_since_epoch() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_else(|_| Duration::from_secs(0))
+		.as_secs()
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Deserialize)]
 pub struct ConfigImageSection {
+	/// Also set by `--scaling`
 	pub antialiasing: Option<String>,
+	/// The maximal speed of panning with the keyboard in logical pixels per second
+	pub pan_key_speed: Option<f32>,
+	/// How quickly panning with the keyboard reaches its maximal speed
+	/// (and how quickly it stops) in logical pixels per second squared
+	pub pan_key_accel: Option<f32>,
+	/// How many seconds the zoom keys have to be held to zoom at `zoom_key_max_rate`. Zooming
+	/// starts slowly and speeds up until then.
+	pub zoom_key_accel: Option<f32>,
+	/// The fastest zoom of the zoom keys, in how many times the size doubles per second
+	pub zoom_key_max_rate: Option<f32>,
+	/// The view to start from whenever a new image is shown. When this is not set, the
+	/// scaling of the previous image is kept. Also set by `--fit` and `--zoom`.
+	pub startup_fit: Option<StartupFit>,
+	/// Show images that are smaller than the window at their original size when fitting them
+	/// with `startup_fit`
+	pub no_upscale_on_fit: Option<bool>,
+	/// How images that are at most `small_image_threshold` pixels wide and high are fit. This
+	/// applies instead of `no_upscale_on_fit` and whether fitting stretches images.
+	pub small_image_policy: Option<SmallImagePolicy>,
+	pub small_image_threshold: Option<u32>,
+	/// Snap the zoom to the fitting size and to `zoom_snap_targets` when it gets close to them
+	pub zoom_snap: Option<bool>,
+	/// How close the zoom has to get to a snap target, in percent of the target
+	pub zoom_snap_tolerance: Option<f32>,
+	/// Zoom levels in percent of the original size
+	pub zoom_snap_targets: Option<Vec<f32>>,
+	/// What is shown around the image where it doesn't cover the window. Also set by `--bg`.
+	pub letterbox_color: Option<LetterboxColor>,
+	/// The margin that is kept around fitted images. It doesn't affect manual zoom.
+	pub fit_padding: Option<FitPadding>,
+	/// Fit fitted images again on every frame while the window is resized, so that they follow
+	/// the window when it animates. When this is off, they keep their size until the window
+	/// stops changing. True by default.
+	pub continuous_fit: Option<bool>,
+	/// How many degrees the `rotate_step_cw` and `rotate_step_ccw` actions rotate the view by
+	pub rotation_step: Option<f32>,
+	/// What happens to the corners when the view is rotated by an angle that isn't a multiple
+	/// of 90 degrees
+	pub rotation_fill: Option<RotationFill>,
+	/// The composition guides that are shown over the image at startup. The
+	/// `composition_overlay` action cycles through them.
+	pub composition_overlay: Option<CompositionOverlay>,
+	/// `[r, g, b]`
+	pub overlay_color: Option<[u8; 3]>,
+	/// Between 0 and 1
+	pub overlay_opacity: Option<f32>,
+	/// `[r, g, b]`, the color of the edges that focus peaking highlights
+	pub focus_peaking_color: Option<[u8; 3]>,
+	/// Between 0 and 1. Higher values highlight edges with less contrast.
+	pub focus_peaking_sensitivity: Option<f32>,
+	/// The opacity of the current image over the reference image, between 0 and 1
+	pub reference_opacity: Option<f32>,
+	/// The clipping warning shows the pixels whose channels are all at least `clip_highlight`
+	/// in red, and the pixels whose channels are all at most `clip_shadow` in blue. Both are
+	/// between 0 and 1, where 1 is the largest value of the file.
+	pub clip_highlight: Option<f32>,
+	pub clip_shadow: Option<f32>,
+	/// The zoom inside the magnifier in percent of the original size
+	pub magnifier_zoom: Option<f32>,
+	/// The width and height of the magnifier in logical pixels
+	pub magnifier_size: Option<f32>,
+	pub magnifier_shape: Option<MagnifierShape>,
+	/// Whether the `magnifier` key turns the magnifier on and off, instead of showing it while
+	/// the key is held
+	pub magnifier_toggle: Option<bool>,
+	/// Shorter frames of animations are shown for this many milliseconds. The default is 20.
+	pub min_frame_delay_ms: Option<u64>,
+	/// Longer frames of animations are cut to this many milliseconds
+	pub max_frame_delay_ms: Option<u64>,
+	/// Animations continue from the frame they were left at when they are shown again in the
+	/// same session
+	pub remember_animation_position: Option<bool>,
+	/// What the `copy_view` action copies to the clipboard
+	pub copy_view_area: Option<CopyViewArea>,
+	/// A text that is drawn over the image, like a watermark. `{name}` is replaced by the file
+	/// name and `{date}` by the capture date.
+	pub overlay_text: Option<String>,
+	pub overlay_text_position: Option<OverlayPosition>,
+	/// The font size of `overlay_text` in logical pixels
+	pub overlay_text_size: Option<f32>,
+	/// Between 0 and 1
+	pub overlay_text_opacity: Option<f32>,
+	/// Draw `overlay_text` onto the images of `copy_view` and of the exports as well
+	pub watermark_on_export: Option<bool>,
+	/// Show the pixel coordinates of the cursor in the status bar at startup. The
+	/// `toggle_coordinates` action turns them on and off.
+	pub show_coordinates: Option<bool>,
+	/// Show a scale bar below the image at startup, see `toggle_scale_bar`
+	pub scale_bar: Option<bool>,
+	/// The unit of the scale bar and of the `measure` tool. Images that don't name their
+	/// resolution are measured in pixels.
+	pub measure_unit: Option<MeasureUnit>,
+}
+
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MeasureUnit {
+	#[default]
+	Pixels,
+	Micrometers,
+	Millimeters,
+	Centimeters,
+	Inches,
+}
+
+impl MeasureUnit {
+	/// How many of the unit make up a millimeter, `None` for pixels
+	pub fn per_mm(self) -> Option<f64> {
+		match self {
+			MeasureUnit::Pixels => None,
+			MeasureUnit::Micrometers => Some(1000.0),
+			MeasureUnit::Millimeters => Some(1.0),
+			MeasureUnit::Centimeters => Some(0.1),
+			MeasureUnit::Inches => Some(1.0 / 25.4),
+		}
+	}
+
+	pub fn symbol(self) -> &'static str {
+		match self {
+			MeasureUnit::Pixels => "px",
+			MeasureUnit::Micrometers => "µm",
+			MeasureUnit::Millimeters => "mm",
+			MeasureUnit::Centimeters => "cm",
+			MeasureUnit::Inches => "in",
+		}
+	}
+}
+
+/// Where `overlay_text` is drawn on the image
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlayPosition {
+	TopLeft,
+	TopRight,
+	BottomLeft,
+	#[default]
+	BottomRight,
+	Center,
+}
+
+/// The area that `copy_view` copies, with the zoom, rotation and adjustments as shown
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CopyViewArea {
+	/// The whole area of the window that shows the image, as it's shown
+	#[default]
+	View,
+	/// The part of the view that the image covers
+	Image,
+}
+
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MagnifierShape {
+	#[default]
+	Circle,
+	Square,
+}
+
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompositionOverlay {
+	#[default]
+	None,
+	/// The rule of thirds
+	Thirds,
+	/// Lines that divide the image in the golden ratio
+	GoldenRatio,
+	/// The diagonals of the image
+	Diagonals,
+	/// A grid with this many cells in each direction
+	Grid(u32),
+}
+
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RotationFill {
+	/// Enlarge the image so that it covers its unrotated frame, and cut off what's outside
+	#[default]
+	Crop,
+	/// Show the whole image, with the letterbox color in the empty corners
+	Expand,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FitPadding {
+	/// In logical pixels on each side
+	Pixels(f32),
+	/// In percent of the width and the height of the window on each side
+	Percent(f32),
+}
+
+impl FitPadding {
+	pub fn is_valid(self) -> bool {
+		match self {
+			FitPadding::Pixels(value) | FitPadding::Percent(value) => value >= 0.0,
+		}
+	}
+}
+
+/// `[r, g, b]` or one of the named options
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum LetterboxColor {
+	Rgb([u8; 3]),
+	Named(NamedLetterboxColor),
+}
+
+impl Default for LetterboxColor {
+	fn default() -> Self {
+		LetterboxColor::Named(NamedLetterboxColor::Theme)
+	}
+}
+
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NamedLetterboxColor {
+	/// The background color of the theme
+	#[default]
+	Theme,
+	/// The average color along the edges of the image
+	AverageEdge,
+	/// A blurred copy of the image, stretched to cover the window
+	Blur,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupFit {
+	/// Fit the whole image into the window
+	Fit,
+	/// Fit the width of the image to the window, showing the top of the image
+	FitWidth,
+	/// Fit the height of the image to the window, showing the left side of the image
+	FitHeight,
+	OneToOne,
+	/// Zoom to this percentage of the original size
+	Percent(f32),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmallImagePolicy {
+	/// Show the image at its original size
+	ShowActual,
+	/// Fit the image into the window, but enlarge it at most this many times
+	FitWithLimit(f32),
+	/// Fit the image into the window, drawing the pixels as sharp squares
+	FitNearest,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
@@ -58,24 +393,466 @@ pub struct CacheWindowSection {
 	pub win_x: i32,
 	pub win_y: i32,
 	pub maximized: bool,
+	#[serde(default = "default_true")]
+	pub toolbar_visible: bool,
+	/// Whether the main window was fullscreen when the program exited, see `restore_session`
+	#[serde(default)]
+	pub fullscreen: bool,
+	/// The monitor that the main window was fullscreen on
+	#[serde(default)]
+	pub fullscreen_monitor: Option<String>,
+	#[serde(default)]
+	pub two_page: bool,
 }
 impl Default for CacheWindowSection {
 	fn default() -> Self {
-		Self { dark: false, win_w: 580, win_h: 558, win_x: 64, win_y: 64, maximized: false }
+		Self {
+			dark: false,
+			win_w: 580,
+			win_h: 558,
+			win_x: 64,
+			win_y: 64,
+			maximized: false,
+			toolbar_visible: true,
+			fullscreen: false,
+			fullscreen_monitor: None,
+			two_page: false,
+		}
 	}
 }
 
+fn default_true() -> bool {
+	true
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct ConfigWindowSection {
 	pub start_fullscreen: Option<bool>,
+	/// Starts in fullscreen and in the two page mode if the last session exited that way, unless
+	/// `start_fullscreen` or `two_page` is set. If the monitor that was fullscreen is gone, the
+	/// window is opened instead. Off by default.
+	pub restore_session: Option<bool>,
 	pub start_maximized: Option<bool>,
 	pub show_bottom_bar: Option<bool>,
+	pub show_status_bar: Option<bool>,
+	/// See `StatusBar` for the placeholders
+	pub status_format: Option<String>,
 	pub theme: Option<Theme>,
 	pub use_last_window_area: Option<bool>,
 	pub win_w: Option<u32>,
 	pub win_h: Option<u32>,
 	pub win_x: Option<i32>,
 	pub win_y: Option<i32>,
+	/// Antialiases the edges of the toolbar and the other UI elements. The image is drawn without
+	/// it.
+	pub msaa_samples: Option<u8>,
+	/// "auto", "hardware" or "software"
+	pub renderer: Option<String>,
+	/// "auto", "on" or "off"
+	pub hdr: Option<String>,
+	pub single_instance: Option<bool>,
+	/// Whether to add emulsion to the applications that the system offers for opening images on
+	/// the first launch. The default is false, `--register` does it any time.
+	pub register_file_types: Option<bool>,
+	/// How much the window is darkened while it isn't focused, between 0 and 1. The default is
+	/// 0, which doesn't darken it.
+	pub dim_on_unfocus: Option<f32>,
+	pub fullscreen_toolbar: Option<FullscreenToolbar>,
+	/// The distance from the bottom edge in logical pixels, within which the cursor reveals the
+	/// toolbar when `fullscreen_toolbar` is `edge_reveal`
+	pub reveal_margin: Option<f32>,
+	/// The width and height of the toolbar buttons in logical pixels
+	pub toolbar_icon_size: Option<f32>,
+	/// The height of the toolbar in logical pixels
+	pub toolbar_height: Option<f32>,
+	/// The font size of the status bar in logical pixels
+	pub panel_font_size: Option<f32>,
+	/// Tell how to open an image and show a few keys while no image is open. True by default.
+	pub show_empty_state: Option<bool>,
+	/// Show a slider to seek through the frames of animations, see `toggle_frame_scrubber`. True
+	/// by default.
+	pub show_frame_scrubber: Option<bool>,
+	/// How long the cursor has to stay on an image on the slider of the toolbar for its preview
+	/// to show up
+	pub hover_preview_delay_ms: Option<u64>,
+	/// The longer side of the preview in logical pixels, 0 turns the preview off
+	pub hover_preview_size: Option<u32>,
+	/// How many images one notch of the mouse wheel over the slider of the toolbar moves, 1 by
+	/// default
+	pub slider_scroll_speed: Option<f32>,
+	/// How long the slider of the toolbar keeps gliding after the wheel or a fling in seconds, 0
+	/// stops it right away. 0.15 by default.
+	pub scroll_inertia: Option<f32>,
+	pub close_button_action: Option<CloseButtonAction>,
+	/// How long quitting waits at most for the update check and the cache on disk before the
+	/// state is saved, 2000 by default
+	pub shutdown_timeout_ms: Option<u64>,
+	pub confirm_quit: Option<ConfirmQuit>,
+	/// Replaces the scale factor that the window system reports, which scales both the
+	/// widgets and the image
+	pub ui_scale_override: Option<UiScale>,
+	/// The monitor that `toggle_mirror_display` shows the image on, counting from 0 in the order
+	/// that the system lists them. By default it's the first monitor that the window isn't on.
+	pub mirror_monitor: Option<usize>,
+	pub mirror_view: Option<MirrorView>,
+	/// Linked windows also share the zoom and the position of the image, not just the steps to
+	/// the next and previous images. Off by default.
+	pub link_view: Option<bool>,
+}
+
+/// What the window of `toggle_mirror_display` copies from the main window
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MirrorView {
+	/// The image, its rotation and whether it's fit to the window. The zoom and the panning
+	/// aren't copied, a zoomed image is shown fit to the mirror.
+	#[default]
+	Fit,
+	/// The zoom and the panning as well
+	Same,
+}
+
+/// When quitting has to be confirmed by quitting a second time
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfirmQuit {
+	#[default]
+	Never,
+	/// Only when there are changes that would be lost. Every file operation is carried out right
+	/// away, so for now there never are.
+	IfUnsaved,
+	Always,
+}
+
+/// `"auto"` or the number of physical pixels per logical pixel
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum UiScale {
+	Factor(f32),
+	Named(NamedUiScale),
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NamedUiScale {
+	/// Use the scale factor of the window system
+	Auto,
+}
+
+/// What happens when the window is closed
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CloseButtonAction {
+	#[default]
+	Quit,
+	Minimize,
+	/// Hides the window but keeps emulsion running, so that it can be shown again by opening an
+	/// image. This requires `single_instance`.
+	Hide,
+}
+
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnimatedThumbnail {
+	#[default]
+	FirstFrame,
+	/// The frame with the most detail among the first few, in case the animation starts with
+	/// an empty frame
+	RepresentativeFrame,
+	/// Like `representative_frame`, but the preview of the hovered image on the slider plays
+	/// the animation
+	AnimatePlaying,
+}
+
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsupportedFileAction {
+	/// Show an error that names the format of the file
+	#[default]
+	ShowError,
+	/// Show the image that comes after the file in its folder instead
+	SkipInList,
+	/// Open the file with the default program of the system
+	OpenExternally,
+}
+
+/// How the toolbar is shown while the window is fullscreen
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FullscreenToolbar {
+	/// Only shown when it's toggled with the `toggle_toolbar` action
+	#[default]
+	AlwaysHidden,
+	/// Shown while the cursor is near the bottom edge of the window
+	EdgeReveal,
+	AlwaysShown,
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Deserialize)]
+pub struct ConfigMouseSection {
+	/// The name of the action to carry out when the image is double-clicked
+	pub double_click_action: Option<String>,
+	/// The name of the action to carry out when the image is clicked with the middle button
+	pub middle_click_action: Option<String>,
+	/// The maximal time between two clicks for them to count as a double-click
+	pub double_click_ms: Option<u64>,
+	/// The mouse button that pans the image when dragged
+	pub pan_button: Option<PanButton>,
+	/// Clicking or tapping a part of the image area carries out the action of that part. This
+	/// replaces the double-click action.
+	pub tap_zones: Option<bool>,
+	/// The parts of the image area for `tap_zones`. The first one that contains the click is
+	/// used. When this is not set, the left and right thirds go to the previous and next image,
+	/// and the middle third toggles the toolbar.
+	pub tap_zone: Option<Vec<TapZone>>,
+	/// Changes the cursor with the mode, like the closed hand while panning and the crosshair
+	/// while straightening. True by default.
+	pub mode_cursors: Option<bool>,
+	/// Pans a zoomed in image when the cursor is near the edge of the picture area, faster the
+	/// closer it is. Off by default.
+	pub edge_autopan: Option<bool>,
+	/// How close to the edge the cursor has to be for `edge_autopan`, in logical pixels. The
+	/// default is 40.
+	pub edge_margin: Option<f32>,
+	/// The wheel scrolls an image that is taller than the picture area instead of zooming it,
+	/// and scrolling on past its top or bottom edge goes to the previous or next image. The
+	/// wheel still zooms while Ctrl is held. Off by default.
+	pub edge_scroll_navigate: Option<bool>,
+	/// An image file for the cursor that is shown over the magnifier. The center of the image is
+	/// the point of the cursor.
+	pub loupe_cursor: Option<String>,
+	/// The modifier that slows down zooming with the wheel and panning by dragging while it's
+	/// held, for fine adjustments. Alt by default.
+	pub precision_modifier: Option<PrecisionModifier>,
+	/// The fraction of the usual zoom step while `precision_modifier` is held. The default is 0.2.
+	pub precision_zoom_factor: Option<f32>,
+	/// The fraction of the cursor movement that a dragged image follows while
+	/// `precision_modifier` is held. The default is 0.25.
+	pub precision_pan_factor: Option<f32>,
+}
+
+/// A rectangle of the image area with the action of a click in it. The edges are fractions of
+/// the width and height of the image area, from 0 to 1.
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+pub struct TapZone {
+	pub left: f32,
+	pub top: f32,
+	pub right: f32,
+	pub bottom: f32,
+	pub action: String,
+}
+
+impl TapZone {
+	pub fn new(left: f32, top: f32, right: f32, bottom: f32, action: &str) -> TapZone {
+		TapZone { left, top, right, bottom, action: action.to_owned() }
+	}
+
+	/// `x` and `y` are fractions of the width and height of the image area
+	pub fn contains(&self, x: f32, y: f32) -> bool {
+		x >= self.left && x < self.right && y >= self.top && y < self.bottom
+	}
+}
+
+/// See `precision_modifier`
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrecisionModifier {
+	#[default]
+	Alt,
+	Ctrl,
+	Shift,
+	/// The Windows key, or Command on macOS
+	Logo,
+	/// Zooming and panning always go at the usual speed
+	None,
+}
+
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PanButton {
+	#[default]
+	Left,
+	Middle,
+	Right,
+}
+
+/// What happens when trying to step past the first or the last image of the folder
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndBehavior {
+	/// Continue from the other end of the folder
+	#[default]
+	Wrap,
+	/// Stay on the current image
+	Stop,
+	/// Turn back and continue in the opposite direction
+	Bounce,
+}
+
+/// What happens when the shown file is deleted by another program
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CurrentDeleted {
+	/// Keep showing the image with a note that the file no longer exists
+	#[default]
+	ShowMissing,
+	/// Show the next image of the folder
+	AdvanceNext,
+	/// Keep showing the image as if nothing happened
+	KeepStale,
+}
+
+/// An action that can be followed by showing another image, see `auto_advance_on`
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoAdvanceTrigger {
+	/// Moving the image to the trash
+	Delete,
+	/// Copying the image to the clipboard
+	Copy,
+	/// A custom command that exits successfully, like one that moves the image into a folder
+	Command,
+}
+
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoAdvanceDirection {
+	#[default]
+	Next,
+	Prev,
+}
+
+/// The order in which the images of a folder are navigated
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+	/// By the file name, with the numbers in the names compared by their value
+	#[default]
+	Name,
+	/// By the EXIF shooting parameters, from the smallest to the largest. The images without
+	/// the value come last, in the order of their names.
+	ExifAperture,
+	ExifIso,
+	ExifFocalLength,
+	ExifShutterSpeed,
+}
+
+/// The order of the pages in two page mode
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadingDirection {
+	/// The current image is on the left, and the next image is on its right
+	#[default]
+	LeftToRight,
+	/// The current image is on the right, and the next image is on its left. The next and the
+	/// previous image actions are swapped, so that navigation follows the pages.
+	RightToLeft,
+}
+
+/// What happens after opening a folder or an archive
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpenBehavior {
+	/// Show the first image
+	#[default]
+	FirstImage,
+	/// Show the first image and start the presentation
+	Slideshow,
+}
+
+/// What happens after opening a single image file
+#[derive(Default, Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpenFile {
+	/// Navigate the other images of its folder too
+	#[default]
+	BrowseFolder,
+	/// Show only that image, with nothing before or after it
+	SingleImage,
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Deserialize)]
+pub struct ConfigNavigationSection {
+	pub end_behavior: Option<EndBehavior>,
+	/// When stepping through the folder faster than this many images per second, only the file
+	/// names are shown, and the image that the stepping stops at is loaded
+	pub nav_max_rate: Option<f32>,
+	pub on_open_directory: Option<OpenBehavior>,
+	pub on_open_archive: Option<OpenBehavior>,
+	/// Also set to `single_image` by `--single`
+	pub on_open_file: Option<OpenFile>,
+	/// Show the current and the next image side-by-side, like the pages of a book
+	pub two_page: Option<bool>,
+	pub reading_direction: Option<ReadingDirection>,
+	/// Show the first image alone in two page mode, because it is usually the cover
+	pub cover_is_single: Option<bool>,
+	/// Look for similar images in each folder in the background, and show how many there are
+	pub find_duplicates: Option<bool>,
+	/// The number of images after the current one that are loaded ahead of time. When this is
+	/// not set, images are loaded until a part of the memory is used up.
+	pub preload_count: Option<usize>,
+	/// Generate the thumbnails of every image in each folder in the background
+	pub eager_thumbnail: Option<bool>,
+	/// Which frame of an animation its thumbnails and previews show
+	pub animated_thumbnail: Option<AnimatedThumbnail>,
+	/// The number of threads that generate thumbnails. When this is not set, half of
+	/// `decode_threads` is used.
+	pub thumbnail_threads: Option<u32>,
+	/// The number of threads that load images in the background. When this is not set, half of
+	/// the processor cores are used.
+	pub decode_threads: Option<u32>,
+	/// The size limit in megabytes of a cache on disk for the images that are slow to decode.
+	/// The cache is turned off when this is not set or zero.
+	pub disk_cache_size_mb: Option<u64>,
+	pub on_current_deleted: Option<CurrentDeleted>,
+	/// Also set by `--sort`
+	pub sort_order: Option<SortOrder>,
+	/// What to do when a file that is not an image is opened
+	pub unsupported_file_action: Option<UnsupportedFileAction>,
+	/// The actions after which the next image is shown, for culling. Empty by default.
+	pub auto_advance_on: Option<Vec<AutoAdvanceTrigger>>,
+	/// Which way `auto_advance_on` goes
+	pub auto_advance_direction: Option<AutoAdvanceDirection>,
+	/// The glob patterns of the files that are left out of the navigation, like `*.thumb.jpg`.
+	/// They are matched against the file names, or against the path within the folder or the
+	/// archive when they contain a `/`. The file that is opened is never left out.
+	pub navigation_ignore: Option<Vec<String>>,
+	/// Navigate the files whose names start with a dot. True by default.
+	pub show_hidden_files: Option<bool>,
+	/// Refuse every action that changes the files, like deleting, moving into the triage
+	/// folders, undoing those and running the custom commands. Also set by `--safe`.
+	pub read_only: Option<bool>,
+	/// Keep fewer images in memory, don't load any ahead of time, and shrink large images to the
+	/// size of the monitor until they are zoomed into. When this is not set, it's turned on if
+	/// the system has less than 4 GB of memory.
+	pub low_memory: Option<bool>,
+	/// Shrink the images that have more pixels than this to that many when they are decoded,
+	/// until they are zoomed into. This limits the memory of a single image, in any mode.
+	pub max_decode_megapixels: Option<f32>,
+	/// Show the preview that photos and raw files have embedded right away, until the image
+	/// is decoded
+	pub embedded_preview: Option<bool>,
+	/// The folders that `triage_a` and `triage_b` move the shown image into. Relative paths are
+	/// relative to the folder of the image.
+	pub triage_folder_a: Option<String>,
+	pub triage_folder_b: Option<String>,
+	/// What happens after `idle_timeout_seconds` without any input, for unattended displays
+	pub idle_action: Option<IdleAction>,
+	pub idle_timeout_seconds: Option<f32>,
+}
+
+/// See `idle_action`
+#[derive(Default, Debug, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdleAction {
+	#[default]
+	None,
+	/// Start the presentation over from the first image of the folder
+	RestartSlideshow,
+	/// Stop the presentation and show this image
+	GotoImage(PathBuf),
+	/// Exit without asking for confirmation
+	Quit,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, Deserialize)]
@@ -103,10 +880,7 @@ impl CacheUpdateSection {
 	}
 
 	pub fn set_update_check_time(&mut self) {
-		self.last_checked = SystemTime::now()
-			.duration_since(UNIX_EPOCH)
-			.unwrap_or_else(|_| Duration::from_secs(0))
-			.as_secs();
+		self.last_checked = secs_since_epoch();
 	}
 }
 
@@ -115,13 +889,15 @@ struct IncompleteCache {
 	pub window: Option<CacheWindowSection>,
 	pub updates: Option<CacheUpdateSection>,
 	pub image: Option<CacheImageSection>,
+	pub folders: Option<CacheFoldersSection>,
 }
 
-#[derive(Debug, Default, Eq, PartialEq, Clone, Serialize)]
+#[derive(Debug, Default, PartialEq, Clone, Serialize)]
 pub struct Cache {
 	pub window: CacheWindowSection,
 	pub updates: CacheUpdateSection,
 	pub image: CacheImageSection,
+	pub folders: CacheFoldersSection,
 }
 impl From<IncompleteCache> for Cache {
 	fn from(cache: IncompleteCache) -> Self {
@@ -129,6 +905,7 @@ impl From<IncompleteCache> for Cache {
 			window: cache.window.unwrap_or_default(),
 			updates: cache.updates.unwrap_or_default(),
 			image: cache.image.unwrap_or_default(),
+			folders: cache.folders.unwrap_or_default(),
 		}
 	}
 }
@@ -154,10 +931,14 @@ impl Cache {
 		Ok(result.into())
 	}
 
-	pub fn save<P: AsRef<Path>>(&self, file_path: P) -> Result<(), String> {
+	pub fn save<P: AsRef<Path>>(&mut self, file_path: P) -> Result<(), String> {
 		let file_path = file_path.as_ref();
+		self.folders.prune();
 		let string = toml::to_string(self).map_err(|e| format!("{}", e))?;
-		fs::write(file_path, string)
+		// Written under a temporary name first, so that exiting midway doesn't leave half a file
+		let partial = file_path.with_extension("partial");
+		fs::write(&partial, string)
+			.and_then(|()| fs::rename(&partial, file_path))
 			.map_err(|_| format!("Could not write to cache file {:?}", file_path))?;
 		Ok(())
 	}
@@ -169,18 +950,42 @@ pub struct EnvVar {
 	pub value: String,
 }
 
+/// A custom command to run on the current image.
+///
+/// The command is not run through a shell: `program` is started directly and each element of
+/// `args` is passed as a single argument. In the arguments `${img}` is replaced by the path of
+/// the image, `${folder}` by the path of its folder and `${name}` by its file name. The values
+/// are inserted verbatim, so a path with spaces or quotes in it stays a single argument and
+/// can't run anything by itself. When `program` is a shell, pass the path as a separate
+/// argument (`args = ["-c", "upload \"$1\"", "sh", "${img}"]`) instead of pasting it into
+/// the script.
 #[derive(Debug, Default, Eq, PartialEq, Clone, Deserialize)]
 pub struct Command {
+	/// Lets the command be bound to keys in the `[bindings]` section like the built-in actions
+	pub name: Option<String>,
+	#[serde(default)]
 	pub input: Vec<String>,
 	pub program: String,
 	pub args: Option<Vec<String>>,
 	pub envs: Option<Vec<EnvVar>>,
+	/// Reload the image after the command exits, for commands that edit the image in place
+	pub reload: Option<bool>,
+}
+
+impl Command {
+	/// The name shown in notifications about the command
+	pub fn display_name(&self) -> &str {
+		self.name.as_deref().unwrap_or(&self.program)
+	}
 }
 
 #[derive(Debug, Default, Eq, PartialEq, Clone, Deserialize)]
 pub struct TitleSection {
 	pub displayed_folders: Option<u32>,
 	pub show_program_name: Option<bool>,
+	/// The whole title, with the tokens in `TITLE_TOKENS`, like "{name} ({index}/{count}) : {app}".
+	/// The title is the name, the bit depth, the playback state and the program name by default.
+	pub format: Option<String>,
 }
 impl TitleSection {
 	pub fn format_file_path<'a>(&self, file_path: &'a Path) -> Cow<'a, str> {
@@ -213,6 +1018,50 @@ impl TitleSection {
 			_ => " : E M U L S I O N",
 		}
 	}
+
+	/// The tokens of `format` that aren't in `TITLE_TOKENS`, which are left in the title as they
+	/// are
+	pub fn unknown_format_tokens(&self) -> Vec<&str> {
+		let mut unknown = Vec::new();
+		let mut rest = self.format.as_deref().unwrap_or_default();
+		while let Some(start) = rest.find('{') {
+			let Some(len) = rest[start..].find('}') else {
+				break;
+			};
+			let token = &rest[start..start + len + 1];
+			if !TITLE_TOKENS.contains(&token) {
+				unknown.push(token);
+			}
+			rest = &rest[start + len + 1..];
+		}
+		unknown
+	}
+}
+
+/// The tokens of the title format. `{name}` is the file name, with as many folders as
+/// `displayed_folders`, `{path}` the whole path, and `{mode}` names the playback state and
+/// whether only a preview is shown, like " : Playing".
+pub const TITLE_TOKENS: [&str; 10] = [
+	"{name}",
+	"{path}",
+	"{index}",
+	"{count}",
+	"{zoom}",
+	"{dimensions}",
+	"{filesize}",
+	"{depth}",
+	"{mode}",
+	"{app}",
+];
+
+/// Replaces the tokens of the title format. A token whose value is unknown, like the zoom
+/// before an image is loaded, becomes "-".
+pub fn format_title(format: &str, values: &[(&str, Option<String>)]) -> String {
+	let mut title = format.to_string();
+	for (token, value) in values {
+		title = title.replace(token, value.as_deref().unwrap_or("-"));
+	}
+	title
 }
 
 #[derive(Debug, Default, Clone, Deserialize)]
@@ -223,6 +1072,11 @@ pub struct Configuration {
 	pub title: Option<TitleSection>,
 	pub image: Option<ConfigImageSection>,
 	pub window: Option<ConfigWindowSection>,
+	pub mouse: Option<ConfigMouseSection>,
+	pub navigation: Option<ConfigNavigationSection>,
+	/// The decoder that is tried first for the files with an extension, like `jpg = "image"`,
+	/// when several decoders can decode them
+	pub decoders: Option<BTreeMap<String, String>>,
 }
 impl Configuration {
 	pub fn load<P: AsRef<Path>>(file_path: P) -> Result<Configuration, String> {
@@ -233,4 +1087,17 @@ impl Configuration {
 		//println!("Read config from file:\n{:#?}", result);
 		Ok(result)
 	}
+
+	/// Returns what to do after opening `path`. Regular image files are always just shown.
+	pub fn open_behavior(&self, path: &Path) -> OpenBehavior {
+		let navigation = self.navigation.as_ref();
+		let behavior = if path.is_dir() {
+			navigation.and_then(|s| s.on_open_directory)
+		} else if is_archive(path) {
+			navigation.and_then(|s| s.on_open_archive)
+		} else {
+			None
+		};
+		behavior.unwrap_or_default()
+	}
 }
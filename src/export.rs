@@ -0,0 +1,301 @@
+//! Exporting a whole folder as a contact sheet or as a slideshow video, without opening a window.
+//!
+//! The images are decoded and scaled with the same code as `headless`. Both exports write to a
+//! temporary file first, which is renamed once it's done, so an interrupted export never leaves a
+//! file behind that looks complete.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use gelatin::image::{
+	codecs::gif::{GifEncoder, Repeat},
+	imageops, Delay, Frame, ImageFormat, Rgba, RgbaImage,
+};
+
+use crate::headless::{render_image, shrink_to_fit};
+use crate::image_cache::{
+	directory::list_images,
+	image_loader::{ImageLoaderError, Result},
+	thumbnails::render_small,
+};
+use crate::widgets::{
+	text_notification::{render_colored_text, TEXT_RENDER_SCALE},
+	watermark::WatermarkStyle,
+};
+
+/// The space between the cells of a contact sheet and around them, in pixels
+const SHEET_SPACING: u32 = 8;
+const SHEET_BACKGROUND: Rgba<u8> = Rgba([32, 32, 32, 255]);
+const LABEL_COLOR: [u8; 3] = [220, 220, 220];
+/// The frame rate of the video, the slides are repeated to fill their duration
+const VIDEO_FPS: u32 = 30;
+
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy)]
+pub struct ContactSheetOptions {
+	pub columns: u32,
+	/// The width and the height of the area that each image is fitted into
+	pub cell_size: u32,
+	/// Write the file name of each image below it
+	pub labels: bool,
+}
+
+#[derive(Clone, Copy)]
+pub struct SlideshowOptions {
+	/// How long each image is shown, in seconds
+	pub duration: f32,
+	pub width: u32,
+	pub height: u32,
+}
+
+fn error(description: String) -> ImageLoaderError {
+	ImageLoaderError { description: description.into() }
+}
+
+/// Stops the running export before the next image, and removes its temporary file. This only
+/// stores a flag, so it's safe to call from a signal handler.
+pub fn cancel() {
+	CANCELLED.store(true, Ordering::SeqCst);
+}
+
+fn check_cancelled() -> Result<()> {
+	if CANCELLED.load(Ordering::SeqCst) {
+		return Err(error("The export was cancelled".into()));
+	}
+	Ok(())
+}
+
+fn folder_images(folder: &Path) -> Result<Vec<PathBuf>> {
+	let paths = list_images(folder).map_err(|e| error(e.to_string()))?;
+	if paths.is_empty() {
+		return Err(error(format!("There are no images in {:?}", folder)));
+	}
+	Ok(paths)
+}
+
+/// The file that the export is written to until it's done. It keeps the extension of `output`
+/// because `ffmpeg` picks the container from it.
+fn partial_path(output: &Path) -> PathBuf {
+	let mut name = output.file_stem().unwrap_or_default().to_os_string();
+	name.push(".partial");
+	if let Some(extension) = output.extension() {
+		name.push(".");
+		name.push(extension);
+	}
+	output.with_file_name(name)
+}
+
+/// Moves the temporary file to `output` if `written` succeeded, and removes it otherwise
+fn finish(written: Result<()>, partial: &Path, output: &Path) -> Result<()> {
+	if let Err(e) = written {
+		let _ = std::fs::remove_file(partial);
+		return Err(e);
+	}
+	std::fs::rename(partial, output)?;
+	Ok(())
+}
+
+/// Renders the file name of `path`, cut to `max_width`
+fn render_label(path: &Path, max_width: u32) -> Option<RgbaImage> {
+	let name = path.file_name()?.to_string_lossy();
+	let label = render_colored_text(&name, LABEL_COLOR, SHEET_BACKGROUND.0)?;
+	let scale = 1.0 / TEXT_RENDER_SCALE;
+	let w = ((label.width() as f32 * scale).round() as u32).max(1);
+	let h = ((label.height() as f32 * scale).round() as u32).max(1);
+	let label = imageops::resize(&label, w, h, imageops::FilterType::Triangle);
+	Some(imageops::crop_imm(&label, 0, 0, w.min(max_width), h).to_image())
+}
+
+/// The height of the labels, which is the same for every line of text
+fn label_height() -> u32 {
+	let label = render_colored_text("X", LABEL_COLOR, SHEET_BACKGROUND.0);
+	label.map_or(0, |label| (label.height() as f32 / TEXT_RENDER_SCALE).round() as u32)
+}
+
+/// Places the images of `folder` into a grid and writes it to `output`. The format is
+/// determined by the extension of `output`.
+///
+/// `progress` is called with the number of images done and the number of all images.
+pub fn export_contact_sheet(
+	folder: &Path,
+	output: &Path,
+	options: ContactSheetOptions,
+	watermark: Option<&WatermarkStyle>,
+	mut progress: impl FnMut(usize, usize),
+) -> Result<()> {
+	let format = ImageFormat::from_path(output)?;
+	let paths = folder_images(folder)?;
+	let columns = options.columns.max(1);
+	let cell = options.cell_size.max(1);
+	let rows = (paths.len() as u32).div_ceil(columns);
+	let label_height = if options.labels { label_height() } else { 0 };
+	let step_x = cell + SHEET_SPACING;
+	let step_y = cell + label_height + SHEET_SPACING;
+	let width = columns * step_x + SHEET_SPACING;
+	let height = rows * step_y + SHEET_SPACING;
+	let mut sheet = RgbaImage::from_pixel(width, height, SHEET_BACKGROUND);
+
+	for (i, path) in paths.iter().enumerate() {
+		check_cancelled()?;
+		progress(i, paths.len());
+		let x = SHEET_SPACING + (i as u32 % columns) * step_x;
+		let y = SHEET_SPACING + (i as u32 / columns) * step_y;
+		match render_small(path, cell) {
+			Ok(mut image) => {
+				if let Some(watermark) = watermark {
+					let bounds = (0.0, 0.0, image.width() as f32, image.height() as f32);
+					watermark.stamp(path, &mut image, bounds, 1.0);
+				}
+				// Centered in the cell
+				let image_x = x + (cell - image.width()) / 2;
+				let image_y = y + (cell - image.height()) / 2;
+				imageops::overlay(&mut sheet, &image, image_x as i64, image_y as i64);
+			}
+			Err(e) => eprintln!("Could not load {:?}: {}", path, e),
+		}
+		if options.labels {
+			if let Some(label) = render_label(path, cell) {
+				let label_x = x + (cell - label.width()) / 2;
+				imageops::overlay(&mut sheet, &label, label_x as i64, (y + cell) as i64);
+			}
+		}
+	}
+	progress(paths.len(), paths.len());
+
+	let partial = partial_path(output);
+	let sheet = match format {
+		ImageFormat::Jpeg | ImageFormat::Pnm => {
+			gelatin::image::DynamicImage::ImageRgba8(sheet).into_rgb8().into()
+		}
+		_ => gelatin::image::DynamicImage::ImageRgba8(sheet),
+	};
+	let written = sheet.save_with_format(&partial, format).map_err(Into::into);
+	finish(written, &partial, output)
+}
+
+/// Draws the image centered on a black frame of the video size
+fn slide(path: &Path, options: SlideshowOptions, watermark: Option<&WatermarkStyle>) -> RgbaImage {
+	let mut frame = RgbaImage::from_pixel(options.width, options.height, Rgba([0, 0, 0, 255]));
+	let max_size = options.width.max(options.height);
+	match render_image(path, max_size) {
+		Ok(image) => {
+			let image = shrink_to_fit(image, options.width, options.height);
+			let x = (options.width - image.width()) / 2;
+			let y = (options.height - image.height()) / 2;
+			imageops::overlay(&mut frame, &image, x as i64, y as i64);
+			if let Some(watermark) = watermark {
+				let bounds = (x as f32, y as f32, image.width() as f32, image.height() as f32);
+				watermark.stamp(path, &mut frame, bounds, 1.0);
+			}
+		}
+		Err(e) => eprintln!("Could not load {:?}: {}", path, e),
+	}
+	frame
+}
+
+fn ffmpeg_available() -> bool {
+	Command::new("ffmpeg")
+		.arg("-version")
+		.stdout(Stdio::null())
+		.stderr(Stdio::null())
+		.status()
+		.is_ok()
+}
+
+/// Encodes the images of `folder` into a video at `output`. Videos are encoded with `ffmpeg`.
+/// A GIF is written instead if the extension of `output` is `gif` or `ffmpeg` can't be found,
+/// and the path of the written file is returned.
+pub fn export_slideshow(
+	folder: &Path,
+	output: &Path,
+	options: SlideshowOptions,
+	watermark: Option<&WatermarkStyle>,
+	mut progress: impl FnMut(usize, usize),
+) -> Result<PathBuf> {
+	let paths = folder_images(folder)?;
+	let wants_gif = output.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gif"));
+	let output = if wants_gif || ffmpeg_available() {
+		output.to_owned()
+	} else {
+		let gif = output.with_extension("gif");
+		eprintln!("Could not find ffmpeg, writing a GIF to {:?} instead", gif);
+		gif
+	};
+	let partial = partial_path(&output);
+	let frames = paths.iter().enumerate().map(|(i, path)| {
+		progress(i, paths.len());
+		slide(path, options, watermark)
+	});
+
+	let written = if output.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gif")) {
+		write_gif(&partial, frames, options)
+	} else {
+		write_video(&partial, frames, options)
+	};
+	finish(written, &partial, &output)?;
+	progress(paths.len(), paths.len());
+	Ok(output)
+}
+
+fn write_gif(
+	partial: &Path,
+	frames: impl Iterator<Item = RgbaImage>,
+	options: SlideshowOptions,
+) -> Result<()> {
+	let file = std::fs::File::create(partial)?;
+	let mut encoder = GifEncoder::new_with_speed(file, 10);
+	encoder.set_repeat(Repeat::Infinite)?;
+	let delay = Delay::from_numer_denom_ms((options.duration * 1000.0).round() as u32, 1);
+	for image in frames {
+		check_cancelled()?;
+		encoder.encode_frame(Frame::from_parts(image, 0, 0, delay))?;
+	}
+	Ok(())
+}
+
+/// Pipes the frames into `ffmpeg`, which is killed if anything goes wrong before it's done
+fn write_video(
+	partial: &Path,
+	frames: impl Iterator<Item = RgbaImage>,
+	options: SlideshowOptions,
+) -> Result<()> {
+	let size = format!("{}x{}", options.width, options.height);
+	let mut ffmpeg = Command::new("ffmpeg")
+		.args(["-y", "-loglevel", "error", "-f", "rawvideo", "-pix_fmt", "rgba", "-s"])
+		.arg(&size)
+		.args(["-r", &VIDEO_FPS.to_string(), "-i", "-"])
+		.args(["-c:v", "libx264", "-pix_fmt", "yuv420p"])
+		.arg(partial)
+		.stdin(Stdio::piped())
+		.spawn()
+		.map_err(|e| error(format!("Could not start ffmpeg: {}", e)))?;
+	let repeats = ((options.duration * VIDEO_FPS as f32).round() as u32).max(1);
+	let written = write_frames(ffmpeg.stdin.take().unwrap(), frames, repeats);
+	if written.is_err() {
+		let _ = ffmpeg.kill();
+	}
+	let status = ffmpeg.wait()?;
+	written?;
+	if !status.success() {
+		return Err(error(format!("ffmpeg exited with {}", status)));
+	}
+	Ok(())
+}
+
+/// Writes each frame `repeats` times, `stdin` is closed when this returns
+fn write_frames(
+	mut stdin: ChildStdin,
+	frames: impl Iterator<Item = RgbaImage>,
+	repeats: u32,
+) -> Result<()> {
+	for image in frames {
+		check_cancelled()?;
+		for _ in 0..repeats {
+			stdin.write_all(image.as_raw())?;
+		}
+	}
+	Ok(())
+}
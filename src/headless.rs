@@ -0,0 +1,82 @@
+//! Rendering an image straight to a file, without creating a window.
+//!
+//! This uses the same decoders as the viewer, but the scaling is done on the CPU so that it works
+//! without a GPU or a display server.
+
+use std::path::Path;
+use std::time::Duration;
+
+use gelatin::image::{imageops, DynamicImage, ImageFormat, RgbaImage};
+
+use crate::image_cache::image_loader::{
+	apply_orientation, complex_load_image, ImageLoaderError, LoadResult, Result,
+};
+
+/// Decodes the first frame of the image at `input`, shrinks it so that neither of its
+/// dimensions exceed `max_size` and writes it to `output`.
+///
+/// The output format is determined by the extension of `output`. Images that are already small
+/// enough are not enlarged.
+pub fn render_to_file(input: &Path, output: &Path, max_size: u32) -> Result<()> {
+	write_image(render_image(input, max_size)?, output)
+}
+
+/// Writes `image` to `output`, in the format of the extension of `output`
+pub fn write_image(image: RgbaImage, output: &Path) -> Result<()> {
+	let output_format = ImageFormat::from_path(output)?;
+	let image = match output_format {
+		// These can't store an alpha channel
+		ImageFormat::Jpeg | ImageFormat::Pnm => {
+			DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(image).into_rgb8())
+		}
+		_ => DynamicImage::ImageRgba8(image),
+	};
+	image.save_with_format(output, output_format)?;
+	Ok(())
+}
+
+/// Decodes the first frame of the image at `input` and shrinks it so that neither of its
+/// dimensions exceed `max_size`
+pub fn render_image(input: &Path, max_size: u32) -> Result<RgbaImage> {
+	Ok(shrink_to_fit(decode_image(input)?, max_size, max_size))
+}
+
+/// Decodes the first frame of the image at `input`, turned the way it's shown
+pub fn decode_image(input: &Path) -> Result<RgbaImage> {
+	let mut image = None;
+	complex_load_image(input, 1, 0, |frame| {
+		if let LoadResult::Frame { image: frame_image, orientation, .. } = frame {
+			image = Some(apply_orientation(frame_image, orientation));
+		}
+		Ok(())
+	})?;
+	image.ok_or_else(|| ImageLoaderError { description: "The image contains no frames".into() })
+}
+
+/// Decodes at most `max_frames` frames of the image at `input`, turned the way they're shown,
+/// along with how long each frame is shown. Still images have a single frame.
+pub fn decode_frames(input: &Path, max_frames: usize) -> Result<Vec<(RgbaImage, Duration)>> {
+	let mut frames = Vec::new();
+	complex_load_image(input, max_frames, 0, |frame| {
+		if let LoadResult::Frame { image, orientation, delay_nano, .. } = frame {
+			frames.push((apply_orientation(image, orientation), Duration::from_nanos(delay_nano)));
+		}
+		Ok(())
+	})?;
+	if frames.is_empty() {
+		return Err(ImageLoaderError { description: "The image contains no frames".into() });
+	}
+	Ok(frames)
+}
+
+/// Scales the image down so that it fits into `max_w` × `max_h`, keeping its aspect ratio
+pub fn shrink_to_fit(image: RgbaImage, max_w: u32, max_h: u32) -> RgbaImage {
+	let (w, h) = image.dimensions();
+	if w <= max_w && h <= max_h {
+		return image;
+	}
+	let scale = (max_w as f32 / w as f32).min(max_h as f32 / h as f32);
+	let new_w = ((w as f32 * scale).round() as u32).max(1);
+	let new_h = ((h as f32 * scale).round() as u32).max(1);
+	imageops::resize(&image, new_w, new_h, imageops::FilterType::Lanczos3)
+}
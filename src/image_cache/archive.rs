@@ -0,0 +1,455 @@
+//! Reading images from inside of zip and rar archives (for example comic book archives).
+//!
+//! The images inside of an archive are identified by virtual paths that consist of the path of
+//! the archive followed by the name of the entry. For example the path
+//! `/comics/issue.cbz/chapter 1/001.jpg` refers to the `chapter 1/001.jpg` entry of the
+//! `/comics/issue.cbz` archive. The archive itself is treated like a folder.
+//!
+//! Zip archives are read directly; rar archives are read through the `unrar` program.
+
+use std::convert::TryFrom;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use flate2::read::DeflateDecoder;
+
+use super::image_loader::is_extension_supported;
+
+const EOCD_SIGNATURE: u32 = 0x0605_4b50;
+const ZIP64_EOCD_LOCATOR_SIGNATURE: u32 = 0x0706_4b50;
+const ZIP64_EOCD_SIGNATURE: u32 = 0x0606_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const EOCD_SIZE: usize = 22;
+const MAX_ZIP_COMMENT_LEN: usize = 0xFFFF;
+/// The most that is reserved up front for the contents of an entry. The size in the archive
+/// can't be trusted, larger entries grow the buffer while they are read.
+const MAX_ENTRY_RESERVE: u64 = 16 * 1024 * 1024;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum ArchiveKind {
+	Zip,
+	Rar,
+}
+
+fn archive_kind(path: &Path) -> Option<ArchiveKind> {
+	let ext = path.extension()?.to_str()?.to_lowercase();
+	match ext.as_str() {
+		"zip" | "cbz" => Some(ArchiveKind::Zip),
+		"rar" | "cbr" => Some(ArchiveKind::Rar),
+		_ => None,
+	}
+}
+
+/// Returns true if the path points to an archive file that can be opened like a folder.
+pub fn is_archive(path: &Path) -> bool {
+	archive_kind(path).is_some() && path.is_file()
+}
+
+/// If the path points inside of an archive, returns the path of the archive and the name of the
+/// entry within the archive.
+pub fn split_archive_path(path: &Path) -> Option<(&Path, String)> {
+	let archive = path.ancestors().skip(1).find(|p| is_archive(p))?;
+	let entry_path = path.strip_prefix(archive).ok()?;
+	let mut name = String::new();
+	for component in entry_path.components() {
+		if let Component::Normal(part) = component {
+			if !name.is_empty() {
+				name.push('/');
+			}
+			name.push_str(part.to_str()?);
+		}
+	}
+	Some((archive, name))
+}
+
+/// Returns the virtual paths of the supported images in the archive. Entries in nested folders
+/// are included, other files are skipped.
+pub fn list_images(archive: &Path) -> io::Result<Vec<PathBuf>> {
+	let names = match archive_kind(archive) {
+		Some(ArchiveKind::Zip) => {
+			let mut file = fs::File::open(archive)?;
+			read_zip_directory(&mut file)?.into_iter().map(|entry| entry.name).collect()
+		}
+		Some(ArchiveKind::Rar) => list_rar_entries(archive)?,
+		None => return Err(io::Error::other(format!("{:?} is not an archive", archive))),
+	};
+	Ok(names
+		.into_iter()
+		.filter(|name| !name.ends_with('/') && is_extension_supported(Path::new(name)))
+		.filter(|name| is_safe_entry_name(name))
+		.map(|name| archive.join(name))
+		.collect())
+}
+
+/// Whether the entry name stays inside of the archive when it's joined to its path. Absolute
+/// names would replace the path of the archive and point at a file on the disk instead.
+fn is_safe_entry_name(name: &str) -> bool {
+	let has_drive = name.as_bytes().get(1) == Some(&b':');
+	!has_drive
+		&& Path::new(name)
+			.components()
+			.all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+/// Returns the uncompressed contents of an entry.
+pub fn read_entry(archive: &Path, name: &str) -> io::Result<Vec<u8>> {
+	match archive_kind(archive) {
+		Some(ArchiveKind::Zip) => {
+			let mut file = fs::File::open(archive)?;
+			let entry = read_zip_directory(&mut file)?
+				.into_iter()
+				.find(|entry| entry.name == name)
+				.ok_or_else(|| {
+					io::Error::new(
+						io::ErrorKind::NotFound,
+						format!("Could not find {:?} in {:?}", name, archive),
+					)
+				})?;
+			read_zip_entry(&mut file, &entry)
+		}
+		Some(ArchiveKind::Rar) => read_rar_entry(archive, name),
+		None => Err(io::Error::other(format!("{:?} is not an archive", archive))),
+	}
+}
+
+struct ZipEntry {
+	name: String,
+	method: u16,
+	encrypted: bool,
+	compressed_size: u64,
+	uncompressed_size: u64,
+	local_header_offset: u64,
+}
+
+fn invalid_zip(description: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, format!("Invalid zip archive: {}", description))
+}
+
+fn u16_at(data: &[u8], pos: usize) -> io::Result<u16> {
+	data.get(pos..pos + 2)
+		.map(|b| u16::from_le_bytes([b[0], b[1]]))
+		.ok_or_else(|| invalid_zip("unexpected end of data"))
+}
+
+fn u32_at(data: &[u8], pos: usize) -> io::Result<u32> {
+	data.get(pos..pos + 4)
+		.map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+		.ok_or_else(|| invalid_zip("unexpected end of data"))
+}
+
+fn u64_at(data: &[u8], pos: usize) -> io::Result<u64> {
+	let low = u32_at(data, pos)? as u64;
+	let high = u32_at(data, pos + 4)? as u64;
+	Ok(low | (high << 32))
+}
+
+/// Reads `len` bytes from `offset`, after checking that they are inside of the file
+fn read_at<R: Read + Seek>(file: &mut R, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+	let file_len = file.seek(SeekFrom::End(0))?;
+	if offset.checked_add(len as u64).is_none_or(|end| end > file_len) {
+		return Err(invalid_zip("a record is outside of the file"));
+	}
+	let mut data = vec![0; len];
+	file.seek(SeekFrom::Start(offset))?;
+	file.read_exact(&mut data)?;
+	Ok(data)
+}
+
+/// Returns the offset and the size of the central directory
+fn find_central_directory<R: Read + Seek>(file: &mut R) -> io::Result<(u64, u64)> {
+	let file_len = file.seek(SeekFrom::End(0))?;
+	let tail_len = file_len.min((EOCD_SIZE + MAX_ZIP_COMMENT_LEN) as u64) as usize;
+	let tail_start = file_len - tail_len as u64;
+	let tail = read_at(file, tail_start, tail_len)?;
+	let eocd_pos = (0..=tail_len.saturating_sub(EOCD_SIZE))
+		.rev()
+		.find(|&pos| u32_at(&tail, pos).ok() == Some(EOCD_SIGNATURE))
+		.ok_or_else(|| invalid_zip("could not find the end of central directory record"))?;
+	let eocd = &tail[eocd_pos..];
+	let size = u32_at(eocd, 12)?;
+	let offset = u32_at(eocd, 16)?;
+	if size != u32::MAX && offset != u32::MAX {
+		return Ok((offset as u64, size as u64));
+	}
+
+	// Zip64
+	let locator_pos = eocd_pos
+		.checked_sub(20)
+		.filter(|&pos| u32_at(&tail, pos).ok() == Some(ZIP64_EOCD_LOCATOR_SIGNATURE))
+		.ok_or_else(|| invalid_zip("could not find the zip64 end of central directory locator"))?;
+	let zip64_eocd_offset = u64_at(&tail, locator_pos + 8)?;
+	let zip64_eocd = read_at(file, zip64_eocd_offset, 56)?;
+	if u32_at(&zip64_eocd, 0)? != ZIP64_EOCD_SIGNATURE {
+		return Err(invalid_zip("invalid zip64 end of central directory record"));
+	}
+	Ok((u64_at(&zip64_eocd, 48)?, u64_at(&zip64_eocd, 40)?))
+}
+
+fn read_zip_directory<R: Read + Seek>(file: &mut R) -> io::Result<Vec<ZipEntry>> {
+	let (offset, size) = find_central_directory(file)?;
+	let size = usize::try_from(size).map_err(|_| invalid_zip("the directory is too large"))?;
+	let directory = read_at(file, offset, size)?;
+	let mut entries = Vec::new();
+	let mut pos = 0;
+	while pos + 46 <= directory.len() {
+		if u32_at(&directory, pos)? != CENTRAL_HEADER_SIGNATURE {
+			break;
+		}
+		let flags = u16_at(&directory, pos + 8)?;
+		let method = u16_at(&directory, pos + 10)?;
+		let mut compressed_size = u32_at(&directory, pos + 20)? as u64;
+		let mut uncompressed_size = u32_at(&directory, pos + 24)? as u64;
+		let name_len = u16_at(&directory, pos + 28)? as usize;
+		let extra_len = u16_at(&directory, pos + 30)? as usize;
+		let comment_len = u16_at(&directory, pos + 32)? as usize;
+		let mut local_header_offset = u32_at(&directory, pos + 42)? as u64;
+		let name_start = pos + 46;
+		let name = directory
+			.get(name_start..name_start + name_len)
+			.ok_or_else(|| invalid_zip("unexpected end of data"))?;
+		let name = String::from_utf8_lossy(name).replace('\\', "/");
+
+		// The zip64 extended information only contains the fields which don't fit in the
+		// regular header, in this order.
+		let extra_start = name_start + name_len;
+		let extra = directory
+			.get(extra_start..extra_start + extra_len)
+			.ok_or_else(|| invalid_zip("unexpected end of data"))?;
+		let mut extra_pos = 0;
+		while extra_pos + 4 <= extra.len() {
+			let id = u16_at(extra, extra_pos)?;
+			let len = u16_at(extra, extra_pos + 2)? as usize;
+			if id == 0x0001 {
+				let mut field_pos = extra_pos + 4;
+				for field in
+					[&mut uncompressed_size, &mut compressed_size, &mut local_header_offset]
+				{
+					if *field == u32::MAX as u64 {
+						*field = u64_at(extra, field_pos)?;
+						field_pos += 8;
+					}
+				}
+			}
+			extra_pos += 4 + len;
+		}
+
+		entries.push(ZipEntry {
+			name,
+			method,
+			encrypted: flags & 1 != 0,
+			compressed_size,
+			uncompressed_size,
+			local_header_offset,
+		});
+		pos = extra_start + extra_len + comment_len;
+	}
+	Ok(entries)
+}
+
+fn read_zip_entry<R: Read + Seek>(file: &mut R, entry: &ZipEntry) -> io::Result<Vec<u8>> {
+	if entry.encrypted {
+		return Err(io::Error::other(format!("{:?} is encrypted", entry.name)));
+	}
+	let local_header = read_at(file, entry.local_header_offset, 30)?;
+	if u32_at(&local_header, 0)? != LOCAL_HEADER_SIGNATURE {
+		return Err(invalid_zip("invalid local file header"));
+	}
+	let name_len = u16_at(&local_header, 26)? as u64;
+	let extra_len = u16_at(&local_header, 28)? as u64;
+	let data_start = entry.local_header_offset + 30 + name_len + extra_len;
+	let file_len = file.seek(SeekFrom::End(0))?;
+	if data_start.checked_add(entry.compressed_size).is_none_or(|end| end > file_len) {
+		return Err(invalid_zip("an entry is outside of the file"));
+	}
+	file.seek(SeekFrom::Start(data_start))?;
+	let compressed = file.by_ref().take(entry.compressed_size);
+	let mut data = Vec::with_capacity(entry.uncompressed_size.min(MAX_ENTRY_RESERVE) as usize);
+	// One byte more than the size in the archive is read, to find out if the entry is longer
+	let limit = entry.uncompressed_size.saturating_add(1);
+	match entry.method {
+		0 => {
+			compressed.take(limit).read_to_end(&mut data)?;
+		}
+		8 => {
+			DeflateDecoder::new(compressed).take(limit).read_to_end(&mut data)?;
+		}
+		method => {
+			return Err(io::Error::other(format!(
+				"The compression method ({}) of {:?} is not supported",
+				method, entry.name
+			)));
+		}
+	}
+	if data.len() as u64 != entry.uncompressed_size {
+		return Err(invalid_zip("the size of an entry is wrong"));
+	}
+	Ok(data)
+}
+
+/// The paths in `args` have to come after a `--`, so that names starting with `-` aren't taken
+/// as switches
+fn run_unrar(args: &[&std::ffi::OsStr]) -> io::Result<Vec<u8>> {
+	let output = Command::new("unrar")
+		.args(args)
+		.stdin(Stdio::null())
+		.stderr(Stdio::null())
+		.output()
+		.map_err(|e| io::Error::other(format!("Could not run `unrar`: {}", e)))?;
+	if !output.status.success() {
+		return Err(io::Error::other(format!("`unrar` failed with {}", output.status)));
+	}
+	Ok(output.stdout)
+}
+
+fn list_rar_entries(archive: &Path) -> io::Result<Vec<String>> {
+	let output = run_unrar(&["lb".as_ref(), "--".as_ref(), archive.as_os_str()])?;
+	Ok(String::from_utf8_lossy(&output)
+		.lines()
+		.filter(|line| !line.is_empty())
+		.map(|line| line.replace('\\', "/"))
+		.collect())
+}
+
+fn read_rar_entry(archive: &Path, name: &str) -> io::Result<Vec<u8>> {
+	run_unrar(&["p".as_ref(), "-inul".as_ref(), "--".as_ref(), archive.as_os_str(), name.as_ref()])
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+	use super::*;
+	use rand::{rngs::StdRng, Rng, SeedableRng};
+	use std::io::Cursor;
+
+	/// A zip archive with one stored entry, whose size in the headers is `declared_size`
+	fn stored_zip(name: &str, data: &[u8], declared_size: u32) -> Vec<u8> {
+		stored_zip_entries(&[(name, data, declared_size)])
+	}
+
+	/// A zip archive with the stored entries `(name, data, declared_size)`, in this order
+	pub(in crate::image_cache) fn stored_zip_entries(entries: &[(&str, &[u8], u32)]) -> Vec<u8> {
+		let mut zip = Vec::new();
+		let push_u16 = |zip: &mut Vec<u8>, value: u16| zip.extend_from_slice(&value.to_le_bytes());
+		let push_u32 = |zip: &mut Vec<u8>, value: u32| zip.extend_from_slice(&value.to_le_bytes());
+		let mut local_header_offsets = Vec::new();
+		for (name, data, declared_size) in entries {
+			local_header_offsets.push(zip.len() as u32);
+			push_u32(&mut zip, LOCAL_HEADER_SIGNATURE);
+			for value in [20, 0, 0, 0, 0] {
+				push_u16(&mut zip, value);
+			}
+			for value in [0, data.len() as u32, *declared_size] {
+				push_u32(&mut zip, value);
+			}
+			push_u16(&mut zip, name.len() as u16);
+			push_u16(&mut zip, 0);
+			zip.extend_from_slice(name.as_bytes());
+			zip.extend_from_slice(data);
+		}
+
+		let directory_offset = zip.len() as u32;
+		for ((name, data, declared_size), offset) in entries.iter().zip(local_header_offsets) {
+			push_u32(&mut zip, CENTRAL_HEADER_SIGNATURE);
+			for value in [20, 20, 0, 0, 0, 0] {
+				push_u16(&mut zip, value);
+			}
+			for value in [0, data.len() as u32, *declared_size] {
+				push_u32(&mut zip, value);
+			}
+			for value in [name.len() as u16, 0, 0, 0, 0] {
+				push_u16(&mut zip, value);
+			}
+			push_u32(&mut zip, 0);
+			push_u32(&mut zip, offset);
+			zip.extend_from_slice(name.as_bytes());
+		}
+		let directory_size = zip.len() as u32 - directory_offset;
+
+		push_u32(&mut zip, EOCD_SIGNATURE);
+		let count = entries.len() as u16;
+		for value in [0, 0, count, count] {
+			push_u16(&mut zip, value);
+		}
+		push_u32(&mut zip, directory_size);
+		push_u32(&mut zip, directory_offset);
+		push_u16(&mut zip, 0);
+		zip
+	}
+
+	fn read_only_entry(zip: &[u8]) -> io::Result<Vec<u8>> {
+		let mut file = Cursor::new(zip);
+		let entries = read_zip_directory(&mut file)?;
+		let entry = entries.first().ok_or_else(|| invalid_zip("no entries"))?;
+		read_zip_entry(&mut file, entry)
+	}
+
+	#[test]
+	fn reads_a_stored_entry() {
+		let zip = stored_zip("pages/001.png", b"pixels", 6);
+		assert_eq!(read_only_entry(&zip).unwrap(), b"pixels");
+	}
+
+	#[test]
+	fn rejects_a_wrong_entry_size() {
+		for declared_size in [0, 5, 7, u32::MAX - 1] {
+			let zip = stored_zip("001.png", b"pixels", declared_size);
+			assert!(read_only_entry(&zip).is_err(), "declared size {}", declared_size);
+		}
+	}
+
+	#[test]
+	fn rejects_a_directory_outside_of_the_file() {
+		let mut zip = stored_zip("001.png", b"pixels", 6);
+		let size_pos = zip.len() - 10;
+		zip[size_pos..size_pos + 4].copy_from_slice(&(u32::MAX - 1).to_le_bytes());
+		assert!(read_zip_directory(&mut Cursor::new(&zip)).is_err());
+	}
+
+	#[test]
+	fn rejects_every_truncated_archive() {
+		let zip = stored_zip("001.png", b"pixels", 6);
+		for len in 0..zip.len() {
+			assert!(read_only_entry(&zip[..len]).is_err(), "truncated to {} bytes", len);
+		}
+	}
+
+	#[test]
+	fn rejects_random_data_after_the_local_header() {
+		let zip = stored_zip("001.png", b"pixels", 6);
+		let local_header_len = 30 + "001.png".len();
+		let mut rng = StdRng::seed_from_u64(146);
+		for _ in 0..2000 {
+			let mut data = zip[..local_header_len].to_vec();
+			let len = rng.gen_range(0..200);
+			data.extend((0..len).map(|_| rng.gen::<u8>()));
+			assert!(read_only_entry(&data).is_err());
+		}
+	}
+
+	#[test]
+	fn survives_corrupted_archives() {
+		let zip = stored_zip("001.png", b"pixels", 6);
+		let mut rng = StdRng::seed_from_u64(146);
+		for _ in 0..2000 {
+			let mut zip = zip.clone();
+			for _ in 0..rng.gen_range(1..4) {
+				let pos = rng.gen_range(0..zip.len());
+				zip[pos] = rng.gen();
+			}
+			// Anything but a panic is fine, a corrupted size or method can still be readable
+			let _ = read_only_entry(&zip);
+		}
+	}
+
+	#[test]
+	fn entry_names_stay_inside_of_the_archive() {
+		for name in ["001.png", "chapter 1/001.png", "./001.png"] {
+			assert!(is_safe_entry_name(name), "{}", name);
+		}
+		for name in ["/home/u/x.png", "../x.png", "a/../../x.png", "C:/x.png", "c:x.png"] {
+			assert!(!is_safe_entry_name(name), "{}", name);
+		}
+	}
+}
@@ -0,0 +1,50 @@
+use std::fmt;
+use std::path::Path;
+
+use chrono::{DateTime, Datelike, Local};
+
+use super::source;
+
+/// The day on which a photo was taken
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CaptureDate {
+	pub year: i32,
+	pub month: u32,
+	pub day: u32,
+}
+
+impl fmt::Display for CaptureDate {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+	}
+}
+
+/// Returns the day from the EXIF `DateTimeOriginal` field of the file. If that's not available,
+/// the modification date of the file is used instead.
+pub fn capture_date(path: &Path) -> Option<CaptureDate> {
+	exif_capture_date(path).or_else(|| {
+		let modified = source::metadata(path).and_then(|m| m.modified()).ok()?;
+		let modified: DateTime<Local> = modified.into();
+		Some(CaptureDate { year: modified.year(), month: modified.month(), day: modified.day() })
+	})
+}
+
+fn exif_capture_date(path: &Path) -> Option<CaptureDate> {
+	let mut reader = source::open(path).ok()?;
+	let exif = exif::Reader::new().read_from_container(&mut reader).ok()?;
+	let field = exif.get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)?;
+	if let exif::Value::Ascii(ref values) = field.value {
+		let date_time = exif::DateTime::from_ascii(values.first()?).ok()?;
+		// Unknown dates are stored as zeros
+		if date_time.year == 0 || date_time.month == 0 || date_time.day == 0 {
+			return None;
+		}
+		Some(CaptureDate {
+			year: date_time.year as i32,
+			month: date_time.month as u32,
+			day: date_time.day as u32,
+		})
+	} else {
+		None
+	}
+}
@@ -0,0 +1,349 @@
+//! The list of image decoders.
+//!
+//! The format of a file is determined by asking each registered decoder whether it can decode the
+//! file. First only the magic bytes at the start of the file are offered to the decoders, and if
+//! none of them accepts the file, only the file extension is.
+//!
+//! The built-in decoders are registered when the registry is first used. Additional decoders can
+//! be added with `register_decoder`, for example from `main` in a custom build.
+//!
+//! When several decoders accept a file, the one that was registered last is tried first, unless
+//! the `[decoders]` section of the configuration prefers another one for the extension. The
+//! others are tried in turn if a decoder fails.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use gelatin::image::{
+	self,
+	codecs::{gif::GifDecoder, png::PngDecoder},
+	AnimationDecoder, ColorType, DynamicImage, ImageBuffer, ImageDecoder, ImageFormat, Rgba,
+	Rgba32FImage, RgbaImage,
+};
+use lazy_static::lazy_static;
+use usvg::fontdb;
+
+use super::image_loader::Result;
+use super::psd::decode_psd;
+use super::source;
+
+pub type Rgba16Image = ImageBuffer<Rgba<u16>, Vec<u16>>;
+
+/// One frame of an animated image
+pub struct DecodedFrame {
+	pub image: RgbaImage,
+	pub delay_nano: u64,
+}
+
+pub enum DecodedImage {
+	Still(RgbaImage),
+	/// An image with 16 bits per channel
+	StillDeep(Rgba16Image),
+	/// A still image of which only a part could be decoded, because the file is truncated or
+	/// damaged
+	Partial(RgbaImage),
+	/// An image with linear floating point values, which may be brighter than white
+	StillHdr(Rgba32FImage),
+	/// The frames are decoded as the iterator is advanced
+	Animation(Box<dyn Iterator<Item = Result<DecodedFrame>>>),
+}
+
+pub trait Decoder: Send + Sync {
+	/// The name of the decoder in the `[decoders]` section of the configuration and in the
+	/// status bar, like "image"
+	fn name(&self) -> &'static str;
+
+	/// Returns true if this decoder can decode the file.
+	///
+	/// `magic` holds the first (up to 512) bytes of the file, and `ext` holds the lowercase file
+	/// extension. During detection one of them is always empty; see the module documentation.
+	fn can_decode(&self, magic: &[u8], ext: &str) -> bool;
+
+	fn decode(&self, bytes: &[u8]) -> Result<DecodedImage>;
+
+	/// Decodes the image at `path`. Override this if the decoder needs more than the contents of
+	/// the file, for example to resolve relative references.
+	fn decode_file(&self, path: &Path) -> Result<DecodedImage> {
+		self.decode(&source::read(path)?)
+	}
+}
+
+lazy_static! {
+	static ref DECODERS: RwLock<Vec<Arc<dyn Decoder>>> =
+		RwLock::new(vec![Arc::new(ImageCrateDecoder), Arc::new(SvgDecoder), Arc::new(PsdDecoder),]);
+	/// The names of the decoders that are tried first, by the lowercase file extension
+	static ref PREFERRED_DECODERS: RwLock<HashMap<String, String>> = Default::default();
+}
+
+/// Adds a decoder to the registry. Decoders registered later take precedence over the ones
+/// registered earlier, including the built-in decoders.
+#[allow(dead_code)]
+pub fn register_decoder(decoder: impl Decoder + 'static) {
+	DECODERS.write().unwrap().push(Arc::new(decoder));
+}
+
+/// Sets the decoders that are tried first for the files with some extensions, from the
+/// `[decoders]` section of the configuration. The names that no decoder in this build has are
+/// reported and ignored.
+pub fn set_preferred_decoders(preferred: &BTreeMap<String, String>) {
+	let decoders = DECODERS.read().unwrap();
+	let mut preferred_decoders = PREFERRED_DECODERS.write().unwrap();
+	preferred_decoders.clear();
+	for (ext, name) in preferred {
+		let ext = ext.trim_start_matches('.').to_lowercase();
+		if !decoders.iter().any(|decoder| decoder.name() == name) {
+			eprintln!("There is no decoder named {:?} for .{} files in this build.", name, ext);
+			let names: Vec<_> = decoders.iter().map(|decoder| decoder.name()).collect();
+			eprintln!("The decoders are {}.", names.join(", "));
+			continue;
+		}
+		preferred_decoders.insert(ext, name.clone());
+	}
+}
+
+/// The name of the decoder that is tried first for files with the lowercase extension `ext`,
+/// if the configuration picks one
+pub fn preferred_decoder(ext: &str) -> Option<String> {
+	PREFERRED_DECODERS.read().unwrap().get(ext).cloned()
+}
+
+/// Returns the decoders for a file with the given magic bytes and extension, in the order in
+/// which they should be tried.
+pub fn find_decoders(magic: &[u8], ext: &str) -> Vec<Arc<dyn Decoder>> {
+	let decoders = DECODERS.read().unwrap();
+	let find = |magic: &[u8], ext: &str| -> Vec<_> {
+		decoders.iter().rev().filter(|decoder| decoder.can_decode(magic, ext)).cloned().collect()
+	};
+	let mut found = if magic.is_empty() { Vec::new() } else { find(magic, "") };
+	if found.is_empty() && !ext.is_empty() {
+		found = find(&[], ext);
+	}
+	if let Some(preferred) = PREFERRED_DECODERS.read().unwrap().get(ext) {
+		// The sort is stable, so the others stay in their order
+		found.sort_by_key(|decoder| decoder.name() != preferred);
+	}
+	found
+}
+
+/// Returns the decoder that is tried first for a file with the given magic bytes and extension.
+pub fn find_decoder(magic: &[u8], ext: &str) -> Option<Arc<dyn Decoder>> {
+	find_decoders(magic, ext).into_iter().next()
+}
+
+/// Returns true if any of the decoders accepts files with this extension.
+pub fn is_extension_supported(ext: &str) -> bool {
+	find_decoder(&[], ext).is_some()
+}
+
+/// The formats supported by the `image` crate. Animated GIF and PNG files are also handled here.
+struct ImageCrateDecoder;
+
+impl Decoder for ImageCrateDecoder {
+	fn name(&self) -> &'static str {
+		"image"
+	}
+
+	fn can_decode(&self, magic: &[u8], ext: &str) -> bool {
+		if !magic.is_empty() {
+			return image::guess_format(magic).is_ok();
+		}
+		match ext {
+			"jpg" | "jpeg" | "png" | "apng" | "gif" | "webp" | "tif" | "tiff" | "tga" | "bmp"
+			| "ico" | "hdr" | "exr" | "pbm" | "pam" | "ppm" | "pgm" => true,
+			#[cfg(feature = "avif")]
+			"avif" => true,
+			_ => false,
+		}
+	}
+
+	fn decode(&self, bytes: &[u8]) -> Result<DecodedImage> {
+		let format = image::guess_format(bytes)?;
+		match format {
+			ImageFormat::Gif => {
+				let decoder = GifDecoder::new(Cursor::new(bytes.to_vec()))?;
+				return Ok(DecodedImage::Animation(decode_animation(decoder)));
+			}
+			ImageFormat::Png => {
+				let decoder = PngDecoder::new(Cursor::new(bytes.to_vec()))?;
+				if decoder.is_apng()? {
+					return Ok(DecodedImage::Animation(decode_animation(decoder.apng()?)));
+				}
+			}
+			_ => (),
+		}
+		decode_still(bytes, format)
+	}
+
+	fn decode_file(&self, path: &Path) -> Result<DecodedImage> {
+		let bytes = source::read(path)?;
+		if image::guess_format(&bytes).is_ok() {
+			return self.decode(&bytes);
+		}
+		// The file was accepted based on its extension
+		let format = ImageFormat::from_path(path)?;
+		decode_still(&bytes, format)
+	}
+}
+
+/// The largest buffer that is allocated for decoding a damaged file, the same as the default
+/// allocation limit of the `image` crate
+const MAX_PARTIAL_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Decodes a still image, or as much of it as possible if the file is damaged
+fn decode_still(bytes: &[u8], format: ImageFormat) -> Result<DecodedImage> {
+	match image::load_from_memory_with_format(bytes, format) {
+		// Some decoders fill in the missing part of a truncated file without an error
+		Ok(image) if is_truncated(bytes, format) => Ok(DecodedImage::Partial(image.into_rgba8())),
+		Ok(image) => Ok(still_image(image)),
+		Err(error) => match decode_partial(bytes, format) {
+			Some(image) => Ok(DecodedImage::Partial(image)),
+			None => Err(error.into()),
+		},
+	}
+}
+
+/// Returns true if the file doesn't end with the marker that closes images of this format
+fn is_truncated(bytes: &[u8], format: ImageFormat) -> bool {
+	// Some programs add a few bytes of padding after the end
+	let tail = &bytes[bytes.len().saturating_sub(64)..];
+	match format {
+		ImageFormat::Jpeg => !tail.windows(2).any(|w| w == [0xFF, 0xD9]),
+		ImageFormat::Png => !tail.windows(4).any(|w| w == b"IEND"),
+		_ => false,
+	}
+}
+
+/// Returns the pixels that were decoded before the decoder stopped with an error, or `None` if
+/// there are none. The rest of the image is left black.
+fn decode_partial(bytes: &[u8], format: ImageFormat) -> Option<RgbaImage> {
+	let decoder = image::io::Reader::with_format(Cursor::new(bytes), format).into_decoder().ok()?;
+	let (w, h) = decoder.dimensions();
+	let color = decoder.color_type();
+	let total_bytes = decoder.total_bytes();
+	if total_bytes > MAX_PARTIAL_BYTES {
+		return None;
+	}
+	let mut buffer = vec![0; total_bytes as usize];
+	let _ = decoder.read_image(&mut buffer);
+	if buffer.iter().all(|&byte| byte == 0) {
+		return None;
+	}
+	let image = match color {
+		ColorType::L8 => DynamicImage::ImageLuma8(ImageBuffer::from_raw(w, h, buffer)?),
+		ColorType::La8 => DynamicImage::ImageLumaA8(ImageBuffer::from_raw(w, h, buffer)?),
+		ColorType::Rgb8 => DynamicImage::ImageRgb8(ImageBuffer::from_raw(w, h, buffer)?),
+		ColorType::Rgba8 => DynamicImage::ImageRgba8(ImageBuffer::from_raw(w, h, buffer)?),
+		ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16 => {
+			let samples: Vec<u16> =
+				buffer.chunks_exact(2).map(|pair| u16::from_ne_bytes([pair[0], pair[1]])).collect();
+			match color {
+				ColorType::L16 => DynamicImage::ImageLuma16(ImageBuffer::from_raw(w, h, samples)?),
+				ColorType::La16 => {
+					DynamicImage::ImageLumaA16(ImageBuffer::from_raw(w, h, samples)?)
+				}
+				ColorType::Rgb16 => DynamicImage::ImageRgb16(ImageBuffer::from_raw(w, h, samples)?),
+				_ => DynamicImage::ImageRgba16(ImageBuffer::from_raw(w, h, samples)?),
+			}
+		}
+		_ => return None,
+	};
+	Some(image.into_rgba8())
+}
+
+fn still_image(image: DynamicImage) -> DecodedImage {
+	match image {
+		DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+			DecodedImage::StillHdr(image.into_rgba32f())
+		}
+		DynamicImage::ImageLuma16(_)
+		| DynamicImage::ImageLumaA16(_)
+		| DynamicImage::ImageRgb16(_)
+		| DynamicImage::ImageRgba16(_) => DecodedImage::StillDeep(image.into_rgba16()),
+		image => DecodedImage::Still(image.into_rgba8()),
+	}
+}
+
+fn decode_animation(
+	decoder: impl AnimationDecoder<'static>,
+) -> Box<dyn Iterator<Item = Result<DecodedFrame>>> {
+	let frames = decoder.into_frames();
+
+	Box::new(frames.map(move |frame| {
+		Ok(frame.map(|frame| {
+			let (mut numerator_ms, mut denom) = frame.delay().numer_denom_ms();
+			if numerator_ms == 0 {
+				// Some animated gifs specify a 0 inter-frame delay, but
+				// most gif viewers interpret this as a 0.1 second delay.
+				numerator_ms = 100;
+				denom = 1;
+			}
+			let numerator_nano = numerator_ms as u64 * 1_000_000;
+			let delay_nano = numerator_nano / (denom as u64);
+			DecodedFrame { image: frame.into_buffer(), delay_nano }
+		})?)
+	}))
+}
+
+struct SvgDecoder;
+
+impl SvgDecoder {
+	fn render(svg_data: &[u8], resources_dir: Option<std::path::PathBuf>) -> Result<RgbaImage> {
+		let rtree = {
+			let opt = usvg::Options { resources_dir, ..Default::default() };
+
+			let mut fontdb = fontdb::Database::new();
+			fontdb.load_system_fonts();
+
+			usvg::Tree::from_data(svg_data, &opt, &fontdb)?
+		};
+		let width = rtree.size().width();
+		let height = rtree.size().height();
+		// Scale to fit 4096
+		let zoom = 4096. / width.max(height);
+		let (width, height) = ((width * zoom) as u32, (height * zoom) as u32);
+		// These unwrapped Options are fine as long as the dimensions are correct
+		let mut pixmap = tiny_skia::Pixmap::new(width, height).unwrap();
+		let transform = tiny_skia::Transform::from_scale(zoom, zoom);
+		resvg::render(&rtree, transform, &mut pixmap.as_mut());
+		Ok(RgbaImage::from_raw(width, height, pixmap.take()).unwrap())
+	}
+}
+
+impl Decoder for SvgDecoder {
+	fn name(&self) -> &'static str {
+		"svg"
+	}
+
+	fn can_decode(&self, _magic: &[u8], ext: &str) -> bool {
+		ext == "svg"
+	}
+
+	fn decode(&self, bytes: &[u8]) -> Result<DecodedImage> {
+		Ok(DecodedImage::Still(Self::render(bytes, None)?))
+	}
+
+	fn decode_file(&self, path: &Path) -> Result<DecodedImage> {
+		let svg_data = source::read(path)?;
+		let resources_dir =
+			std::fs::canonicalize(path).ok().and_then(|p| p.parent().map(|p| p.to_path_buf()));
+		Ok(DecodedImage::Still(Self::render(&svg_data, resources_dir)?))
+	}
+}
+
+struct PsdDecoder;
+
+impl Decoder for PsdDecoder {
+	fn name(&self) -> &'static str {
+		"psd"
+	}
+
+	fn can_decode(&self, magic: &[u8], ext: &str) -> bool {
+		magic.starts_with(b"8BPS") || ext == "psd"
+	}
+
+	fn decode(&self, bytes: &[u8]) -> Result<DecodedImage> {
+		Ok(DecodedImage::Still(decode_psd(bytes)?))
+	}
+}
@@ -1,3 +1,5 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::ffi::{OsStr, OsString};
 use std::fmt;
 use std::fs;
@@ -5,8 +7,15 @@ use std::path::{Path, PathBuf};
 
 use log::debug;
 
+use super::archive;
+use super::capture_date::{capture_date, CaptureDate};
+use super::duplicates::{DuplicateFinder, Duplicates};
 use super::image_loader::is_file_supported;
+use super::shooting_info::{cmp_sort_keys, shooting_info, ShootingInfo};
+use super::thumbnails::{ThumbnailGenerator, DEFAULT_THUMBNAIL_THREADS};
+use crate::configuration::SortOrder;
 use crate::parallel_action::ParallelAction;
+use crate::utils::{matches_glob, matches_name_pattern};
 
 #[derive(Debug)]
 pub enum Error {
@@ -49,6 +58,61 @@ macro_rules! step_to_next_img {
 	};
 }
 
+/// The order in which the files of a folder are navigated
+fn cmp_file_names(a: &Path, b: &Path) -> Ordering {
+	lexical_sort::natural_lexical_cmp(
+		&a.file_name().unwrap().to_string_lossy(),
+		&b.file_name().unwrap().to_string_lossy(),
+	)
+}
+
+/// The order in which the entries of an archive are navigated. Entries in nested folders of the
+/// archive are sorted by their whole path within the archive, so that the folders are navigated
+/// one after the other.
+fn cmp_entry_paths(a: &Path, b: &Path) -> Ordering {
+	lexical_sort::natural_lexical_cmp(&a.to_string_lossy(), &b.to_string_lossy())
+}
+
+/// Returns the first image in the folder, in navigation order,
+/// for which the file name matches the pattern. See `matches_name_pattern`.
+pub fn find_image_by_name(dir_path: &Path, pattern: &str) -> Result<Option<PathBuf>> {
+	let mut paths = fs::read_dir(dir_path)?
+		.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+		.filter(|path| path.is_file())
+		.collect::<Vec<_>>();
+	paths.sort_unstable_by(|a, b| cmp_file_names(a, b));
+	Ok(paths.into_iter().find(|path| {
+		let name = path.file_name().unwrap().to_string_lossy();
+		matches_name_pattern(&name, pattern) && is_file_supported(path)
+	}))
+}
+
+/// Returns the images of a folder or an archive in navigation order
+pub fn list_images(dir_path: &Path) -> Result<Vec<PathBuf>> {
+	if archive::is_archive(dir_path) {
+		let mut paths = archive::list_images(dir_path)?;
+		paths.sort_unstable_by(|a, b| cmp_entry_paths(a, b));
+		return Ok(paths);
+	}
+	let mut paths = fs::read_dir(dir_path)?
+		.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+		.filter(|path| path.is_file() && is_file_supported(path))
+		.collect::<Vec<_>>();
+	paths.sort_unstable_by(|a, b| cmp_file_names(a, b));
+	Ok(paths)
+}
+
+/// The image of the folder of `path` that comes after it in navigation order, or the last one
+/// if there are none after it. `path` itself doesn't have to be an image.
+pub fn image_after(path: &Path) -> Result<Option<PathBuf>> {
+	let Some(dir_path) = path.parent() else {
+		return Ok(None);
+	};
+	let images = list_images(dir_path)?;
+	let after = images.iter().find(|image| cmp_file_names(image, path) == Ordering::Greater);
+	Ok(after.or(images.last()).cloned())
+}
+
 #[derive(Clone)]
 pub struct DirItem {
 	pub path: PathBuf,
@@ -91,6 +155,37 @@ pub struct Directory {
 
 	//filter_state: Arc<Mutex<FilterState>>,
 	filter_action: ParallelAction<Vec<DirItem>, Vec<usize>>,
+
+	/// The capture dates of the images, indexed by the image index.
+	/// These are only read when they are first needed.
+	capture_dates: Option<Vec<Option<CaptureDate>>>,
+	capture_dates_requested: bool,
+	capture_date_action: ParallelAction<Vec<PathBuf>, Vec<Option<CaptureDate>>>,
+
+	sort_order: SortOrder,
+	/// True once the files were sorted by something else than `SortOrder::Name`. The files are
+	/// listed in the name order, so they only have to be sorted if this is set.
+	key_sorted: bool,
+	/// The EXIF data of the images of the folder, for sorting by it. This is kept when the
+	/// folder is collected again, so that the files are only read once.
+	shooting_infos: HashMap<PathBuf, ShootingInfo>,
+	shooting_infos_requested: bool,
+	shooting_info_action: ParallelAction<Vec<PathBuf>, Vec<(PathBuf, ShootingInfo)>>,
+
+	/// The groups of similar images. These are only searched for when they are first needed.
+	duplicates: Option<Duplicates>,
+	duplicates_requested: bool,
+	duplicate_finder: DuplicateFinder,
+	thumbnails_requested: bool,
+	thumbnail_generator: ThumbnailGenerator,
+
+	/// The glob patterns of the files that are left out, see `set_ignore`
+	ignore: Vec<String>,
+	show_hidden: bool,
+	/// Navigate the ignored and hidden files anyway, for this session
+	show_ignored: bool,
+	/// List only the file that is opened, see `set_single_image`
+	single_image: bool,
 }
 
 fn get_action() -> impl FnMut(Vec<DirItem>) -> Vec<usize> {
@@ -114,23 +209,102 @@ impl Directory {
 			curr_image_idx: 0,
 			current_req_id: 0,
 			filter_action: ParallelAction::new(get_action()),
+			capture_dates: None,
+			capture_dates_requested: false,
+			capture_date_action: ParallelAction::new(|paths: Vec<PathBuf>| {
+				paths.iter().map(|path| capture_date(path)).collect()
+			}),
+			sort_order: SortOrder::Name,
+			key_sorted: false,
+			shooting_infos: HashMap::new(),
+			shooting_infos_requested: false,
+			shooting_info_action: ParallelAction::new(|paths: Vec<PathBuf>| {
+				paths.into_iter().map(|path| (path.clone(), shooting_info(&path))).collect()
+			}),
+			duplicates: None,
+			duplicates_requested: false,
+			duplicate_finder: DuplicateFinder::new(),
+			thumbnails_requested: false,
+			thumbnail_generator: ThumbnailGenerator::new(DEFAULT_THUMBNAIL_THREADS),
+			ignore: Vec::new(),
+			show_hidden: true,
+			show_ignored: false,
+			single_image: false,
+		}
+	}
+
+	/// Leaves the files whose names match one of the glob `patterns` out of the navigation, and
+	/// the hidden files unless `show_hidden` is set. A pattern with a `/` is matched against the
+	/// path within the folder or the archive instead. The file that is opened is always kept.
+	/// This takes effect when the folder is collected the next time.
+	pub fn set_ignore(&mut self, patterns: Vec<String>, show_hidden: bool) {
+		self.ignore = patterns;
+		self.show_hidden = show_hidden;
+	}
+
+	/// Navigates the ignored and hidden files too. This takes effect when the folder is
+	/// collected the next time.
+	pub fn set_show_ignored(&mut self, show: bool) {
+		self.show_ignored = show;
+	}
+
+	pub fn show_ignored(&self) -> bool {
+		self.show_ignored
+	}
+
+	/// Lists only the file that is opened by its path from now on, as if it was alone in its
+	/// folder. Opening a folder or an archive still lists all of its images.
+	pub fn set_single_image(&mut self, single_image: bool) {
+		if self.single_image != single_image {
+			self.single_image = single_image;
+			// So that the folder is listed again, even if it stays the same
+			self.path = PathBuf::new();
+		}
+	}
+
+	fn is_ignored(&self, path: &Path) -> bool {
+		if self.show_ignored {
+			return false;
+		}
+		let Some(name) = path.file_name().map(|name| name.to_string_lossy()) else {
+			return false;
+		};
+		if !self.show_hidden && name.starts_with('.') {
+			return true;
 		}
+		let relative = path.strip_prefix(&self.path).unwrap_or(path);
+		let relative = relative.to_string_lossy().replace('\\', "/");
+		self.ignore.iter().any(|pattern| {
+			let target = if pattern.contains('/') { relative.as_str() } else { &name };
+			matches_glob(target, pattern)
+		})
 	}
 
 	pub fn change_directory(&mut self, path: &Path) -> Result<()> {
+		self.change_directory_keeping(path, None)
+	}
+
+	/// Like `change_directory`, but `keep` stays in the folder even if it's ignored
+	fn change_directory_keeping(&mut self, path: &Path, keep: Option<&Path>) -> Result<()> {
 		if self.path != path {
 			path.clone_into(&mut self.path);
-			self.collect_directory()
+			self.shooting_infos.clear();
+			self.collect_directory(keep)
 		} else {
 			Ok(())
 		}
 	}
 
 	pub fn change_directory_with_filename(&mut self, path: &Path, filename: &OsStr) -> Result<()> {
-		self.change_directory(path)?;
 		// Look up the index of the filename in the directory
+		let file_path = path.join(filename);
+		self.change_directory_keeping(path, Some(&file_path))?;
+		if self.single_image && !self.files.iter().any(|desc| desc.path == file_path) {
+			// Another file of the folder is opened, which replaces the one that was listed
+			self.collect_directory(Some(&file_path))?;
+		}
 		for (index, desc) in self.files.iter().enumerate() {
-			if desc.path.file_name().unwrap() == filename {
+			if desc.path == file_path {
 				self.curr_file_idx = index;
 				self.set_image_index_from_file_index();
 				// If we already finished filtering somehow
@@ -218,18 +392,113 @@ impl Directory {
 		}
 	}
 
+	/// Returns the capture dates of the images, indexed by the image index.
+	///
+	/// The dates are read in the background when this is first called for a folder.
+	/// Until they are ready, this returns `None`.
+	pub fn capture_dates(&mut self) -> Option<&[Option<CaptureDate>]> {
+		if !self.check_filter_ready() {
+			return None;
+		}
+		if !self.capture_dates_requested {
+			let paths = self.img_i_to_file_i.iter().map(|&i| self.files[i].path.clone()).collect();
+			self.capture_date_action.give_input(paths);
+			self.capture_dates_requested = true;
+		}
+		if let Some(dates) = self.capture_date_action.try_get_output() {
+			self.capture_dates = Some(dates);
+		}
+		self.capture_dates.as_deref()
+	}
+
+	/// Returns the groups of similar images in the folder.
+	///
+	/// The images are hashed in the background when this is first called for a folder.
+	/// Until that's done, this returns `None`.
+	pub fn duplicates(&mut self) -> Option<&Duplicates> {
+		if !self.check_filter_ready() {
+			return None;
+		}
+		if !self.duplicates_requested {
+			let paths = self.img_i_to_file_i.iter().map(|&i| self.files[i].path.clone()).collect();
+			self.duplicate_finder.start(paths);
+			self.duplicates_requested = true;
+		}
+		if let Some(duplicates) = self.duplicate_finder.try_get_result() {
+			self.duplicates = Some(duplicates);
+		}
+		self.duplicates.as_ref()
+	}
+
+	/// Sorts the folder by `order` from now on. The EXIF orders take effect once the EXIF data
+	/// of the folder is read in the background.
+	pub fn set_sort_order(&mut self, order: SortOrder) {
+		if self.sort_order == order {
+			return;
+		}
+		self.sort_order = order;
+		if self.check_filter_ready() {
+			self.request_sort();
+		}
+	}
+
+	/// Returns true while the EXIF data for the sort order is being read
+	pub fn sorting(&mut self) -> bool {
+		self.check_filter_ready();
+		self.shooting_infos_requested
+	}
+
+	/// The value that the image at `path` is sorted by, like "f/2.8". `None` when the folder is
+	/// sorted by name or the value isn't known.
+	pub fn sort_key_text(&self, path: &Path) -> Option<String> {
+		self.shooting_infos.get(path)?.describe(self.sort_order)
+	}
+
+	/// Sets the number of threads that generate thumbnails. Only has an effect before the
+	/// thumbnails are first requested.
+	pub fn set_thumbnail_threads(&mut self, threads: u32) {
+		self.thumbnail_generator.set_threads(threads);
+	}
+
+	/// Returns how many of the thumbnails of the folder are done, and how many images there are.
+	///
+	/// The thumbnails are generated in the background when this is first called for a folder,
+	/// starting from the current image and going outward in both directions. Returns `None`
+	/// when all of them are done.
+	pub fn thumbnail_progress(&mut self) -> Option<(usize, usize)> {
+		if !self.check_filter_ready() {
+			return None;
+		}
+		if !self.thumbnails_requested {
+			let count = self.img_i_to_file_i.len();
+			let curr = self.curr_img_index().unwrap_or(0);
+			let mut paths = Vec::with_capacity(count);
+			for distance in 0..count {
+				let after = curr + distance;
+				if after < count {
+					paths.push(self.files[self.img_i_to_file_i[after]].path.clone());
+				}
+				if distance > 0 && distance <= curr {
+					paths.push(self.files[self.img_i_to_file_i[curr - distance]].path.clone());
+				}
+			}
+			self.thumbnail_generator.start(paths);
+			self.thumbnails_requested = true;
+		}
+		self.thumbnail_generator.progress()
+	}
+
 	pub fn update_directory(&mut self) -> Result<()> {
-		let curr_filename = self.curr_filename();
-		let curr_filename = curr_filename.as_deref();
+		let curr_path = self.curr_descriptor().map(|desc| desc.path.clone());
 		let curr_index = self.curr_file_idx;
 		debug!(
-			"Directory: `update_directory`. Current filename: {:?}, curr_index: {:?}",
-			curr_filename, curr_index
+			"Directory: `update_directory`. Current path: {:?}, curr_index: {:?}",
+			curr_path, curr_index
 		);
-		self.collect_directory()?;
-		if curr_filename.is_some() {
+		self.collect_directory(curr_path.as_deref())?;
+		if let Some(curr_path) = curr_path {
 			for (index, desc) in self.files.iter().enumerate() {
-				if desc.path.file_name() == curr_filename {
+				if desc.path == curr_path {
 					debug!("Found file the previously 'current' file in the directory.");
 					self.curr_file_idx = index;
 					self.set_image_index_from_file_index();
@@ -256,30 +525,46 @@ impl Directory {
 		Ok(())
 	}
 
-	pub fn collect_directory(&mut self) -> Result<()> {
-		let mut dir_files: Vec<_> = fs::read_dir(&self.path)?
-			.filter_map(|x| match x {
-				Ok(entry) => match entry.file_type() {
-					Ok(file_type) => {
-						if file_type.is_file() || file_type.is_symlink() {
-							self.current_req_id += 1;
-							Some(DirItem { path: entry.path(), request_id: self.current_req_id })
-						} else {
-							None
+	/// Lists the files of the folder again. `keep` is listed even if it's ignored.
+	pub fn collect_directory(&mut self, keep: Option<&Path>) -> Result<()> {
+		let mut dir_files: Vec<_> = if archive::is_archive(&self.path) {
+			let mut paths = archive::list_images(&self.path)?;
+			paths.sort_unstable_by(|a, b| cmp_entry_paths(a, b));
+			paths
+				.into_iter()
+				.map(|path| {
+					self.current_req_id += 1;
+					DirItem { path, request_id: self.current_req_id }
+				})
+				.collect()
+		} else {
+			let mut dir_files: Vec<_> = fs::read_dir(&self.path)?
+				.filter_map(|x| match x {
+					Ok(entry) => match entry.file_type() {
+						Ok(file_type) => {
+							if file_type.is_file() || file_type.is_symlink() {
+								self.current_req_id += 1;
+								Some(DirItem {
+									path: entry.path(),
+									request_id: self.current_req_id,
+								})
+							} else {
+								None
+							}
 						}
-					}
+						Err(_) => None,
+					},
 					Err(_) => None,
-				},
-				Err(_) => None,
-			})
-			.collect();
-
-		dir_files.sort_unstable_by(|a, b| {
-			lexical_sort::natural_lexical_cmp(
-				&a.path.file_name().unwrap().to_string_lossy(),
-				&b.path.file_name().unwrap().to_string_lossy(),
-			)
-		});
+				})
+				.collect();
+			dir_files.sort_unstable_by(|a, b| cmp_file_names(&a.path, &b.path));
+			dir_files
+		};
+		match keep {
+			Some(keep) if self.single_image => dir_files.retain(|item| item.path == keep),
+			_ => dir_files
+				.retain(|item| Some(item.path.as_path()) == keep || !self.is_ignored(&item.path)),
+		}
 
 		// Set the current file index to the first image
 		for (i, item) in dir_files.iter().enumerate() {
@@ -289,6 +574,15 @@ impl Directory {
 			}
 		}
 		self.filter_action.give_input(dir_files.clone());
+		self.shooting_infos_requested = false;
+		self.capture_dates = None;
+		self.capture_dates_requested = false;
+		self.duplicates = None;
+		self.duplicates_requested = false;
+		self.duplicate_finder.cancel();
+		self.thumbnails_requested = false;
+		self.thumbnail_generator.cancel();
+		self.key_sorted = false;
 		self.img_i_to_file_i.clear();
 		self.file_i_to_img_i.clear();
 		self.files = dir_files;
@@ -319,8 +613,220 @@ impl Directory {
 		if let Some(out) = self.filter_action.try_get_output() {
 			self.img_i_to_file_i = out;
 			self.finished_filtering();
+			self.request_sort();
 			return true;
 		}
-		self.filter_action.is_ready()
+		let ready = self.filter_action.is_ready();
+		if ready && self.shooting_infos_requested {
+			if let Some(infos) = self.shooting_info_action.try_get_output() {
+				self.shooting_infos.extend(infos);
+				self.shooting_infos_requested = false;
+				self.sort();
+			}
+		}
+		ready
+	}
+
+	/// Reads the EXIF data that the sort order needs in the background, or sorts right away if
+	/// it's already known
+	fn request_sort(&mut self) {
+		if self.sort_order == SortOrder::Name && !self.key_sorted {
+			return;
+		}
+		let missing: Vec<_> = match self.sort_order {
+			SortOrder::Name => Vec::new(),
+			_ => self
+				.img_i_to_file_i
+				.iter()
+				.map(|&i| &self.files[i].path)
+				.filter(|path| !self.shooting_infos.contains_key(*path))
+				.cloned()
+				.collect(),
+		};
+		if missing.is_empty() {
+			self.sort();
+		} else {
+			self.shooting_info_action.give_input(missing);
+			self.shooting_infos_requested = true;
+		}
+	}
+
+	/// Puts the files into `sort_order`, keeping the current file. Everything that refers to the
+	/// images by their index is started over.
+	fn sort(&mut self) {
+		let images: HashSet<u32> =
+			self.img_i_to_file_i.iter().map(|&i| self.files[i].request_id).collect();
+		let curr_id = self.files.get(self.curr_file_idx).map(|file| file.request_id);
+		let order = self.sort_order;
+		let infos = &self.shooting_infos;
+		let key = |item: &DirItem| infos.get(&item.path).and_then(|info| info.sort_key(order));
+		// The ties are in the order of the listing
+		let cmp_listed =
+			if archive::is_archive(&self.path) { cmp_entry_paths } else { cmp_file_names };
+		self.files.sort_by(|a, b| {
+			cmp_sort_keys(key(a), key(b)).then_with(|| cmp_listed(&a.path, &b.path))
+		});
+		self.key_sorted = order != SortOrder::Name;
+		self.img_i_to_file_i = (self.files.iter().enumerate())
+			.filter(|(_, file)| images.contains(&file.request_id))
+			.map(|(i, _)| i)
+			.collect();
+		if let Some(index) = self.files.iter().position(|file| Some(file.request_id) == curr_id) {
+			self.curr_file_idx = index;
+		}
+		self.finished_filtering();
+		self.capture_dates = None;
+		self.capture_dates_requested = false;
+		self.duplicates = None;
+		self.duplicates_requested = false;
+		self.duplicate_finder.cancel();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::image_cache::image_loader::describe_unsupported;
+
+	/// A new folder in the temporary folder with the given files in it
+	fn folder(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("emulsion-{}-{}", name, std::process::id()));
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(&dir).unwrap();
+		for (file_name, contents) in files {
+			fs::write(dir.join(file_name), contents).unwrap();
+		}
+		dir
+	}
+
+	const MIXED_FILES: &[(&str, &[u8])] = &[
+		// A damaged image is still an image, the decoder reports what's wrong with it
+		("1.png", b"\x89PNG\r\n\x1a\n damaged"),
+		("2.txt", b"notes"),
+		("3.mp4", b"\0\0\0\x18ftypmp42"),
+		("10.jpg", b"\xff\xd8\xff"),
+		("20.txt", b"more notes"),
+	];
+
+	#[test]
+	fn unsupported_files_are_left_out_of_navigation() {
+		let dir = folder("skip-list", MIXED_FILES);
+		let images = list_images(&dir).unwrap();
+		fs::remove_dir_all(&dir).unwrap();
+		assert_eq!(images, [dir.join("1.png"), dir.join("10.jpg")]);
+	}
+
+	#[test]
+	fn skipping_an_unsupported_file_opens_the_next_image() {
+		let dir = folder("skip-next", MIXED_FILES);
+		let after = |name: &str| image_after(&dir.join(name)).unwrap();
+		let (after_text, after_video, after_last) =
+			(after("2.txt"), after("3.mp4"), after("20.txt"));
+		fs::remove_dir_all(&dir).unwrap();
+
+		assert_eq!(after_text, Some(dir.join("10.jpg")));
+		assert_eq!(after_video, Some(dir.join("10.jpg")));
+		// There is nothing after the last file, so it stays on the last image
+		assert_eq!(after_last, Some(dir.join("10.jpg")));
+	}
+
+	#[test]
+	fn skipping_in_a_folder_without_images_opens_nothing() {
+		let dir = folder("skip-none", &[("notes.txt", b"notes")]);
+		let after = image_after(&dir.join("notes.txt")).unwrap();
+		fs::remove_dir_all(&dir).unwrap();
+		assert_eq!(after, None);
+	}
+
+	fn file_names(dir: &Directory) -> Vec<String> {
+		let names = dir.files.iter().map(|item| item.path.file_name().unwrap().to_string_lossy());
+		names.map(|name| name.into_owned()).collect()
+	}
+
+	#[test]
+	fn the_opened_file_is_kept_when_it_is_ignored() {
+		let files: &[(&str, &[u8])] =
+			&[("a.png", b""), ("skip_b.png", b""), ("c.png", b""), (".hidden.png", b"")];
+		let path = folder("keep", files);
+		let mut dir = Directory::new();
+		dir.set_ignore(vec!["skip_*".to_owned()], false);
+		path.clone_into(&mut dir.path);
+
+		dir.collect_directory(None).unwrap();
+		let without_keep = file_names(&dir);
+		dir.collect_directory(Some(&path.join("skip_b.png"))).unwrap();
+		let keeping_ignored = file_names(&dir);
+		dir.collect_directory(Some(&path.join(".hidden.png"))).unwrap();
+		let keeping_hidden = file_names(&dir);
+		fs::remove_dir_all(&path).unwrap();
+
+		assert_eq!(without_keep, ["a.png", "c.png"]);
+		assert_eq!(keeping_ignored, ["a.png", "c.png", "skip_b.png"]);
+		assert_eq!(keeping_hidden, [".hidden.png", "a.png", "c.png"]);
+	}
+
+	/// Waits for the background work of `dir` that `ready` tells about
+	fn wait_for(dir: &mut Directory, mut ready: impl FnMut(&mut Directory) -> bool) {
+		let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+		while !ready(dir) {
+			assert!(std::time::Instant::now() < deadline, "timed out");
+			std::thread::sleep(std::time::Duration::from_millis(5));
+		}
+	}
+
+	#[test]
+	fn archive_folders_are_navigated_one_after_the_other() {
+		let entries: &[(&str, &[u8], u32)] = &[
+			("b/1.png", b"", 0),
+			("a/2.png", b"", 0),
+			("a/1.png", b"", 0),
+			("b/2.png", b"", 0),
+			("a/10.png", b"", 0),
+		];
+		let path = folder("nested-archive", &[]).join("book.cbz");
+		fs::write(&path, archive::tests::stored_zip_entries(entries)).unwrap();
+		let mut dir = Directory::new();
+		path.clone_into(&mut dir.path);
+		dir.collect_directory(None).unwrap();
+		wait_for(&mut dir, Directory::check_filter_ready);
+		let listed = dir.files.iter().map(|item| item.path.clone()).collect::<Vec<_>>();
+		dir.set_sort_order(SortOrder::ExifIso);
+		wait_for(&mut dir, |dir| !dir.sorting());
+		dir.set_sort_order(SortOrder::Name);
+		let sorted_back = dir.files.iter().map(|item| item.path.clone()).collect::<Vec<_>>();
+		fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+		let expected =
+			["a/1.png", "a/2.png", "a/10.png", "b/1.png", "b/2.png"].map(|e| path.join(e));
+		assert_eq!(listed, expected);
+		// None of the entries have EXIF data, so the ties keep the order of the listing
+		assert_eq!(sorted_back, expected);
+	}
+
+	#[test]
+	fn patterns_with_a_slash_match_the_path_within_the_folder() {
+		let mut dir = Directory::new();
+		dir.set_ignore(vec!["covers/*".to_owned()], true);
+		dir.path = PathBuf::from("book.cbz");
+		assert!(dir.is_ignored(Path::new("book.cbz/covers/001.png")));
+		assert!(!dir.is_ignored(Path::new("book.cbz/chapter 1/001.png")));
+		// Without a slash only the name is matched
+		dir.set_ignore(vec!["0*".to_owned()], true);
+		assert!(dir.is_ignored(Path::new("book.cbz/chapter 1/001.png")));
+	}
+
+	#[test]
+	fn unsupported_formats_are_told_apart_from_damaged_images() {
+		let dir = folder("sniff", MIXED_FILES);
+		let supported = |name: &str| is_file_supported(&dir.join(name));
+		let (png, text, video) = (supported("1.png"), supported("2.txt"), supported("3.mp4"));
+		let text_error = describe_unsupported(&dir.join("2.txt"));
+		let video_error = describe_unsupported(&dir.join("3.mp4"));
+		fs::remove_dir_all(&dir).unwrap();
+
+		assert!(png);
+		assert!(!text && !video);
+		assert_eq!(text_error, "Text files are not supported");
+		assert_eq!(video_error, "Videos are not supported");
 	}
 }
@@ -0,0 +1,246 @@
+//! Keeping the decoded pixels of images that are slow to decode on disk.
+//!
+//! The images are stored as deflate compressed RGBA in the cache folder, named after a hash of
+//! the path, the modification time and the size of the file, and of the settings that change how
+//! it's decoded. An image that was changed gets a
+//! different name, so the old entry is never read again and is eventually evicted. Once the
+//! folder grows beyond its limit, the entries that were used least recently are deleted.
+
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use gelatin::image::RgbaImage;
+use log::debug;
+
+use super::decoders;
+use super::image_loader::Orientation;
+use crate::PROJECT_DIRS;
+
+/// Only the images that took longer than this to decode are cached
+pub const MIN_DECODE_TIME: Duration = Duration::from_millis(250);
+/// Changed whenever the layout of the files or the way the images are decoded changes, so that
+/// the old entries aren't used
+const FORMAT_VERSION: u8 = 1;
+const MAGIC: &[u8; 4] = b"EMDC";
+/// Once the limit is exceeded, entries are evicted until the folder is this fraction of it
+const EVICT_TO: f64 = 0.9;
+
+/// The size limit of the cache in bytes, set from `disk_cache_size_mb`. The cache is turned off
+/// when this is zero.
+pub static DISK_CACHE_LIMIT: AtomicU64 = AtomicU64::new(0);
+/// The number of entries that are being written on the background threads
+static PENDING_WRITES: AtomicUsize = AtomicUsize::new(0);
+
+pub fn enabled() -> bool {
+	DISK_CACHE_LIMIT.load(Ordering::Relaxed) > 0
+}
+
+/// The folder of the cache, or `None` if there is no cache folder on this system
+fn cache_dir() -> Option<PathBuf> {
+	PROJECT_DIRS.as_ref().map(|dirs| dirs.cache_dir().join("decoded"))
+}
+
+fn entry_path(dir: &Path, path: &Path, metadata: &fs::Metadata) -> PathBuf {
+	// FNV-1a, because the names have to stay the same across builds
+	let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+	let modified = metadata.modified().ok().and_then(|time| time.duration_since(UNIX_EPOCH).ok());
+	let nanos = modified.map_or(0, |duration| duration.as_nanos());
+	// A different decoder may give different pixels
+	let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("").to_lowercase();
+	let decoder = decoders::preferred_decoder(&ext).unwrap_or_default();
+	let path = path.to_string_lossy();
+	let (nanos, len) = (nanos.to_le_bytes(), metadata.len().to_le_bytes());
+	let bytes = path.as_bytes().iter().chain(nanos.iter()).chain(len.iter());
+	let bytes = bytes.chain(&[0]).chain(decoder.as_bytes());
+	for byte in bytes.chain(&[FORMAT_VERSION]) {
+		hash ^= *byte as u64;
+		hash = hash.wrapping_mul(0x0100_0000_01b3);
+	}
+	dir.join(format!("{:016x}.bin", hash))
+}
+
+/// Reads the cached pixels of the image at `path`, `None` if they aren't cached. `metadata` is
+/// the metadata of the image file.
+pub fn load(path: &Path, metadata: &fs::Metadata) -> Option<(RgbaImage, Orientation)> {
+	if !enabled() {
+		return None;
+	}
+	let entry = entry_path(&cache_dir()?, path, metadata);
+	let file = File::open(&entry).ok()?;
+	let mut reader = BufReader::new(file);
+	let mut magic = [0; 4];
+	let mut width = [0; 4];
+	let mut height = [0; 4];
+	let mut orientation = [0; 1];
+	reader.read_exact(&mut magic).ok()?;
+	if &magic != MAGIC {
+		return None;
+	}
+	reader.read_exact(&mut width).ok()?;
+	reader.read_exact(&mut height).ok()?;
+	reader.read_exact(&mut orientation).ok()?;
+	let (width, height) = (u32::from_le_bytes(width), u32::from_le_bytes(height));
+	let orientation = orientation_from_exif(orientation[0])?;
+	let mut pixels = Vec::new();
+	DeflateDecoder::new(reader).read_to_end(&mut pixels).ok()?;
+	let image = RgbaImage::from_raw(width, height, pixels)?;
+	// The modification time of the entries is when they were last used
+	if let Ok(file) = File::options().write(true).open(&entry) {
+		let _ = file.set_modified(SystemTime::now());
+	}
+	Some((image, orientation))
+}
+
+/// Writes the pixels of the image at `path` to the cache on a background thread, and evicts old
+/// entries if the cache got too large
+pub fn store(path: &Path, metadata: &fs::Metadata, image: RgbaImage, orientation: Orientation) {
+	let Some(dir) = cache_dir().filter(|_| enabled()) else {
+		return;
+	};
+	let entry = entry_path(&dir, path, metadata);
+	PENDING_WRITES.fetch_add(1, Ordering::SeqCst);
+	std::thread::spawn(move || {
+		match write_entry(&dir, &entry, &image, orientation) {
+			Ok(()) => evict(&dir, DISK_CACHE_LIMIT.load(Ordering::Relaxed)),
+			Err(e) => {
+				debug!("Could not write the cache entry {:?}: {}", entry, e);
+				let _ = fs::remove_file(entry.with_extension("partial"));
+			}
+		}
+		PENDING_WRITES.fetch_sub(1, Ordering::SeqCst);
+	});
+}
+
+/// Waits for the entries that are being written, until `deadline`. Returns false if some of
+/// them weren't done in time. Those are never read, because an entry only gets its name once
+/// it's complete.
+pub fn wait_for_writes(deadline: Instant) -> bool {
+	while PENDING_WRITES.load(Ordering::SeqCst) > 0 {
+		if Instant::now() >= deadline {
+			return false;
+		}
+		std::thread::sleep(Duration::from_millis(10));
+	}
+	true
+}
+
+fn write_entry(
+	dir: &Path,
+	entry: &Path,
+	image: &RgbaImage,
+	orientation: Orientation,
+) -> std::io::Result<()> {
+	fs::create_dir_all(dir)?;
+	// Written under a temporary name first, so that a reader never sees half a file
+	let partial = entry.with_extension("partial");
+	let mut writer = BufWriter::new(File::create(&partial)?);
+	writer.write_all(MAGIC)?;
+	writer.write_all(&image.width().to_le_bytes())?;
+	writer.write_all(&image.height().to_le_bytes())?;
+	writer.write_all(&[orientation_to_exif(orientation)])?;
+	let mut encoder = DeflateEncoder::new(writer, Compression::fast());
+	encoder.write_all(image.as_raw())?;
+	encoder.finish()?.flush()?;
+	fs::rename(&partial, entry)
+}
+
+/// The entries of the cache with their sizes and the time they were last used
+fn entries(dir: &Path) -> Vec<(PathBuf, u64, SystemTime)> {
+	let Ok(read_dir) = fs::read_dir(dir) else {
+		return Vec::new();
+	};
+	read_dir
+		.filter_map(|entry| {
+			let entry = entry.ok()?;
+			let metadata = entry.metadata().ok()?;
+			let used = metadata.modified().unwrap_or(UNIX_EPOCH);
+			metadata.is_file().then(|| (entry.path(), metadata.len(), used))
+		})
+		.collect()
+}
+
+fn evict(dir: &Path, limit: u64) {
+	let mut entries = entries(dir);
+	let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+	if total <= limit {
+		return;
+	}
+	entries.sort_by_key(|(_, _, used)| *used);
+	let target = (limit as f64 * EVICT_TO) as u64;
+	for (path, size, _) in entries {
+		if total <= target {
+			break;
+		}
+		if fs::remove_file(&path).is_ok() {
+			total -= size;
+		}
+	}
+}
+
+/// Deletes every entry of the cache, and returns how many bytes were freed
+pub fn clear() -> u64 {
+	let Some(dir) = cache_dir() else {
+		return 0;
+	};
+	entries(&dir)
+		.into_iter()
+		.filter(|(path, _, _)| fs::remove_file(path).is_ok())
+		.map(|(_, size, _)| size)
+		.sum()
+}
+
+fn orientation_to_exif(orientation: Orientation) -> u8 {
+	match orientation {
+		Orientation::Deg0 => 1,
+		Orientation::Deg0HorFlip => 2,
+		Orientation::Deg180 => 3,
+		Orientation::Deg180HorFlip => 4,
+		Orientation::Deg90VerFlip => 5,
+		Orientation::Deg270 => 6,
+		Orientation::Deg270VerFlip => 7,
+		Orientation::Deg90 => 8,
+	}
+}
+
+fn orientation_from_exif(value: u8) -> Option<Orientation> {
+	Some(match value {
+		1 => Orientation::Deg0,
+		2 => Orientation::Deg0HorFlip,
+		3 => Orientation::Deg180,
+		4 => Orientation::Deg180HorFlip,
+		5 => Orientation::Deg90VerFlip,
+		6 => Orientation::Deg270,
+		7 => Orientation::Deg270VerFlip,
+		8 => Orientation::Deg90,
+		_ => return None,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::collections::BTreeMap;
+
+	#[test]
+	fn entries_depend_on_the_preferred_decoder() {
+		let file =
+			std::env::temp_dir().join(format!("emulsion-cache-key-{}.tif", std::process::id()));
+		fs::write(&file, b"pixels").unwrap();
+		let metadata = fs::metadata(&file).unwrap();
+		let dir = Path::new("cache");
+
+		let default_entry = entry_path(dir, &file, &metadata);
+		assert_eq!(entry_path(dir, &file, &metadata), default_entry);
+		let preferred = BTreeMap::from([("tif".to_owned(), "image".to_owned())]);
+		decoders::set_preferred_decoders(&preferred);
+		let preferred_entry = entry_path(dir, &file, &metadata);
+		decoders::set_preferred_decoders(&BTreeMap::new());
+		fs::remove_file(&file).unwrap();
+
+		assert_ne!(preferred_entry, default_entry);
+	}
+}
@@ -0,0 +1,182 @@
+//! Finding the images of a folder that look alike.
+//!
+//! Each image is reduced to a difference hash: the image is shrunk to 9×8 pixels, and each bit
+//! of the hash tells whether a pixel is brighter than its neighbour on the right. Resized,
+//! recompressed or slightly edited copies of an image get the same hash, or one that differs in
+//! only a few bits.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use gelatin::image::{imageops, RgbaImage};
+use log::debug;
+
+use super::image_loader::{complex_load_image, LoadResult};
+use super::source;
+
+/// Two images are similar if their hashes differ in at most this many bits
+const MAX_HASH_DISTANCE: u32 = 5;
+
+/// The groups of similar images in a folder
+#[derive(Debug, Default)]
+pub struct Duplicates {
+	groups: Vec<Vec<usize>>,
+	/// The group of each image, indexed by the image index
+	group_of_image: Vec<Option<usize>>,
+}
+
+impl Duplicates {
+	/// Returns the indices of all images in the group of the image at `index`, including
+	/// `index` itself, in ascending order. Returns `None` if there are no similar images.
+	pub fn group_of(&self, index: usize) -> Option<&[usize]> {
+		let group = (*self.group_of_image.get(index)?)?;
+		Some(&self.groups[group])
+	}
+
+	/// Returns the next image in the group of the image at `index`. If the image has no similar
+	/// images, returns the first image after it that has.
+	pub fn next_duplicate(&self, index: usize) -> Option<usize> {
+		if let Some(group) = self.group_of(index) {
+			let position = group.iter().position(|&i| i == index)?;
+			return Some(group[(position + 1) % group.len()]);
+		}
+		let count = self.group_of_image.len();
+		(1..count).map(|offset| (index + offset) % count).find(|&i| self.group_of(i).is_some())
+	}
+
+	fn from_hashes(hashes: &[Option<u64>]) -> Duplicates {
+		// Union-find over the pairs of similar images
+		let mut parents: Vec<usize> = (0..hashes.len()).collect();
+		fn root(parents: &mut [usize], mut i: usize) -> usize {
+			while parents[i] != i {
+				parents[i] = parents[parents[i]];
+				i = parents[i];
+			}
+			i
+		}
+		for (i, a) in hashes.iter().enumerate() {
+			let Some(a) = a else { continue };
+			for (j, b) in hashes.iter().enumerate().skip(i + 1) {
+				let Some(b) = b else { continue };
+				if (a ^ b).count_ones() <= MAX_HASH_DISTANCE {
+					let (root_i, root_j) = (root(&mut parents, i), root(&mut parents, j));
+					parents[root_j] = root_i;
+				}
+			}
+		}
+		let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+		for i in 0..hashes.len() {
+			let root = root(&mut parents, i);
+			members.entry(root).or_default().push(i);
+		}
+		let mut groups: Vec<Vec<usize>> =
+			members.into_values().filter(|group| group.len() > 1).collect();
+		groups.sort_unstable_by_key(|group| group[0]);
+		let mut group_of_image = vec![None; hashes.len()];
+		for (group_index, group) in groups.iter().enumerate() {
+			for &i in group {
+				group_of_image[i] = Some(group_index);
+			}
+		}
+		Duplicates { groups, group_of_image }
+	}
+}
+
+fn difference_hash(path: &Path) -> Option<u64> {
+	let mut image: Option<RgbaImage> = None;
+	complex_load_image(path, 1, 0, |frame| {
+		if let LoadResult::Frame { image: frame_image, .. } = frame {
+			image = Some(frame_image);
+		}
+		Ok(())
+	})
+	.ok()?;
+	let small = imageops::thumbnail(&image?, 9, 8);
+	let luma = |x: u32, y: u32| {
+		let [r, g, b, _] = small.get_pixel(x, y).0;
+		299 * r as u32 + 587 * g as u32 + 114 * b as u32
+	};
+	let mut hash = 0u64;
+	for y in 0..8 {
+		for x in 0..8 {
+			hash = (hash << 1) | (luma(x, y) > luma(x + 1, y)) as u64;
+		}
+	}
+	Some(hash)
+}
+
+struct Job {
+	generation: u64,
+	paths: Vec<PathBuf>,
+}
+
+/// Hashes the images of a folder on a background thread.
+///
+/// Starting a new folder cancels the previous one. The hashes are kept for the whole session,
+/// keyed by the path and the modification time of the file, so returning to a folder is fast.
+pub struct DuplicateFinder {
+	generation: Arc<AtomicU64>,
+	job_sender: Sender<Job>,
+	result_receiver: Receiver<(u64, Duplicates)>,
+}
+
+impl DuplicateFinder {
+	pub fn new() -> DuplicateFinder {
+		let generation = Arc::new(AtomicU64::new(0));
+		let (job_sender, job_receiver) = channel::<Job>();
+		let (result_sender, result_receiver) = channel();
+		let current_generation = generation.clone();
+		std::thread::spawn(move || {
+			let mut hash_cache: HashMap<PathBuf, (Option<SystemTime>, Option<u64>)> =
+				HashMap::new();
+			'jobs: for job in job_receiver.iter() {
+				let mut hashes = Vec::with_capacity(job.paths.len());
+				for path in job.paths {
+					if current_generation.load(Ordering::SeqCst) != job.generation {
+						debug!("Cancelled looking for duplicates");
+						continue 'jobs;
+					}
+					let modified = source::metadata(&path).and_then(|m| m.modified()).ok();
+					let hash = match hash_cache.get(&path) {
+						Some(&(cached_modified, hash)) if cached_modified == modified => hash,
+						_ => {
+							let hash = difference_hash(&path);
+							hash_cache.insert(path, (modified, hash));
+							hash
+						}
+					};
+					hashes.push(hash);
+				}
+				let duplicates = Duplicates::from_hashes(&hashes);
+				if result_sender.send((job.generation, duplicates)).is_err() {
+					break;
+				}
+			}
+		});
+		DuplicateFinder { generation, job_sender, result_receiver }
+	}
+
+	/// Starts looking for similar images among `paths`, and cancels the previous search
+	pub fn start(&self, paths: Vec<PathBuf>) {
+		let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+		let _ = self.job_sender.send(Job { generation, paths });
+	}
+
+	pub fn cancel(&self) {
+		self.generation.fetch_add(1, Ordering::SeqCst);
+	}
+
+	/// Returns the result of the latest search once it's done
+	pub fn try_get_result(&self) -> Option<Duplicates> {
+		let current = self.generation.load(Ordering::SeqCst);
+		self.result_receiver
+			.try_iter()
+			.filter(|(generation, _)| *generation == current)
+			.last()
+			.map(|(_, duplicates)| duplicates)
+	}
+}
@@ -1,18 +1,24 @@
 use std::borrow::Cow;
 use std::fs;
-use std::io::{BufReader, Read};
+use std::io::Read;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Instant;
 
 use gelatin::image::{
 	self,
-	codecs::{gif::GifDecoder, png::PngDecoder},
-	AnimationDecoder, ImageFormat,
+	imageops::{
+		flip_horizontal_in_place, flip_vertical_in_place, rotate180_in_place, rotate270, rotate90,
+	},
+	DynamicImage,
 };
-use usvg::fontdb;
+
+use super::decoders::{self, find_decoders, DecodedFrame, DecodedImage, Decoder, Rgba16Image};
+use super::{disk_cache, source, srgb_decode};
 
 #[derive(Debug, thiserror::Error)]
 #[error("error happened in the image_loader: {description}")]
@@ -44,11 +50,36 @@ pub type Result<T> = std::result::Result<T, ImageLoaderError>;
 pub static PRIORITY_REQUEST_ID: AtomicU32 = AtomicU32::new(0); // The first request usually
 pub const NON_EXISTENT_REQUEST_ID: u32 = u32::MAX;
 
-pub enum ImgFormat {
-	Image(ImageFormat),
-	Svg,
+/// The number of images that the loader threads are decoding right now. The thumbnail generator
+/// waits until this is zero so that it doesn't slow down the prefetching.
+pub static ACTIVE_LOADS: AtomicU32 = AtomicU32::new(0);
+
+const MIN_DEFAULT_DECODE_THREADS: u32 = 2;
+const MAX_DEFAULT_DECODE_THREADS: u32 = 8;
+const MAX_DECODE_THREADS: u32 = 64;
+
+/// The number of loader threads for the `decode_threads` value of the configuration
+pub fn decode_thread_count(configured: Option<u32>) -> u32 {
+	match configured {
+		Some(threads) => threads.clamp(1, MAX_DECODE_THREADS),
+		None => {
+			let cores = thread::available_parallelism().map(|n| n.get() as u32).unwrap_or(4);
+			(cores / 2).clamp(MIN_DEFAULT_DECODE_THREADS, MAX_DEFAULT_DECODE_THREADS)
+		}
+	}
 }
 
+/// True if the window can display colors brighter than white. In this case HDR images are
+/// also loaded with their full range. See `LoadResult::Frame`
+pub static HDR_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// True if the images are shown in front of a blurred copy of themselves. See
+/// `LoadResult::Frame`
+pub static MAKE_BACKDROPS: AtomicBool = AtomicBool::new(false);
+
+/// The size of the longer side of a backdrop in pixels
+const BACKDROP_SIZE: u32 = 48;
+
 /// These values define the transformation for a pixel array which is to be displayed.
 ///
 /// The default case is when the 0th row is at the top and the 0th column is at the left side of the
@@ -83,31 +114,31 @@ pub enum Orientation {
 	Deg90,
 }
 
-/// Detects the format of an image file. It looks at the first 512 bytes;
-/// if that fails, it uses the file ending.
-pub fn detect_format(path: &Path) -> Result<ImgFormat> {
-	let mut file = fs::File::open(path)?;
-	let mut file_start_bytes = [0; 512];
+/// Finds the decoder for an image file. See the `decoders` module for how the
+/// format is detected.
+pub fn detect_decoder(path: &Path) -> Result<Arc<dyn Decoder>> {
+	Ok(detect_decoders(path)?.swap_remove(0))
+}
 
-	// Try to detect the format from the first 512 bytes
-	if file.read_exact(&mut file_start_bytes).is_ok() {
-		if path.extension() == Some(std::ffi::OsStr::new("svg")) {
-			return Ok(ImgFormat::Svg);
-		}
-		if let Ok(format) = image::guess_format(&file_start_bytes) {
-			return Ok(ImgFormat::Image(format));
-		}
+/// Finds the decoders for an image file, in the order in which they should be tried. The list
+/// is never empty.
+pub fn detect_decoders(path: &Path) -> Result<Vec<Arc<dyn Decoder>>> {
+	let mut magic = Vec::with_capacity(512);
+	source::open(path)?.take(512).read_to_end(&mut magic)?;
+	let ext = path.extension().and_then(|ext| ext.to_str()).unwrap_or("").to_lowercase();
+	let decoders = find_decoders(&magic, &ext);
+	if decoders.is_empty() {
+		return Err(ImageLoaderError {
+			description: format!("The format of {:?} is not supported", path).into(),
+		});
 	}
-
-	// If that didn't work, try to detect the format from the file ending
-	Ok(ImgFormat::Image(ImageFormat::from_path(path)?))
+	Ok(decoders)
 }
 
 pub fn detect_orientation(path: &Path) -> std::result::Result<Orientation, exif::Error> {
-	let file = std::fs::File::open(path)?;
-	let mut bufreader = std::io::BufReader::new(&file);
+	let mut reader = source::open(path)?;
 	let exifreader = exif::Reader::new();
-	let exif = exifreader.read_from_container(&mut bufreader)?;
+	let exif = exifreader.read_from_container(&mut reader)?;
 	if let Some(orientation) = exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY) {
 		if let exif::Value::Short(ref shorts) = orientation.value {
 			if let Some(&exif_orientation) = shorts.first() {
@@ -137,146 +168,309 @@ pub fn detect_orientation(path: &Path) -> std::result::Result<Orientation, exif:
 	}
 }
 
-pub fn simple_load_image(path: &Path, image_format: ImageFormat) -> Result<image::RgbaImage> {
-	let reader = BufReader::new(fs::File::open(path)?);
-	Ok(image::load(reader, image_format)?.into_rgba8())
+/// Returns the image transformed so that it's displayed correctly without any further rotation or
+/// flipping.
+pub fn apply_orientation(
+	mut image: image::RgbaImage,
+	orientation: Orientation,
+) -> image::RgbaImage {
+	// Note: the imageops functions use clockwise rotation whereas the
+	// `Orientation` type describes counter-clockwise rotation.
+	match orientation {
+		Orientation::Deg0 => image,
+		Orientation::Deg0HorFlip => {
+			flip_horizontal_in_place(&mut image);
+			image
+		}
+		Orientation::Deg90 => rotate270(&image),
+		Orientation::Deg90VerFlip => {
+			let mut result = rotate270(&image);
+			flip_vertical_in_place(&mut result);
+			result
+		}
+		Orientation::Deg180 => {
+			rotate180_in_place(&mut image);
+			image
+		}
+		Orientation::Deg180HorFlip => {
+			// This is identical to just a vertical flip with no rotation.
+			flip_vertical_in_place(&mut image);
+			image
+		}
+		Orientation::Deg270 => rotate90(&image),
+		Orientation::Deg270VerFlip => {
+			let mut result = rotate90(&image);
+			flip_vertical_in_place(&mut result);
+			result
+		}
+	}
 }
 
-/// Returns an iterator over the animation frames of a GIF file
-pub fn load_gif(path: &Path, req_id: u32) -> Result<impl Iterator<Item = Result<LoadResult>>> {
-	let file = fs::File::open(path)?;
-	let reader = BufReader::new(file);
-	let decoder = GifDecoder::new(reader)?;
-	Ok(load_animation(req_id, decoder))
+fn make_backdrop(image: &image::RgbaImage) -> image::RgbaImage {
+	let (w, h) = image.dimensions();
+	let scale = BACKDROP_SIZE as f32 / w.max(h) as f32;
+	let small_w = ((w as f32 * scale).round() as u32).max(1);
+	let small_h = ((h as f32 * scale).round() as u32).max(1);
+	let small = image::imageops::thumbnail(image, small_w, small_h);
+	let mut backdrop = image::imageops::blur(&small, 2.0);
+	// The backdrop is drawn with the image shader, which would show the checkerboard through
+	// the transparent parts
+	for pixel in backdrop.pixels_mut() {
+		pixel.0[3] = 255;
+	}
+	backdrop
 }
 
-/// Parse, render and gather an SVG into a ImageBuffer<Rgba>
-pub fn load_svg(path: &std::path::Path) -> Result<image::RgbaImage> {
-	let svg_data = fs::read(path)?;
-	let rtree = {
-		let opt = usvg::Options {
-			resources_dir: std::fs::canonicalize(path)
-				.ok()
-				.and_then(|p| p.parent().map(|p| p.to_path_buf())),
-			..Default::default()
-		};
-
-		let mut fontdb = fontdb::Database::new();
-		fontdb.load_system_fonts();
-
-		usvg::Tree::from_data(&svg_data, &opt, &fontdb)?
-	};
-	let width = rtree.size().width();
-	let height = rtree.size().height();
-	// Scale to fit 4096
-	let zoom = 4096. / width.max(height);
-	let (width, height) = ((width * zoom) as u32, (height * zoom) as u32);
-	// These unwrapped Options are fine as long as the dimensions are correct
-	let mut pixmap = tiny_skia::Pixmap::new(width, height).unwrap();
-	let transform = tiny_skia::Transform::from_scale(zoom, zoom);
-	resvg::render(&rtree, transform, &mut pixmap.as_mut());
-	Ok(image::RgbaImage::from_raw(width, height, pixmap.take()).unwrap())
+/// The largest JPEG preview in the EXIF data of the file, or `None` if there's none. Photos
+/// usually have a small one, and raw files a larger one.
+fn embedded_preview(path: &Path) -> Option<image::RgbaImage> {
+	let mut reader = source::open(path).ok()?;
+	let exif = exif::Reader::new().read_from_container(&mut reader).ok()?;
+	let previews = [exif::In::PRIMARY, exif::In::THUMBNAIL].iter().filter_map(|&ifd| {
+		let offset = exif.get_field(exif::Tag::JPEGInterchangeFormat, ifd)?.value.get_uint(0)?;
+		let len = exif.get_field(exif::Tag::JPEGInterchangeFormatLength, ifd)?.value.get_uint(0)?;
+		exif.buf().get(offset as usize..offset as usize + len as usize)
+	});
+	let largest = previews.max_by_key(|bytes| bytes.len())?;
+	let image = image::load_from_memory_with_format(largest, image::ImageFormat::Jpeg).ok()?;
+	Some(image.into_rgba8())
+}
+
+/// Shrinks the image to fit into a square of `max_side` pixels and to have at most `max_pixels`
+/// pixels, as the request asks. Returns the original size if it was shrunk.
+fn downscale(image: &mut image::RgbaImage, request: &LoadRequest) -> Option<(u32, u32)> {
+	let (w, h) = image.dimensions();
+	let side_scale = request.max_side.map_or(1.0, |max_side| max_side as f64 / w.max(h) as f64);
+	let pixel_scale = request
+		.max_pixels
+		.map_or(1.0, |max_pixels| (max_pixels as f64 / (w as f64 * h as f64)).sqrt());
+	let scale = side_scale.min(pixel_scale);
+	if scale >= 1.0 {
+		return None;
+	}
+	// Rounded down, so that the image never has more pixels than it's allowed
+	let small_w = ((w as f64 * scale).floor() as u32).max(1);
+	let small_h = ((h as f64 * scale).floor() as u32).max(1);
+	*image = image::imageops::thumbnail(image, small_w, small_h);
+	Some((w, h))
+}
+
+/// Returns the linear values of an sRGB image with 16 bits per channel
+fn linearize(image: &Rgba16Image) -> image::Rgba32FImage {
+	let table: Vec<f32> = (0..=u16::MAX).map(|v| srgb_decode(v as f32 / 65535.0)).collect();
+	let pixels = image
+		.as_raw()
+		.chunks_exact(4)
+		.flat_map(|p| {
+			[
+				table[p[0] as usize],
+				table[p[1] as usize],
+				table[p[2] as usize],
+				p[3] as f32 / 65535.0,
+			]
+		})
+		.collect();
+	let (w, h) = image.dimensions();
+	image::Rgba32FImage::from_raw(w, h, pixels).unwrap()
 }
 
+/// Decodes the image at `path` and passes its frames to `process_image`. Animations stop after
+/// `max_frames` frames.
 pub fn complex_load_image<F>(
 	path: &Path,
-	allow_animation: bool,
+	max_frames: usize,
 	req_id: u32,
 	mut process_image: F,
 ) -> Result<()>
 where
 	F: FnMut(LoadResult) -> Result<()>,
 {
-	let image_format = detect_format(path)?;
 	let orientation = detect_orientation(path).unwrap_or(Orientation::Deg0);
-
-	match image_format {
-		ImgFormat::Image(ImageFormat::Gif) => {
-			let mut frames = load_gif(path, req_id)?;
-			if allow_animation {
-				for frame in frames {
-					process_image(frame?)?;
-				}
-			} else if let Some(frame) = frames.next() {
-				process_image(frame?)?;
+	let mut decoded = None;
+	let mut first_error = None;
+	let decoders = detect_decoders(path)?;
+	for (i, decoder) in decoders.iter().enumerate() {
+		match decoder.decode_file(path) {
+			Ok(image) => {
+				decoded = Some((decoder.name(), image));
+				break;
 			}
-		}
-		ImgFormat::Image(ImageFormat::Png) => {
-			let file = fs::File::open(path)?;
-			let reader = BufReader::new(file);
-			let decoder = PngDecoder::new(reader)?;
-			if decoder.is_apng()? {
-				let mut animation = load_animation(req_id, decoder.apng()?);
-				if allow_animation {
-					for frame in animation {
-						process_image(frame?)?;
-					}
-				} else if let Some(frame) = animation.next() {
-					process_image(frame?)?;
+			Err(error) => {
+				if i + 1 < decoders.len() {
+					eprintln!(
+						"The {} decoder could not decode {:?}, trying the next one\n    {}",
+						decoder.name(),
+						path,
+						error
+					);
 				}
-			} else {
-				let image = simple_load_image(path, ImageFormat::Png)?;
-				process_image(LoadResult::Frame { req_id, image, delay_nano: 0, orientation })?;
+				first_error.get_or_insert(error);
 			}
 		}
-		ImgFormat::Image(image_format) => {
-			let image = simple_load_image(path, image_format)?;
-			process_image(LoadResult::Frame { req_id, image, delay_nano: 0, orientation })?;
+	}
+	let (decoder, decoded) = match decoded {
+		Some(decoded) => decoded,
+		None => return Err(first_error.expect("There is at least one decoder")),
+	};
+
+	match decoded {
+		DecodedImage::Still(image) => {
+			let hdr_image = None;
+			process_image(LoadResult::Frame {
+				req_id,
+				image,
+				hdr_image,
+				deep_image: None,
+				backdrop: None,
+				delay_nano: 0,
+				orientation,
+				bit_depth: 8,
+				truncated: false,
+				downscaled_from: None,
+				preview: false,
+				decoder,
+			})?;
+		}
+		DecodedImage::Partial(image) => {
+			eprintln!("{:?} is truncated or damaged, showing the part that could be decoded", path);
+			process_image(LoadResult::Frame {
+				req_id,
+				image,
+				hdr_image: None,
+				deep_image: None,
+				backdrop: None,
+				delay_nano: 0,
+				orientation,
+				bit_depth: 8,
+				truncated: true,
+				downscaled_from: None,
+				preview: false,
+				decoder,
+			})?;
+		}
+		DecodedImage::StillDeep(image) => {
+			let image = DynamicImage::ImageRgba16(image);
+			let sdr_image = image.to_rgba8();
+			process_image(LoadResult::Frame {
+				req_id,
+				image: sdr_image,
+				hdr_image: None,
+				deep_image: Some(image.into_rgba16()),
+				backdrop: None,
+				delay_nano: 0,
+				orientation,
+				bit_depth: 16,
+				truncated: false,
+				downscaled_from: None,
+				preview: false,
+				decoder,
+			})?;
+		}
+		DecodedImage::StillHdr(image) => {
+			let image = DynamicImage::ImageRgba32F(image);
+			let sdr_image = image.to_rgba8();
+			let hdr_image = HDR_OUTPUT.load(Ordering::Relaxed).then(|| image.into_rgba32f());
+			process_image(LoadResult::Frame {
+				req_id,
+				image: sdr_image,
+				hdr_image,
+				deep_image: None,
+				backdrop: None,
+				delay_nano: 0,
+				orientation,
+				bit_depth: 32,
+				truncated: false,
+				downscaled_from: None,
+				preview: false,
+				decoder,
+			})?;
 		}
-		ImgFormat::Svg => {
-			let image = load_svg(path)?;
-			process_image(LoadResult::Frame { req_id, image, delay_nano: 0, orientation })?;
+		DecodedImage::Animation(frames) => {
+			for frame in frames.take(max_frames) {
+				let DecodedFrame { image, delay_nano } = frame?;
+				let orientation = Orientation::Deg0;
+				let hdr_image = None;
+				process_image(LoadResult::Frame {
+					req_id,
+					image,
+					hdr_image,
+					deep_image: None,
+					backdrop: None,
+					delay_nano,
+					orientation,
+					bit_depth: 8,
+					truncated: false,
+					downscaled_from: None,
+					preview: false,
+					decoder,
+				})?;
+			}
 		}
 	}
 
 	Ok(())
 }
 
-fn load_animation(
-	req_id: u32,
-	decoder: impl AnimationDecoder<'static>,
-) -> impl Iterator<Item = Result<LoadResult>> {
-	let frames = decoder.into_frames();
-
-	frames.map(move |frame| {
-		Ok(frame.map(|frame| {
-			let (mut numerator_ms, mut denom) = frame.delay().numer_denom_ms();
-			if numerator_ms == 0 {
-				// Some animated gifs specify a 0 inter-frame delay, but
-				// most gif viewers interpret this as a 0.1 second delay.
-				numerator_ms = 100;
-				denom = 1;
-			}
-			let numerator_nano = numerator_ms as u64 * 1_000_000;
-			let delay_nano = numerator_nano / (denom as u64);
-			let image = frame.into_buffer();
-			LoadResult::Frame { req_id, image, delay_nano, orientation: Orientation::Deg0 }
-		})?)
-	})
+/// Returns true if the file ending is one of the known image formats
+pub fn is_extension_supported(filename: &Path) -> bool {
+	match filename.extension().and_then(|ext| ext.to_str()) {
+		Some(ext) => decoders::is_extension_supported(&ext.to_lowercase()),
+		None => false,
+	}
 }
 
 pub fn is_file_supported(filename: &Path) -> bool {
-	if let Some(ext) = filename.extension() {
-		if let Some(ext) = ext.to_str() {
-			let ext = ext.to_lowercase();
-			match ext.as_str() {
-				"jpg" | "jpeg" | "png" | "apng" | "gif" | "webp" | "tif" | "tiff" | "tga"
-				| "bmp" | "ico" | "hdr" | "pbm" | "pam" | "ppm" | "pgm" => {
-					return true;
-				}
-				#[cfg(feature = "avif")]
-				"avif" => return true,
-				_ => (),
-			}
+	is_extension_supported(filename) || detect_decoder(filename).is_ok()
+}
+
+/// Explains why a file for which `is_file_supported` is false can't be opened, naming its
+/// format if it's a common one
+pub fn describe_unsupported(path: &Path) -> String {
+	let mut magic = Vec::with_capacity(512);
+	if let Ok(reader) = source::open(path) {
+		let _ = reader.take(512).read_to_end(&mut magic);
+	}
+	let format = match magic.as_slice() {
+		[] => Some("It's an empty file"),
+		[b'%', b'P', b'D', b'F', ..] => Some("PDF documents are not supported"),
+		[_, _, _, _, b'f', b't', b'y', b'p', b'a', b'v', b'i', b'f', ..] => {
+			Some("This build of emulsion can't open AVIF images")
+		}
+		[_, _, _, _, b'f', b't', b'y', b'p', b'h', b'e', b'i', _, ..]
+		| [_, _, _, _, b'f', b't', b'y', b'p', b'm', b'i', b'f', b'1', ..] => {
+			Some("HEIF images are not supported")
+		}
+		[_, _, _, _, b'f', b't', b'y', b'p', ..] => Some("Videos are not supported"),
+		[0x1A, 0x45, 0xDF, 0xA3, ..] => Some("Videos are not supported"),
+		[b'R', b'I', b'F', b'F', _, _, _, _, b'A', b'V', b'I', ..] => {
+			Some("Videos are not supported")
+		}
+		[b'I', b'D', b'3', ..] | [b'O', b'g', b'g', b'S', ..] | [b'f', b'L', b'a', b'C', ..] => {
+			Some("Audio files are not supported")
 		}
+		bytes if !bytes.contains(&0) && std::str::from_utf8(bytes).is_ok() => {
+			Some("Text files are not supported")
+		}
+		_ => None,
+	};
+	match (format, path.extension()) {
+		(Some(format), _) => format.to_owned(),
+		(None, Some(ext)) => format!(".{} files are not supported", ext.to_string_lossy()),
+		(None, None) => "The format of the file is not supported".to_owned(),
 	}
-	detect_format(filename).is_ok()
 }
 
 #[derive(Debug, Clone)]
 pub struct LoadRequest {
 	pub req_id: u32,
 	pub path: PathBuf,
+	/// Larger images are shrunk to fit into a square of this many pixels, in the low memory
+	/// mode
+	pub max_side: Option<u32>,
+	/// Larger images are shrunk to this many pixels, from `max_decode_megapixels`
+	pub max_pixels: Option<u64>,
+	/// Send the preview that is embedded in the file first, if it has one
+	pub preview: bool,
 }
 
 pub enum LoadResult {
@@ -287,16 +481,40 @@ pub enum LoadResult {
 	Frame {
 		req_id: u32,
 		image: image::RgbaImage,
+		/// The linear pixel values of an HDR image. Only provided when `HDR_OUTPUT` is set, but
+		/// `image` holds the clamped version of the image either way. The image loader also
+		/// provides it for images with 16 bits per channel, from `deep_image`.
+		hdr_image: Option<image::Rgba32FImage>,
+		/// The pixels of an image with 16 bits per channel, which `image` holds truncated to 8
+		/// bits
+		deep_image: Option<Rgba16Image>,
+		/// A small blurred copy of the image, to be stretched behind it. Only provided by the
+		/// image cache when `MAKE_BACKDROPS` is set.
+		backdrop: Option<image::RgbaImage>,
 		delay_nano: u64,
 
 		/// How much does the image need to be rotated counter-clockwise to be shown correctly
 		orientation: Orientation,
+		/// The number of bits per channel in the file, 32 for floating point images
+		bit_depth: u8,
+		/// The file is truncated or damaged, and `image` holds the part that could be decoded
+		truncated: bool,
+		/// The size of the image in the file, if `image` was shrunk to save memory. The high
+		/// precision copies of the image are dropped in that case.
+		downscaled_from: Option<(u32, u32)>,
+		/// `image` is the preview that is embedded in the file. It's followed by the decoded
+		/// image, which replaces it.
+		preview: bool,
+		/// The name of the decoder, see `Decoder::name`. It's "disk cache" when the pixels were
+		/// read from the disk cache, and "embedded preview" for the preview.
+		decoder: &'static str,
 	},
 	Done {
 		req_id: u32,
 	},
 	Failed {
 		req_id: u32,
+		error: String,
 	},
 }
 
@@ -376,7 +594,9 @@ impl ImageLoader {
 					continue;
 				}
 			};
+			ACTIVE_LOADS.fetch_add(1, Ordering::SeqCst);
 			Self::load_and_send(&img_sender, request);
+			ACTIVE_LOADS.fetch_sub(1, Ordering::SeqCst);
 		}
 	}
 
@@ -390,24 +610,117 @@ impl ImageLoader {
 
 	fn load_and_send(img_sender: &Sender<LoadResult>, request: LoadRequest) {
 		fn try_load_and_send(img_sender: &Sender<LoadResult>, request: &LoadRequest) -> Result<()> {
-			let metadata = fs::metadata(&request.path)?;
-			img_sender.send(LoadResult::Start { req_id: request.req_id, metadata }).unwrap();
-			complex_load_image(&request.path, true, request.req_id, |frame| {
+			let metadata = source::metadata(&request.path)?;
+			let start = LoadResult::Start { req_id: request.req_id, metadata: metadata.clone() };
+			img_sender.send(start).unwrap();
+			if let Some((mut image, orientation)) = disk_cache::load(&request.path, &metadata) {
+				let downscaled_from = downscale(&mut image, request);
+				let backdrop =
+					MAKE_BACKDROPS.load(Ordering::Relaxed).then(|| make_backdrop(&image));
+				img_sender
+					.send(LoadResult::Frame {
+						req_id: request.req_id,
+						image,
+						hdr_image: None,
+						deep_image: None,
+						backdrop,
+						delay_nano: 0,
+						orientation,
+						bit_depth: 8,
+						truncated: false,
+						downscaled_from,
+						preview: false,
+						decoder: "disk cache",
+					})
+					.unwrap();
+				return Ok(());
+			}
+			if let Some(mut image) =
+				request.preview.then(|| embedded_preview(&request.path)).flatten()
+			{
+				let downscaled_from = downscale(&mut image, request);
+				let backdrop =
+					MAKE_BACKDROPS.load(Ordering::Relaxed).then(|| make_backdrop(&image));
+				img_sender
+					.send(LoadResult::Frame {
+						req_id: request.req_id,
+						image,
+						hdr_image: None,
+						deep_image: None,
+						backdrop,
+						delay_nano: 0,
+						orientation: detect_orientation(&request.path).unwrap_or_default(),
+						bit_depth: 8,
+						truncated: false,
+						downscaled_from,
+						preview: true,
+						decoder: "embedded preview",
+					})
+					.unwrap();
+			}
+			let decode_start = Instant::now();
+			let mut frame_count = 0;
+			// The first frame, if it should go into the disk cache. It's only stored once it's
+			// clear that there are no further frames.
+			let mut to_cache = None;
+			complex_load_image(&request.path, usize::MAX, request.req_id, |mut frame| {
+				if let LoadResult::Frame {
+					image,
+					hdr_image,
+					deep_image,
+					backdrop,
+					orientation,
+					bit_depth,
+					truncated,
+					downscaled_from,
+					..
+				} = &mut frame
+				{
+					frame_count += 1;
+					let slow = decode_start.elapsed() >= disk_cache::MIN_DECODE_TIME;
+					if frame_count == 1 && slow && *bit_depth == 8 && !*truncated {
+						to_cache = disk_cache::enabled().then(|| (image.clone(), *orientation));
+					}
+					*downscaled_from = downscale(image, request);
+					if downscaled_from.is_some() {
+						*hdr_image = None;
+						*deep_image = None;
+					}
+					if MAKE_BACKDROPS.load(Ordering::Relaxed) {
+						*backdrop = Some(make_backdrop(image));
+					}
+					// Shown from floating point textures, so that nothing is lost to rounding
+					if let Some(deep_image) = deep_image.take() {
+						*hdr_image = Some(linearize(&deep_image));
+					}
+				}
 				img_sender.send(frame).unwrap();
 				Ok(())
 			})?;
+			if let (Some((image, orientation)), 1) = (to_cache, frame_count) {
+				disk_cache::store(&request.path, &metadata, image, orientation);
+			}
 			Ok(())
 		}
 
+		// A malformed file must not take the loader thread down with it
+		let result =
+			panic::catch_unwind(AssertUnwindSafe(|| try_load_and_send(img_sender, &request)))
+				.unwrap_or_else(|_| {
+					Err(ImageLoaderError { description: "The decoder crashed on this file".into() })
+				});
 		img_sender
-			.send(match try_load_and_send(img_sender, &request) {
+			.send(match result {
 				Ok(()) => LoadResult::Done { req_id: request.req_id },
 				Err(error) => {
 					eprintln!(
 						"Request #{}: Error occurred while loading file {:?}\n    {}",
 						request.req_id, request.path, error,
 					);
-					LoadResult::Failed { req_id: request.req_id }
+					LoadResult::Failed {
+						req_id: request.req_id,
+						error: error.description.to_string(),
+					}
 				}
 			})
 			.unwrap();
@@ -419,7 +732,15 @@ impl Drop for ImageLoader {
 		self.running.store(false, Ordering::Release);
 		if let Some(join_handles) = self.join_handles.take() {
 			for _ in join_handles.iter() {
-				self.path_tx.send(LoadRequest { req_id: 0, path: PathBuf::from("") }).unwrap();
+				self.path_tx
+					.send(LoadRequest {
+						req_id: 0,
+						path: PathBuf::from(""),
+						max_side: None,
+						max_pixels: None,
+						preview: false,
+					})
+					.unwrap();
 			}
 
 			for handle in join_handles.into_iter() {
@@ -1,8 +1,8 @@
 use std::{
 	borrow::Cow,
-	collections::BTreeMap,
+	collections::{BTreeMap, HashSet},
 	ffi::{OsStr, OsString},
-	fs, mem,
+	mem,
 	path::{Path, PathBuf},
 	rc::Rc,
 	sync::atomic::Ordering,
@@ -14,15 +14,32 @@ use log::trace;
 use gelatin::{
 	glium::{
 		self,
-		texture::{MipmapsOption, RawImage2d, SrgbTexture2d},
+		texture::{
+			MipmapsOption, RawImage2d, SrgbTexture2d, Texture2d, ToClientFormat,
+			UncompressedFloatFormat,
+		},
 		CapabilitiesSource,
 	},
 	image,
 };
 
+pub mod archive;
+pub mod capture_date;
+pub mod decoders;
+pub mod disk_cache;
+pub mod duplicates;
 pub mod image_loader;
-
-use self::{directory::DirItem, image_loader::*};
+pub mod motion_photo;
+mod psd;
+pub mod resolution;
+pub mod shooting_info;
+pub mod source;
+pub mod thumbnails;
+
+use self::{
+	capture_date::CaptureDate, directory::DirItem, duplicates::Duplicates, image_loader::*,
+};
+use crate::configuration::SortOrder;
 
 mod pending_requests;
 use pending_requests::PendingRequests;
@@ -30,11 +47,18 @@ use pending_requests::PendingRequests;
 pub mod directory;
 use directory::Directory;
 
+/// The cache is this many times smaller in the low memory mode
+const LOW_MEMORY_CAPACITY_DIVISOR: isize = 4;
+
 #[derive(Debug, thiserror::Error)]
 pub enum TextureError {
 	#[error("ImageCache is waiting for the image loader to send result")]
 	WaitingOnLoader,
 
+	/// The image loader couldn't decode the file
+	#[error("{0}")]
+	LoadFailed(String),
+
 	#[error("Other texture error: {0}")]
 	Other(Cow<'static, str>),
 }
@@ -53,11 +77,6 @@ impl From<std::io::Error> for TextureError {
 		Self::Other(format!("IO error occured during texture creation: {value}").into())
 	}
 }
-impl TextureError {
-	pub fn from_failed_request(req_id: u32) -> Self {
-		Self::Other(format!("Failed to load #{req_id}").into())
-	}
-}
 
 pub type TextureResult<T> = std::result::Result<T, TextureError>;
 
@@ -108,8 +127,24 @@ impl<'a> RequestKind<'a> {
 	}
 }
 
+/// The texture of a grid cell. Floating point textures hold the linear pixel values of images
+/// with 16 bits per channel, and of HDR images when the window can display them.
+pub enum GridTexture {
+	Srgb(SrgbTexture2d),
+	Float(Texture2d),
+}
+
+impl GridTexture {
+	pub fn dimensions(&self) -> (u32, u32) {
+		match self {
+			GridTexture::Srgb(tex) => tex.dimensions(),
+			GridTexture::Float(tex) => tex.dimensions(),
+		}
+	}
+}
+
 pub struct TextureGridItem {
-	pub tex: SrgbTexture2d,
+	pub tex: GridTexture,
 	pub col: u32,
 	pub row: u32,
 }
@@ -136,16 +171,59 @@ pub struct AnimationFrameTexture {
 	/// The total height of the image. This equals to the sum of the heights of the
 	/// textures from a single column of the grid
 	pub h: u32,
+
+	/// The average linear color of the pixels along the edges of the image
+	pub edge_color: [f32; 4],
+	/// See `LoadResult::Frame`
+	pub backdrop: Option<Rc<SrgbTexture2d>>,
+	/// See `LoadResult::Frame`
+	pub bit_depth: u8,
+	/// See `LoadResult::Frame`
+	pub truncated: bool,
+	/// See `LoadResult::Frame`
+	pub downscaled_from: Option<(u32, u32)>,
+	/// See `LoadResult::Frame`
+	pub preview: bool,
+	/// See `LoadResult::Frame`
+	pub decoder: &'static str,
 }
 impl AnimationFrameTexture {
+	/// Like "16-bit"
+	pub fn bit_depth_text(&self) -> String {
+		match self.bit_depth {
+			32 => "32-bit float".to_string(),
+			depth => format!("{}-bit", depth),
+		}
+	}
+
+	/// Uploads `hdr_image` into floating point textures if it's provided, otherwise uploads
+	/// `image`. The two must have the same dimensions.
 	pub fn from_image(
 		display: &gelatin::Display,
 		image: image::RgbaImage,
+		hdr_image: Option<image::Rgba32FImage>,
+		backdrop: Option<image::RgbaImage>,
 		delay_nano: u64,
 		orientation: Orientation,
+		bit_depth: u8,
 	) -> TextureResult<Self> {
 		let (w, h) = image.dimensions();
-		let img_bytes = image.into_raw();
+		let edge_color = edge_color(&image);
+		let backdrop = match backdrop {
+			Some(backdrop) => {
+				let (backdrop_w, backdrop_h) = backdrop.dimensions();
+				let raw_image =
+					image_rect(backdrop_w, backdrop.as_raw(), 0, 0, backdrop_w, backdrop_h);
+				Some(Rc::new(SrgbTexture2d::with_mipmaps(
+					display,
+					raw_image,
+					MipmapsOption::NoMipmap,
+				)?))
+			}
+			None => None,
+		};
+		let hdr_bytes = hdr_image.map(|image| image.into_raw());
+		let img_bytes = if hdr_bytes.is_some() { Vec::new() } else { image.into_raw() };
 		let mut tex_grid = Vec::new();
 
 		// The reasoning behind dividing by 2 and taking the min with 4*1024, is
@@ -160,6 +238,7 @@ impl AnimationFrameTexture {
 
 		let grid_cols = ((w - 1) / max_size) + 1;
 		let grid_rows = ((h - 1) / max_size) + 1;
+		let mipmaps = mipmaps_for_size(w, h);
 
 		for row in 0..grid_rows {
 			for col in 0..grid_cols {
@@ -167,9 +246,18 @@ impl AnimationFrameTexture {
 				let offset_y = row * max_size;
 				let cell_w = (w - offset_x).min(max_size);
 				let cell_h = (h - offset_y).min(max_size);
-				let tex = texture_from_img_rect(
-					display, w, h, &img_bytes, offset_x, offset_y, cell_w, cell_h,
-				)?;
+				let tex = if let Some(hdr_bytes) = &hdr_bytes {
+					let raw_image = image_rect(w, hdr_bytes, offset_x, offset_y, cell_w, cell_h);
+					GridTexture::Float(Texture2d::with_format(
+						display,
+						raw_image,
+						UncompressedFloatFormat::F16F16F16F16,
+						mipmaps,
+					)?)
+				} else {
+					let raw_image = image_rect(w, &img_bytes, offset_x, offset_y, cell_w, cell_h);
+					GridTexture::Srgb(SrgbTexture2d::with_mipmaps(display, raw_image, mipmaps)?)
+				};
 				let item = TextureGridItem { tex, col, row };
 				tex_grid.push(item);
 			}
@@ -182,6 +270,13 @@ impl AnimationFrameTexture {
 			w,
 			h,
 			cell_step_size: max_size,
+			edge_color,
+			backdrop,
+			bit_depth,
+			truncated: false,
+			downscaled_from: None,
+			preview: false,
+			decoder: "",
 		})
 	}
 
@@ -194,54 +289,88 @@ impl AnimationFrameTexture {
 	}
 }
 
-/// img_bytes has to be an rgba8 buffer.
-#[allow(clippy::too_many_arguments)]
-fn texture_from_img_rect(
-	display: &gelatin::Display,
+pub fn srgb_to_linear(value: u8) -> f32 {
+	srgb_decode(value as f32 / 255.0)
+}
+
+/// Converts an sRGB encoded value between 0 and 1 to linear
+pub fn srgb_decode(value: f32) -> f32 {
+	if value <= 0.04045 {
+		value / 12.92
+	} else {
+		((value + 0.055) / 1.055).powf(2.4)
+	}
+}
+
+fn edge_color(image: &image::RgbaImage) -> [f32; 4] {
+	let (w, h) = image.dimensions();
+	let mut sum = [0.0; 3];
+	let mut count = 0;
+	let mut add = |x: u32, y: u32| {
+		let pixel = image.get_pixel(x, y).0;
+		for (sum, &value) in sum.iter_mut().zip(pixel.iter()) {
+			*sum += srgb_to_linear(value);
+		}
+		count += 1;
+	};
+	for x in 0..w {
+		add(x, 0);
+		add(x, h - 1);
+	}
+	for y in 0..h {
+		add(0, y);
+		add(w - 1, y);
+	}
+	let count = count as f32;
+	[sum[0] / count, sum[1] / count, sum[2] / count, 1.0]
+}
+
+/// Returns a rectangle of an image. `img_data` has to hold 4 interleaved (RGBA) values for
+/// each pixel.
+fn image_rect<T: ToClientFormat + Clone>(
 	img_w: u32,
-	img_h: u32,
-	img_bytes: &[u8],
+	img_data: &[T],
 	offset_x: u32,
 	offset_y: u32,
 	cell_w: u32,
 	cell_h: u32,
-) -> TextureResult<SrgbTexture2d> {
-	let raw_image;
+) -> RawImage2d<'_, T> {
 	if img_w == cell_w {
 		assert!(offset_x == 0);
 		let start = (offset_y as usize * img_w as usize) * 4;
 		let end = start + (cell_h as usize * cell_w as usize * 4);
-		raw_image = RawImage2d {
-			data: Cow::Borrowed(&img_bytes[start..end]),
-			format: glium::texture::ClientFormat::U8U8U8U8,
+		RawImage2d {
+			data: Cow::Borrowed(&img_data[start..end]),
+			format: T::rgba_format(),
 			width: cell_w,
 			height: cell_h,
-		};
+		}
 	} else {
 		let cell_size = cell_w as usize * cell_h as usize * 4;
 		let mut cell_pixels = Vec::with_capacity(cell_size);
 		for y in offset_y..(offset_y + cell_h) {
 			// We multiply by four becase we need to convert from a pixel offset to
-			// a byte offset and each pixel is 4 bytes wide.
+			// a value offset and each pixel has four values.
 			let start = (y as usize * img_w as usize + offset_x as usize) * 4;
 			let end = start + (cell_w as usize * 4);
-			cell_pixels.extend_from_slice(&img_bytes[start..end]);
+			cell_pixels.extend_from_slice(&img_data[start..end]);
 		}
-		raw_image = RawImage2d::from_raw_rgba(cell_pixels, (cell_w, cell_h));
+		RawImage2d::from_raw_rgba(cell_pixels, (cell_w, cell_h))
 	}
+}
 
+fn mipmaps_for_size(img_w: u32, img_h: u32) -> MipmapsOption {
 	let x_pow = 31 - img_w.leading_zeros();
 	let y_pow = 31 - img_h.leading_zeros();
 
 	let max_mipmap_levels = x_pow.min(y_pow).min(4);
 
-	let mipmaps = if max_mipmap_levels == 1 {
+	if max_mipmap_levels == 1 {
 		MipmapsOption::NoMipmap
 	} else {
 		MipmapsOption::AutoGeneratedMipmapsMax(max_mipmap_levels)
 		//MipmapsOption::AutoGeneratedMipmaps
-	};
-	Ok(SrgbTexture2d::with_mipmaps(display, raw_image, mipmaps)?)
+	}
 }
 
 struct CachedTexture {
@@ -257,9 +386,9 @@ struct CachedTexture {
 	/// If it's not fully loaded yet a `WaitingOnLoader` error is returned.
 	fully_loaded: bool,
 
-	/// - `false` if loading is still in progress or if succeeded.
-	/// - `true` if this failed to load,
-	failed: bool,
+	/// - `None` if loading is still in progress or if succeeded.
+	/// - The reason if this failed to load
+	error: Option<String>,
 
 	/// If the target file is an image this vector will have a single texture once the
 	/// image uploaded to the GPU. If the target file is an animated image like a gif,
@@ -289,7 +418,19 @@ pub struct ImageCache {
 
 	remaining_capacity: isize,
 	total_capacity: isize,
+	/// The capacity outside of the low memory mode
+	normal_capacity: isize,
 	curr_est_size: isize,
+	preload_count: Option<usize>,
+	/// See `set_low_memory`
+	low_memory_max_side: Option<u32>,
+	/// See `set_max_decode_pixels`
+	max_decode_pixels: Option<u64>,
+	/// The images that are loaded at their full size in the low memory mode or despite
+	/// `max_decode_pixels`, because they were zoomed into
+	full_resolution: HashSet<u32>,
+	/// See `set_embedded_preview`
+	embedded_preview: bool,
 
 	pending_requests: PendingRequests,
 	texture_cache: BTreeMap<u32, CachedTexture>,
@@ -312,7 +453,13 @@ impl ImageCache {
 
 			remaining_capacity: capacity,
 			total_capacity: capacity,
+			normal_capacity: capacity,
 			curr_est_size: 1000, // 1 kb, an optimistic estimate for the image size before anything is loaded
+			preload_count: None,
+			low_memory_max_side: None,
+			max_decode_pixels: None,
+			full_resolution: HashSet::new(),
+			embedded_preview: false,
 
 			pending_requests: PendingRequests::new(),
 			texture_cache: BTreeMap::new(),
@@ -328,6 +475,11 @@ impl ImageCache {
 		self.current_filename().map(|name| self.dir.path().join(name))
 	}
 
+	/// The index of the frame of the current image that was loaded last
+	pub fn current_frame_index(&self) -> usize {
+		self.current_frame_idx
+	}
+
 	/// Returns `None` when the directory hasn't finished filtering image files.
 	pub fn current_file_index(&mut self) -> Option<usize> {
 		self.dir.curr_img_index()
@@ -338,11 +490,141 @@ impl ImageCache {
 		self.dir.image_count()
 	}
 
+	pub fn file_path_at_index(&mut self, index: usize) -> Option<PathBuf> {
+		self.dir.image_by_index(index).map(|desc| desc.path.clone())
+	}
+
+	/// See `Directory::duplicates`
+	pub fn duplicates(&mut self) -> Option<&Duplicates> {
+		self.dir.duplicates()
+	}
+
+	/// See `Directory::thumbnail_progress`
+	pub fn thumbnail_progress(&mut self) -> Option<(usize, usize)> {
+		self.dir.thumbnail_progress()
+	}
+
+	pub fn set_thumbnail_threads(&mut self, threads: u32) {
+		self.dir.set_thumbnail_threads(threads);
+	}
+
+	/// See `Directory::set_sort_order`
+	pub fn set_sort_order(&mut self, order: SortOrder) {
+		self.dir.set_sort_order(order);
+	}
+
+	/// See `Directory::set_ignore`
+	pub fn set_ignore(&mut self, patterns: Vec<String>, show_hidden: bool) {
+		self.dir.set_ignore(patterns, show_hidden);
+	}
+
+	/// See `Directory::set_single_image`
+	pub fn set_single_image(&mut self, single_image: bool) {
+		self.dir.set_single_image(single_image);
+	}
+
+	/// See `Directory::set_show_ignored`
+	pub fn set_show_ignored(&mut self, show: bool) {
+		self.dir.set_show_ignored(show);
+	}
+
+	pub fn show_ignored(&self) -> bool {
+		self.dir.show_ignored()
+	}
+
+	/// See `Directory::sorting`
+	pub fn sorting(&mut self) -> bool {
+		self.dir.sorting()
+	}
+
+	/// See `Directory::sort_key_text`
+	pub fn sort_key_text(&self, path: &Path) -> Option<String> {
+		self.dir.sort_key_text(path)
+	}
+
+	/// Limits the number of images after the current one that are loaded ahead of time.
+	/// Without a limit, images are loaded until the cache is full.
+	pub fn set_preload_count(&mut self, preload_count: Option<usize>) {
+		self.preload_count = preload_count;
+	}
+
+	/// Turns the low memory mode on when `max_side` is set. In that mode the cache is smaller,
+	/// no images are loaded ahead of time, and larger images are shrunk to fit into a square of
+	/// `max_side` pixels when they are decoded.
+	pub fn set_low_memory(&mut self, max_side: Option<u32>) {
+		self.low_memory_max_side = max_side;
+		if max_side.is_some() {
+			self.total_capacity = self.normal_capacity / LOW_MEMORY_CAPACITY_DIVISOR;
+		} else {
+			self.total_capacity = self.normal_capacity;
+			self.full_resolution.clear();
+			// Loaded again at their full size when they are shown
+			self.texture_cache
+				.retain(|_, texture| texture.frames.iter().all(|f| f.downscaled_from.is_none()));
+		}
+		self.refresh_cache();
+	}
+
+	pub fn low_memory(&self) -> bool {
+		self.low_memory_max_side.is_some()
+	}
+
+	/// Shrinks the images with more than `max_pixels` pixels to that many when they are decoded,
+	/// whether the low memory mode is on or not
+	pub fn set_max_decode_pixels(&mut self, max_pixels: Option<u64>) {
+		self.max_decode_pixels = max_pixels;
+	}
+
+	/// Shows the preview that is embedded in a file while the image that has to be shown right
+	/// away is decoded
+	pub fn set_embedded_preview(&mut self, enabled: bool) {
+		self.embedded_preview = enabled;
+	}
+
+	pub fn embedded_preview(&self) -> bool {
+		self.embedded_preview
+	}
+
+	/// Loads the current image again at its full size, if it was shrunk when it was decoded
+	pub fn request_full_resolution(&mut self) {
+		let Some(req_id) = self.dir.curr_descriptor().map(|desc| desc.request_id) else {
+			return;
+		};
+		let shrinks = self.low_memory_max_side.is_some() || self.max_decode_pixels.is_some();
+		if !shrinks || !self.full_resolution.insert(req_id) {
+			return;
+		}
+		if let Some(texture) = self.texture_cache.remove(&req_id) {
+			self.remaining_capacity += get_anim_size_estimate(&texture.frames);
+		}
+	}
+
+	/// Drops every image from the cache except for the current one
+	pub fn release_memory(&mut self) {
+		let current = self.dir.curr_descriptor().map(|desc| desc.request_id);
+		self.texture_cache.retain(|req_id, _| Some(*req_id) == current);
+		self.full_resolution.retain(|req_id| Some(*req_id) == current);
+		self.refresh_cache();
+	}
+
+	/// See `Directory::capture_dates`
+	pub fn capture_dates(&mut self) -> Option<&[Option<CaptureDate>]> {
+		self.dir.capture_dates()
+	}
+
 	fn curr_dir_item(&self) -> Option<DirItem> {
 		self.dir.curr_descriptor().cloned()
 	}
 
 	/// Returns tru if and only if the current image has been fully loaded and it has a single frame.
+	/// The number of frames of the current image that were decoded so far, and whether that's
+	/// all of them
+	pub fn current_frame_count(&self) -> Option<(usize, bool)> {
+		let desc = self.dir.curr_descriptor()?;
+		let img = self.texture_cache.get(&desc.request_id)?;
+		Some((img.frames.len(), img.fully_loaded))
+	}
+
 	pub fn loaded_still_image(&self) -> bool {
 		if let Some(desc) = self.dir.curr_descriptor() {
 			if let Some(img) = self.texture_cache.get(&desc.request_id) {
@@ -407,9 +689,12 @@ impl ImageCache {
 		trace!("Receive prefetched done");
 		let target_file_name;
 		let parent;
-		if path.is_dir() {
+		if path.is_dir() || archive::is_archive(path) {
 			parent = path.to_owned();
 			target_file_name = None;
+		} else if let Some((archive, entry_name)) = archive::split_archive_path(path) {
+			parent = archive.canonicalize()?;
+			target_file_name = Some(OsString::from(entry_name));
 		} else {
 			let filename_and_parent = get_file_name_and_parent(path)?;
 			target_file_name = Some(filename_and_parent.0);
@@ -417,6 +702,14 @@ impl ImageCache {
 		}
 
 		let prev_img_index = self.dir.curr_img_index();
+		if let Some(target_file_name) = &target_file_name {
+			// The folder is still opened so that the images next to the file can be navigated to
+			let file_path = parent.join(target_file_name);
+			if file_path.is_file() && !is_file_supported(&file_path) {
+				self.change_directory(&parent)?;
+				return Err(TextureError::LoadFailed(describe_unsupported(&file_path)));
+			}
+		}
 		if let Some(target_file_name) = target_file_name {
 			self.change_directory_with_filename(&parent, &target_file_name)?;
 		} else {
@@ -605,10 +898,10 @@ impl ImageCache {
 
 		// Check if it is inside the texture cache first
 		if let Some(tex) = self.texture_cache.get(&req_id) {
-			if tex.failed {
-				return Err(TextureError::from_failed_request(req_id));
+			if let Some(error) = &tex.error {
+				return Err(TextureError::LoadFailed(error.clone()));
 			}
-			let modified = fs::metadata(&path).ok().and_then(|m| m.modified().ok());
+			let modified = source::metadata(&path).ok().and_then(|m| m.modified().ok());
 			let mut get_from_cache = false;
 			if let Some(curr_mod_time) = modified {
 				if let Some(mod_time) = tex.mod_time {
@@ -622,12 +915,8 @@ impl ImageCache {
 			if get_from_cache {
 				let count = tex.frames.len() as isize;
 				if tex.fully_loaded || (frame_id >= 0 && frame_id < count) {
-					let wrapped_id;
-					if frame_id < 0 {
-						wrapped_id = count + (frame_id % count);
-					} else {
-						wrapped_id = frame_id % count;
-					}
+					let wrapped_id =
+						if frame_id < 0 { count + (frame_id % count) } else { frame_id % count };
 					if let Some(frame) = tex.frames.get(wrapped_id as usize) {
 						self.current_frame_idx = wrapped_id as usize;
 						return Ok(frame.clone());
@@ -669,7 +958,7 @@ impl ImageCache {
 							needs_update: false,
 							fully_loaded: false,
 							mod_time: curr_mod_time,
-							failed: false,
+							error: None,
 							frames: Vec::new(),
 						});
 					}
@@ -694,7 +983,20 @@ impl ImageCache {
 				}
 				Ok(None)
 			}
-			LoadResult::Frame { req_id, image, delay_nano, orientation } => {
+			LoadResult::Frame {
+				req_id,
+				image,
+				hdr_image,
+				backdrop,
+				delay_nano,
+				orientation,
+				bit_depth,
+				truncated,
+				downscaled_from,
+				preview,
+				decoder,
+				..
+			} => {
 				if let Some(cancelled) = self.pending_requests.cancelled(&req_id) {
 					if cancelled {
 						return Ok(None);
@@ -702,10 +1004,30 @@ impl ImageCache {
 				} else {
 					return Ok(None);
 				}
-				let size_estimate = get_image_size_estimate(image.width(), image.height());
+				let mut size_estimate = get_image_size_estimate(image.width(), image.height());
+				if hdr_image.is_some() {
+					// Half floats take twice the space
+					size_estimate *= 2;
+				}
 				if let Some(entry) = self.texture_cache.get_mut(&req_id) {
-					let anim_frame =
-						AnimationFrameTexture::from_image(display, image, delay_nano, orientation)?;
+					let mut anim_frame = AnimationFrameTexture::from_image(
+						display,
+						image,
+						hdr_image,
+						backdrop,
+						delay_nano,
+						orientation,
+						bit_depth,
+					)?;
+					anim_frame.truncated = truncated;
+					anim_frame.downscaled_from = downscaled_from;
+					anim_frame.preview = preview;
+					anim_frame.decoder = decoder;
+					if entry.frames.first().is_some_and(|frame| frame.preview) {
+						// The decoded image takes the place of the preview
+						self.remaining_capacity += get_anim_size_estimate(&entry.frames);
+						entry.frames.clear();
+					}
 					entry.frames.push(anim_frame.clone());
 					self.remaining_capacity -= size_estimate;
 					return Ok(Some(anim_frame));
@@ -725,10 +1047,10 @@ impl ImageCache {
 				self.pending_requests.set_finished(&req_id);
 				Ok(None)
 			}
-			LoadResult::Failed { req_id } => {
+			LoadResult::Failed { req_id, error } => {
 				if let Some(tex) = self.texture_cache.get_mut(&req_id) {
 					tex.fully_loaded = true;
-					tex.failed = true;
+					tex.error = Some(error.clone());
 				}
 				let _ = PRIORITY_REQUEST_ID.compare_exchange(
 					req_id,
@@ -737,17 +1059,50 @@ impl ImageCache {
 					Ordering::SeqCst,
 				);
 				self.pending_requests.set_finished(&req_id);
-				Err(TextureError::from_failed_request(req_id))
+				Err(TextureError::LoadFailed(error))
+			}
+		}
+	}
+
+	/// Returns the first frame of the image at `index` of the current folder if it is loaded,
+	/// and requests it otherwise. This doesn't change the current image.
+	pub fn texture_at_index(
+		&mut self,
+		display: &gelatin::Display,
+		index: usize,
+	) -> Option<AnimationFrameTexture> {
+		let DirItem { path, request_id: req_id } = self.dir.image_by_index(index)?.clone();
+		if let Some(results) = self.pending_requests.take_results(req_id) {
+			for load_result in results {
+				if let Err(e) = self.upload_to_texture(display, load_result) {
+					eprintln!("Error while loading the image at index {}:\n{}", index, e);
+					return None;
+				}
+			}
+		}
+		match self.texture_cache.get(&req_id) {
+			Some(tex) if tex.error.is_some() => None,
+			Some(tex) => tex.frames.first().cloned(),
+			None => {
+				// The image is needed right away, so the capacity of the cache isn't checked
+				self.send_request_for_file(path, req_id, RequestKind::NonPriority);
+				None
 			}
 		}
 	}
 
 	pub fn prefetch_neighbors(&mut self) {
+		if self.low_memory() {
+			return;
+		}
 		if let Some(mut index) = self.dir.curr_img_index() {
 			// Send enough load requests so that the estimated total will just fill the cache
 			let mut estimated_remaining_cap = self.remaining_capacity;
+			let last_index = self.preload_count.map(|count| index + count);
 
-			while estimated_remaining_cap > self.curr_est_size {
+			while estimated_remaining_cap > self.curr_est_size
+				&& last_index.is_none_or(|last| index < last)
+			{
 				// Send a load request for the closest file not in the cache or outdated
 				index += 1;
 				if self.prefetch_at_index(index) {
@@ -760,6 +1115,9 @@ impl ImageCache {
 	}
 
 	pub fn prefetch_at_index(&mut self, index: usize) -> bool {
+		if self.low_memory() {
+			return false;
+		}
 		if self.remaining_capacity > self.curr_est_size {
 			let params =
 				self.dir.image_by_index(index).map(|desc| (desc.path.clone(), desc.request_id));
@@ -798,7 +1156,7 @@ impl ImageCache {
 				texture.needs_update = false;
 				if let Some(existing_mod_time) = texture.mod_time {
 					let new_mod_time =
-						fs::metadata(&file_path).ok().and_then(|m| m.modified().ok());
+						source::metadata(&file_path).ok().and_then(|m| m.modified().ok());
 					if let Some(new_mod_time) = new_mod_time {
 						if new_mod_time == existing_mod_time {
 							return false;
@@ -812,13 +1170,18 @@ impl ImageCache {
 		if cache_enty_invalid {
 			self.texture_cache.remove(&req_id);
 		}
-		if kind.priority() {
+		let priority = kind.priority();
+		if priority {
 			PRIORITY_REQUEST_ID.store(req_id, Ordering::SeqCst);
 		}
 		if self.pending_requests.contains(&req_id) {
 			return false;
 		}
-		let request = LoadRequest { req_id, path: file_path };
+		let full_resolution = self.full_resolution.contains(&req_id);
+		let max_side = self.low_memory_max_side.filter(|_| !full_resolution);
+		let max_pixels = self.max_decode_pixels.filter(|_| !full_resolution);
+		let preview = self.embedded_preview && priority;
+		let request = LoadRequest { req_id, path: file_path, max_side, max_pixels, preview };
 		self.pending_requests.add_request(request.clone());
 		self.loader.send_load_request(request);
 		true
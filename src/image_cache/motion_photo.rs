@@ -0,0 +1,92 @@
+//! Finding the video clips of motion photos.
+//!
+//! Google and Samsung phones append an MP4 clip to the end of the JPEG or HEIC file, and name
+//! its length in the XMP data or put a marker in front of it. Apple keeps the clip of a Live
+//! Photo in a separate MOV file with the same name as the photo.
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use super::source;
+use crate::PROJECT_DIRS;
+
+/// The extensions of the photos that can have a clip
+const PHOTO_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "heic", "heif"];
+const SIDECAR_EXTENSIONS: [&str; 2] = ["MOV", "mov"];
+/// The marker that Samsung phones put in front of the clip
+const SAMSUNG_MARKER: &[u8] = b"MotionPhoto_Data";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MotionClip {
+	/// The clip is the bytes of the photo file from `offset` to the end
+	Embedded { offset: usize },
+	/// A Live Photo, whose clip is in this file
+	Sidecar(PathBuf),
+}
+
+/// Finds the clip of the photo at `path`, `None` if it's not a motion photo
+pub fn find_motion_clip(path: &Path) -> Option<MotionClip> {
+	let ext = path.extension()?.to_str()?.to_lowercase();
+	if !PHOTO_EXTENSIONS.contains(&ext.as_str()) {
+		return None;
+	}
+	let sidecar =
+		SIDECAR_EXTENSIONS.iter().map(|ext| path.with_extension(ext)).find(|p| p.is_file());
+	if let Some(sidecar) = sidecar {
+		return Some(MotionClip::Sidecar(sidecar));
+	}
+	let mut bytes = Vec::new();
+	source::open(path).ok()?.read_to_end(&mut bytes).ok()?;
+	embedded_clip_offset(&bytes).map(|offset| MotionClip::Embedded { offset })
+}
+
+fn embedded_clip_offset(bytes: &[u8]) -> Option<usize> {
+	// The older Google format names the distance of the clip from the end of the file, the
+	// newer one names the length of the last item of the container
+	let from_end = xmp_number(bytes, b"MicroVideoOffset").or_else(|| {
+		let semantic = find(bytes, b"Semantic=\"MotionPhoto\"")?;
+		xmp_number(&bytes[semantic..], b"Length")
+	});
+	let offset = match from_end {
+		Some(len) => bytes.len().checked_sub(len)?,
+		None => find(bytes, SAMSUNG_MARKER)? + SAMSUNG_MARKER.len(),
+	};
+	// The clip is an MP4 or QuickTime file, which starts with the size and the type of the
+	// first box
+	(bytes.get(offset + 4..offset + 8) == Some(b"ftyp")).then_some(offset)
+}
+
+/// The value of an XMP property whose name ends with `name`, either written as an attribute
+/// or as an element
+fn xmp_number(bytes: &[u8], name: &[u8]) -> Option<usize> {
+	let start = find(bytes, name)? + name.len();
+	let rest = &bytes[start..];
+	let rest = rest.strip_prefix(b"=\"").or_else(|| rest.strip_prefix(b">"))?;
+	let digits = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+	std::str::from_utf8(&rest[..digits]).ok()?.parse().ok().filter(|&n| n > 0)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+	haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// A file that a video player can open, copying an embedded clip into the cache folder
+pub fn clip_file(path: &Path, clip: &MotionClip) -> io::Result<PathBuf> {
+	let offset = match clip {
+		MotionClip::Sidecar(sidecar) => return Ok(sidecar.clone()),
+		MotionClip::Embedded { offset } => *offset,
+	};
+	let dir = PROJECT_DIRS
+		.as_ref()
+		.map(|dirs| dirs.cache_dir().join("motion"))
+		.ok_or_else(|| io::Error::other("There is no cache folder on this system"))?;
+	fs::create_dir_all(&dir)?;
+	let mut bytes = Vec::new();
+	source::open(path)?.read_to_end(&mut bytes)?;
+	let clip_bytes = bytes.get(offset..).ok_or_else(|| io::Error::other("The file changed"))?;
+	let stem = path.file_stem().unwrap_or_default();
+	let target = dir.join(format!("{}.mp4", stem.to_string_lossy()));
+	fs::write(&target, clip_bytes)?;
+	Ok(target)
+}
@@ -0,0 +1,654 @@
+//! A decoder for the flattened image of Photoshop (PSD and PSB) files.
+//!
+//! Only the final, composited image is of interest here. Most files store this directly, in which
+//! case it's used as is. If the file indicates that this is not available, the visible layers are
+//! composited instead, using normal blending.
+
+use gelatin::image::RgbaImage;
+
+use super::image_loader::{ImageLoaderError, Result};
+
+const COLOR_MODE_BITMAP: u16 = 0;
+const COLOR_MODE_GRAYSCALE: u16 = 1;
+const COLOR_MODE_INDEXED: u16 = 2;
+const COLOR_MODE_RGB: u16 = 3;
+const COLOR_MODE_CMYK: u16 = 4;
+const COLOR_MODE_MULTICHANNEL: u16 = 7;
+const COLOR_MODE_DUOTONE: u16 = 8;
+
+/// The image resource that tells whether the file contains a valid flattened image
+const RESOURCE_VERSION_INFO: u16 = 1057;
+/// The image resource with the embedded ICC profile
+const RESOURCE_ICC_PROFILE: u16 = 1039;
+
+const LAYER_FLAG_INVISIBLE: u8 = 0b10;
+/// The largest width and height allowed by the format, for PSD and PSB files
+const MAX_PSD_SIZE: usize = 30_000;
+const MAX_PSB_SIZE: usize = 300_000;
+/// The most pixels that the image or a layer may have, which is 1 GiB in RGBA. The format allows
+/// much larger images, and the rows of a file that is only a few megabytes may expand to them.
+const MAX_PIXELS: usize = 1 << 28;
+/// The most bytes that the channels of the image and of all the layers may take up together,
+/// before they are converted to 8 bits
+const MAX_CHANNEL_BYTES: usize = 1 << 31;
+
+fn error(description: &'static str) -> ImageLoaderError {
+	ImageLoaderError { description: description.into() }
+}
+
+fn check_pixel_count(width: usize, height: usize) -> Result<()> {
+	match width.checked_mul(height) {
+		Some(pixels) if pixels <= MAX_PIXELS => Ok(()),
+		_ => Err(error("The PSD file is too large to be decoded")),
+	}
+}
+
+struct Reader<'a> {
+	data: &'a [u8],
+	pos: usize,
+}
+
+impl<'a> Reader<'a> {
+	fn bytes(&mut self, count: usize) -> Result<&'a [u8]> {
+		let end = self.pos.checked_add(count).filter(|&end| end <= self.data.len());
+		let end = end.ok_or_else(|| error("Unexpected end of the PSD file"))?;
+		let bytes = &self.data[self.pos..end];
+		self.pos = end;
+		Ok(bytes)
+	}
+
+	fn u8(&mut self) -> Result<u8> {
+		Ok(self.bytes(1)?[0])
+	}
+
+	fn u16(&mut self) -> Result<u16> {
+		let b = self.bytes(2)?;
+		Ok(u16::from_be_bytes([b[0], b[1]]))
+	}
+
+	fn u32(&mut self) -> Result<u32> {
+		let b = self.bytes(4)?;
+		Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+	}
+
+	fn u64(&mut self) -> Result<u64> {
+		let b = self.bytes(8)?;
+		Ok(u64::from_be_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+	}
+
+	fn i16(&mut self) -> Result<i16> {
+		Ok(self.u16()? as i16)
+	}
+
+	fn i32(&mut self) -> Result<i32> {
+		Ok(self.u32()? as i32)
+	}
+
+	/// Reads a length field that is 8 bytes wide in PSB files and 4 bytes wide in PSD files
+	fn length(&mut self, is_psb: bool) -> Result<usize> {
+		if is_psb {
+			Ok(self.u64()? as usize)
+		} else {
+			Ok(self.u32()? as usize)
+		}
+	}
+
+	/// Returns a reader for the next `len` bytes and moves past them
+	fn section(&mut self, len: usize) -> Result<Reader<'a>> {
+		Ok(Reader { data: self.bytes(len)?, pos: 0 })
+	}
+}
+
+struct Header {
+	is_psb: bool,
+	channels: usize,
+	width: usize,
+	height: usize,
+	depth: u16,
+	color_mode: u16,
+}
+
+/// A single channel of an image, converted to 8 bits per pixel
+struct Plane {
+	id: i16,
+	pixels: Vec<u8>,
+}
+
+struct Layer {
+	top: i32,
+	left: i32,
+	width: usize,
+	height: usize,
+	opacity: u8,
+	visible: bool,
+	planes: Vec<Plane>,
+}
+
+pub fn decode_psd(data: &[u8]) -> Result<RgbaImage> {
+	let mut reader = Reader { data, pos: 0 };
+
+	let header = read_header(&mut reader)?;
+	let mut budget = MAX_CHANNEL_BYTES;
+	let color_mode_data = {
+		let len = reader.u32()? as usize;
+		reader.bytes(len)?
+	};
+	let has_merged_data = {
+		let len = reader.u32()? as usize;
+		read_image_resources(reader.section(len)?)?
+	};
+	let (layers, merged_alpha) = {
+		let len = reader.length(header.is_psb)?;
+		read_layers(&header, reader.section(len)?, &mut budget)?
+	};
+
+	if !has_merged_data && !layers.is_empty() {
+		return composite_layers(&header, color_mode_data, &layers);
+	}
+
+	let compression = reader.u16()?;
+	let rows = (0..header.channels).map(|_| header.height);
+	let planes = read_planes(&header, &mut reader, compression, header.width, rows, &mut budget)?;
+	let planes = planes
+		.into_iter()
+		.enumerate()
+		.map(|(i, pixels)| Plane { id: i as i16, pixels })
+		.collect::<Vec<_>>();
+	let mut image = RgbaImage::new(header.width as u32, header.height as u32);
+	fill_rgba(&header, color_mode_data, &planes, merged_alpha, &mut image)?;
+	Ok(image)
+}
+
+fn read_header(reader: &mut Reader) -> Result<Header> {
+	if reader.bytes(4)? != b"8BPS" {
+		return Err(error("Not a PSD file"));
+	}
+	let is_psb = match reader.u16()? {
+		1 => false,
+		2 => true,
+		_ => return Err(error("Unknown PSD version")),
+	};
+	reader.bytes(6)?;
+	let channels = reader.u16()? as usize;
+	let height = reader.u32()? as usize;
+	let width = reader.u32()? as usize;
+	let depth = reader.u16()?;
+	let color_mode = reader.u16()?;
+	if !matches!(depth, 1 | 8 | 16 | 32) {
+		return Err(error("Unsupported PSD bit depth"));
+	}
+	if width == 0 || height == 0 || channels == 0 {
+		return Err(error("The PSD file contains an empty image"));
+	}
+	let max_size = if is_psb { MAX_PSB_SIZE } else { MAX_PSD_SIZE };
+	if width > max_size || height > max_size {
+		return Err(error("The PSD file is larger than the format allows"));
+	}
+	check_pixel_count(width, height)?;
+	Ok(Header { is_psb, channels, width, height, depth, color_mode })
+}
+
+/// Returns false if the file explicitly states that it doesn't contain a flattened image
+fn read_image_resources(mut reader: Reader) -> Result<bool> {
+	let mut has_merged_data = true;
+	while reader.pos < reader.data.len() {
+		if reader.bytes(4)? != b"8BIM" {
+			return Err(error("Invalid PSD image resource"));
+		}
+		let id = reader.u16()?;
+		// The name is a pascal string padded to an even size
+		let name_len = reader.u8()? as usize;
+		reader.bytes(name_len + (name_len + 1) % 2)?;
+		let data_len = reader.u32()? as usize;
+		let resource_data = reader.bytes(data_len)?;
+		reader.bytes(data_len % 2)?;
+		match id {
+			RESOURCE_VERSION_INFO if resource_data.len() >= 5 => {
+				has_merged_data = resource_data[4] != 0;
+			}
+			RESOURCE_ICC_PROFILE => {
+				log::debug!(
+					"Ignoring the {} byte ICC profile of the PSD file, color management is not supported",
+					resource_data.len()
+				);
+			}
+			_ => (),
+		}
+	}
+	Ok(has_merged_data)
+}
+
+/// Returns the layers from bottom to top, and whether the first extra channel of the
+/// flattened image is its transparency.
+fn read_layers(
+	header: &Header,
+	mut reader: Reader,
+	budget: &mut usize,
+) -> Result<(Vec<Layer>, bool)> {
+	if reader.data.is_empty() {
+		return Ok((Vec::new(), false));
+	}
+	let layer_info_len = reader.length(header.is_psb)?;
+	if layer_info_len == 0 {
+		return Ok((Vec::new(), false));
+	}
+	let mut reader = reader.section(layer_info_len)?;
+	let layer_count = reader.i16()?;
+	// A negative count means that the first alpha channel contains the transparency of the
+	// merged result
+	let merged_alpha = layer_count < 0;
+	let layer_count = layer_count.unsigned_abs() as usize;
+
+	struct Record {
+		top: i32,
+		left: i32,
+		bottom: i32,
+		right: i32,
+		channels: Vec<(i16, usize)>,
+		opacity: u8,
+		flags: u8,
+	}
+	let mut records = Vec::with_capacity(layer_count);
+	for _ in 0..layer_count {
+		let top = reader.i32()?;
+		let left = reader.i32()?;
+		let bottom = reader.i32()?;
+		let right = reader.i32()?;
+		let channel_count = reader.u16()? as usize;
+		let mut channels = Vec::with_capacity(channel_count);
+		for _ in 0..channel_count {
+			let id = reader.i16()?;
+			let len = reader.length(header.is_psb)?;
+			channels.push((id, len));
+		}
+		if reader.bytes(4)? != b"8BIM" {
+			return Err(error("Invalid PSD layer record"));
+		}
+		let _blend_mode = reader.bytes(4)?;
+		let opacity = reader.u8()?;
+		let _clipping = reader.u8()?;
+		let flags = reader.u8()?;
+		let _filler = reader.u8()?;
+		let extra_len = reader.u32()? as usize;
+		reader.bytes(extra_len)?;
+		records.push(Record { top, left, bottom, right, channels, opacity, flags });
+	}
+
+	let mut layers = Vec::with_capacity(layer_count);
+	for record in records {
+		let width = (record.right as i64 - record.left as i64).max(0) as usize;
+		let height = (record.bottom as i64 - record.top as i64).max(0) as usize;
+		let max_size = if header.is_psb { MAX_PSB_SIZE } else { MAX_PSD_SIZE };
+		if width > max_size || height > max_size {
+			return Err(error("A PSD layer is larger than the format allows"));
+		}
+		check_pixel_count(width, height)?;
+		let mut planes = Vec::new();
+		for (id, len) in record.channels {
+			let mut channel_reader = reader.section(len)?;
+			// User and vector masks have a different size than the layer, these are not needed.
+			if id < -1 || width == 0 || height == 0 || len < 2 {
+				continue;
+			}
+			let compression = channel_reader.u16()?;
+			let pixels = read_planes(
+				header,
+				&mut channel_reader,
+				compression,
+				width,
+				std::iter::once(height),
+				budget,
+			);
+			match pixels {
+				Ok(mut pixels) => planes.push(Plane { id, pixels: pixels.remove(0) }),
+				Err(e) => log::warn!("Skipping a channel of a PSD layer: {}", e),
+			}
+		}
+		layers.push(Layer {
+			top: record.top,
+			left: record.left,
+			width,
+			height,
+			opacity: record.opacity,
+			visible: record.flags & LAYER_FLAG_INVISIBLE == 0,
+			planes,
+		});
+	}
+	Ok((layers, merged_alpha))
+}
+
+/// Reads consecutive planar channels. `rows` yields the height of each channel. Their size is
+/// taken from `budget`, and they aren't read if it's too small.
+fn read_planes(
+	header: &Header,
+	reader: &mut Reader,
+	compression: u16,
+	width: usize,
+	rows: impl Iterator<Item = usize> + Clone,
+	budget: &mut usize,
+) -> Result<Vec<Vec<u8>>> {
+	let row_len =
+		if header.depth == 1 { width.div_ceil(8) } else { width * header.depth as usize / 8 };
+	let len = rows
+		.clone()
+		.try_fold(0_usize, |total, height| total.checked_add(height))
+		.and_then(|total_rows| total_rows.checked_mul(row_len))
+		.filter(|&len| len <= *budget)
+		.ok_or_else(|| error("The PSD file is too large to be decoded"))?;
+	*budget -= len;
+	let mut raw_planes = Vec::new();
+	match compression {
+		0 => {
+			for height in rows {
+				raw_planes.push(reader.bytes(row_len * height)?.to_vec());
+			}
+		}
+		1 => {
+			// RLE: all the compressed row sizes come first, then the PackBits encoded rows
+			let mut row_sizes = Vec::new();
+			for height in rows.clone() {
+				for _ in 0..height {
+					row_sizes.push(if header.is_psb {
+						reader.u32()?
+					} else {
+						reader.u16()? as u32
+					});
+				}
+			}
+			let mut row_sizes = row_sizes.into_iter();
+			for height in rows {
+				let mut plane = Vec::with_capacity(row_len * height);
+				for row_size in row_sizes.by_ref().take(height) {
+					let packed = reader.bytes(row_size as usize)?;
+					unpack_bits(packed, row_len, &mut plane);
+				}
+				raw_planes.push(plane);
+			}
+		}
+		_ => return Err(error("Unsupported PSD compression method")),
+	}
+	Ok(raw_planes.into_iter().map(|raw| to_8_bit(header.depth, width, row_len, &raw)).collect())
+}
+
+/// Decodes a PackBits encoded row, always appending exactly `row_len` bytes to `output`
+fn unpack_bits(mut packed: &[u8], row_len: usize, output: &mut Vec<u8>) {
+	let start = output.len();
+	while let Some((&header, rest)) = packed.split_first() {
+		packed = rest;
+		let header = header as i8;
+		if header >= 0 {
+			let count = (header as usize + 1).min(packed.len());
+			output.extend_from_slice(&packed[..count]);
+			packed = &packed[count..];
+		} else if header != -128 {
+			if let Some((&value, rest)) = packed.split_first() {
+				packed = rest;
+				output.extend(std::iter::repeat_n(value, (1 - header as isize) as usize));
+			}
+		}
+	}
+	output.resize(start + row_len, 0);
+}
+
+fn to_8_bit(depth: u16, width: usize, row_len: usize, raw: &[u8]) -> Vec<u8> {
+	match depth {
+		1 => raw
+			.chunks(row_len)
+			.flat_map(|row| (0..width).map(move |x| row[x / 8] >> (7 - x % 8) & 1))
+			// In bitmap mode 1 is black
+			.map(|bit| if bit == 1 { 0 } else { 255 })
+			.collect(),
+		8 => raw.to_vec(),
+		16 => raw.chunks_exact(2).map(|v| v[0]).collect(),
+		_ => raw
+			.chunks_exact(4)
+			.map(|v| {
+				let linear = f32::from_be_bytes([v[0], v[1], v[2], v[3]]).clamp(0.0, 1.0);
+				// 32 bit documents are stored in linear space.
+				(linear.powf(1.0 / 2.2) * 255.0).round() as u8
+			})
+			.collect(),
+	}
+}
+
+/// Converts the planes to RGBA and writes them into `image`. The planes are expected to have the
+/// size of `image`. Plane ids follow the layer convention: -1 is transparency, the color channels
+/// start from 0.
+fn fill_rgba(
+	header: &Header,
+	color_mode_data: &[u8],
+	planes: &[Plane],
+	first_extra_is_alpha: bool,
+	image: &mut RgbaImage,
+) -> Result<()> {
+	let color_channels = match header.color_mode {
+		COLOR_MODE_BITMAP | COLOR_MODE_GRAYSCALE | COLOR_MODE_INDEXED | COLOR_MODE_DUOTONE => 1,
+		COLOR_MODE_RGB => 3,
+		COLOR_MODE_CMYK => 4,
+		COLOR_MODE_MULTICHANNEL => planes.len().clamp(1, 3),
+		_ => return Err(error("Unsupported PSD color mode")),
+	};
+	let plane = |id: i16| planes.iter().find(|p| p.id == id).map(|p| p.pixels.as_slice());
+	let color = (0..color_channels as i16).map(plane).collect::<Option<Vec<_>>>();
+	let color = color.ok_or_else(|| error("A color channel is missing from the PSD file"))?;
+	let alpha =
+		plane(-1).or_else(
+			|| {
+				if first_extra_is_alpha {
+					plane(color_channels as i16)
+				} else {
+					None
+				}
+			},
+		);
+
+	for (i, pixel) in image.pixels_mut().enumerate() {
+		let rgb = match header.color_mode {
+			COLOR_MODE_RGB => [color[0][i], color[1][i], color[2][i]],
+			COLOR_MODE_CMYK => {
+				// The CMYK values are stored inverted, 255 means no ink
+				let [c, m, y, k] = [color[0][i], color[1][i], color[2][i], color[3][i]];
+				let mul = |a: u8, b: u8| ((a as u32 * b as u32 + 127) / 255) as u8;
+				[mul(c, k), mul(m, k), mul(y, k)]
+			}
+			COLOR_MODE_INDEXED if color_mode_data.len() >= 768 => {
+				let index = color[0][i] as usize;
+				[color_mode_data[index], color_mode_data[256 + index], color_mode_data[512 + index]]
+			}
+			COLOR_MODE_MULTICHANNEL if color_channels == 3 => {
+				[color[0][i], color[1][i], color[2][i]]
+			}
+			_ => [color[0][i]; 3],
+		};
+		let a = alpha.map_or(255, |alpha| alpha[i]);
+		pixel.0 = [rgb[0], rgb[1], rgb[2], a];
+	}
+	Ok(())
+}
+
+fn composite_layers(
+	header: &Header,
+	color_mode_data: &[u8],
+	layers: &[Layer],
+) -> Result<RgbaImage> {
+	let mut canvas = RgbaImage::new(header.width as u32, header.height as u32);
+	for layer in layers.iter().filter(|l| l.visible && l.opacity > 0 && !l.planes.is_empty()) {
+		let mut layer_image = RgbaImage::new(layer.width as u32, layer.height as u32);
+		if let Err(e) = fill_rgba(header, color_mode_data, &layer.planes, false, &mut layer_image) {
+			log::warn!("Skipping a PSD layer: {}", e);
+			continue;
+		}
+		for (x, y, src) in layer_image.enumerate_pixels() {
+			let canvas_x = layer.left as i64 + x as i64;
+			let canvas_y = layer.top as i64 + y as i64;
+			if canvas_x < 0
+				|| canvas_y < 0
+				|| canvas_x as usize >= header.width
+				|| canvas_y as usize >= header.height
+			{
+				continue;
+			}
+			let dst = canvas.get_pixel_mut(canvas_x as u32, canvas_y as u32);
+			let src_a = src[3] as f32 / 255.0 * layer.opacity as f32 / 255.0;
+			let dst_a = dst[3] as f32 / 255.0;
+			let out_a = src_a + dst_a * (1.0 - src_a);
+			if out_a <= 0.0 {
+				continue;
+			}
+			for c in 0..3 {
+				let src_c = src[c] as f32 * src_a;
+				let dst_c = dst[c] as f32 * dst_a * (1.0 - src_a);
+				dst[c] = ((src_c + dst_c) / out_a).round() as u8;
+			}
+			dst[3] = (out_a * 255.0).round() as u8;
+		}
+	}
+	Ok(canvas)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rand::{rngs::StdRng, Rng, SeedableRng};
+
+	fn push_u16(psd: &mut Vec<u8>, value: u16) {
+		psd.extend_from_slice(&value.to_be_bytes());
+	}
+
+	fn push_u32(psd: &mut Vec<u8>, value: u32) {
+		psd.extend_from_slice(&value.to_be_bytes());
+	}
+
+	/// The header of a 2x2 RGB image with 8 bits per channel
+	fn header() -> Vec<u8> {
+		let mut psd = b"8BPS".to_vec();
+		push_u16(&mut psd, 1);
+		psd.extend_from_slice(&[0; 6]);
+		push_u16(&mut psd, 3);
+		push_u32(&mut psd, 2);
+		push_u32(&mut psd, 2);
+		push_u16(&mut psd, 8);
+		push_u16(&mut psd, COLOR_MODE_RGB);
+		psd
+	}
+
+	/// A file with only the flattened image, stored uncompressed
+	fn flattened_psd() -> Vec<u8> {
+		let mut psd = header();
+		for _ in 0..3 {
+			// The color mode data, the image resources and the layers are empty
+			push_u32(&mut psd, 0);
+		}
+		push_u16(&mut psd, 0);
+		psd.extend((0..12).map(|i| i * 20));
+		psd
+	}
+
+	/// A file with a single layer and without a flattened image
+	fn layered_psd() -> Vec<u8> {
+		let mut psd = header();
+		push_u32(&mut psd, 0);
+
+		let mut resources = b"8BIM".to_vec();
+		push_u16(&mut resources, RESOURCE_VERSION_INFO);
+		resources.extend_from_slice(&[0, 0]);
+		push_u32(&mut resources, 5);
+		resources.extend_from_slice(&[0, 0, 0, 1, 0, 0]);
+		push_u32(&mut psd, resources.len() as u32);
+		psd.extend(resources);
+
+		let mut layers = Vec::new();
+		push_u16(&mut layers, 1);
+		for value in [0, 0, 2, 2] {
+			push_u32(&mut layers, value);
+		}
+		push_u16(&mut layers, 3);
+		for id in 0..3 {
+			push_u16(&mut layers, id);
+			push_u32(&mut layers, 6);
+		}
+		layers.extend_from_slice(b"8BIMnorm");
+		layers.extend_from_slice(&[255, 0, 0, 0]);
+		push_u32(&mut layers, 0);
+		for channel in 0..3 {
+			push_u16(&mut layers, 0);
+			layers.extend((0..4).map(|i| channel * 60 + i));
+		}
+		push_u32(&mut psd, layers.len() as u32 + 4);
+		push_u32(&mut psd, layers.len() as u32);
+		psd.extend(layers);
+		psd
+	}
+
+	#[test]
+	fn decodes_the_flattened_image_and_the_layers() {
+		for psd in [flattened_psd(), layered_psd()] {
+			let image = decode_psd(&psd).unwrap();
+			assert_eq!(image.dimensions(), (2, 2));
+		}
+	}
+
+	#[test]
+	fn rejects_every_truncated_file() {
+		for psd in [flattened_psd(), layered_psd()] {
+			for len in 0..psd.len() {
+				assert!(decode_psd(&psd[..len]).is_err(), "truncated to {} bytes", len);
+			}
+		}
+	}
+
+	#[test]
+	fn survives_random_data_after_the_header() {
+		let mut rng = StdRng::seed_from_u64(146);
+		for _ in 0..2000 {
+			let mut psd = header();
+			let len = rng.gen_range(0..200);
+			psd.extend((0..len).map(|_| rng.gen::<u8>()));
+			if let Ok(image) = decode_psd(&psd) {
+				assert_eq!(image.dimensions(), (2, 2));
+			}
+		}
+	}
+
+	#[test]
+	fn rejects_images_too_large_to_decode() {
+		let image_with = |version: u16, width: u32, height: u32, depth: u16| {
+			let mut psd = header();
+			psd[4..6].copy_from_slice(&version.to_be_bytes());
+			psd[14..18].copy_from_slice(&height.to_be_bytes());
+			psd[18..22].copy_from_slice(&width.to_be_bytes());
+			psd[22..24].copy_from_slice(&depth.to_be_bytes());
+			push_u32(&mut psd, 0);
+			push_u32(&mut psd, 0);
+			psd.extend_from_slice(&[0; 8]);
+			// Rows of RLE compressed data that expand to the full width
+			push_u16(&mut psd, 1);
+			psd.extend(std::iter::repeat_n(0, 3 * height as usize * 4));
+			psd
+		};
+		// Too many pixels for a PSB file that is only a few megabytes
+		let result = decode_psd(&image_with(2, 300_000, 300_000, 8));
+		assert_eq!(result.unwrap_err().description, "The PSD file is too large to be decoded");
+		// Few enough pixels, but the 32 bit channels are too large together
+		let result = decode_psd(&image_with(2, 16_384, 16_384, 32));
+		assert_eq!(result.unwrap_err().description, "The PSD file is too large to be decoded");
+	}
+
+	#[test]
+	fn survives_corrupted_files() {
+		let mut rng = StdRng::seed_from_u64(146);
+		for psd in [flattened_psd(), layered_psd()] {
+			for _ in 0..2000 {
+				let mut psd = psd.clone();
+				for _ in 0..rng.gen_range(1..4) {
+					let pos = rng.gen_range(0..psd.len());
+					psd[pos] = rng.gen();
+				}
+				// Only the header can change the size, and it's limited
+				if let Ok(image) = decode_psd(&psd) {
+					assert!(image.width() as usize * image.height() as usize <= MAX_PIXELS);
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,64 @@
+//! Reading how many pixels of an image make up a millimeter, for measuring in real units.
+//!
+//! PNG files name it in their `pHYs` chunk, the others in the `XResolution` and
+//! `ResolutionUnit` fields of their EXIF data.
+
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use super::source;
+
+const PNG_SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+const MM_PER_INCH: f64 = 25.4;
+
+/// The number of pixels per millimeter along the width of the image, `None` if the file
+/// doesn't name its resolution
+pub fn pixels_per_mm(path: &Path) -> Option<f64> {
+	let mut reader = source::open(path).ok()?;
+	let mut signature = [0; 8];
+	reader.read_exact(&mut signature).ok()?;
+	if &signature == PNG_SIGNATURE {
+		return png_pixels_per_mm(&mut reader);
+	}
+	reader.seek(SeekFrom::Start(0)).ok()?;
+	let exif = exif::Reader::new().read_from_container(&mut reader).ok()?;
+	let resolution = match &exif.get_field(exif::Tag::XResolution, exif::In::PRIMARY)?.value {
+		exif::Value::Rational(values) => values.first().filter(|v| v.denom != 0)?.to_f64(),
+		_ => return None,
+	};
+	let unit = exif
+		.get_field(exif::Tag::ResolutionUnit, exif::In::PRIMARY)
+		.and_then(|field| field.value.get_uint(0))
+		.unwrap_or(2);
+	let mm_per_unit = match unit {
+		2 => MM_PER_INCH,
+		3 => 10.0,
+		// Without a unit only the aspect ratio of the pixels is known
+		_ => return None,
+	};
+	Some(resolution / mm_per_unit).filter(|&ppmm| ppmm > 0.0)
+}
+
+/// Reads the chunks after the signature until the `pHYs` chunk, which comes before the image
+/// data
+fn png_pixels_per_mm(reader: &mut impl Read) -> Option<f64> {
+	loop {
+		let mut header = [0; 8];
+		reader.read_exact(&mut header).ok()?;
+		let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+		match &header[4..] {
+			b"pHYs" if len == 9 => {
+				let mut data = [0; 9];
+				reader.read_exact(&mut data).ok()?;
+				let per_meter = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+				// The unit is 1 for meters, and 0 when only the aspect ratio is known
+				return (data[8] == 1 && per_meter > 0).then(|| per_meter as f64 / 1000.0);
+			}
+			b"IDAT" | b"IEND" => return None,
+			// Skips the data and the CRC
+			_ => {
+				std::io::copy(&mut reader.take(len as u64 + 4), &mut std::io::sink()).ok()?;
+			}
+		}
+	}
+}
@@ -0,0 +1,92 @@
+use std::cmp::Ordering;
+use std::path::Path;
+
+use super::source;
+use crate::configuration::SortOrder;
+
+/// The shooting parameters from the EXIF data of a photo
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct ShootingInfo {
+	/// The f-number
+	pub aperture: Option<f64>,
+	pub iso: Option<u32>,
+	/// In millimeters
+	pub focal_length: Option<f64>,
+	/// The exposure time in seconds
+	pub shutter_speed: Option<f64>,
+}
+
+impl ShootingInfo {
+	/// The value that the images are sorted by with `order`, `None` if the file doesn't have it
+	/// or if `order` doesn't sort by EXIF data
+	pub fn sort_key(&self, order: SortOrder) -> Option<f64> {
+		match order {
+			SortOrder::Name => None,
+			SortOrder::ExifAperture => self.aperture,
+			SortOrder::ExifIso => self.iso.map(f64::from),
+			SortOrder::ExifFocalLength => self.focal_length,
+			SortOrder::ExifShutterSpeed => self.shutter_speed,
+		}
+	}
+
+	/// Like "f/2.8" or "1/250 s"
+	pub fn describe(&self, order: SortOrder) -> Option<String> {
+		match order {
+			SortOrder::Name => None,
+			SortOrder::ExifAperture => self.aperture.map(|f| format!("f/{}", round_to(f, 1))),
+			SortOrder::ExifIso => self.iso.map(|iso| format!("ISO {}", iso)),
+			SortOrder::ExifFocalLength => {
+				self.focal_length.map(|mm| format!("{} mm", round_to(mm, 1)))
+			}
+			SortOrder::ExifShutterSpeed => self.shutter_speed.map(|s| {
+				if s > 0.0 && s < 1.0 {
+					format!("1/{} s", (1.0 / s).round())
+				} else {
+					format!("{} s", round_to(s, 1))
+				}
+			}),
+		}
+	}
+}
+
+fn round_to(value: f64, decimals: i32) -> f64 {
+	let scale = 10f64.powi(decimals);
+	(value * scale).round() / scale
+}
+
+/// Orders the keys from smallest to largest, with the missing keys at the end
+pub fn cmp_sort_keys(a: Option<f64>, b: Option<f64>) -> Ordering {
+	match (a, b) {
+		(Some(a), Some(b)) => a.total_cmp(&b),
+		(Some(_), None) => Ordering::Less,
+		(None, Some(_)) => Ordering::Greater,
+		(None, None) => Ordering::Equal,
+	}
+}
+
+/// Reads the shooting parameters of the file. The fields that the file doesn't have are `None`.
+pub fn shooting_info(path: &Path) -> ShootingInfo {
+	let Some(exif) = source::open(path)
+		.ok()
+		.and_then(|mut reader| exif::Reader::new().read_from_container(&mut reader).ok())
+	else {
+		return ShootingInfo::default();
+	};
+	let field = |tag| exif.get_field(tag, exif::In::PRIMARY);
+	let rational = |tag| match &field(tag)?.value {
+		exif::Value::Rational(values) => {
+			let value = values.first()?;
+			// Unknown values are stored as zeros
+			(value.denom != 0 && value.num != 0).then(|| value.to_f64())
+		}
+		_ => None,
+	};
+	ShootingInfo {
+		aperture: rational(exif::Tag::FNumber),
+		iso: field(exif::Tag::PhotographicSensitivity)
+			.and_then(|f| f.value.get_uint(0))
+			.filter(|&iso| iso != 0),
+		focal_length: rational(exif::Tag::FocalLength),
+		shutter_speed: rational(exif::Tag::ExposureTime),
+	}
+}
@@ -0,0 +1,83 @@
+//! Access to the contents of image files, which are either regular files or entries of an
+//! archive. See the `archive` module for how archive entries are addressed.
+
+use std::fs;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use super::archive::{read_entry, split_archive_path};
+
+/// A buffered reader over the contents of an image file
+pub enum SourceReader {
+	File(BufReader<fs::File>),
+	Memory(Cursor<Vec<u8>>),
+}
+
+impl Read for SourceReader {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		match self {
+			SourceReader::File(reader) => reader.read(buf),
+			SourceReader::Memory(reader) => reader.read(buf),
+		}
+	}
+}
+
+impl BufRead for SourceReader {
+	fn fill_buf(&mut self) -> io::Result<&[u8]> {
+		match self {
+			SourceReader::File(reader) => reader.fill_buf(),
+			SourceReader::Memory(reader) => reader.fill_buf(),
+		}
+	}
+
+	fn consume(&mut self, amt: usize) {
+		match self {
+			SourceReader::File(reader) => reader.consume(amt),
+			SourceReader::Memory(reader) => reader.consume(amt),
+		}
+	}
+}
+
+impl Seek for SourceReader {
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		match self {
+			SourceReader::File(reader) => reader.seek(pos),
+			SourceReader::Memory(reader) => reader.seek(pos),
+		}
+	}
+}
+
+/// Opens the file for reading. Archive entries are decompressed into memory.
+pub fn open(path: &Path) -> io::Result<SourceReader> {
+	match fs::File::open(path) {
+		Ok(file) => Ok(SourceReader::File(BufReader::new(file))),
+		Err(err) => match split_archive_path(path) {
+			Some((archive, name)) => {
+				Ok(SourceReader::Memory(Cursor::new(read_entry(archive, &name)?)))
+			}
+			None => Err(err),
+		},
+	}
+}
+
+/// Reads the whole file, like `std::fs::read`
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+	match fs::read(path) {
+		Ok(data) => Ok(data),
+		Err(err) => match split_archive_path(path) {
+			Some((archive, name)) => read_entry(archive, &name),
+			None => Err(err),
+		},
+	}
+}
+
+/// Returns the metadata of the file. For archive entries this is the metadata of the archive.
+pub fn metadata(path: &Path) -> io::Result<fs::Metadata> {
+	match fs::metadata(path) {
+		Ok(metadata) => Ok(metadata),
+		Err(err) => match split_archive_path(path) {
+			Some((archive, _)) => fs::metadata(archive),
+			None => Err(err),
+		},
+	}
+}
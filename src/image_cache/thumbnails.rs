@@ -0,0 +1,251 @@
+//! Generating the thumbnails of a whole folder ahead of time.
+//!
+//! The thumbnails are written to the cache folder as PNG files, named after a hash of the path
+//! and the modification time of the image. A thumbnail that already exists is not generated
+//! again, so it's cheap to start over after returning to a folder.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use gelatin::image::RgbaImage;
+use log::debug;
+
+use super::image_loader::{
+	apply_orientation, complex_load_image, ImageLoaderError, LoadResult, Result, ACTIVE_LOADS,
+	NON_EXISTENT_REQUEST_ID, PRIORITY_REQUEST_ID,
+};
+use super::source;
+use crate::headless::{render_image, shrink_to_fit, write_image};
+use crate::PROJECT_DIRS;
+
+/// The longer side of a thumbnail in pixels
+pub const THUMBNAIL_SIZE: u32 = 256;
+pub const DEFAULT_THUMBNAIL_THREADS: u32 = 2;
+
+/// The number of frames at the start of an animation that its representative frame is picked
+/// from
+const REPRESENTATIVE_SAMPLE_FRAMES: usize = 24;
+/// The number of pixels along each side of the grid that the detail of a frame is measured on
+const DETAIL_GRID_SIZE: u32 = 64;
+
+/// True if the thumbnails of animations should show their most detailed frame instead of the
+/// first one, set from `animated_thumbnail`
+pub static REPRESENTATIVE_FRAME: AtomicBool = AtomicBool::new(false);
+
+/// How long the generator waits before checking again whether the viewer is still loading an
+/// image that it needs right away
+const YIELD_INTERVAL: Duration = Duration::from_millis(20);
+
+/// The folder of the thumbnails, or `None` if there is no cache folder on this system
+pub fn thumbnail_dir() -> Option<PathBuf> {
+	PROJECT_DIRS.as_ref().map(|dirs| dirs.cache_dir().join("thumbnails"))
+}
+
+/// The file that holds the thumbnail of the image at `path`
+pub fn thumbnail_path(dir: &Path, path: &Path, modified: Option<SystemTime>) -> PathBuf {
+	// FNV-1a, because the names have to stay the same across builds
+	let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+	let modified = modified.and_then(|time| time.duration_since(UNIX_EPOCH).ok());
+	let nanos = modified.map_or(0, |duration| duration.as_nanos());
+	let path = path.to_string_lossy();
+	// So that switching `animated_thumbnail` doesn't show the thumbnails of the other setting
+	let variant: &[u8] = if REPRESENTATIVE_FRAME.load(Ordering::Relaxed) { b"repr" } else { b"" };
+	for byte in path.as_bytes().iter().chain(nanos.to_le_bytes().iter()).chain(variant) {
+		hash ^= *byte as u64;
+		hash = hash.wrapping_mul(0x0100_0000_01b3);
+	}
+	dir.join(format!("{:016x}.png", hash))
+}
+
+/// Decodes the image scaled down to fit `max_size`, from the thumbnail if there is one that's
+/// large enough
+pub fn render_small(path: &Path, max_size: u32) -> Result<RgbaImage> {
+	if max_size <= THUMBNAIL_SIZE {
+		if let Some(dir) = thumbnail_dir() {
+			let modified = source::metadata(path).and_then(|m| m.modified()).ok();
+			let thumbnail = thumbnail_path(&dir, path, modified);
+			if let Ok(image) = gelatin::image::open(&thumbnail) {
+				return Ok(shrink_to_fit(image.into_rgba8(), max_size, max_size));
+			}
+		}
+	}
+	render_thumbnail(path, max_size)
+}
+
+/// Decodes the frame that thumbnails show, scaled down to fit `max_size`
+fn render_thumbnail(path: &Path, max_size: u32) -> Result<RgbaImage> {
+	if !REPRESENTATIVE_FRAME.load(Ordering::Relaxed) {
+		return render_image(path, max_size);
+	}
+	let mut best: Option<(f64, RgbaImage)> = None;
+	complex_load_image(path, REPRESENTATIVE_SAMPLE_FRAMES, 0, |frame| {
+		if let LoadResult::Frame { image, orientation, .. } = frame {
+			let score = detail(&image);
+			if !matches!(&best, Some((best_score, _)) if *best_score >= score) {
+				best = Some((score, apply_orientation(image, orientation)));
+			}
+		}
+		Ok(())
+	})?;
+	let (_, image) = best
+		.ok_or_else(|| ImageLoaderError { description: "The image contains no frames".into() })?;
+	Ok(shrink_to_fit(image, max_size, max_size))
+}
+
+/// How much there is to see in the image, as the variance of the brightness of a grid of its
+/// pixels. Transparent pixels count as black.
+fn detail(image: &RgbaImage) -> f64 {
+	let (w, h) = image.dimensions();
+	let step_x = (w / DETAIL_GRID_SIZE).max(1) as usize;
+	let step_y = (h / DETAIL_GRID_SIZE).max(1) as usize;
+	let (mut sum, mut sum_sq, mut count) = (0.0, 0.0, 0.0);
+	for y in (0..h).step_by(step_y) {
+		for x in (0..w).step_by(step_x) {
+			let [r, g, b, a] = image.get_pixel(x, y).0;
+			let luma = 0.2126 * r as f64 + 0.7152 * g as f64 + 0.0722 * b as f64;
+			let luma = luma * a as f64 / 255.0;
+			sum += luma;
+			sum_sq += luma * luma;
+			count += 1.0;
+		}
+	}
+	if count == 0.0 {
+		return 0.0;
+	}
+	let mean = sum / count;
+	sum_sq / count - mean * mean
+}
+
+#[derive(Default)]
+struct Queue {
+	generation: u64,
+	paths: VecDeque<PathBuf>,
+}
+
+struct Shared {
+	queue: Mutex<Queue>,
+	job_available: Condvar,
+	/// The number of thumbnails of the current folder that are done
+	done: AtomicUsize,
+	/// The number of workers that are generating a thumbnail right now
+	busy: AtomicU32,
+}
+
+/// Generates the thumbnails of a folder on a few background threads.
+///
+/// Starting a new folder cancels the previous one. The workers wait while the viewer is
+/// loading an image that it needs right away, so that they don't slow it down.
+pub struct ThumbnailGenerator {
+	shared: Arc<Shared>,
+	threads: u32,
+	started_threads: bool,
+	total: usize,
+}
+
+impl ThumbnailGenerator {
+	pub fn new(threads: u32) -> ThumbnailGenerator {
+		let shared = Arc::new(Shared {
+			queue: Mutex::new(Queue::default()),
+			job_available: Condvar::new(),
+			done: AtomicUsize::new(0),
+			busy: AtomicU32::new(0),
+		});
+		ThumbnailGenerator { shared, threads: threads.max(1), started_threads: false, total: 0 }
+	}
+
+	pub fn set_threads(&mut self, threads: u32) {
+		if !self.started_threads {
+			self.threads = threads.max(1);
+		}
+	}
+
+	/// Starts generating the thumbnails of the images at `paths` in this order, and cancels
+	/// the previous folder
+	pub fn start(&mut self, paths: Vec<PathBuf>) {
+		let Some(dir) = thumbnail_dir() else {
+			return;
+		};
+		if !self.started_threads {
+			if let Err(e) = std::fs::create_dir_all(&dir) {
+				eprintln!("Could not create the thumbnail folder {:?}: {}", dir, e);
+				return;
+			}
+			for _ in 0..self.threads {
+				let shared = self.shared.clone();
+				let dir = dir.clone();
+				std::thread::spawn(move || run_worker(&shared, &dir));
+			}
+			self.started_threads = true;
+		}
+		self.total = paths.len();
+		let mut queue = self.shared.queue.lock().unwrap();
+		queue.generation += 1;
+		queue.paths = paths.into();
+		self.shared.done.store(0, Ordering::SeqCst);
+		self.shared.job_available.notify_all();
+	}
+
+	pub fn cancel(&mut self) {
+		let mut queue = self.shared.queue.lock().unwrap();
+		queue.generation += 1;
+		queue.paths.clear();
+		self.total = 0;
+	}
+
+	/// Returns the number of thumbnails that are done and the number of images in the folder,
+	/// or `None` when there is nothing left to do
+	pub fn progress(&self) -> Option<(usize, usize)> {
+		let done = self.shared.done.load(Ordering::SeqCst).min(self.total);
+		let idle = self.shared.queue.lock().unwrap().paths.is_empty()
+			&& self.shared.busy.load(Ordering::SeqCst) == 0;
+		if done >= self.total || idle {
+			None
+		} else {
+			Some((done, self.total))
+		}
+	}
+}
+
+fn run_worker(shared: &Shared, dir: &Path) {
+	loop {
+		let (generation, path) = {
+			let mut queue = shared.queue.lock().unwrap();
+			loop {
+				if let Some(path) = queue.paths.pop_front() {
+					shared.busy.fetch_add(1, Ordering::SeqCst);
+					break (queue.generation, path);
+				}
+				queue = shared.job_available.wait(queue).unwrap();
+			}
+		};
+		// The image that is shown and the images that are loaded ahead of it come first
+		while PRIORITY_REQUEST_ID.load(Ordering::SeqCst) != NON_EXISTENT_REQUEST_ID
+			|| ACTIVE_LOADS.load(Ordering::SeqCst) > 0
+		{
+			std::thread::sleep(YIELD_INTERVAL);
+		}
+		let cancelled = shared.queue.lock().unwrap().generation != generation;
+		let modified = source::metadata(&path).and_then(|m| m.modified()).ok();
+		let thumbnail = thumbnail_path(dir, &path, modified);
+		if !cancelled && !thumbnail.exists() {
+			// Written under a temporary name first, so that a reader never sees half a file
+			let partial = thumbnail.with_extension("partial.png");
+			let result = render_thumbnail(&path, THUMBNAIL_SIZE)
+				.and_then(|image| write_image(image, &partial))
+				.and_then(|()| Ok(std::fs::rename(&partial, &thumbnail)?));
+			if let Err(e) = result {
+				debug!("Could not generate the thumbnail of {:?}: {}", path, e);
+				let _ = std::fs::remove_file(&partial);
+			}
+		}
+		// Only count it if the folder hasn't changed in the meantime
+		let queue = shared.queue.lock().unwrap();
+		if queue.generation == generation {
+			shared.done.fetch_add(1, Ordering::SeqCst);
+		}
+		shared.busy.fetch_sub(1, Ordering::SeqCst);
+	}
+}
@@ -1,21 +1,70 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
-use std::process::Command;
 use std::rc::Rc;
 
-use crate::configuration::Configuration;
-use gelatin::winit::keyboard::ModifiersState;
+use crate::configuration::{Command as ConfigCommand, Configuration};
+use gelatin::winit::{event::MouseButton, keyboard::ModifiersState};
 use lazy_static::lazy_static;
 
 pub static TOGGLE_FULLSCREEN_NAME: &str = "toggle_fullscreen";
+pub static FULLSCREEN_NEXT_MONITOR_NAME: &str = "fullscreen_next_monitor";
 pub static ESCAPE_NAME: &str = "escape";
+pub static QUIT_NAME: &str = "quit";
+pub static TOGGLE_TOOLBAR_NAME: &str = "toggle_toolbar";
+pub static TOGGLE_STATUS_BAR_NAME: &str = "toggle_status_bar";
 pub static IMG_NEXT_NAME: &str = "img_next";
 pub static IMG_PREV_NAME: &str = "img_prev";
+pub static NEXT_GROUP_NAME: &str = "next_group";
+pub static PREV_GROUP_NAME: &str = "prev_group";
+pub static NEXT_DUPLICATE_NAME: &str = "next_duplicate";
+pub static AB_FLICKER_NAME: &str = "ab_flicker";
+pub static SET_REFERENCE_NAME: &str = "set_reference";
+pub static CLEAR_REFERENCE_NAME: &str = "clear_reference";
+pub static REFERENCE_DIFFERENCE_NAME: &str = "reference_difference";
+pub static REFERENCE_OPACITY_UP_NAME: &str = "reference_opacity_up";
+pub static REFERENCE_OPACITY_DOWN_NAME: &str = "reference_opacity_down";
+pub static TOGGLE_CLIPPING_WARNING_NAME: &str = "toggle_clipping_warning";
+pub static TOGGLE_TWO_PAGE_NAME: &str = "toggle_two_page";
+pub static TOGGLE_FIT_PADDING_NAME: &str = "toggle_fit_padding";
+pub static ROTATE_RIGHT_NAME: &str = "rotate_right";
+pub static ROTATE_LEFT_NAME: &str = "rotate_left";
+pub static ROTATE_STEP_CW_NAME: &str = "rotate_step_cw";
+pub static ROTATE_STEP_CCW_NAME: &str = "rotate_step_ccw";
+pub static RESET_ROTATION_NAME: &str = "reset_rotation";
+pub static STRAIGHTEN_NAME: &str = "straighten";
+pub static COMPOSITION_OVERLAY_NAME: &str = "composition_overlay";
+pub static TOGGLE_COORDINATES_NAME: &str = "toggle_coordinates";
+pub static MEASURE_NAME: &str = "measure";
+pub static TOGGLE_SCALE_BAR_NAME: &str = "toggle_scale_bar";
+pub static TOGGLE_FRAME_SCRUBBER_NAME: &str = "toggle_frame_scrubber";
 pub static IMG_ORIG_NAME: &str = "img_orig";
 pub static IMG_FIT_NAME: &str = "img_fit";
 pub static IMG_FIT_BEST_NAME: &str = "img_fit_best";
+pub static RESET_VIEW_NAME: &str = "reset_view";
 pub static IMG_DEL_NAME: &str = "img_del";
 pub static IMG_COPY_NAME: &str = "img_copy";
+pub static COPY_VIEW_NAME: &str = "copy_view";
+pub static TOGGLE_FOCUS_PEAKING_NAME: &str = "toggle_focus_peaking";
+pub static CYCLE_CHANNEL_VIEW_NAME: &str = "cycle_channel_view";
+pub static PEAKING_SENSITIVITY_UP_NAME: &str = "peaking_sensitivity_up";
+pub static PEAKING_SENSITIVITY_DOWN_NAME: &str = "peaking_sensitivity_down";
+pub static TOGGLE_WATERMARK_NAME: &str = "toggle_watermark";
+pub static UNDO_NAME: &str = "undo";
+pub static REDO_NAME: &str = "redo";
+pub static TOGGLE_DELETED_ITEMS_NAME: &str = "toggle_deleted_items";
+pub static CLEAR_DISK_CACHE_NAME: &str = "clear_disk_cache";
+pub static TOGGLE_LOW_MEMORY_NAME: &str = "toggle_low_memory";
+pub static TOGGLE_IGNORED_FILES_NAME: &str = "toggle_ignored_files";
+pub static TOGGLE_EMBEDDED_PREVIEW_NAME: &str = "toggle_embedded_preview";
+pub static PLAY_MOTION_PHOTO_NAME: &str = "play_motion_photo";
+pub static OPEN_IN_NEW_WINDOW_NAME: &str = "open_in_new_window";
+pub static TOGGLE_MIRROR_DISPLAY_NAME: &str = "toggle_mirror_display";
+pub static LINK_WINDOWS_NAME: &str = "link_windows";
+pub static UNLINK_WINDOWS_NAME: &str = "unlink_windows";
+pub static TRIAGE_A_NAME: &str = "triage_a";
+pub static TRIAGE_B_NAME: &str = "triage_b";
+pub static SET_TRIAGE_A_NAME: &str = "set_triage_a";
+pub static SET_TRIAGE_B_NAME: &str = "set_triage_b";
 pub static PAN_NAME: &str = "pan";
 pub static PAN_VERT_NAME: &str = "pan_vert"; // Vertical panning
 pub static PAN_HOR_NAME: &str = "pan_hor"; // Horizontal panning
@@ -24,35 +73,154 @@ pub static PLAY_PRESENT_NAME: &str = "play_present";
 pub static PLAY_PRESENT_RND_NAME: &str = "play_present_rnd";
 pub static TOGGLE_ANTIALIAS_NAME: &str = "toggle_antialias";
 pub static SET_AUTOMATIC_ANTIALIAS_NAME: &str = "automatic_antialias";
+pub static SAVE_FOLDER_SETTINGS_NAME: &str = "save_folder_settings";
+pub static CLEAR_FOLDER_SETTINGS_NAME: &str = "clear_folder_settings";
+pub static UI_SCALE_UP_NAME: &str = "ui_scale_up";
+pub static UI_SCALE_DOWN_NAME: &str = "ui_scale_down";
 pub static ZOOM_IN_NAME: &str = "zoom_in";
 pub static ZOOM_OUT_NAME: &str = "zoom_out";
 pub static PAN_LEFT_NAME: &str = "pan_left";
 pub static PAN_RIGHT_NAME: &str = "pan_right";
 pub static PAN_UP_NAME: &str = "pan_up";
 pub static PAN_DOWN_NAME: &str = "pan_down";
+pub static MAGNIFIER_NAME: &str = "magnifier";
 
 lazy_static! {
+	/// Set by `EMULSION_LOG_INPUT=1`, which prints every input with the actions it triggers to
+	/// find out why a binding doesn't work
+	pub static ref LOG_INPUT: bool = std::env::var("EMULSION_LOG_INPUT").as_deref() == Ok("1");
+
 	pub static ref DEFAULT_BINDINGS: HashMap<&'static str, Vec<&'static str>> = {
 		let mut m = HashMap::new();
 		m.insert(TOGGLE_FULLSCREEN_NAME, vec!["F11", "Return"]);
+		m.insert(FULLSCREEN_NEXT_MONITOR_NAME, vec!["Alt+F11"]);
 		m.insert(ESCAPE_NAME, vec!["Escape"]);
-		m.insert(IMG_NEXT_NAME, vec!["D", "Right", "PageDown"]);
-		m.insert(IMG_PREV_NAME, vec!["A", "Left", "PageUp"]);
+		m.insert(TOGGLE_TOOLBAR_NAME, vec!["T"]);
+		m.insert(TOGGLE_STATUS_BAR_NAME, vec!["I"]);
+		m.insert(IMG_NEXT_NAME, vec!["D", "Right", "PageDown", "MouseForward"]);
+		m.insert(IMG_PREV_NAME, vec!["A", "Left", "PageUp", "MouseBack"]);
+		m.insert(NEXT_GROUP_NAME, vec!["Alt+PageDown"]);
+		m.insert(PREV_GROUP_NAME, vec!["Alt+PageUp"]);
+		m.insert(NEXT_DUPLICATE_NAME, vec!["Alt+D"]);
+		m.insert(AB_FLICKER_NAME, vec!["B"]);
+		m.insert(SET_REFERENCE_NAME, vec!["Alt+B"]);
+		m.insert(CLEAR_REFERENCE_NAME, vec!["CmdCtrl+Alt+B"]);
+		m.insert(REFERENCE_DIFFERENCE_NAME, vec!["Alt+X"]);
+		m.insert(REFERENCE_OPACITY_UP_NAME, vec!["Alt+."]);
+		m.insert(REFERENCE_OPACITY_DOWN_NAME, vec!["Alt+,"]);
+		m.insert(TOGGLE_CLIPPING_WARNING_NAME, vec!["Alt+W"]);
+		m.insert(TOGGLE_FOCUS_PEAKING_NAME, vec!["K"]);
+		m.insert(CYCLE_CHANNEL_VIEW_NAME, vec!["Alt+N"]);
+		m.insert(PEAKING_SENSITIVITY_UP_NAME, vec!["Alt+K"]);
+		m.insert(PEAKING_SENSITIVITY_DOWN_NAME, vec!["CmdCtrl+Alt+K"]);
+		m.insert(TOGGLE_TWO_PAGE_NAME, vec!["2"]);
+		m.insert(TOGGLE_FIT_PADDING_NAME, vec!["Alt+M"]);
+		m.insert(ROTATE_RIGHT_NAME, vec!["R"]);
+		m.insert(ROTATE_LEFT_NAME, vec!["L"]);
+		m.insert(ROTATE_STEP_CW_NAME, vec!["]"]);
+		m.insert(ROTATE_STEP_CCW_NAME, vec!["["]);
+		m.insert(RESET_ROTATION_NAME, vec!["0"]);
+		m.insert(STRAIGHTEN_NAME, vec!["Alt+H"]);
+		m.insert(COMPOSITION_OVERLAY_NAME, vec!["G"]);
+		m.insert(TOGGLE_COORDINATES_NAME, vec!["Alt+C"]);
+		m.insert(MEASURE_NAME, vec!["U"]);
+		m.insert(TOGGLE_SCALE_BAR_NAME, vec!["Alt+U"]);
+		m.insert(TOGGLE_FRAME_SCRUBBER_NAME, vec!["Alt+T"]);
 		m.insert(IMG_ORIG_NAME, vec!["Q", "1"]);
 		m.insert(IMG_FIT_NAME, vec!["F"]);
 		m.insert(IMG_FIT_BEST_NAME, vec!["E"]);
+		m.insert(RESET_VIEW_NAME, vec!["Backspace"]);
 		m.insert(IMG_DEL_NAME, vec!["Delete"]);
 		m.insert(IMG_COPY_NAME, vec!["CmdCtrl+C"]);
+		m.insert(COPY_VIEW_NAME, vec!["CmdCtrl+Alt+C"]);
+		m.insert(TOGGLE_WATERMARK_NAME, vec!["Alt+O"]);
+		m.insert(UNDO_NAME, vec!["CmdCtrl+Z"]);
+		m.insert(REDO_NAME, vec!["CmdCtrl+Y", "CmdCtrl+Shift+Z"]);
+		m.insert(TOGGLE_DELETED_ITEMS_NAME, vec!["Alt+Delete"]);
+		m.insert(CLEAR_DISK_CACHE_NAME, vec!["CmdCtrl+Shift+Delete"]);
+		m.insert(TOGGLE_LOW_MEMORY_NAME, vec!["CmdCtrl+Alt+L"]);
+		m.insert(TOGGLE_IGNORED_FILES_NAME, vec!["CmdCtrl+Alt+I"]);
+		m.insert(TOGGLE_EMBEDDED_PREVIEW_NAME, vec!["CmdCtrl+Alt+E"]);
+		m.insert(PLAY_MOTION_PHOTO_NAME, vec!["Alt+L"]);
+		m.insert(OPEN_IN_NEW_WINDOW_NAME, vec!["CmdCtrl+N"]);
+		m.insert(TOGGLE_MIRROR_DISPLAY_NAME, vec!["CmdCtrl+Alt+F11"]);
+		m.insert(LINK_WINDOWS_NAME, vec!["CmdCtrl+Alt+J"]);
+		m.insert(UNLINK_WINDOWS_NAME, vec!["CmdCtrl+Alt+U"]);
+		m.insert(TRIAGE_A_NAME, vec!["Alt+Left"]);
+		m.insert(TRIAGE_B_NAME, vec!["Alt+Right"]);
+		m.insert(SET_TRIAGE_A_NAME, vec!["CmdCtrl+Alt+Left"]);
+		m.insert(SET_TRIAGE_B_NAME, vec!["CmdCtrl+Alt+Right"]);
 		m.insert(PAN_NAME, vec!["Space"]);
+		m.insert(MAGNIFIER_NAME, vec!["Z"]);
 		m.insert(PLAY_ANIM_NAME, vec!["Alt+A", "Alt+V"]);
 		m.insert(PLAY_PRESENT_NAME, vec!["P"]);
 		m.insert(PLAY_PRESENT_RND_NAME, vec!["Alt+P"]);
 		m.insert(TOGGLE_ANTIALIAS_NAME, vec!["S"]);
 		m.insert(SET_AUTOMATIC_ANTIALIAS_NAME, vec!["Alt+S"]);
+		m.insert(SAVE_FOLDER_SETTINGS_NAME, vec!["Alt+F"]);
+		m.insert(CLEAR_FOLDER_SETTINGS_NAME, vec!["Alt+R"]);
+		m.insert(UI_SCALE_UP_NAME, vec!["CmdCtrl+Alt+add"]);
+		m.insert(UI_SCALE_DOWN_NAME, vec!["CmdCtrl+Alt+subtract"]);
 		m
 	};
 }
 
+/// Returns true if `name` is an action that can be triggered by a single key press
+/// or mouse click
+pub fn is_action_name(name: &str) -> bool {
+	name == QUIT_NAME || DEFAULT_BINDINGS.contains_key(name)
+}
+
+/// The input key of the side buttons of the mouse, which are bound like the keys of the
+/// keyboard. The other buttons have their own options.
+pub fn mouse_button_input_key(button: MouseButton) -> Option<&'static str> {
+	match button {
+		MouseButton::Back => Some("mouseback"),
+		MouseButton::Forward => Some("mouseforward"),
+		_ => None,
+	}
+}
+
+/// Prints an input and the actions and commands that it triggers, also when it triggers
+/// nothing. `input_key` is the name that the bindings use for the input, `None` for the inputs
+/// that can't be bound.
+pub fn log_input(
+	config: &Rc<RefCell<Configuration>>,
+	description: &str,
+	input_key: Option<&str>,
+	modifiers: ModifiersState,
+) {
+	let held = [
+		(modifiers.control_key(), "Ctrl"),
+		(modifiers.alt_key(), "Alt"),
+		(modifiers.shift_key(), "Shift"),
+		(modifiers.super_key(), "Logo"),
+	];
+	let held: Vec<_> = held.iter().filter(|(held, _)| *held).map(|(_, name)| *name).collect();
+	let held = if held.is_empty() { "none".to_string() } else { held.join("+") };
+	let Some(input_key) = input_key else {
+		eprintln!("Input: {}, modifiers: {}", description, held);
+		return;
+	};
+	let mut triggered: Vec<_> = DEFAULT_BINDINGS
+		.keys()
+		.chain(std::iter::once(&QUIT_NAME))
+		.filter(|action| action_triggered(config, action, input_key, modifiers))
+		.map(|action| action.to_string())
+		.collect();
+	triggered.sort();
+	triggered.dedup();
+	let commands = triggered_commands(&config.borrow(), input_key, modifiers);
+	triggered
+		.extend(commands.iter().map(|command| format!("command {:?}", command.display_name())));
+	let triggered =
+		if triggered.is_empty() { "no action".to_string() } else { triggered.join(", ") };
+	eprintln!(
+		"Input: {}, input key: {:?}, modifiers: {} -> {}",
+		description, input_key, held, triggered
+	);
+}
+
 pub fn char_to_input_key(ch: &str) -> String {
 	let mut input_key = String::with_capacity(8);
 	if ch == " " {
@@ -67,47 +235,29 @@ pub fn char_to_input_key(ch: &str) -> String {
 	input_key
 }
 
-fn substitute_command_parameters(string: &str, var_map: &HashMap<&str, &str>) -> String {
-	let mut result = String::from(string);
-	for (&var_name, &substitute) in var_map.iter() {
-		result = result.replace(var_name, substitute);
-	}
-	result
-}
-
-/// Execute all custom commands that were triggered by the input key and modifier set.
-/// Note: img_path and folder_path both have to be str instead of Path because we
-/// wouldn't be able to construct a command from them if they cannot be converted to
-/// valid UTF-8.
-pub fn execute_triggered_commands(
-	config: Rc<RefCell<Configuration>>,
+/// Returns the custom commands that were triggered by the input key and modifier set, either
+/// through their own `input` or through the bindings of their name.
+pub fn triggered_commands(
+	config: &Configuration,
 	input_key: &str,
 	modifiers: ModifiersState,
-	img_path: &str,
-	folder_path: &str,
-) {
-	let config = config.borrow();
-	if let Some(ref commands) = config.commands {
-		let mut var_map = HashMap::with_capacity(2);
-		var_map.insert("${img}", img_path);
-		var_map.insert("${folder}", folder_path);
-		for command in commands.iter() {
-			if keys_triggered(&command.input, input_key, modifiers) {
-				let mut cmd = Command::new(&command.program);
-				if let Some(ref args) = command.args {
-					cmd.args(args.iter().map(|arg| substitute_command_parameters(arg, &var_map)));
-				}
-				if let Some(ref envs) = command.envs {
-					cmd.envs(
-						envs.iter().map(|env_var| (env_var.name.as_str(), env_var.value.as_str())),
-					);
-				}
-				if let Err(e) = cmd.status() {
-					eprintln!("Error while executing the following user command. See the error below.\n{:?}\nError: {:?}", command, e);
-				}
-			}
-		}
-	}
+) -> Vec<ConfigCommand> {
+	let Some(commands) = &config.commands else {
+		return Vec::new();
+	};
+	let bound_keys = |command: &ConfigCommand| {
+		let name = command.name.as_ref()?;
+		config.bindings.as_ref()?.get(name)
+	};
+	commands
+		.iter()
+		.filter(|command| {
+			keys_triggered(&command.input, input_key, modifiers)
+				|| bound_keys(command)
+					.is_some_and(|keys| keys_triggered(keys, input_key, modifiers))
+		})
+		.cloned()
+		.collect()
 }
 
 pub fn keys_triggered<S: AsRef<str>>(
@@ -3,10 +3,10 @@
 use std::cell::{Cell, RefCell};
 use std::f32;
 use std::fmt::Debug;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, PoisonError};
 use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 
@@ -21,7 +21,8 @@ use log::{debug, trace};
 use gelatin::winit::{
 	dpi::{PhysicalPosition, PhysicalSize},
 	event::WindowEvent,
-	window::Icon,
+	event_loop::EventLoopProxy,
+	window::{Icon, WindowId},
 };
 use gelatin::{
 	application::*,
@@ -31,28 +32,51 @@ use gelatin::{
 	line_layout_container::*,
 	misc::*,
 	picture::*,
-	window::{Window, WindowDescriptorBuilder},
+	window::{Renderer, Window, WindowDescriptorBuilder},
 	NextUpdate, Widget,
 };
 
 use crate::configuration::Theme;
-use crate::configuration::{Cache, ConfigWindowSection, Configuration};
+use crate::configuration::{
+	Cache, CloseButtonAction, ConfigWindowSection, Configuration, NamedUiScale, OpenBehavior,
+	OpenFile, UiScale,
+};
+use crate::image_cache::{disk_cache, image_loader::HDR_OUTPUT};
 use crate::version::Version;
 use crate::widgets::{
-	bottom_bar::BottomBar, copy_notification::CopyNotifications, help_screen::*, picture_widget::*,
+	badge::Badge,
+	bottom_bar::BottomBar,
+	copy_notification::CopyNotifications,
+	deleted_items::DeletedItemsPanel,
+	empty_state::EmptyState,
+	frame_scrubber::FrameScrubber,
+	help_screen::*,
+	hover_preview::HoverPreview,
+	picture_widget::*,
+	status_bar::StatusBar,
+	text_notification::TextNotification,
+	watermark::{Watermark, WatermarkStyle},
 };
 
+mod benchmark;
 mod clipboard_handler;
 mod cmd_line;
+mod command_runner;
 mod configuration;
+mod export;
 mod handle_panic;
+mod headless;
 mod image_cache;
 mod input_handling;
 mod parallel_action;
 mod playback_manager;
+mod registration;
 mod shaders;
+mod single_instance;
+mod undo_stack;
 mod utils;
 mod version;
+mod view_edits;
 mod widgets;
 
 lazy_static! {
@@ -72,6 +96,12 @@ pub enum EmulsionEvent {
 	/// Used to signal the event loop to wake up, because an image was loaded
 	/// and the UI may need to update to display the image
 	ImageLoaded,
+	/// Another instance was asked to open this path. See `single_instance`
+	Open { path: PathBuf, behavior: OpenBehavior },
+	/// Another instance was started without a path
+	Focus,
+	/// An action of the picture widget of a window asked for a window
+	WindowRequest(WindowId, WindowRequest),
 }
 
 // ========================================================
@@ -87,24 +117,138 @@ fn main() {
 
 	let args = cmd_line::parse_args(&config_path, &cache_path);
 
+	if let Some(render) = &args.render {
+		let result =
+			headless::render_to_file(render.input.as_ref(), render.output.as_ref(), render.size);
+		if let Err(e) = result {
+			eprintln!("Could not render {:?} to {:?}: {}", render.input, render.output, e);
+			std::process::exit(1);
+		}
+		return;
+	}
+
+	if let Some(export) = &args.export {
+		run_export(export, &config_path);
+		return;
+	}
+
+	if let Some(benchmark) = &args.benchmark {
+		run_benchmark(benchmark);
+		return;
+	}
+
+	if args.register || args.unregister {
+		let (result, done) = if args.register {
+			(registration::register(), "Registered emulsion as an application for images")
+		} else {
+			(registration::unregister(), "Removed the registration of emulsion")
+		};
+		match result {
+			Ok(()) => println!("{}", done),
+			Err(e) => {
+				eprintln!("{}", e);
+				std::process::exit(1);
+			}
+		}
+		return;
+	}
+
 	let cache = Cache::load(&cache_path);
 	let config = Configuration::load(&config_path);
 
 	debug!("Read cache: {cache:#?}");
 	debug!("Read config: {config:#?}");
 
+	let renderer = config.as_ref().ok().and_then(|c| c.window.as_ref()?.renderer.as_deref());
+	match parse_renderer(renderer) {
+		// This has to happen before any thread is started
+		Some(Renderer::Software) => gelatin::window::use_software_rendering(),
+		Some(_) => (),
+		None => {
+			eprintln!("Illegal configuration value {:?} for renderer!", renderer.unwrap());
+			eprintln!(r#"Allowed values are "auto", "hardware" and "software"."#);
+		}
+	}
+
 	let first_launch = cache.is_err();
 	let cache = Arc::new(Mutex::new(cache.unwrap_or_default()));
+	{
+		let cache = cache.clone();
+		std::thread::spawn(move || forget_deleted_folders(&cache));
+	}
 	let config = Rc::new(RefCell::new(config.unwrap_or_default()));
+	if let Some(preferred) = &config.borrow().decoders {
+		image_cache::decoders::set_preferred_decoders(preferred);
+	}
+
+	let register_file_types =
+		config.borrow().window.as_ref().and_then(|w| w.register_file_types).unwrap_or(false);
+	if first_launch && register_file_types {
+		std::thread::spawn(|| {
+			if let Err(e) = registration::register() {
+				eprintln!("Could not register emulsion as an application for images: {}", e);
+			}
+		});
+	}
 
 	if args.displayed_folders.is_some() {
 		config.borrow_mut().title.get_or_insert_with(Default::default).displayed_folders =
 			args.displayed_folders;
 	}
+	if args.safe {
+		config.borrow_mut().navigation.get_or_insert_with(Default::default).read_only = Some(true);
+	}
+	if args.single {
+		config.borrow_mut().navigation.get_or_insert_with(Default::default).on_open_file =
+			Some(OpenFile::SingleImage);
+	}
+	{
+		let mut config = config.borrow_mut();
+		let image_config = config.image.get_or_insert_with(Default::default);
+		if let Some(fit) = args.startup_fit {
+			image_config.startup_fit = Some(fit);
+		}
+		if let Some(antialiasing) = &args.antialiasing {
+			image_config.antialiasing = Some(antialiasing.clone());
+		}
+		if let Some(color) = args.letterbox_color {
+			image_config.letterbox_color = Some(color);
+		}
+		if let Some(order) = args.sort_order {
+			config.navigation.get_or_insert_with(Default::default).sort_order = Some(order);
+		}
+	}
+
+	let single_instance =
+		config.borrow().window.as_ref().and_then(|w| w.single_instance).unwrap_or(false);
+	let mut instance_listener = None;
+	if single_instance {
+		let socket_path = instance_socket_path(&cache_path);
+		let open = path_to_open(&args, &config.borrow());
+		let open = open.as_ref().map(|(path, behavior)| (path.as_path(), *behavior));
+		match single_instance::connect_or_listen(&socket_path, open) {
+			Ok(None) => return,
+			Ok(Some(listener)) => instance_listener = Some((listener, socket_path)),
+			Err(e) => eprintln!("Could not listen on {:?} for other instances: {}", socket_path, e),
+		}
+	}
+
+	let mut close_button_action =
+		config.borrow().window.as_ref().and_then(|w| w.close_button_action).unwrap_or_default();
+	if close_button_action == CloseButtonAction::Hide && !single_instance {
+		eprintln!(
+			"close_button_action = \"hide\" requires single_instance = true, the window will be minimized instead."
+		);
+		close_button_action = CloseButtonAction::Minimize;
+	}
+	let shutdown_timeout =
+		config.borrow().window.as_ref().and_then(|w| w.shutdown_timeout_ms).unwrap_or(2000);
+	// Quitting goes through the picture widget, which may ask for confirmation
+	set_exit_on_close(false);
 
 	let mut application = Application::new();
 
-	let app_handler = AppHandler {
+	let mut app_handler = AppHandler {
 		cache_path,
 		args,
 		first_launch,
@@ -116,16 +260,90 @@ fn main() {
 		update_available: Arc::new(AtomicBool::new(false)),
 		update_check_done: Arc::new(AtomicBool::new(false)),
 		ui_elements: None,
+		secondary_windows: Vec::new(),
+		mirror: None,
+		linked_windows: Vec::new(),
+		instance_socket_path: None,
+		close_button_action,
+		shutdown_timeout: Duration::from_millis(shutdown_timeout),
+		proxy: None,
+		pending_opens: Vec::new(),
 	};
 
-	let event_loop = event_loop::EventLoop::<()>::new();
+	let event_loop = event_loop::EventLoop::<EmulsionEvent>::new();
+	app_handler.proxy = Some(event_loop.create_proxy());
+	if let Some((listener, socket_path)) = instance_listener {
+		listener.start(event_loop.create_proxy());
+		app_handler.instance_socket_path = Some(socket_path);
+	}
 
 	application.start_event_loop(app_handler, event_loop);
 }
 
+fn run_export(export: &cmd_line::ExportArgs, config_path: &Path) {
+	let config = Configuration::load(config_path).unwrap_or_default();
+	let watermark = WatermarkStyle::for_export(&config);
+	// Ctrl+C stops the export and removes what was written so far, instead of killing the
+	// process before that can happen
+	#[cfg(unix)]
+	{
+		extern "C" fn on_interrupt(_: libc::c_int) {
+			export::cancel();
+		}
+		let handler: extern "C" fn(libc::c_int) = on_interrupt;
+		unsafe {
+			libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+		}
+	}
+	let report = |done: usize, total: usize| {
+		eprint!("\rExporting {}/{}", done, total);
+		if done == total {
+			eprintln!();
+		}
+	};
+	let result = match export {
+		cmd_line::ExportArgs::ContactSheet { folder, output, options } => {
+			let (folder, output) = (folder.as_ref(), output.as_ref());
+			export::export_contact_sheet(folder, output, *options, watermark.as_ref(), report)
+				.map(|()| PathBuf::from(output))
+		}
+		cmd_line::ExportArgs::Slideshow { folder, output, options } => {
+			let (folder, output) = (folder.as_ref(), output.as_ref());
+			export::export_slideshow(folder, output, *options, watermark.as_ref(), report)
+		}
+	};
+	match result {
+		Ok(output) => println!("Wrote {:?}", output),
+		Err(e) => {
+			eprintln!("\nCould not export: {}", e);
+			std::process::exit(1);
+		}
+	}
+}
+
+fn run_benchmark(args: &cmd_line::BenchmarkArgs) {
+	let report = |done: usize, total: usize| {
+		eprint!("\rDecoding {}/{}", done, total);
+		if done == total {
+			eprintln!();
+		}
+	};
+	match benchmark::run_benchmark(args.folder.as_ref(), args.options, report) {
+		Ok(results) if args.options.json => println!("{}", results.to_json()),
+		Ok(results) => println!("{}", results.to_table()),
+		Err(e) => {
+			eprintln!("\nCould not run the benchmark: {}", e);
+			std::process::exit(1);
+		}
+	}
+}
+
 struct UiElements {
 	set_theme: Rc<dyn Fn()>,
 
+	window: Rc<Window>,
+	picture_widget: Rc<PictureWidget>,
+
 	update_notification: Rc<HorizontalLayoutContainer>,
 	help_screen: Rc<HelpScreen>,
 }
@@ -145,13 +363,32 @@ struct AppHandler {
 	update_checker_join_handle: Option<JoinHandle<()>>,
 
 	ui_elements: Option<UiElements>,
+	/// The windows opened with `open_in_new_window`. Each of them browses on its own, and closing
+	/// one only closes that window.
+	secondary_windows: Vec<UiElements>,
+	/// The window of `toggle_mirror_display`, which shows what the main window shows
+	mirror: Option<UiElements>,
+	/// The windows of `link_windows`, which go to the next and previous images together
+	linked_windows: Vec<WindowId>,
+
+	/// The socket that other instances connect to, when running in single instance mode
+	instance_socket_path: Option<PathBuf>,
+	close_button_action: CloseButtonAction,
+	/// How long `shutdown` waits at most for the work in the background
+	shutdown_timeout: Duration,
+	proxy: Option<EventLoopProxy<EmulsionEvent>>,
+	/// The paths that other instances sent before the window was created
+	pending_opens: Vec<(PathBuf, OpenBehavior)>,
 }
 
 impl AppHandler {
+	/// Opens a window showing `open`. A `secondary` window is one opened with
+	/// `open_in_new_window`, which doesn't remember its area and doesn't check for updates.
 	fn create_window(
 		&mut self,
 		event_loop: &mut ActiveEventLoop,
-		args: cmd_line::Args,
+		open: Option<(PathBuf, OpenBehavior)>,
+		secondary: bool,
 		first_launch: bool,
 		cache: Arc<Mutex<Cache>>,
 		config: Rc<RefCell<Configuration>>,
@@ -161,14 +398,18 @@ impl AppHandler {
 			let window_cfg = &config.borrow().window;
 			let window_defaults = configuration::CacheWindowSection::default();
 
-			if let Some(ConfigWindowSection {
-				use_last_window_area: Some(false),
-				win_x,
-				win_y,
-				win_w,
-				win_h,
-				..
-			}) = window_cfg
+			// The other windows take the area of the main window
+			if let (
+				false,
+				Some(ConfigWindowSection {
+					use_last_window_area: Some(false),
+					win_x,
+					win_y,
+					win_w,
+					win_h,
+					..
+				}),
+			) = (secondary, window_cfg)
 			{
 				window_cache.win_x = if let Some(x) = win_x { *x } else { window_defaults.win_x };
 				window_cache.win_y = if let Some(y) = win_y { *y } else { window_defaults.win_y };
@@ -176,31 +417,111 @@ impl AppHandler {
 				window_cache.win_h = if let Some(h) = win_h { *h } else { window_defaults.win_h };
 			}
 
-			if let Some(window_cfg) = window_cfg {
+			if let (false, Some(window_cfg)) = (secondary, window_cfg) {
 				if let Some(start_maximized) = window_cfg.start_maximized {
 					window_cache.maximized = start_maximized;
 				}
 			}
 
+			let msaa_samples = match window_cfg.as_ref().and_then(|w| w.msaa_samples) {
+				None => 1,
+				Some(samples @ (1 | 2 | 4 | 8)) => samples,
+				Some(samples) => {
+					eprintln!("Illegal configuration value {:?} for msaa_samples!", samples);
+					eprintln!("Allowed values are 1, 2, 4 and 8.");
+					1
+				}
+			};
+
+			// An illegal value was reported at startup
+			let renderer = parse_renderer(window_cfg.as_ref().and_then(|w| w.renderer.as_deref()))
+				.unwrap_or_default();
+
+			// Whether HDR output is requested, and whether to complain if it's not available
+			let (request_hdr, require_hdr) =
+				match window_cfg.as_ref().and_then(|w| w.hdr.as_deref()) {
+					None | Some("auto") => (true, false),
+					Some("on") => (true, true),
+					Some("off") => (false, false),
+					Some(val) => {
+						eprintln!("Illegal configuration value {:?} for hdr!", val);
+						eprintln!(r#"Allowed values are "auto", "on" and "off"."#);
+						(true, false)
+					}
+				};
+
+			// The system places the other windows, so that they don't cover the main one
 			let pos = PhysicalPosition::new(window_cache.win_x, window_cache.win_y);
+			let pos = (!secondary).then_some(pos);
 			let size = PhysicalSize::new(window_cache.win_w, window_cache.win_h);
 			let window_desc = WindowDescriptorBuilder::default()
 				.icon(Some(make_icon()))
-				.maximized(window_cache.maximized)
+				.maximized(window_cache.maximized && !secondary)
 				.size(size)
-				.position(Some(pos))
+				.position(pos)
 				.app_id(Some("Emulsion".into()))
+				.msaa_samples(msaa_samples)
+				.renderer(renderer)
+				.hdr(request_hdr)
 				.build()
 				.unwrap();
 
-			let window = event_loop.create_window(window_desc).unwrap();
+			let window = match event_loop.create_window(window_desc) {
+				Ok(window) => window,
+				Err(err) => {
+					eprintln!("Could not create the window: {}", err);
+					std::process::exit(1);
+				}
+			};
+			debug!("Effective MSAA sample count: {}", window.msaa_samples());
+			if require_hdr && !window.hdr_output() {
+				eprintln!("HDR output was requested but it's not available. Using SDR output.");
+			}
+			HDR_OUTPUT.store(window.hdr_output(), Ordering::Relaxed);
+
+			match window_cfg.as_ref().and_then(|w| w.ui_scale_override) {
+				None | Some(UiScale::Named(NamedUiScale::Auto)) => {}
+				Some(UiScale::Factor(scale)) if (MIN_UI_SCALE..=MAX_UI_SCALE).contains(&scale) => {
+					window.set_ui_scale_override(Some(scale));
+				}
+				Some(UiScale::Factor(scale)) => {
+					eprintln!("Illegal configuration value {:?} for ui_scale_override!", scale);
+					eprintln!(
+						r#"Allowed values are "auto" and numbers from {} to {}."#,
+						MIN_UI_SCALE, MAX_UI_SCALE
+					);
+				}
+			}
+
+			match window_cfg.as_ref().and_then(|w| w.dim_on_unfocus) {
+				Some(dim) if (0.0..=1.0).contains(&dim) => window.set_unfocused_dim(dim),
+				Some(dim) => {
+					eprintln!("Illegal configuration value {} for dim_on_unfocus!", dim);
+					eprintln!("It has to be between 0 and 1.");
+				}
+				None => {}
+			}
 
-			if let Some(ConfigWindowSection { start_fullscreen: Some(true), .. }) = window_cfg {
-				window.set_fullscreen(true);
+			let start_fullscreen = window_cfg.as_ref().and_then(|w| w.start_fullscreen);
+			let restore_session = window_cfg.as_ref().and_then(|w| w.restore_session);
+			match (secondary, start_fullscreen) {
+				(false, Some(true)) => window.set_fullscreen(true),
+				(false, None) if restore_session == Some(true) && window_cache.fullscreen => {
+					match &window_cache.fullscreen_monitor {
+						Some(name) if !window.set_fullscreen_on(name) => {
+							debug!("The monitor {:?} is gone, starting in a window", name);
+						}
+						Some(_) => {}
+						None => window.set_fullscreen(true),
+					}
+				}
+				_ => {}
 			}
 			window
 		};
-		add_window_movement_listener(&window, cache.clone());
+		if !secondary {
+			add_window_movement_listener(&window, cache.clone());
+		}
 
 		let update_label_image = Rc::new(Picture::from_encoded_bytes(NEW_VERSION));
 		let update_label_image_light = Rc::new(Picture::from_encoded_bytes(NEW_VERSION_LIGHT));
@@ -215,34 +536,95 @@ impl AppHandler {
 
 		let copy_notifications_widget = Rc::new(Label::new());
 		let copy_notifications = CopyNotifications::new(&copy_notifications_widget);
-
-		let bottom_bar = Rc::new(BottomBar::new(&config.borrow()));
+		let notification_widget = Rc::new(Label::new());
+		let notification = TextNotification::new(&notification_widget);
+		let duplicate_badge_widget = Rc::new(Label::new());
+		let duplicate_badge = Badge::new(&duplicate_badge_widget, Alignment::End, Alignment::Start);
+		let thumbnail_badge_widget = Rc::new(Label::new());
+		let thumbnail_badge = Badge::new(&thumbnail_badge_widget, Alignment::Start, Alignment::End);
+		let warning_badge_widget = Rc::new(Label::new());
+		let warning_badge = Badge::new(&warning_badge_widget, Alignment::Start, Alignment::Start);
+		let error_card_widget = Rc::new(Label::new());
+		let error_card = Badge::new(&error_card_widget, Alignment::Center, Alignment::Center);
+		let edited_badge_widget = Rc::new(Label::new());
+		let edited_badge = Badge::new(&edited_badge_widget, Alignment::End, Alignment::End);
+		let scale_badge_widget = Rc::new(Label::new());
+		let scale_badge = Badge::new(&scale_badge_widget, Alignment::Center, Alignment::End);
+		let hover_preview_widget = Rc::new(Label::new());
+		let hover_preview = HoverPreview::new(&hover_preview_widget, &config.borrow());
+		let watermark_widget = Rc::new(Label::new());
+		let watermark = Watermark::new(&watermark_widget, &config.borrow());
+		let deleted_items = DeletedItemsPanel::new();
+		let deleted_items_widget = deleted_items.widget.clone();
+		let empty_state_widget = Rc::new(Label::new());
+		let empty_state = EmptyState::new(&empty_state_widget, &config.borrow());
+		let frame_scrubber = FrameScrubber::new(&config.borrow());
+		let frame_scrubber_widget = frame_scrubber.widget.clone();
+		let frame_slider = frame_scrubber.slider.clone();
+
+		let bottom_bar = Rc::new(BottomBar::new(&config.borrow(), &cache.lock().unwrap()));
+		bottom_bar.set_fullscreen(window.fullscreen());
+		let status_bar = Rc::new(StatusBar::new(&config.borrow()));
 		let picture_widget = make_picture_widget(
 			&window,
 			bottom_bar.clone(),
+			status_bar.clone(),
 			left_to_pan_hint.clone(),
 			copy_notifications,
+			notification,
+			duplicate_badge,
+			thumbnail_badge,
+			warning_badge,
+			error_card,
+			edited_badge,
+			scale_badge,
+			hover_preview,
+			watermark,
+			deleted_items,
+			empty_state,
+			frame_scrubber,
 			config.clone(),
 			cache.clone(),
 		);
 
-		if let Some(file_path) = &args.file_path {
-			picture_widget.jump_to_path(file_path);
+		if let Some((file_path, behavior)) = open {
+			picture_widget.jump_to_path(file_path, behavior);
+		}
+		if let Some(proxy) = self.proxy.clone() {
+			let window_id = window.get_id();
+			picture_widget.set_on_window_request(move |request| {
+				let _ = proxy.send_event(EmulsionEvent::WindowRequest(window_id, request));
+			});
 		}
 
 		let picture_area_container = make_picture_area_container();
 		picture_area_container.add_child(picture_widget.clone());
+		picture_area_container.add_child(empty_state_widget);
+		picture_area_container.add_child(watermark_widget);
 		picture_area_container.add_child(copy_notifications_widget);
+		picture_area_container.add_child(notification_widget);
+		picture_area_container.add_child(duplicate_badge_widget);
+		picture_area_container.add_child(thumbnail_badge_widget);
+		picture_area_container.add_child(warning_badge_widget);
+		picture_area_container.add_child(error_card_widget);
+		picture_area_container.add_child(edited_badge_widget);
+		picture_area_container.add_child(scale_badge_widget);
+		picture_area_container.add_child(hover_preview_widget);
+		picture_area_container.add_child(deleted_items_widget);
+		picture_area_container.add_child(frame_scrubber_widget);
 		picture_area_container.add_child(left_to_pan_hint);
 		picture_area_container.add_child(help_screen.clone());
 		picture_area_container.add_child(update_notification.clone());
 
 		let root_container = make_root_container();
 		root_container.add_child(picture_area_container);
+		root_container.add_child(status_bar.widget.clone());
 		root_container.add_child(bottom_bar.widget.clone());
 
-		self.update_available = Arc::new(AtomicBool::new(false));
-		self.update_check_done = Arc::new(AtomicBool::new(false));
+		if !secondary {
+			self.update_available = Arc::new(AtomicBool::new(false));
+			self.update_check_done = Arc::new(AtomicBool::new(false));
+		}
 
 		let theme = {
 			Rc::new(Cell::new(match &config.borrow().window {
@@ -251,31 +633,56 @@ impl AppHandler {
 			}))
 		};
 
+		// The callbacks only hold weak references to the widgets, so that reference cycles
+		// through the buttons don't keep a closed window alive
 		let set_theme = {
-			let update_label = update_label.clone();
-			let picture_widget = picture_widget.clone();
-			let update_notification = update_notification.clone();
-			let window = window.clone();
+			let update_label = Rc::downgrade(&update_label);
+			let picture_widget = Rc::downgrade(&picture_widget);
+			let update_notification = Rc::downgrade(&update_notification);
+			let window = Rc::downgrade(&window);
 			let theme = theme.clone();
 			let update_available = self.update_available.clone();
-			let bottom_bar = bottom_bar.clone();
+			let bottom_bar = Rc::downgrade(&bottom_bar);
+			let status_bar = Rc::downgrade(&status_bar);
 
 			Rc::new(move || {
+				let (
+					Some(update_label),
+					Some(picture_widget),
+					Some(update_notification),
+					Some(window),
+					Some(bottom_bar),
+					Some(status_bar),
+				) = (
+					update_label.upgrade(),
+					picture_widget.upgrade(),
+					update_notification.upgrade(),
+					window.upgrade(),
+					bottom_bar.upgrade(),
+					status_bar.upgrade(),
+				)
+				else {
+					return;
+				};
 				match theme.get() {
 					Theme::Light => {
 						picture_widget.set_bright_shade(0.96);
 						window.set_bg_color([0.85, 0.85, 0.85, 1.0]);
+						window.set_focus_color([0.05, 0.25, 0.7, 1.0]);
 						update_notification.set_bg_color([0.06, 0.06, 0.06, 1.0]);
 						update_label.set_icon(Some(update_label_image_light.clone()));
 					}
 					Theme::Dark => {
 						picture_widget.set_bright_shade(0.11);
 						window.set_bg_color([0.03, 0.03, 0.03, 1.0]);
+						window.set_focus_color([0.45, 0.65, 1.0, 1.0]);
 						update_notification.set_bg_color([0.85, 0.85, 0.85, 1.0]);
 						update_label.set_icon(Some(update_label_image.clone()));
 					}
 				}
 				bottom_bar.set_theme(theme.get(), update_available.load(Ordering::SeqCst));
+				status_bar.set_theme(theme.get());
+				picture_widget.set_theme(theme.get());
 			})
 		};
 		set_theme();
@@ -290,27 +697,49 @@ impl AppHandler {
 			});
 		}
 		{
-			let slider = bottom_bar.slider.clone();
-			let picture_widget = picture_widget.clone();
+			let slider = Rc::downgrade(&frame_slider);
+			let picture_widget = Rc::downgrade(&picture_widget);
+			frame_slider.set_on_value_change(move || {
+				if let (Some(slider), Some(picture_widget)) =
+					(slider.upgrade(), picture_widget.upgrade())
+				{
+					picture_widget.seek_frame(slider.value());
+				}
+			});
+		}
+		{
+			let slider = Rc::downgrade(&bottom_bar.slider);
+			let picture_widget = Rc::downgrade(&picture_widget);
 			bottom_bar.slider.set_on_value_change(move || {
-				picture_widget.jump_to_index(slider.value());
+				if let (Some(slider), Some(picture_widget)) =
+					(slider.upgrade(), picture_widget.upgrade())
+				{
+					picture_widget.jump_to_index(slider.value());
+				}
 			});
 		}
 		{
-			let picture_widget = picture_widget.clone();
+			let picture_widget = Rc::downgrade(&picture_widget);
 			bottom_bar.orig_scale_button.set_on_click(move || {
-				picture_widget.set_img_size_to_orig();
+				if let Some(picture_widget) = picture_widget.upgrade() {
+					picture_widget.set_img_size_to_orig();
+				}
 			});
 		}
 		{
-			let picture_widget = picture_widget.clone();
+			let picture_widget = Rc::downgrade(&picture_widget);
 			bottom_bar.fit_best_button.set_on_click(move || {
-				picture_widget.set_img_size_to_fit(false);
+				if let Some(picture_widget) = picture_widget.upgrade() {
+					picture_widget.set_img_size_to_fit(false);
+				}
 			});
 		}
 		{
+			let picture_widget = Rc::downgrade(&picture_widget);
 			bottom_bar.fit_stretch_button.set_on_click(move || {
-				picture_widget.set_img_size_to_fit(true);
+				if let Some(picture_widget) = picture_widget.upgrade() {
+					picture_widget.set_img_size_to_fit(true);
+				}
 			});
 		}
 		let help_visible = Cell::new(first_launch);
@@ -321,9 +750,12 @@ impl AppHandler {
 			let update_available = self.update_available.clone();
 			let help_screen = help_screen.clone();
 			let update_notification = update_notification.clone();
-			let bottom_bar_clone = bottom_bar.clone();
+			let bottom_bar_weak = Rc::downgrade(&bottom_bar);
 
 			bottom_bar.help_button.set_on_click(move || {
+				let Some(bottom_bar_clone) = bottom_bar_weak.upgrade() else {
+					return;
+				};
 				help_visible.set(!help_visible.get());
 				help_screen.set_visible(help_visible.get());
 				bottom_bar_clone.set_help_visible(help_visible.get());
@@ -337,6 +769,14 @@ impl AppHandler {
 		let check_updates_enabled =
 			config.borrow().updates.as_ref().map(|u| u.check_updates).unwrap_or(true);
 
+		let ui_elements =
+			UiElements { set_theme, window, picture_widget, update_notification, help_screen };
+		if secondary {
+			self.secondary_windows.push(ui_elements);
+			return None;
+		}
+		self.ui_elements = Some(ui_elements);
+
 		let update_checker_join_handle = {
 			let updates = &mut cache.lock().unwrap().updates;
 			let cache = cache.clone();
@@ -358,29 +798,222 @@ impl AppHandler {
 			}
 		};
 
-		self.ui_elements = Some(UiElements { set_theme, update_notification, help_screen });
-
 		update_checker_join_handle
 	}
+
+	/// Opens the mirror display, or closes it if it's open
+	fn toggle_mirror(&mut self, event_loop: &mut ActiveEventLoop) {
+		let Some(ui) = &self.ui_elements else {
+			return;
+		};
+		let picture_widget = ui.picture_widget.clone();
+		if let Some(mirror) = self.mirror.take() {
+			event_loop.close_window(mirror.window.get_id());
+			picture_widget.show_notification("Closed the mirror display");
+			return;
+		}
+		let window_cfg = self.config.borrow().window.clone();
+		let mirror_monitor = window_cfg.and_then(|w| w.mirror_monitor);
+		let main_monitor = ui.window.monitor_index();
+		let (cache, config) = (self.cache.clone(), self.config.clone());
+		self.create_window(event_loop, None, true, false, cache, config);
+		let Some(mirror) = self.secondary_windows.pop() else {
+			return;
+		};
+		// Only the image is shown, and the mirror doesn't take any requests of its own
+		mirror.window.set_root(mirror.picture_widget.clone());
+		mirror.window.set_bg_color([0.0, 0.0, 0.0, 1.0]);
+		mirror.picture_widget.set_bright_shade(0.0);
+		mirror.picture_widget.set_on_window_request(|_| {});
+		let monitor = mirror_monitor.or_else(|| {
+			(0..mirror.window.monitor_count()).find(|&index| Some(index) != main_monitor)
+		});
+		let text = match monitor.and_then(|index| mirror.window.fullscreen_on_monitor(index)) {
+			Some(name) => format!("Mirroring to {}", name),
+			None if mirror_monitor.is_some() => {
+				eprintln!(
+					"Illegal configuration value {} for mirror_monitor!",
+					mirror_monitor.unwrap()
+				);
+				eprintln!("There are {} monitors.", mirror.window.monitor_count());
+				"Mirroring to a window".to_string()
+			}
+			None => "Mirroring to a window, there's no other monitor".to_string(),
+		};
+		picture_widget.show_notification(&text);
+		self.mirror = Some(mirror);
+		self.update_mirror();
+	}
+
+	/// Shows the image and the view of the main window on the mirror display
+	fn update_mirror(&self) {
+		let (Some(ui), Some(mirror)) = (&self.ui_elements, &self.mirror) else {
+			return;
+		};
+		let mirror_view = self.config.borrow().window.as_ref().and_then(|w| w.mirror_view);
+		let state = ui.picture_widget.mirror_state(mirror_view.unwrap_or_default());
+		mirror.picture_widget.apply_mirror_state(&state);
+	}
+
+	/// The main window and the ones opened with `open_in_new_window`
+	fn windows(&self) -> impl Iterator<Item = &UiElements> {
+		self.ui_elements.iter().chain(self.secondary_windows.iter())
+	}
+
+	/// Links all the windows, so that going to the next or the previous image in one of them
+	/// does the same in the others. Each window stays in its own folder.
+	fn link_windows(&mut self, window_id: WindowId) {
+		self.linked_windows = self.windows().map(|ui| ui.window.get_id()).collect();
+		let count = self.linked_windows.len();
+		if count < 2 {
+			self.linked_windows.clear();
+		}
+		for ui in self.windows() {
+			ui.picture_widget.set_linked(count >= 2);
+		}
+		let text = match count {
+			0 | 1 => "There are no other windows to link to".to_string(),
+			count => format!("Linked {} windows", count),
+		};
+		if let Some(ui) = self.windows().find(|ui| ui.window.get_id() == window_id) {
+			ui.picture_widget.show_notification(&text);
+		}
+	}
+
+	fn unlink_windows_silently(&mut self) {
+		self.linked_windows.clear();
+		for ui in self.windows() {
+			ui.picture_widget.set_linked(false);
+		}
+	}
+
+	fn unlink_windows(&mut self, window_id: WindowId) {
+		let was_linked = !self.linked_windows.is_empty();
+		self.unlink_windows_silently();
+		let text = if was_linked { "Unlinked the windows" } else { "The windows aren't linked" };
+		if let Some(ui) = self.windows().find(|ui| ui.window.get_id() == window_id) {
+			ui.picture_widget.show_notification(text);
+		}
+	}
+
+	/// The linked windows other than `window_id`, or none if that one isn't linked
+	fn linked_to(&self, window_id: WindowId) -> impl Iterator<Item = &UiElements> {
+		let linked = self.linked_windows.contains(&window_id);
+		self.windows().filter(move |ui| {
+			let id = ui.window.get_id();
+			linked && id != window_id && self.linked_windows.contains(&id)
+		})
+	}
+
+	/// Shows the zoom and the position of the image of `window_id` in the windows linked to it,
+	/// with `link_view`
+	fn update_linked_views(&self, window_id: WindowId) {
+		let link_view = self.config.borrow().window.as_ref().and_then(|w| w.link_view);
+		if !link_view.unwrap_or(false) {
+			return;
+		}
+		let Some(source) = self.windows().find(|ui| ui.window.get_id() == window_id) else {
+			return;
+		};
+		let state = source.picture_widget.linked_view();
+		for ui in self.linked_to(window_id) {
+			ui.picture_widget.apply_mirror_state(&state);
+		}
+	}
+
+	/// Saves the state that is kept between sessions, however the program is quit. The work in
+	/// the background is waited for until `shutdown_timeout`, so that nothing can keep the
+	/// program from exiting.
+	fn shutdown(&mut self) {
+		let deadline = Instant::now() + self.shutdown_timeout;
+		if let Some(socket_path) = &self.instance_socket_path {
+			let _ = std::fs::remove_file(socket_path);
+		}
+		// For `restore_session`
+		if let Some(ui) = &self.ui_elements {
+			let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+			let fullscreen = ui.window.fullscreen();
+			cache.window.fullscreen = fullscreen;
+			cache.window.fullscreen_monitor = ui.window.monitor_name().filter(|_| fullscreen);
+			cache.window.two_page = ui.picture_widget.two_page();
+		}
+		let update_checker = self.update_checker_join_handle.take();
+		save_state(&self.cache, &self.cache_path, update_checker, deadline);
+	}
+
+	/// Closes the window that the close button was pressed on, following `close_button_action`
+	/// for the main window. When the main window is closed while other windows are open, one of
+	/// them becomes the main window instead of quitting.
+	fn close_window(&mut self, event_loop: &mut ActiveEventLoop, window_id: WindowId) {
+		self.linked_windows.retain(|&id| id != window_id);
+		if self.linked_windows.len() < 2 {
+			self.unlink_windows_silently();
+		}
+		if self.mirror.as_ref().is_some_and(|mirror| mirror.window.get_id() == window_id) {
+			self.mirror = None;
+			event_loop.close_window(window_id);
+			return;
+		}
+		let index = self.secondary_windows.iter().position(|ui| ui.window.get_id() == window_id);
+		if let Some(index) = index {
+			self.secondary_windows.remove(index);
+			event_loop.close_window(window_id);
+			return;
+		}
+		let Some(ui) = &self.ui_elements else {
+			request_exit();
+			return;
+		};
+		match self.close_button_action {
+			CloseButtonAction::Quit if !self.secondary_windows.is_empty() => {
+				event_loop.close_window(window_id);
+				if let Some(mirror) = self.mirror.take() {
+					event_loop.close_window(mirror.window.get_id());
+				}
+				let ui = self.secondary_windows.remove(0);
+				// Only the main window remembers its area, which is now this one
+				if !ui.window.fullscreen() {
+					store_window_area(&ui.window, &mut self.cache.lock().unwrap());
+				}
+				add_window_movement_listener(&ui.window, self.cache.clone());
+				self.ui_elements = Some(ui);
+			}
+			CloseButtonAction::Quit => ui.picture_widget.request_quit(),
+			CloseButtonAction::Minimize => ui.window.set_minimized(true),
+			CloseButtonAction::Hide => ui.window.set_visible(false),
+		}
+	}
 }
 
-impl ApplicationHandler for AppHandler {
+impl ApplicationHandler<EmulsionEvent> for AppHandler {
 	fn handle_can_create_surface(&mut self, event_loop: &mut ActiveEventLoop) {
+		let open = path_to_open(&self.args, &self.config.borrow());
 		self.update_checker_join_handle = self.create_window(
 			event_loop,
-			self.args.clone(),
+			open,
+			false,
 			self.first_launch,
 			self.cache.clone(),
 			self.config.clone(),
 		);
+		if let Some(ui) = &self.ui_elements {
+			for (path, behavior) in self.pending_opens.drain(..) {
+				ui.picture_widget.jump_to_path(path, behavior);
+			}
+		}
 	}
 
 	fn handle_window_event(
 		&mut self,
-		_event_loop: &ActiveEventLoop,
-		_window_id: gelatin::winit::window::WindowId,
-		_event: &WindowEvent,
+		event_loop: &mut ActiveEventLoop,
+		window_id: WindowId,
+		event: &WindowEvent,
 	) -> NextUpdate {
+		if let WindowEvent::CloseRequested = event {
+			self.close_window(event_loop, window_id);
+		}
+		self.update_mirror();
+		self.update_linked_views(window_id);
 		if self.update_presented {
 			return NextUpdate::Latest;
 		}
@@ -396,11 +1029,50 @@ impl ApplicationHandler for AppHandler {
 		NextUpdate::WaitUntil(Instant::now() + Duration::from_secs(1))
 	}
 
-	fn exiting(&mut self) {
-		self.cache.lock().unwrap().save(&self.cache_path).unwrap();
-		if let Some(h) = self.update_checker_join_handle.take() {
-			h.join().unwrap();
+	fn handle_user_event(
+		&mut self,
+		event_loop: &mut ActiveEventLoop,
+		event: EmulsionEvent,
+	) -> NextUpdate {
+		let Some(ui) = &self.ui_elements else {
+			if let EmulsionEvent::Open { path, behavior } = event {
+				self.pending_opens.push((path, behavior));
+			}
+			return NextUpdate::Latest;
+		};
+		match event {
+			EmulsionEvent::ImageLoaded => {}
+			EmulsionEvent::Open { path, behavior } => {
+				ui.picture_widget.jump_to_path(path, behavior);
+				ui.window.focus();
+			}
+			EmulsionEvent::Focus => ui.window.focus(),
+			EmulsionEvent::WindowRequest(_, WindowRequest::OpenInNewWindow(path)) => {
+				let open = Some((path, OpenBehavior::FirstImage));
+				let (cache, config) = (self.cache.clone(), self.config.clone());
+				self.create_window(event_loop, open, true, false, cache, config);
+			}
+			EmulsionEvent::WindowRequest(_, WindowRequest::ToggleMirrorDisplay) => {
+				self.toggle_mirror(event_loop);
+			}
+			EmulsionEvent::WindowRequest(window_id, WindowRequest::LinkWindows) => {
+				self.link_windows(window_id);
+			}
+			EmulsionEvent::WindowRequest(window_id, WindowRequest::UnlinkWindows) => {
+				self.unlink_windows(window_id);
+			}
+			EmulsionEvent::WindowRequest(window_id, WindowRequest::LinkedStep { forward }) => {
+				for ui in self.linked_to(window_id) {
+					ui.picture_widget.step_linked(forward);
+				}
+			}
 		}
+		self.update_mirror();
+		NextUpdate::Soonest
+	}
+
+	fn exiting(&mut self) {
+		self.shutdown();
 	}
 }
 
@@ -411,8 +1083,110 @@ fn make_icon() -> Icon {
 	Icon::from_rgba(rgba.into_raw(), w, h).unwrap()
 }
 
+/// Returns the path given on the command line, and what to do after opening it.
+fn path_to_open(args: &cmd_line::Args, config: &Configuration) -> Option<(PathBuf, OpenBehavior)> {
+	let file_path = args.file_path.as_ref()?;
+	let behavior = args.open_behavior.unwrap_or_else(|| config.open_behavior(file_path.as_ref()));
+	let file_path = match &args.start {
+		Some(pattern) => start_image_path(file_path.as_ref(), pattern),
+		None => file_path.into(),
+	};
+	Some((file_path, behavior))
+}
+
+/// Finds the image to show first for the `--start` argument.
+/// Returns the folder itself if there's no image matching the pattern.
+fn start_image_path(folder: &Path, pattern: &str) -> PathBuf {
+	let folder = if folder.is_dir() { folder } else { folder.parent().unwrap_or(folder) };
+	match image_cache::directory::find_image_by_name(folder, pattern) {
+		Ok(Some(path)) => path,
+		Ok(None) => {
+			eprintln!(
+				"No image matches {:?} in {:?}, starting at the first image",
+				pattern, folder
+			);
+			folder.to_owned()
+		}
+		Err(e) => {
+			eprintln!("Could not search {:?} for the start image: {}", folder, e);
+			folder.to_owned()
+		}
+	}
+}
+
+/// Writes `cache` to `cache_path`, once the update check and the writes to the cache on disk
+/// are done or `deadline` has passed
+fn save_state(
+	cache: &Mutex<Cache>,
+	cache_path: &Path,
+	update_checker: Option<JoinHandle<()>>,
+	deadline: Instant,
+) {
+	// The update check stores when it was done in the cache, so it's saved afterwards
+	if let Some(handle) = update_checker {
+		while !handle.is_finished() && Instant::now() < deadline {
+			std::thread::sleep(Duration::from_millis(10));
+		}
+		if handle.is_finished() {
+			let _ = handle.join();
+		}
+	}
+	if !disk_cache::wait_for_writes(deadline) {
+		eprintln!("Some images were left out of the cache on disk, writing them took too long");
+	}
+	let mut cache = cache.lock().unwrap_or_else(PoisonError::into_inner);
+	if let Err(e) = cache.save(cache_path) {
+		eprintln!("{}", e);
+	}
+}
+
+/// Returns `None` if `renderer` isn't the name of a renderer. No name means `Renderer::Auto`
+fn parse_renderer(renderer: Option<&str>) -> Option<Renderer> {
+	match renderer {
+		None | Some("auto") => Some(Renderer::Auto),
+		Some("hardware") => Some(Renderer::Hardware),
+		Some("software") => Some(Renderer::Software),
+		Some(_) => None,
+	}
+}
+
+/// Forgets the saved view settings of the folders that were deleted. This looks up every
+/// folder, which can take a while on network drives, so it's done without holding the lock.
+/// The folders that can't be looked up for other reasons, like an unplugged drive, are kept.
+fn forget_deleted_folders(cache: &Mutex<Cache>) {
+	let folders = cache.lock().unwrap_or_else(PoisonError::into_inner).folders.folders();
+	let deleted = folders
+		.into_iter()
+		.filter(|folder| {
+			matches!(
+				image_cache::source::metadata(folder),
+				Err(e) if e.kind() == std::io::ErrorKind::NotFound
+			)
+		})
+		.collect::<Vec<_>>();
+	let mut cache = cache.lock().unwrap_or_else(PoisonError::into_inner);
+	for folder in deleted {
+		cache.folders.remove(&folder);
+	}
+}
+
+/// Keeps the current area of `window` in the cache, as the movement listener would
+fn store_window_area(window: &Window, cache: &mut Cache) {
+	let winit_window = window.window_mut();
+	let size = winit_window.inner_size();
+	cache.window.win_w = size.width;
+	cache.window.win_h = size.height;
+	if let Ok(pos) = winit_window.outer_position() {
+		cache.window.win_x = pos.x;
+		cache.window.win_y = pos.y;
+	}
+	cache.window.maximized = winit_window.is_maximized();
+}
+
 fn add_window_movement_listener(window: &Window, cache: Arc<Mutex<Cache>>) {
 	window.add_global_event_handler(move |window, event| match event {
+		// The area of the window is restored when leaving fullscreen, so it's the one to keep
+		WindowEvent::Resized(_) | WindowEvent::Moved(_) if window.fullscreen() => {}
 		WindowEvent::Resized(new_size) => {
 			let mut cache = cache.lock().unwrap();
 			cache.window.win_w = new_size.width;
@@ -477,11 +1251,25 @@ fn make_update_notification(update_label: Rc<Label>) -> Rc<HorizontalLayoutConta
 	container
 }
 
+#[allow(clippy::too_many_arguments)]
 fn make_picture_widget(
 	window: &Rc<Window>,
 	bottom_bar: Rc<BottomBar>,
+	status_bar: Rc<StatusBar>,
 	left_to_pan_hint: Rc<HelpScreen>,
 	copy_notifications: CopyNotifications,
+	notification: TextNotification,
+	duplicate_badge: Badge,
+	thumbnail_badge: Badge,
+	warning_badge: Badge,
+	error_card: Badge,
+	edited_badge: Badge,
+	scale_badge: Badge,
+	hover_preview: HoverPreview,
+	watermark: Watermark,
+	deleted_items: DeletedItemsPanel,
+	empty_state: EmptyState,
+	frame_scrubber: FrameScrubber,
 	config: Rc<RefCell<Configuration>>,
 	cache: Arc<Mutex<Cache>>,
 ) -> Rc<PictureWidget> {
@@ -489,8 +1277,21 @@ fn make_picture_widget(
 		&window.display_mut(),
 		window,
 		bottom_bar,
+		status_bar,
 		left_to_pan_hint,
 		copy_notifications,
+		notification,
+		duplicate_badge,
+		thumbnail_badge,
+		warning_badge,
+		error_card,
+		edited_badge,
+		scale_badge,
+		hover_preview,
+		watermark,
+		deleted_items,
+		empty_state,
+		frame_scrubber,
 		config,
 		cache,
 	));
@@ -499,6 +1300,16 @@ fn make_picture_widget(
 	picture_widget
 }
 
+/// The socket is placed in the runtime directory if there is one, otherwise next to the cache.
+fn instance_socket_path(cache_path: &Path) -> PathBuf {
+	let runtime_dir = PROJECT_DIRS.as_ref().and_then(|dirs| dirs.runtime_dir());
+	let folder = match runtime_dir {
+		Some(runtime_dir) if std::fs::create_dir_all(runtime_dir).is_ok() => runtime_dir,
+		_ => cache_path.parent().unwrap(),
+	};
+	folder.join("instance.sock")
+}
+
 pub fn get_config_and_cache_paths() -> (PathBuf, PathBuf) {
 	let config_folder;
 	let cache_folder;
@@ -587,3 +1398,64 @@ mod update {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::configuration::{Antialias, FolderScaling, FolderViewSettings};
+	use std::sync::mpsc;
+
+	#[test]
+	fn the_state_is_saved_when_the_deadline_has_passed() {
+		let dir = std::env::temp_dir().join(format!("emulsion-shutdown-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		let cache_path = dir.join("cache.toml");
+		let mut cache = Cache::default();
+		cache.window.win_w = 1234;
+		cache.window.win_h = 567;
+		let cache = Mutex::new(cache);
+		// An update check that hangs until the end of the test
+		let (hang_up, hung) = mpsc::channel::<()>();
+		let update_checker = std::thread::spawn(move || {
+			let _ = hung.recv();
+		});
+
+		let start = Instant::now();
+		save_state(&cache, &cache_path, Some(update_checker), Instant::now());
+		let elapsed = start.elapsed();
+		let saved = Cache::load(&cache_path);
+		drop(hang_up);
+		std::fs::remove_dir_all(&dir).unwrap();
+
+		assert!(elapsed < Duration::from_secs(1), "took {:?}", elapsed);
+		let saved = saved.unwrap();
+		assert_eq!((saved.window.win_w, saved.window.win_h), (1234, 567));
+	}
+
+	#[test]
+	fn only_the_deleted_folders_are_forgotten() {
+		let dir = std::env::temp_dir().join(format!("emulsion-folders-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		let archive = dir.join("pages.zip");
+		std::fs::write(&archive, b"").unwrap();
+		let kept = [dir.clone(), archive.join("chapter")];
+		let deleted = dir.join("deleted");
+		let mut cache = Cache::default();
+		for folder in kept.iter().chain([&deleted]) {
+			let settings = FolderViewSettings {
+				scaling: FolderScaling::Orig,
+				antialiasing: Antialias::Auto,
+				sort_order: None,
+				fit: None,
+				last_used: 0,
+			};
+			cache.folders.insert(folder, settings);
+		}
+		let cache = Mutex::new(cache);
+
+		forget_deleted_folders(&cache);
+		std::fs::remove_dir_all(&dir).unwrap();
+
+		assert_eq!(cache.into_inner().unwrap().folders.folders(), kept);
+	}
+}
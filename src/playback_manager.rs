@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::io::Write;
 use std::marker::PhantomData;
 use std::mem;
@@ -13,14 +14,17 @@ use log::{debug, trace};
 use gelatin::window::Window;
 use gelatin::Display;
 
+use crate::configuration::{EndBehavior, SortOrder};
 use crate::image_cache::{
-	self, AnimationFrameTexture, ImageCache, PathResolutionError, PathedTextureResult,
-	TextureResult,
+	self, capture_date::CaptureDate, duplicates::Duplicates, AnimationFrameTexture, ImageCache,
+	PathResolutionError, PathedTextureResult, TextureResult,
 };
 
 use image_cache::directory;
 
 const NANOS_PER_SEC: u64 = 1_000_000_000;
+/// Shorter animation frames are shown for this long by default, like browsers do
+pub const DEFAULT_MIN_FRAME_DELAY_MS: u64 = 20;
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum LoadRequest {
@@ -32,6 +36,124 @@ pub enum LoadRequest {
 	Jump(i32),
 }
 
+/// The frames of the animation that is shown, as far as they are decoded
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct AnimationFrames {
+	/// The index of the frame that is shown
+	pub current: usize,
+	/// The number of frames that were decoded so far
+	pub count: usize,
+	/// False while the rest of the frames are being decoded
+	pub complete: bool,
+}
+
+/// The outcome of stepping to the next or previous image of the folder
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum FolderStep {
+	Moved,
+	/// The end of the folder was reached and the current image stays
+	Stopped,
+	/// The end of the folder was reached and the image one step back is loaded
+	Bounced,
+}
+
+/// Returns true if stepping in the given direction would leave the folder.
+/// Returns false if the folder hasn't finished filtering.
+fn at_folder_end(image_cache: &mut ImageCache, forward: bool) -> bool {
+	match (image_cache.current_file_index(), image_cache.current_dir_len()) {
+		(Some(index), Some(len)) if forward => index + 1 >= len,
+		(Some(index), Some(_)) => index == 0,
+		_ => false,
+	}
+}
+
+/// The delay of an animation frame after applying the shortest and the longest delay in
+/// `limits`, all in nanoseconds. The shortest delay wins if the limits overlap.
+pub fn clamp_frame_delay(delay_nano: u64, limits: (u64, u64)) -> u64 {
+	let (min, max) = limits;
+	delay_nano.clamp(min, max.max(min))
+}
+
+/// The index of a folder of `len` images that is one step from `index`, following
+/// `end_behavior` at the ends of the folder. This is where `request_step` goes.
+pub fn step_index(
+	index: usize,
+	len: usize,
+	forward: bool,
+	end_behavior: EndBehavior,
+) -> (usize, FolderStep) {
+	let last = len.saturating_sub(1);
+	let at_end = if forward { index >= last } else { index == 0 };
+	if !at_end {
+		return (if forward { index + 1 } else { index - 1 }, FolderStep::Moved);
+	}
+	match end_behavior {
+		EndBehavior::Wrap => (if forward { 0 } else { last }, FolderStep::Moved),
+		EndBehavior::Stop => (index.min(last), FolderStep::Stopped),
+		EndBehavior::Bounce if forward => (last.saturating_sub(1), FolderStep::Bounced),
+		EndBehavior::Bounce => (1.min(last), FolderStep::Bounced),
+	}
+}
+
+/// Stepping through a folder faster than the images can be shown, like when an arrow key is
+/// held down. The rapid steps only move a target, which is loaded once the stepping has paused,
+/// so that the images on the way aren't decoded.
+pub struct FastNavigation {
+	/// The minimal time between two steps for the images to be loaded
+	min_interval: Duration,
+	last_step_time: Instant,
+	/// The index and the path of the image that the rapid steps arrived at
+	target: Option<(usize, PathBuf)>,
+}
+
+impl FastNavigation {
+	pub fn new(min_interval: Duration) -> Self {
+		FastNavigation { min_interval, last_step_time: Instant::now(), target: None }
+	}
+
+	/// The image that the rapid steps arrived at, while it isn't loaded yet
+	pub fn target(&self) -> Option<&(usize, PathBuf)> {
+		self.target.as_ref()
+	}
+
+	/// Takes a step at `now` from `curr_index` in a folder of `len` images. Returns `None` if the
+	/// step wasn't fast, and the image should be loaded as usual. Otherwise the target is moved
+	/// to the image `path_at` gives for the new index, and the outcome of the step is returned.
+	pub fn step(
+		&mut self,
+		now: Instant,
+		forward: bool,
+		(curr_index, len): (usize, usize),
+		end_behavior: EndBehavior,
+		path_at: impl FnOnce(usize) -> Option<PathBuf>,
+	) -> Option<FolderStep> {
+		let interval = now.duration_since(self.last_step_time);
+		self.last_step_time = now;
+		if (self.target.is_none() && interval >= self.min_interval) || len == 0 {
+			return None;
+		}
+		let index = self.target.as_ref().map_or(curr_index, |(index, _)| *index);
+		let (target, step) = step_index(index, len, forward, end_behavior);
+		self.target = Some((target, path_at(target)?));
+		Some(step)
+	}
+
+	/// When the stepping counts as paused, if there is a target
+	pub fn settle_time(&self) -> Option<Instant> {
+		// Keys repeat at a steady rate, so waiting for two intervals is enough to tell that
+		// the key was released
+		self.target.as_ref().map(|_| self.last_step_time + self.min_interval * 2)
+	}
+
+	/// Returns the index of the image to load, once the stepping has paused at `now`
+	pub fn take_settled(&mut self, now: Instant) -> Option<usize> {
+		if now < self.settle_time()? {
+			return None;
+		}
+		self.target.take().map(|(index, _)| index)
+	}
+}
+
 #[derive(Eq, PartialEq, Copy, Clone)]
 pub enum PlaybackState {
 	Paused,
@@ -135,7 +257,7 @@ impl Playback for AnimPlayback {
 
 	fn delay_nanos(player: &ImgSequencePlayer<Self>) -> u64 {
 		if let Some(ref frame) = player.image_texture {
-			frame.delay_nano
+			clamp_frame_delay(frame.delay_nano, player.frame_delay_limits)
 		} else {
 			0
 		}
@@ -150,10 +272,16 @@ pub struct PlaybackManager {
 	// filename: Option<OsString>,
 	folder_player: ImgSequencePlayer<FolderPlayback>,
 	image_player: ImgSequencePlayer<AnimPlayback>,
+
+	end_behavior: EndBehavior,
+	/// The frame that each animation was last shown at, when `remember_animation_position` is
+	/// set. It's only kept for the session.
+	animation_positions: Option<HashMap<PathBuf, usize>>,
 }
 
 impl PlaybackManager {
-	pub fn new() -> Self {
+	/// `decode_threads` is the number of threads that load the images
+	pub fn new(decode_threads: u32) -> Self {
 		let cache_capaxity = match sys_info::mem_info() {
 			Ok(value) => {
 				// value originally reported in KiB
@@ -166,19 +294,134 @@ impl PlaybackManager {
 			}
 		};
 
-		let thread_count = match sys_info::cpu_num() {
-			Ok(value) => value.clamp(2, 4),
-			_ => 4,
-		};
-
 		PlaybackManager {
 			//playback_state: PlaybackState::Paused,
-			image_cache: ImageCache::new(cache_capaxity, thread_count),
+			image_cache: ImageCache::new(cache_capaxity, decode_threads),
 			folder_player: ImgSequencePlayer::new(),
 			image_player: ImgSequencePlayer::new(),
+			end_behavior: EndBehavior::default(),
+			animation_positions: None,
 		}
 	}
 
+	pub fn set_end_behavior(&mut self, end_behavior: EndBehavior) {
+		self.end_behavior = end_behavior;
+		self.folder_player.end_behavior = end_behavior;
+	}
+
+	pub fn set_preload_count(&mut self, preload_count: Option<usize>) {
+		self.image_cache.set_preload_count(preload_count);
+	}
+
+	pub fn set_thumbnail_threads(&mut self, threads: u32) {
+		self.image_cache.set_thumbnail_threads(threads);
+	}
+
+	pub fn set_sort_order(&mut self, order: SortOrder) {
+		self.image_cache.set_sort_order(order);
+	}
+
+	/// See `Directory::set_ignore`
+	pub fn set_ignore(&mut self, patterns: Vec<String>, show_hidden: bool) {
+		self.image_cache.set_ignore(patterns, show_hidden);
+	}
+
+	/// See `Directory::set_single_image`
+	pub fn set_single_image(&mut self, single_image: bool) {
+		self.image_cache.set_single_image(single_image);
+	}
+
+	/// Navigates the ignored and hidden files too, or leaves them out again. The folder is
+	/// collected again right away.
+	pub fn set_show_ignored(&mut self, show: bool) -> directory::Result<()> {
+		self.image_cache.set_show_ignored(show);
+		self.update_directory()
+	}
+
+	pub fn show_ignored(&self) -> bool {
+		self.image_cache.show_ignored()
+	}
+
+	/// Returns true until the folder is in the sort order
+	pub fn sorting(&mut self) -> bool {
+		self.image_cache.sorting()
+	}
+
+	/// The value that the image at `path` is sorted by, when sorting by EXIF data
+	pub fn sort_key_text(&self, path: &Path) -> Option<String> {
+		self.image_cache.sort_key_text(path)
+	}
+
+	/// The frames of animations are shown for at least `min` and at most `max`
+	pub fn set_frame_delay_limits(&mut self, min: Duration, max: Option<Duration>) {
+		let max = max.map_or(u64::MAX, |max| max.as_nanos() as u64);
+		self.image_player.frame_delay_limits = (min.as_nanos() as u64, max);
+	}
+
+	pub fn set_remember_animation_position(&mut self, remember: bool) {
+		self.animation_positions = remember.then(HashMap::new);
+	}
+
+	/// See `ImageCache::set_low_memory`. The current image is loaded again when it was shrunk
+	/// and the low memory mode is turned off.
+	pub fn set_low_memory(&mut self, max_side: Option<u32>) {
+		self.image_cache.set_low_memory(max_side);
+		if max_side.is_none() && self.image_texture().is_some_and(|t| t.downscaled_from.is_some()) {
+			self.image_player.request_load(LoadRequest::Jump(0));
+		}
+	}
+
+	pub fn low_memory(&self) -> bool {
+		self.image_cache.low_memory()
+	}
+
+	/// See `ImageCache::set_max_decode_pixels`
+	pub fn set_max_decode_pixels(&mut self, max_pixels: Option<u64>) {
+		self.image_cache.set_max_decode_pixels(max_pixels);
+	}
+
+	/// See `ImageCache::set_embedded_preview`
+	pub fn set_embedded_preview(&mut self, enabled: bool) {
+		self.image_cache.set_embedded_preview(enabled);
+	}
+
+	pub fn embedded_preview(&self) -> bool {
+		self.image_cache.embedded_preview()
+	}
+
+	/// Shows the current image at its full size, if it was shrunk when it was decoded. The
+	/// shrunk image is shown until the full one is loaded.
+	pub fn request_full_resolution(&mut self) {
+		self.image_cache.request_full_resolution();
+		self.image_player.request_load(LoadRequest::Jump(0));
+	}
+
+	/// Drops the images from the cache that aren't shown
+	pub fn release_memory(&mut self) {
+		self.image_cache.release_memory();
+	}
+
+	pub fn end_behavior(&self) -> EndBehavior {
+		self.end_behavior
+	}
+
+	/// Requests the next or the previous image of the folder,
+	/// following the end behavior when at the end of the folder.
+	pub fn request_step(&mut self, forward: bool) -> FolderStep {
+		let (step, result) = if at_folder_end(&mut self.image_cache, forward) {
+			match self.end_behavior {
+				EndBehavior::Wrap => (forward, FolderStep::Moved),
+				EndBehavior::Stop => return FolderStep::Stopped,
+				EndBehavior::Bounce => (!forward, FolderStep::Bounced),
+			}
+		} else {
+			(forward, FolderStep::Moved)
+		};
+		let request = if step { LoadRequest::LoadNext } else { LoadRequest::LoadPrevious };
+		self.request_load(request);
+		result
+	}
+
 	pub fn playback_state(&self) -> PlaybackState {
 		self.folder_player.playback_state()
 	}
@@ -195,6 +438,27 @@ impl PlaybackManager {
 		//self.playback_state = PlaybackState::Paused;
 	}
 
+	/// The frames of the image that is shown, `None` while nothing is shown
+	pub fn animation_frames(&self) -> Option<AnimationFrames> {
+		let (count, complete) = self.image_cache.current_frame_count()?;
+		let current = self.image_cache.current_frame_index();
+		Some(AnimationFrames { current, count, complete })
+	}
+
+	/// Shows the frame at `index` of the animation, which stays paused until
+	/// `resume_animation` is called. Every frame is decoded onto the ones before it, so any of
+	/// them can be shown on its own.
+	pub fn seek_frame(&mut self, index: usize) {
+		self.image_player.pause_playback();
+		self.image_player.request_load(LoadRequest::LoadAtIndex(index));
+	}
+
+	pub fn resume_animation(&mut self) {
+		if !self.image_cache.loaded_still_image() {
+			self.image_player.start_playback_forward();
+		}
+	}
+
 	pub fn start_random_presentation(&mut self) {
 		self.folder_player.start_random_presentation(&mut self.image_cache);
 		//self.playback_start_time = Instant::now();
@@ -220,6 +484,26 @@ impl PlaybackManager {
 		self.image_cache.current_dir_len()
 	}
 
+	/// Returns None until the capture dates of the images in the folder are read
+	pub fn capture_dates(&mut self) -> Option<&[Option<CaptureDate>]> {
+		self.image_cache.capture_dates()
+	}
+
+	/// Returns None when the folder hasn't finished filtering
+	pub fn file_path_at_index(&mut self, index: usize) -> Option<PathBuf> {
+		self.image_cache.file_path_at_index(index)
+	}
+
+	/// Returns None until the images in the folder are compared
+	pub fn duplicates(&mut self) -> Option<&Duplicates> {
+		self.image_cache.duplicates()
+	}
+
+	/// See `Directory::thumbnail_progress`
+	pub fn thumbnail_progress(&mut self) -> Option<(usize, usize)> {
+		self.image_cache.thumbnail_progress()
+	}
+
 	pub fn update_directory(&mut self) -> directory::Result<()> {
 		debug!("In `update_directory`");
 		if let LoadRequest::None = self.folder_player.load_request {
@@ -247,11 +531,29 @@ impl PlaybackManager {
 		self.image_player.image_texture()
 	}
 
+	/// Returns the first frame of another image of the folder, or `None` while it's loading
+	pub fn texture_at_index(
+		&mut self,
+		window: &Window,
+		index: usize,
+	) -> Option<AnimationFrameTexture> {
+		let display = window.display_mut();
+		self.image_cache.texture_at_index(&display, index)
+	}
+
 	/// The path to the image file which is currently rendered onto the screen.
 	pub fn shown_file_path(&self) -> &LoadedImgPath {
 		&self.folder_player.file_path
 	}
 
+	/// Why the image at `shown_file_path` couldn't be loaded
+	pub fn load_error(&self) -> Option<&str> {
+		match self.folder_player.file_path {
+			LoadedImgPath::ErrLoading(_) => self.folder_player.load_error.as_deref(),
+			_ => None,
+		}
+	}
+
 	pub fn update_image(&mut self, window: &Window) -> gelatin::NextUpdate {
 		let display = window.display_mut();
 		let prev_file = self.folder_player.image_texture();
@@ -264,16 +566,51 @@ impl PlaybackManager {
 		}
 		if file_changed {
 			self.image_player.start_playback_forward();
-			self.image_player.request_load(LoadRequest::Jump(0));
+			let position = match (&self.animation_positions, &self.folder_player.file_path) {
+				(Some(positions), LoadedImgPath::Loaded(path)) => positions.get(path).copied(),
+				_ => None,
+			};
+			match position {
+				Some(frame) if frame > 0 => {
+					// The first frame is shown until the remembered one is decoded
+					self.image_player.image_texture = self.folder_player.image_texture();
+					self.image_player.request_load(LoadRequest::LoadAtIndex(frame));
+				}
+				_ => self.image_player.request_load(LoadRequest::Jump(0)),
+			}
 		}
 		if self.image_cache.loaded_still_image() {
 			self.image_player.pause_playback();
 		}
+		// Checks whether the decoded image took the place of the preview
+		let showing_preview = self.folder_player.image_texture().is_some_and(|t| t.preview);
+		if showing_preview && self.folder_player.load_request == LoadRequest::None {
+			self.request_load(LoadRequest::Jump(0));
+		}
 		let img_player_next_update =
 			self.image_player.update_image(&display, &mut self.image_cache);
 		trace!("Image player next update: {:?}", img_player_next_update);
+		self.remember_animation_position();
 		next_update.aggregate(img_player_next_update)
 	}
+
+	fn remember_animation_position(&mut self) {
+		let Some(positions) = &mut self.animation_positions else {
+			return;
+		};
+		// While a frame is requested, the frame index still belongs to the previous image
+		if self.image_player.load_request != LoadRequest::None
+			|| self.image_cache.loaded_still_image()
+		{
+			return;
+		}
+		if let LoadedImgPath::Loaded(path) = &self.folder_player.file_path {
+			let frame = self.image_cache.current_frame_index();
+			if positions.get(path) != Some(&frame) {
+				positions.insert(path.clone(), frame);
+			}
+		}
+	}
 }
 
 #[derive(Debug, Clone)]
@@ -292,6 +629,9 @@ impl LoadedImgPath {
 struct ImgSequencePlayer<P: Playback> {
 	playback_state: PlaybackState,
 	present_remaining: Vec<usize>,
+	/// Either 1 or -1 depending on which direction the presentation goes in
+	present_step: i32,
+	end_behavior: EndBehavior,
 
 	last_frame_change_time: Instant,
 	frametime_drift_offset: i64, // in nanosecs
@@ -300,6 +640,10 @@ struct ImgSequencePlayer<P: Playback> {
 
 	image_texture: Option<AnimationFrameTexture>,
 	file_path: LoadedImgPath,
+	/// Why the image couldn't be loaded, while `file_path` is `ErrLoading`
+	load_error: Option<String>,
+	/// The shortest and the longest delay of animation frames, in nanoseconds
+	frame_delay_limits: (u64, u64),
 
 	_playback: PhantomData<P>,
 }
@@ -309,12 +653,16 @@ impl<P: Playback> ImgSequencePlayer<P> {
 		ImgSequencePlayer {
 			playback_state: PlaybackState::Paused,
 			present_remaining: Vec::new(),
+			present_step: 1,
+			end_behavior: EndBehavior::default(),
 			last_frame_change_time: Instant::now(),
 			frametime_drift_offset: 0,
 			//frame_count_since_playback_start: 0,
 			load_request: LoadRequest::None,
 			image_texture: None,
 			file_path: LoadedImgPath::NotYetLoaded,
+			load_error: None,
+			frame_delay_limits: (DEFAULT_MIN_FRAME_DELAY_MS * 1_000_000, u64::MAX),
 
 			_playback: PhantomData,
 		}
@@ -349,6 +697,23 @@ impl<P: Playback> ImgSequencePlayer<P> {
 		self.frametime_drift_offset = 0;
 		//self.frame_count_since_playback_start = 0;
 		self.playback_state = PlaybackState::Present;
+		self.present_step = 1;
+	}
+
+	/// Selects the next image of the presentation. Stops the presentation when the end of the
+	/// folder is reached and the end behavior is `Stop`.
+	fn next_present_request(&mut self, image_cache: &mut ImageCache) -> LoadRequest {
+		if at_folder_end(image_cache, self.present_step > 0) {
+			match self.end_behavior {
+				EndBehavior::Wrap => {}
+				EndBehavior::Stop => {
+					self.playback_state = PlaybackState::Paused;
+					return LoadRequest::None;
+				}
+				EndBehavior::Bounce => self.present_step = -self.present_step,
+			}
+		}
+		LoadRequest::Jump(self.present_step)
 	}
 
 	pub fn request_load(&mut self, request: LoadRequest) {
@@ -382,14 +747,9 @@ impl<P: Playback> ImgSequencePlayer<P> {
 		// function early. And at the same time I want to use it's value as it is at this line.
 		let mut load_request = LoadRequest::None;
 		mem::swap(&mut self.load_request, &mut load_request);
-		let frame_delta_time_nanos;
-		match self.playback_state {
-			PlaybackState::Present | PlaybackState::RandomPresent => {
-				frame_delta_time_nanos = (NANOS_PER_SEC * 6) as i64;
-			}
-			_ => {
-				frame_delta_time_nanos = P::delay_nanos(self) as i64;
-			}
+		let frame_delta_time_nanos = match self.playback_state {
+			PlaybackState::Present | PlaybackState::RandomPresent => (NANOS_PER_SEC * 6) as i64,
+			_ => P::delay_nanos(self) as i64,
 		};
 		if self.playback_state == PlaybackState::Paused {
 			if let Err(e) = image_cache.process_prefetched(display) {
@@ -419,19 +779,16 @@ impl<P: Playback> ImgSequencePlayer<P> {
 			// This assumes that the following frames have the same delay but that's okay considering that
 			// if frame step is greater than 1 it almost certainly means that we couldn't load the
 			// next frame quiclky enough so there's not much else to do here.
-			let frame_step;
-			if frame_delta_time_nanos > 0 {
-				frame_step = elapsed_nanos / frame_delta_time_nanos;
-			} else {
-				frame_step = 0;
-			}
+			let frame_step =
+				if frame_delta_time_nanos > 0 { elapsed_nanos / frame_delta_time_nanos } else { 0 };
 			if frame_step > 0 {
 				load_request = match self.playback_state {
-					PlaybackState::Forward | PlaybackState::Present => {
+					PlaybackState::Forward => {
 						// if we can't load the frames quickly enough,
 						// we won't jump over frames, but instead play the animation slower.
 						LoadRequest::Jump(frame_step.min(1) as i32)
 					}
+					PlaybackState::Present => self.next_present_request(image_cache),
 					PlaybackState::RandomPresent => {
 						let mut target = None;
 						for _ in 0..frame_step {
@@ -482,6 +839,11 @@ impl<P: Playback> ImgSequencePlayer<P> {
 			}
 		}
 		trace!("Attempting actual load in `update_image`");
+		// Loading a frame by its index can be retried, the other requests would move again
+		let retry_request = match load_request {
+			LoadRequest::LoadAtIndex(index) => LoadRequest::LoadAtIndex(index),
+			_ => LoadRequest::Jump(0),
+		};
 		let load_result = match load_request {
 			LoadRequest::LoadNext => Some(P::load_next(image_cache, display)),
 			LoadRequest::LoadPrevious => Some(P::load_prev(image_cache, display)),
@@ -499,16 +861,18 @@ impl<P: Playback> ImgSequencePlayer<P> {
 					Ok(frame) => {
 						self.image_texture = Some(frame);
 						self.file_path = LoadedImgPath::Loaded(path);
+						self.load_error = None;
 					}
 					Err(image_cache::TextureError::WaitingOnLoader) => {
 						// Set the load request to jump in place so that
 						// next time we attempt to load this again.
-						self.load_request = LoadRequest::Jump(0);
+						self.load_request = retry_request;
 						next_update = gelatin::NextUpdate::WaitUntil(few_millisecs_from_now);
 					}
 					Err(err) => {
 						self.image_texture = None;
 						self.file_path = LoadedImgPath::ErrLoading(path);
+						self.load_error = Some(err.to_string());
 						let stderr = &mut ::std::io::stderr();
 						let stderr_errmsg = "Error writing to stderr";
 						writeln!(stderr, "Error occurred while loading image: {}", err)
@@ -522,7 +886,7 @@ impl<P: Playback> ImgSequencePlayer<P> {
 				Err(PathResolutionError::WaitingOnDirFilter) => {
 					// Set the load request to jump in place so that
 					// next time we attempt to load this again.
-					self.load_request = LoadRequest::Jump(0);
+					self.load_request = retry_request;
 					next_update = gelatin::NextUpdate::WaitUntil(few_millisecs_from_now);
 				}
 				Err(PathResolutionError::NotYetSpecified) => {
@@ -548,3 +912,124 @@ impl<P: Playback> ImgSequencePlayer<P> {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::image_cache::decoders::{find_decoder, DecodedImage};
+	use gelatin::image::{codecs::gif::GifEncoder, Delay, Frame, RgbaImage};
+
+	/// The index that a run of `steps` rapid steps from `start` lands on
+	fn land(start: usize, len: usize, forward: bool, steps: usize, end: EndBehavior) -> usize {
+		(0..steps).fold(start, |index, _| step_index(index, len, forward, end).0)
+	}
+
+	#[test]
+	fn steps_inside_of_the_folder() {
+		for end in [EndBehavior::Wrap, EndBehavior::Stop, EndBehavior::Bounce] {
+			assert_eq!(step_index(3, 10, true, end), (4, FolderStep::Moved));
+			assert_eq!(step_index(3, 10, false, end), (2, FolderStep::Moved));
+		}
+	}
+
+	#[test]
+	fn steps_at_the_ends_follow_the_end_behavior() {
+		assert_eq!(step_index(9, 10, true, EndBehavior::Wrap), (0, FolderStep::Moved));
+		assert_eq!(step_index(0, 10, false, EndBehavior::Wrap), (9, FolderStep::Moved));
+		assert_eq!(step_index(9, 10, true, EndBehavior::Stop), (9, FolderStep::Stopped));
+		assert_eq!(step_index(0, 10, false, EndBehavior::Stop), (0, FolderStep::Stopped));
+		assert_eq!(step_index(9, 10, true, EndBehavior::Bounce), (8, FolderStep::Bounced));
+		assert_eq!(step_index(0, 10, false, EndBehavior::Bounce), (1, FolderStep::Bounced));
+		// A single image has nowhere to go
+		for end in [EndBehavior::Wrap, EndBehavior::Stop, EndBehavior::Bounce] {
+			assert_eq!(step_index(0, 1, true, end).0, 0);
+			assert_eq!(step_index(0, 1, false, end).0, 0);
+		}
+	}
+
+	#[test]
+	fn rapid_steps_land_on_one_image() {
+		assert_eq!(land(0, 200, true, 150, EndBehavior::Stop), 150);
+		assert_eq!(land(0, 200, true, 250, EndBehavior::Stop), 199);
+		assert_eq!(land(190, 200, true, 15, EndBehavior::Wrap), 5);
+		assert_eq!(land(5, 200, false, 10, EndBehavior::Wrap), 195);
+		// Holding the key at the end goes back and forth between the last two images
+		assert_eq!(land(195, 200, true, 5, EndBehavior::Bounce), 198);
+		assert_eq!(land(195, 200, true, 6, EndBehavior::Bounce), 199);
+	}
+
+	/// The delays of the frames of a GIF whose frames are shown for `delay_ms`, as decoded
+	fn decoded_gif_delays(delay_ms: u32) -> Vec<u64> {
+		let mut gif = Vec::new();
+		{
+			let mut encoder = GifEncoder::new(&mut gif);
+			for _ in 0..2 {
+				let delay = Delay::from_numer_denom_ms(delay_ms, 1);
+				encoder.encode_frame(Frame::from_parts(RgbaImage::new(2, 2), 0, 0, delay)).unwrap();
+			}
+		}
+		let decoder = find_decoder(&gif, "").unwrap();
+		let delays: Vec<u64> = match decoder.decode(&gif).unwrap() {
+			DecodedImage::Animation(frames) => frames.map(|f| f.unwrap().delay_nano).collect(),
+			_ => panic!("The GIF was not decoded as an animation"),
+		};
+		assert_eq!(delays.len(), 2);
+		delays
+	}
+
+	#[test]
+	fn clamps_the_delays_of_gif_frames() {
+		let ms = |ms: u64| ms * 1_000_000;
+		let default_limits = (ms(DEFAULT_MIN_FRAME_DELAY_MS), u64::MAX);
+		let limits = (ms(200), ms(500));
+		// A delay of 0 is shown for 100 ms, like other viewers do
+		for delay in decoded_gif_delays(0) {
+			assert_eq!(clamp_frame_delay(delay, default_limits), 100_000_000);
+			assert_eq!(clamp_frame_delay(delay, limits), 200_000_000);
+		}
+		for delay in decoded_gif_delays(1000) {
+			assert_eq!(clamp_frame_delay(delay, default_limits), 1_000_000_000);
+			assert_eq!(clamp_frame_delay(delay, limits), 500_000_000);
+		}
+		assert_eq!(clamp_frame_delay(ms(1000), (ms(200), ms(100))), 200_000_000);
+	}
+
+	#[test]
+	fn holding_a_key_only_loads_the_image_it_stops_at() {
+		let interval = Duration::from_millis(50);
+		let key_repeat = Duration::from_millis(30);
+		let len = 100;
+		let path_at = |index: usize| Some(PathBuf::from(format!("{}.png", index)));
+		let start = Instant::now();
+		let mut nav = FastNavigation::new(interval);
+		// The loads that the picture widget would request from the playback manager
+		let mut loads = Vec::new();
+		let mut index = 0;
+
+		// The key repeat only starts after a delay, so the first step is loaded as usual
+		let mut now = start + Duration::from_secs(1);
+		assert_eq!(nav.step(now, true, (index, len), EndBehavior::Stop, path_at), None);
+		index += 1;
+		loads.push(index);
+		for _ in 0..20 {
+			now += key_repeat;
+			let step = nav.step(now, true, (index, len), EndBehavior::Stop, path_at);
+			assert_eq!(step, Some(FolderStep::Moved));
+			loads.extend(nav.take_settled(now));
+		}
+		assert_eq!(nav.target().map(|(target, _)| *target), Some(21));
+		assert_eq!(nav.target().map(|(_, path)| path.clone()), path_at(21));
+
+		// Not yet settled right after the key was released
+		loads.extend(nav.take_settled(now + interval));
+		let settle_time = nav.settle_time().unwrap();
+		loads.extend(nav.take_settled(settle_time));
+		loads.extend(nav.take_settled(settle_time + interval));
+
+		assert_eq!(loads, [1, 21]);
+		assert!(nav.target().is_none());
+		// The next step after the pause is slow again
+		let later = settle_time + Duration::from_secs(1);
+		assert_eq!(nav.step(later, true, (21, len), EndBehavior::Stop, path_at), None);
+	}
+}
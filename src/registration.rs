@@ -0,0 +1,252 @@
+//! Registering emulsion with the operating system as an application that can open images, so
+//! that it shows up under "Open With".
+//!
+//! The registration is per user and only adds emulsion to the list of applications for these
+//! file types, it doesn't make emulsion the default application. It's done with the tools that
+//! come with the system, so that no privileges or extra libraries are needed.
+
+use std::path::PathBuf;
+use std::process::Command;
+
+/// The MIME types that the desktop entry lists
+#[cfg(all(unix, not(target_os = "macos")))]
+const MIME_TYPES: &[&str] = &[
+	"image/jpeg",
+	"image/png",
+	"image/apng",
+	"image/bmp",
+	"image/gif",
+	"image/x-tga",
+	"image/avif",
+	"image/webp",
+	"image/tiff",
+	"image/vnd.microsoft.icon",
+	"image/vnd.radiance",
+	"image/x-exr",
+	"image/svg+xml",
+	"image/x-portable-bitmap",
+	"image/x-portable-graymap",
+	"image/x-portable-pixmap",
+	"image/x-portable-anymap",
+	"application/vnd.comicbook+zip",
+];
+
+fn current_exe() -> Result<PathBuf, String> {
+	std::env::current_exe().map_err(|e| format!("Could not find the emulsion executable: {}", e))
+}
+
+fn run(command: &mut Command) -> Result<(), String> {
+	let status = command.output().map_err(|e| format!("Could not run {:?}: {}", command, e))?;
+	if status.status.success() {
+		Ok(())
+	} else {
+		let stderr = String::from_utf8_lossy(&status.stderr);
+		Err(format!("{:?} failed: {}", command, stderr.trim()))
+	}
+}
+
+#[cfg(windows)]
+mod platform {
+	use super::*;
+
+	/// The same extensions that the installer registers
+	const EXTENSIONS: &[&str] = &[
+		"jpg", "jpeg", "png", "apng", "bmp", "gif", "tga", "avif", "webp", "tif", "tiff", "ico",
+		"hdr", "exr", "svg", "pbm", "pam", "ppm", "pgm", "cbz",
+	];
+	const PROG_ID: &str = "Emulsion.Generic";
+	const CLASSES: &str = r"HKCU\SOFTWARE\Classes";
+	const APP_KEY: &str = r"HKCU\SOFTWARE\Emulsion";
+	const REGISTERED_APPLICATIONS: &str = r"HKCU\SOFTWARE\RegisteredApplications";
+
+	/// Sets a value with `reg.exe`. The default value of the key is set if `name` is `None`.
+	fn reg_add(key: &str, name: Option<&str>, data: &str) -> Result<(), String> {
+		let mut command = Command::new("reg");
+		command.args(["add", key]);
+		match name {
+			Some(name) => command.args(["/v", name]),
+			None => command.arg("/ve"),
+		};
+		run(command.args(["/d", data, "/f"]))
+	}
+
+	fn reg_delete(key: &str, name: Option<&str>) -> Result<(), String> {
+		let mut command = Command::new("reg");
+		command.args(["delete", key]);
+		if let Some(name) = name {
+			command.args(["/v", name]);
+		}
+		run(command.arg("/f"))
+	}
+
+	pub fn register() -> Result<(), String> {
+		let exe = current_exe()?;
+		let exe = exe.to_string_lossy();
+		// The same keys that the installer creates, but for the current user only
+		let prog_key = format!(r"{}\{}", CLASSES, PROG_ID);
+		reg_add(&prog_key, None, "Image")?;
+		reg_add(&format!(r"{}\DefaultIcon", prog_key), None, &format!("\"{}\"", exe))?;
+		let command = format!("\"{}\" \"%1\"", exe);
+		reg_add(&format!(r"{}\Shell\Open\Command", prog_key), None, &command)?;
+		let capabilities = format!(r"{}\Capabilities", APP_KEY);
+		reg_add(&capabilities, Some("ApplicationName"), "Emulsion")?;
+		reg_add(
+			&capabilities,
+			Some("ApplicationDescription"),
+			"A fast and minimalistic image viewer",
+		)?;
+		for ext in EXTENSIONS {
+			let associations = format!(r"{}\FileAssociations", capabilities);
+			let dot_ext = format!(".{}", ext);
+			reg_add(&associations, Some(&dot_ext), PROG_ID)?;
+			let open_with = format!(r"{}\.{}\OpenWithProgids", CLASSES, ext);
+			reg_add(&open_with, Some(PROG_ID), "")?;
+		}
+		reg_add(REGISTERED_APPLICATIONS, Some("Emulsion"), r"SOFTWARE\Emulsion\Capabilities")
+	}
+
+	pub fn unregister() -> Result<(), String> {
+		for ext in EXTENSIONS {
+			let _ = reg_delete(&format!(r"{}\.{}\OpenWithProgids", CLASSES, ext), Some(PROG_ID));
+		}
+		let _ = reg_delete(REGISTERED_APPLICATIONS, Some("Emulsion"));
+		let _ = reg_delete(&format!(r"{}\Capabilities", APP_KEY), None);
+		reg_delete(&format!(r"{}\{}", CLASSES, PROG_ID), None)
+	}
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+	use super::*;
+	use std::path::Path;
+
+	const LSREGISTER: &str = "/System/Library/Frameworks/CoreServices.framework/Frameworks/\
+		LaunchServices.framework/Support/lsregister";
+
+	/// The file types are declared in the `Info.plist` of the bundle, so only an executable that
+	/// runs from a bundle can be registered
+	fn bundle_path() -> Result<PathBuf, String> {
+		let exe = current_exe()?;
+		exe.ancestors()
+			.find(|path| path.extension().is_some_and(|ext| ext == "app"))
+			.map(Path::to_path_buf)
+			.ok_or_else(|| {
+				format!("{:?} is not inside an application bundle, it can't be registered", exe)
+			})
+	}
+
+	pub fn register() -> Result<(), String> {
+		run(Command::new(LSREGISTER).arg("-f").arg(bundle_path()?))
+	}
+
+	pub fn unregister() -> Result<(), String> {
+		run(Command::new(LSREGISTER).arg("-u").arg(bundle_path()?))
+	}
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod platform {
+	use super::*;
+	use directories_next::BaseDirs;
+
+	const DESKTOP_FILE_NAME: &str = "emulsion.desktop";
+
+	fn applications_dir() -> Result<PathBuf, String> {
+		let base_dirs = BaseDirs::new().ok_or("Could not find the home folder")?;
+		Ok(base_dirs.data_local_dir().join("applications"))
+	}
+
+	/// Lets the desktop pick up the change right away. Not every system has the tool, and the
+	/// desktop finds the entry eventually either way.
+	fn update_desktop_database(dir: &std::path::Path) {
+		let _ = run(Command::new("update-desktop-database").arg(dir));
+	}
+
+	/// Quotes `path` for the `Exec` key of a desktop entry. Inside of the quotes `"`, `` ` ``,
+	/// `$` and `\` are escaped with a backslash, then every backslash is escaped again because
+	/// the value is a string, and `%` is written as `%%` so that it isn't a field code.
+	pub(super) fn quote_exec_arg(path: &str) -> String {
+		let mut quoted = String::from("\"");
+		for c in path.chars() {
+			match c {
+				'"' | '`' | '$' => {
+					quoted.push_str("\\\\");
+					quoted.push(c);
+				}
+				'\\' => quoted.push_str("\\\\\\\\"),
+				'%' => quoted.push_str("%%"),
+				c => quoted.push(c),
+			}
+		}
+		quoted.push('"');
+		quoted
+	}
+
+	pub fn register() -> Result<(), String> {
+		let exe = current_exe()?;
+		let exe = exe.to_string_lossy();
+		// The entry is one key per line, and there's no escape for the other control characters
+		if exe.chars().any(char::is_control) {
+			return Err(format!("The path {:?} can't be written to a desktop entry", exe));
+		}
+		let dir = applications_dir()?;
+		std::fs::create_dir_all(&dir)
+			.map_err(|e| format!("Could not create the folder {:?}: {}", dir, e))?;
+		let mut mime_types = MIME_TYPES.join(";");
+		mime_types.push(';');
+		let entry = format!(
+			"[Desktop Entry]\n\
+			Type=Application\n\
+			Name=Emulsion\n\
+			Comment=A fast and minimalistic image viewer\n\
+			Exec={} %f\n\
+			Icon=emulsion\n\
+			Terminal=false\n\
+			Categories=Graphics;Viewer;\n\
+			MimeType={}\n",
+			quote_exec_arg(&exe),
+			mime_types,
+		);
+		let path = dir.join(DESKTOP_FILE_NAME);
+		std::fs::write(&path, entry).map_err(|e| format!("Could not write {:?}: {}", path, e))?;
+		update_desktop_database(&dir);
+		Ok(())
+	}
+
+	pub fn unregister() -> Result<(), String> {
+		let dir = applications_dir()?;
+		let path = dir.join(DESKTOP_FILE_NAME);
+		match std::fs::remove_file(&path) {
+			Ok(()) => {}
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+			Err(e) => return Err(format!("Could not remove {:?}: {}", path, e)),
+		}
+		update_desktop_database(&dir);
+		Ok(())
+	}
+}
+
+/// Adds emulsion to the applications that can open the supported image formats
+pub fn register() -> Result<(), String> {
+	platform::register()
+}
+
+/// Undoes `register`
+pub fn unregister() -> Result<(), String> {
+	platform::unregister()
+}
+
+#[cfg(all(test, unix, not(target_os = "macos")))]
+mod tests {
+	use super::platform::quote_exec_arg;
+
+	#[test]
+	fn quotes_the_exec_path_of_the_desktop_entry() {
+		assert_eq!(quote_exec_arg("/usr/bin/emulsion"), r#""/usr/bin/emulsion""#);
+		assert_eq!(quote_exec_arg("/opt/my apps/emulsion"), r#""/opt/my apps/emulsion""#);
+		assert_eq!(quote_exec_arg(r#"/a"b/emulsion"#), r#""/a\\"b/emulsion""#);
+		assert_eq!(quote_exec_arg("/$HOME/`x`/e"), r#""/\\$HOME/\\`x\\`/e""#);
+		assert_eq!(quote_exec_arg(r"/a\b/e"), r#""/a\\\\b/e""#);
+		assert_eq!(quote_exec_arg("/100%/e"), r#""/100%%/e""#);
+	}
+}
@@ -0,0 +1,29 @@
+#version 140
+uniform sampler2D tex;
+uniform sampler2D reference;
+uniform float bright_shade;
+uniform float lod_level;
+// The magnifier is cut to a circle with this center and radius, in pixels of the framebuffer.
+// A radius of 0 turns this off.
+uniform vec2 lens_center;
+uniform float lens_radius;
+in vec2 v_tex_coords;
+out vec4 f_color;
+void main() {
+    if (lens_radius > 0.0 && distance(gl_FragCoord.xy, lens_center) > lens_radius) {
+        discard;
+    }
+    vec4 color = textureLod(tex, v_tex_coords, lod_level);
+    const float grid_size = 12.0;
+    vec4 grid_color;
+    if ((mod(gl_FragCoord.x, grid_size * 2.0) < grid_size)
+        ^^ (mod(gl_FragCoord.y, grid_size * 2.0) < grid_size)
+    ) {
+        grid_color = vec4(bright_shade);
+    } else {
+        grid_color = vec4(bright_shade * 0.55);
+    }
+    // The reference is drawn with the same background, so transparent areas cancel out
+    vec4 reference_color = texelFetch(reference, ivec2(gl_FragCoord.xy), 0);
+    f_color = vec4(abs(mix(grid_color, color, color.a).rgb - reference_color.rgb), 1.0);
+}
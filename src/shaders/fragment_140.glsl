@@ -2,10 +2,73 @@
 uniform sampler2D tex;
 uniform float bright_shade;
 uniform float lod_level;
+// The magnifier is cut to a circle with this center and radius, in pixels of the framebuffer.
+// A radius of 0 turns this off.
+uniform vec2 lens_center;
+uniform float lens_radius;
+uniform float opacity;
+uniform bool clipping_warning;
+uniform float clip_highlight;
+uniform float clip_shadow;
+// Focus peaking shows the pixels with a local contrast of at least `peaking_threshold` in
+// `peaking_color`, and dims the rest
+uniform bool focus_peaking;
+uniform float peaking_threshold;
+uniform vec3 peaking_color;
+// 0 shows all channels. 1, 2 and 3 show red, green or blue as grayscale, 4 the alpha and 5 the
+// luminance, all of them opaque.
+uniform int channel_view;
 in vec2 v_tex_coords;
 out vec4 f_color;
+// The perceived brightness of the texel at the given offset from the current one
+float brightness(vec2 offset) {
+    vec3 color = textureLod(tex, v_tex_coords + offset, lod_level).rgb;
+    return sqrt(dot(color, vec3(0.2126, 0.7152, 0.0722)));
+}
 void main() {
+    if (lens_radius > 0.0 && distance(gl_FragCoord.xy, lens_center) > lens_radius) {
+        discard;
+    }
     vec4 color = textureLod(tex, v_tex_coords, lod_level);
+    if (channel_view > 0) {
+        float value;
+        if (channel_view == 4) {
+            value = color.a;
+        } else if (channel_view == 5) {
+            value = dot(color.rgb, vec3(0.2126, 0.7152, 0.0722));
+        } else {
+            value = color[channel_view - 1];
+        }
+        color = vec4(vec3(value), 1.0);
+    }
+    if (clipping_warning) {
+        float lowest = min(min(color.r, color.g), color.b);
+        float highest = max(max(color.r, color.g), color.b);
+        if (lowest >= clip_highlight) {
+            color.rgb = vec3(1.0, 0.0, 0.0);
+        } else if (highest <= clip_shadow) {
+            color.rgb = vec3(0.0, 0.0, 1.0);
+        }
+    }
+    if (focus_peaking) {
+        // A Sobel filter on the texels of the mipmap level that is shown
+        vec2 texel = 1.0 / vec2(textureSize(tex, int(lod_level)));
+        float top_left = brightness(vec2(-texel.x, texel.y));
+        float top = brightness(vec2(0.0, texel.y));
+        float top_right = brightness(texel);
+        float left = brightness(vec2(-texel.x, 0.0));
+        float right = brightness(vec2(texel.x, 0.0));
+        float bottom_left = brightness(-texel);
+        float bottom = brightness(vec2(0.0, -texel.y));
+        float bottom_right = brightness(vec2(texel.x, -texel.y));
+        float gx = (top_right + 2.0 * right + bottom_right) - (top_left + 2.0 * left + bottom_left);
+        float gy = (top_left + 2.0 * top + top_right) - (bottom_left + 2.0 * bottom + bottom_right);
+        if (length(vec2(gx, gy)) >= peaking_threshold) {
+            color.rgb = peaking_color;
+        } else {
+            color.rgb *= 0.3;
+        }
+    }
     const float grid_size = 12.0;
     vec4 grid_color;
     if ((mod(gl_FragCoord.x, grid_size * 2.0) < grid_size)
@@ -15,5 +78,5 @@ void main() {
     } else {
         grid_color = vec4(bright_shade * 0.55);
     }
-    f_color = mix(grid_color, color, color.a);
+    f_color = vec4(mix(grid_color, color, color.a).rgb, opacity);
 }
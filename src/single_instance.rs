@@ -0,0 +1,205 @@
+//! Handing the opened path over to an instance of emulsion that is already running.
+//!
+//! The first instance listens on a local socket. Later instances connect to it, send the path
+//! they were asked to open and exit. A message consists of one byte for the open behavior (`f`
+//! for the first image and `s` for a slideshow) followed by the path. An empty message only
+//! asks the running instance to bring its window to the front.
+//!
+//! Only Unix domain sockets are implemented. Windows would need named pipes, which aren't
+//! supported, so every instance opens its own window there.
+
+use gelatin::winit::event_loop::EventLoopProxy;
+
+use crate::configuration::OpenBehavior;
+use crate::EmulsionEvent;
+
+pub use platform::*;
+
+#[cfg(unix)]
+mod platform {
+	use std::ffi::OsStr;
+	use std::io::{self, Read, Write};
+	use std::os::unix::ffi::OsStrExt;
+	use std::os::unix::net::{UnixListener, UnixStream};
+	use std::path::{Path, PathBuf};
+	use std::time::{Duration, Instant};
+
+	use log::{debug, error};
+
+	use super::*;
+
+	/// A client that doesn't finish its whole message in this time is dropped, so that it can't
+	/// block the ones after it
+	const READ_TIMEOUT: Duration = Duration::from_secs(2);
+	/// The longest message that is accepted, which is far longer than any path
+	const MAX_MESSAGE_LEN: u64 = 64 * 1024;
+
+	pub struct InstanceListener {
+		listener: UnixListener,
+	}
+
+	fn encode_behavior(behavior: OpenBehavior) -> u8 {
+		match behavior {
+			OpenBehavior::FirstImage => b'f',
+			OpenBehavior::Slideshow => b's',
+		}
+	}
+
+	fn decode_behavior(byte: u8) -> OpenBehavior {
+		match byte {
+			b's' => OpenBehavior::Slideshow,
+			_ => OpenBehavior::FirstImage,
+		}
+	}
+
+	/// Paths are sent as absolute paths because the running instance may have a different working
+	/// directory.
+	fn absolute_path(path: &Path) -> PathBuf {
+		std::path::absolute(path).unwrap_or_else(|_| path.to_owned())
+	}
+
+	/// Sends the path to the running instance and returns `None` if there is one. Otherwise
+	/// starts listening for later instances.
+	pub fn connect_or_listen(
+		socket_path: &Path,
+		open: Option<(&Path, OpenBehavior)>,
+	) -> io::Result<Option<InstanceListener>> {
+		match UnixStream::connect(socket_path) {
+			Ok(mut stream) => {
+				let mut message = Vec::new();
+				if let Some((file_path, behavior)) = open {
+					message.push(encode_behavior(behavior));
+					message.extend_from_slice(absolute_path(file_path).as_os_str().as_bytes());
+				}
+				stream.write_all(&message)?;
+				return Ok(None);
+			}
+			Err(err) => debug!("Could not connect to a running instance: {err}"),
+		}
+		// Nobody is listening, so the socket file (if there is one) was left behind by an
+		// instance that didn't exit cleanly.
+		if socket_path.exists() {
+			std::fs::remove_file(socket_path)?;
+		}
+		let listener = UnixListener::bind(socket_path)?;
+		Ok(Some(InstanceListener { listener }))
+	}
+
+	/// Reads until the other end closes the stream, or fails when that takes longer than `timeout`
+	/// in total. The timeout of the socket only applies to the individual reads.
+	pub(super) fn read_message(stream: &mut UnixStream, timeout: Duration) -> io::Result<Vec<u8>> {
+		let deadline = Instant::now() + timeout;
+		let mut message = Vec::new();
+		let mut buffer = [0; 4096];
+		loop {
+			let remaining = deadline.saturating_duration_since(Instant::now());
+			if remaining.is_zero() {
+				return Err(io::Error::new(io::ErrorKind::TimedOut, "The message took too long"));
+			}
+			stream.set_read_timeout(Some(remaining))?;
+			let len = match stream.read(&mut buffer) {
+				Ok(0) => return Ok(message),
+				Ok(len) => len,
+				Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+				Err(err) => return Err(err),
+			};
+			message.extend_from_slice(&buffer[..len]);
+			if message.len() as u64 > MAX_MESSAGE_LEN {
+				return Err(io::Error::other("The message is too long"));
+			}
+		}
+	}
+
+	impl InstanceListener {
+		/// Receives the messages on a background thread, and forwards them to the event loop.
+		pub fn start(self, proxy: EventLoopProxy<EmulsionEvent>) {
+			std::thread::spawn(move || {
+				for stream in self.listener.incoming() {
+					let message = match stream.and_then(|mut s| read_message(&mut s, READ_TIMEOUT))
+					{
+						Ok(message) => message,
+						Err(err) => {
+							error!("Failed to receive a message from another instance: {err}");
+							continue;
+						}
+					};
+					let event = match message.split_first() {
+						Some((&behavior, path)) => EmulsionEvent::Open {
+							path: PathBuf::from(OsStr::from_bytes(path)),
+							behavior: decode_behavior(behavior),
+						},
+						None => EmulsionEvent::Focus,
+					};
+					if proxy.send_event(event).is_err() {
+						// The event loop has exited
+						break;
+					}
+				}
+			});
+		}
+	}
+}
+
+#[cfg(not(unix))]
+mod platform {
+	use std::io;
+	use std::path::Path;
+
+	use super::*;
+
+	pub struct InstanceListener;
+
+	/// Single instance mode needs Unix domain sockets. Named pipes aren't implemented for
+	/// Windows, so this always returns a listener that does nothing.
+	pub fn connect_or_listen(
+		_socket_path: &Path,
+		_open: Option<(&Path, OpenBehavior)>,
+	) -> io::Result<Option<InstanceListener>> {
+		eprintln!("The single_instance option is not supported on this platform.");
+		Ok(Some(InstanceListener))
+	}
+
+	impl InstanceListener {
+		pub fn start(self, _proxy: EventLoopProxy<EmulsionEvent>) {}
+	}
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+	use std::io::Write;
+	use std::os::unix::net::UnixStream;
+	use std::time::{Duration, Instant};
+
+	use super::platform::read_message;
+
+	#[test]
+	fn a_message_is_read_until_the_stream_is_closed() {
+		let (mut client, mut server) = UnixStream::pair().unwrap();
+		client.write_all(b"f/some/image.png").unwrap();
+		drop(client);
+		let message = read_message(&mut server, Duration::from_secs(1)).unwrap();
+		assert_eq!(message, b"f/some/image.png");
+	}
+
+	#[test]
+	fn a_slow_message_times_out_as_a_whole() {
+		let (mut client, mut server) = UnixStream::pair().unwrap();
+		// Each byte arrives well within the timeout, but the whole message doesn't
+		let writer = std::thread::spawn(move || {
+			for _ in 0..20 {
+				if client.write_all(b"f").is_err() {
+					break;
+				}
+				std::thread::sleep(Duration::from_millis(50));
+			}
+		});
+		let start = Instant::now();
+		let result = read_message(&mut server, Duration::from_millis(200));
+		let elapsed = start.elapsed();
+		drop(server);
+		writer.join().unwrap();
+
+		assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+		assert!(elapsed < Duration::from_millis(500), "took {:?}", elapsed);
+	}
+}
@@ -0,0 +1,188 @@
+//! The file operations of the current session that can be undone.
+//!
+//! Every operation that changes files pushes an action onto the stack that reverses it.
+//! Operations that cannot be reversed must not be pushed, and the user has to be warned about
+//! them before they are carried out.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The oldest actions are forgotten once there are more than this many on the stack
+const MAX_UNDO_ACTIONS: usize = 64;
+
+pub struct UndoAction {
+	/// Describes the operation that this action reverses, for example
+	/// `Moved "cat.jpg" to the trash`
+	pub description: String,
+	/// Reverses the operation, and returns the path of the image that should be shown afterwards
+	undo: Box<dyn FnOnce() -> Result<PathBuf, String>>,
+}
+
+impl UndoAction {
+	pub fn new(
+		description: String,
+		undo: impl FnOnce() -> Result<PathBuf, String> + 'static,
+	) -> UndoAction {
+		UndoAction { description, undo: Box::new(undo) }
+	}
+
+	pub fn undo(self) -> Result<PathBuf, String> {
+		(self.undo)()
+	}
+}
+
+#[derive(Default)]
+pub struct UndoStack {
+	actions: Vec<UndoAction>,
+}
+
+impl UndoStack {
+	pub fn push(&mut self, action: UndoAction) {
+		if self.actions.len() >= MAX_UNDO_ACTIONS {
+			self.actions.remove(0);
+		}
+		self.actions.push(action);
+	}
+
+	pub fn pop(&mut self) -> Option<UndoAction> {
+		self.actions.pop()
+	}
+}
+
+fn file_name(path: &Path) -> String {
+	path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned()
+}
+
+/// Moves the file to the trash. Returns the action that restores it, or `None` if restoring
+/// from the trash is not supported on this platform.
+pub fn move_to_trash(path: &Path) -> Result<Option<UndoAction>, trash::Error> {
+	trash::delete(path)?;
+	if !RESTORE_SUPPORTED {
+		return Ok(None);
+	}
+	let description = format!("Moved {:?} to the trash", file_name(path));
+	let path = path.to_owned();
+	Ok(Some(UndoAction::new(description, move || restore_from_trash(&path))))
+}
+
+/// Moves the file into `folder`, which is created if it doesn't exist. If there's already a file
+/// of the same name in the folder, the moved file gets a new name. Returns the action that moves
+/// it back.
+pub fn move_to_folder(path: &Path, folder: &Path) -> Result<UndoAction, String> {
+	let name = path.file_name().ok_or_else(|| format!("{:?} is not a file", path))?;
+	fs::create_dir_all(folder).map_err(|e| e.to_string())?;
+	let target = unused_path(&folder.join(name));
+	move_file(path, &target)?;
+	let description = format!("Moved {:?} to {:?}", file_name(path), file_name(folder));
+	let original = path.to_owned();
+	Ok(UndoAction::new(description, move || {
+		let back = unused_path(&original);
+		move_file(&target, &back)?;
+		Ok(back)
+	}))
+}
+
+/// `path` if there's no file there, otherwise the first free path next to it with a number
+fn unused_path(path: &Path) -> PathBuf {
+	if path.exists() {
+		free_path(path, |n| format!(" ({})", n))
+	} else {
+		path.to_owned()
+	}
+}
+
+/// Renames the file, or copies it and deletes the original when the rename fails, which it
+/// does between file systems
+fn move_file(from: &Path, to: &Path) -> Result<(), String> {
+	if fs::rename(from, to).is_ok() {
+		return Ok(());
+	}
+	fs::copy(from, to).map_err(|e| e.to_string())?;
+	fs::remove_file(from).map_err(|e| {
+		let _ = fs::remove_file(to);
+		e.to_string()
+	})
+}
+
+/// Whether `restore_from_trash` and `trashed_paths` work on this platform
+pub const RESTORE_SUPPORTED: bool = cfg!(any(
+	target_os = "windows",
+	all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+));
+
+/// Puts the file that was deleted from `path` back, and returns where it was restored to. The
+/// same path may have been deleted several times, the latest one is restored. If another file
+/// took its place since, the restored file gets a new name next to it.
+#[cfg(any(
+	target_os = "windows",
+	all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+pub fn restore_from_trash(path: &Path) -> Result<PathBuf, String> {
+	use std::fs::rename;
+	use trash::os_limited;
+
+	let items = os_limited::list().map_err(|e| e.to_string())?;
+	let item = items
+		.into_iter()
+		.filter(|item| item.original_path() == path)
+		.max_by_key(|item| item.time_deleted)
+		.ok_or_else(|| format!("Could not find {:?} in the trash", file_name(path)))?;
+	if !path.exists() {
+		os_limited::restore_all([item]).map_err(|e| e.to_string())?;
+		return Ok(path.to_owned());
+	}
+	// The trash only restores to the original path, so the file that is there now is moved out
+	// of the way until the restored one is renamed
+	let target = free_path(path, |n| format!(" ({})", n));
+	let aside = free_path(path, |n| format!(".restoring{}", n));
+	rename(path, &aside).map_err(|e| e.to_string())?;
+	if let Err(e) = os_limited::restore_all([item]) {
+		rename(&aside, path).map_err(|e| e.to_string())?;
+		return Err(e.to_string());
+	}
+	rename(path, &target)
+		.map_err(|e| format!("{}, the file that was in its place is now {:?}", e, aside))?;
+	rename(&aside, path)
+		.map_err(|e| format!("{}, the file that was in its place is now {:?}", e, aside))?;
+	Ok(target)
+}
+
+#[cfg(not(any(
+	target_os = "windows",
+	all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+)))]
+pub fn restore_from_trash(_path: &Path) -> Result<PathBuf, String> {
+	Err("Restoring from the trash is not supported on this platform".into())
+}
+
+/// The original paths of the files in the trash, or `None` if the trash can't be listed
+#[cfg(any(
+	target_os = "windows",
+	all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+))]
+pub fn trashed_paths() -> Option<HashSet<PathBuf>> {
+	let items = trash::os_limited::list().ok()?;
+	Some(items.iter().map(|item| item.original_path()).collect())
+}
+
+#[cfg(not(any(
+	target_os = "windows",
+	all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))
+)))]
+pub fn trashed_paths() -> Option<HashSet<PathBuf>> {
+	None
+}
+
+/// The first path next to `path` that doesn't exist, with `suffix(n)` appended to the file stem
+fn free_path(path: &Path, suffix: impl Fn(u32) -> String) -> PathBuf {
+	let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+	let extension = path.extension().map(|e| format!(".{}", e.to_string_lossy()));
+	(1..)
+		.map(|n| {
+			let name = format!("{}{}{}", stem, suffix(n), extension.as_deref().unwrap_or(""));
+			path.with_file_name(name)
+		})
+		.find(|candidate| !candidate.exists())
+		.unwrap()
+}
@@ -326,3 +326,89 @@ pub fn virtual_keycode_to_string(key: &Key) -> String {
 		},
 	}
 }
+
+/// Returns true if `name` matches `pattern`, ignoring case.
+///
+/// If the pattern contains `*` or `?` it's treated as a glob pattern where `*` matches any
+/// sequence of characters and `?` matches a single character. Otherwise it matches if it's a
+/// substring of `name`.
+pub fn matches_name_pattern(name: &str, pattern: &str) -> bool {
+	if !pattern.contains(['*', '?']) {
+		return name.to_lowercase().contains(&pattern.to_lowercase());
+	}
+	matches_glob(name, pattern)
+}
+
+/// Returns true if `name` matches the glob `pattern` as a whole, ignoring case. `*` matches any
+/// sequence of characters and `?` matches a single character.
+pub fn matches_glob(name: &str, pattern: &str) -> bool {
+	let name = name.to_lowercase();
+	let pattern = pattern.to_lowercase();
+	let name = name.chars().collect::<Vec<_>>();
+	let pattern = pattern.chars().collect::<Vec<_>>();
+	// Greedy matching with backtracking to the last star
+	let (mut n, mut p) = (0, 0);
+	let mut last_star = None;
+	while n < name.len() {
+		if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+			n += 1;
+			p += 1;
+		} else if p < pattern.len() && pattern[p] == '*' {
+			last_star = Some((p, n));
+			p += 1;
+		} else if let Some((star_p, star_n)) = last_star {
+			p = star_p + 1;
+			n = star_n + 1;
+			last_star = Some((star_p, star_n + 1));
+		} else {
+			return false;
+		}
+	}
+	pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn star_matches_any_sequence() {
+		assert!(matches_glob("thumbs.db", "*.db"));
+		assert!(matches_glob("IMG_0001.jpg", "img_*"));
+		assert!(matches_glob("a.b.c", "*.*.*"));
+		assert!(matches_glob("abc", "a*b*c"));
+		assert!(matches_glob("", "*"));
+		assert!(!matches_glob("photo.jpg", "*.png"));
+		assert!(!matches_glob("abc", "a*d"));
+	}
+
+	#[test]
+	fn question_mark_matches_one_character() {
+		assert!(matches_glob("page7.png", "page?.png"));
+		assert!(!matches_glob("page10.png", "page?.png"));
+		assert!(!matches_glob("page.png", "page?.png"));
+		// A character, not a byte
+		assert!(matches_glob("bä.png", "b?.png"));
+	}
+
+	#[test]
+	fn case_is_ignored() {
+		assert!(matches_glob("Thumbs.DB", "thumbs.db"));
+		assert!(matches_glob("thumbs.db", "THUMBS.*"));
+		assert!(matches_glob("ÄPFEL.JPG", "äpfel.*"));
+	}
+
+	#[test]
+	fn patterns_with_a_slash_match_the_relative_path() {
+		assert!(matches_glob("covers/001.png", "covers/*"));
+		assert!(matches_glob("extras/covers/001.png", "*/covers/*"));
+		assert!(!matches_glob("001.png", "covers/*"));
+		assert!(!matches_glob("chapter 1/001.png", "covers/*"));
+	}
+
+	#[test]
+	fn name_patterns_without_wildcards_match_a_part_of_the_name() {
+		assert!(matches_name_pattern("Holiday 2020.jpg", "holiday"));
+		assert!(!matches_name_pattern("Holiday 2020.jpg", "holiday?"));
+	}
+}
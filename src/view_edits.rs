@@ -0,0 +1,80 @@
+//! The view edits of the images in the current session, and their history.
+//!
+//! View edits change how an image is shown but never touch the file, unlike the operations on
+//! the undo stack. Every image has its own history, so going back to an image shows it the way
+//! it was left, and undoing only affects the image that is shown. The histories are kept in
+//! memory only and are gone once the program exits.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// The oldest states of an image are forgotten once its history is longer than this
+const MAX_HISTORY: usize = 64;
+
+/// The non-destructive edits of one image
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct ViewEdit {
+	/// The rotation of the view in degrees clockwise, between -180 and 180
+	pub rotation: f32,
+}
+
+impl ViewEdit {
+	/// Whether the image is shown the way it was loaded
+	pub fn is_unedited(&self) -> bool {
+		*self == ViewEdit::default()
+	}
+}
+
+#[derive(Default)]
+struct EditHistory {
+	current: ViewEdit,
+	undo: Vec<ViewEdit>,
+	redo: Vec<ViewEdit>,
+}
+
+#[derive(Default)]
+pub struct ViewEdits {
+	histories: HashMap<PathBuf, EditHistory>,
+}
+
+impl ViewEdits {
+	/// The current edits of the image at `path`
+	pub fn get(&self, path: &Path) -> ViewEdit {
+		self.histories.get(path).map(|history| history.current).unwrap_or_default()
+	}
+
+	/// Makes `edit` the current state of the image, which can be undone afterwards. This clears
+	/// what could be redone.
+	pub fn set(&mut self, path: &Path, edit: ViewEdit) {
+		let history = self.histories.entry(path.to_owned()).or_default();
+		if history.current == edit {
+			return;
+		}
+		if history.undo.len() >= MAX_HISTORY {
+			history.undo.remove(0);
+		}
+		history.undo.push(history.current);
+		history.current = edit;
+		history.redo.clear();
+	}
+
+	/// Goes back to the previous state of the image and returns it, or `None` if there's nothing
+	/// to undo
+	pub fn undo(&mut self, path: &Path) -> Option<ViewEdit> {
+		let history = self.histories.get_mut(path)?;
+		let previous = history.undo.pop()?;
+		history.redo.push(history.current);
+		history.current = previous;
+		Some(previous)
+	}
+
+	/// Reapplies the last state that was undone and returns it, or `None` if there's nothing to
+	/// redo
+	pub fn redo(&mut self, path: &Path) -> Option<ViewEdit> {
+		let history = self.histories.get_mut(path)?;
+		let next = history.redo.pop()?;
+		history.undo.push(history.current);
+		history.current = next;
+		Some(next)
+	}
+}
@@ -0,0 +1,50 @@
+use std::rc::{Rc, Weak};
+
+use gelatin::{label::Label, misc::*, picture::Picture};
+
+use super::text_notification::{render_text, TEXT_RENDER_SCALE};
+
+/// A short line of text in a corner of the picture that stays until it's changed, like the
+/// number of images that look like the current one.
+pub struct Badge {
+	pub widget: Weak<Label>,
+	shown_text: Option<String>,
+}
+
+impl Badge {
+	pub fn new(
+		widget: &Rc<Label>,
+		horizontal_align: Alignment,
+		vertical_align: Alignment,
+	) -> Badge {
+		widget.set_icon(None);
+		widget.set_ignore_layout(true);
+		widget.set_margin_all(8.0);
+		widget.set_horizontal_align(horizontal_align);
+		widget.set_vertical_align(vertical_align);
+		widget.set_visible(false);
+
+		Badge { widget: Rc::downgrade(widget), shown_text: None }
+	}
+
+	/// Shows the text, or hides the badge if `text` is `None`
+	pub fn set_text(&mut self, text: Option<String>) {
+		if self.shown_text == text {
+			return;
+		}
+		let widget = self.widget.upgrade().unwrap();
+		match text.as_deref().and_then(render_text) {
+			Some(image) => {
+				let size = LogicalVector::new(
+					image.width() as f32 / TEXT_RENDER_SCALE,
+					image.height() as f32 / TEXT_RENDER_SCALE,
+				);
+				widget.set_fixed_size(size);
+				widget.set_icon(Some(Rc::new(Picture::from_image(image))));
+				widget.set_visible(true);
+			}
+			None => widget.set_visible(false),
+		}
+		self.shown_text = text;
+	}
+}
@@ -1,15 +1,19 @@
 use super::picture_widget::ScalingMode;
-use crate::{ConfigWindowSection, Configuration, Theme};
+use crate::configuration::FullscreenToolbar;
+use crate::{Cache, ConfigWindowSection, Configuration, Theme};
 
 use gelatin::{
 	button::Button,
 	line_layout_container::HorizontalLayoutContainer,
 	misc::{Alignment, Length},
 	picture::Picture,
-	slider::Slider,
+	slider::{Scrolling, Slider},
+	Widget,
 };
+use std::cell::Cell;
 use std::f32;
 use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 static MOON: &[u8] = include_bytes!("../../resource/moon.png");
 static LIGHT: &[u8] = include_bytes!("../../resource/light.png");
@@ -29,7 +33,17 @@ const ACTIVE_BG_COLOR: [f32; 4] = [0.3, 0.3, 0.3, 0.5];
 
 const SMALL_BUTTON_GAP: f32 = 4.0;
 const BIG_BUTTON_GAP: f32 = 32.0;
-const BUTTON_SIZE: f32 = 24.0;
+const DEFAULT_BUTTON_SIZE: f32 = 24.0;
+const DEFAULT_BAR_HEIGHT: f32 = 32.0;
+const MIN_BUTTON_SIZE: f32 = 12.0;
+const MAX_BUTTON_SIZE: f32 = 128.0;
+const MAX_BAR_HEIGHT: f32 = 160.0;
+
+const DEFAULT_REVEAL_MARGIN: f32 = 8.0;
+const DEFAULT_SCROLL_SPEED: f32 = 1.0;
+const DEFAULT_SCROLL_INERTIA: f32 = 0.15;
+/// How long a revealed bar stays visible after the cursor leaves it
+const REVEAL_HIDE_DELAY: Duration = Duration::from_millis(1000);
 
 pub struct BottomBar {
 	pub widget: Rc<HorizontalLayoutContainer>,
@@ -40,9 +54,14 @@ pub struct BottomBar {
 	pub theme_button: Rc<Button>,
 	pub help_button: Rc<Button>,
 
-	/// This is false if the configuration requires this to be invisible
-	// and true otherwise.
-	pub should_show: bool,
+	/// Whether this is shown while the window is not fullscreen. This is initialized from the
+	/// configuration or from the state saved in the cache, and is changed by `toggle_visible`.
+	windowed_visible: Cell<bool>,
+	fullscreen: Cell<bool>,
+	fullscreen_toolbar: FullscreenToolbar,
+	reveal_margin: f32,
+	/// When the bar that was revealed by the cursor is hidden again
+	reveal_hide_time: Cell<Option<Instant>>,
 
 	question: Rc<Picture>,
 	question_light: Rc<Picture>,
@@ -59,7 +78,7 @@ pub struct BottomBar {
 }
 
 impl BottomBar {
-	pub fn new(config: &Configuration) -> Self {
+	pub fn new(config: &Configuration, cache: &Cache) -> Self {
 		let question = Rc::new(Picture::from_encoded_bytes(QUESTION_BUTTON));
 		let question_light = Rc::new(Picture::from_encoded_bytes(QUESTION_BUTTON_LIGHT));
 		let question_noti = Rc::new(Picture::from_encoded_bytes(QUESTION_NOTI));
@@ -73,18 +92,39 @@ impl BottomBar {
 		let fit_best = Rc::new(Picture::from_encoded_bytes(FIT_BEST));
 		let fit_best_light = Rc::new(Picture::from_encoded_bytes(FIT_BEST_LIGHT));
 
+		let window_config = config.window.as_ref();
+		let button_size = window_config.and_then(|w| w.toolbar_icon_size);
+		let button_size =
+			button_size.unwrap_or(DEFAULT_BUTTON_SIZE).clamp(MIN_BUTTON_SIZE, MAX_BUTTON_SIZE);
+		// The bar is at least as high as the buttons, with a gap above them
+		let min_height = button_size + SMALL_BUTTON_GAP;
+		let bar_height = window_config.and_then(|w| w.toolbar_height);
+		let bar_height = bar_height
+			.unwrap_or(DEFAULT_BAR_HEIGHT + button_size - DEFAULT_BUTTON_SIZE)
+			.clamp(min_height, MAX_BAR_HEIGHT.max(min_height));
+		let sizes = (button_size, (bar_height - button_size) * 0.5);
+
 		let widget = Rc::new(HorizontalLayoutContainer::new());
 		widget.set_margin_left(0.0);
 		widget.set_margin_right(0.0);
-		widget.set_height(Length::Fixed(32.0));
+		widget.set_height(Length::Fixed(bar_height));
 		widget.set_width(Length::Stretch { min: 0.0, max: f32::INFINITY });
 
-		let orig_scale_button = make_icon_button(Alignment::Start);
-		let fit_best_button = make_icon_button(Alignment::Start);
-		let fit_stretch_button = make_icon_button(Alignment::Start);
-		let slider = make_slider();
-		let theme_button = make_icon_button(Alignment::End);
-		let help_button = make_icon_button(Alignment::End);
+		let orig_scale_button = make_icon_button(Alignment::Start, sizes);
+		let fit_best_button = make_icon_button(Alignment::Start, sizes);
+		let fit_stretch_button = make_icon_button(Alignment::Start, sizes);
+		let slider = make_slider(sizes);
+		slider.set_scrolling(Some(Scrolling {
+			speed: window_config
+				.and_then(|w| w.slider_scroll_speed)
+				.unwrap_or(DEFAULT_SCROLL_SPEED),
+			inertia: window_config
+				.and_then(|w| w.scroll_inertia)
+				.unwrap_or(DEFAULT_SCROLL_INERTIA)
+				.max(0.0),
+		}));
+		let theme_button = make_icon_button(Alignment::End, sizes);
+		let help_button = make_icon_button(Alignment::End, sizes);
 
 		orig_scale_button.set_margin_left(SMALL_BUTTON_GAP);
 		fit_stretch_button.set_margin_right(SMALL_BUTTON_GAP);
@@ -92,6 +132,14 @@ impl BottomBar {
 		help_button.set_margin_left(SMALL_BUTTON_GAP);
 		help_button.set_margin_right(SMALL_BUTTON_GAP);
 
+		for (order, button) in
+			[&orig_scale_button, &fit_best_button, &fit_stretch_button, &theme_button, &help_button]
+				.iter()
+				.enumerate()
+		{
+			button.set_focus_order(Some(order as i32));
+		}
+
 		widget.add_child(orig_scale_button.clone());
 		widget.add_child(fit_best_button.clone());
 		widget.add_child(fit_stretch_button.clone());
@@ -99,13 +147,15 @@ impl BottomBar {
 		widget.add_child(theme_button.clone());
 		widget.add_child(help_button.clone());
 
-		let should_show;
-		if let Some(ConfigWindowSection { show_bottom_bar: Some(false), .. }) = config.window {
-			widget.set_visible(false);
-			should_show = false;
-		} else {
-			should_show = true;
-		}
+		let windowed_visible = match config.window {
+			Some(ConfigWindowSection { show_bottom_bar: Some(show), .. }) => show,
+			_ => cache.window.toolbar_visible,
+		};
+		widget.set_visible(windowed_visible);
+		let fullscreen_toolbar =
+			window_config.and_then(|w| w.fullscreen_toolbar).unwrap_or_default();
+		let reveal_margin =
+			window_config.and_then(|w| w.reveal_margin).unwrap_or(DEFAULT_REVEAL_MARGIN);
 
 		Self {
 			widget,
@@ -115,7 +165,11 @@ impl BottomBar {
 			slider,
 			theme_button,
 			help_button,
-			should_show,
+			windowed_visible: Cell::new(windowed_visible),
+			fullscreen: Cell::new(false),
+			fullscreen_toolbar,
+			reveal_margin,
+			reveal_hide_time: Cell::new(None),
 
 			question,
 			question_light,
@@ -165,10 +219,66 @@ impl BottomBar {
 		}
 	}
 
-	/// Sets this visible iff both the `visible` parameter is `true` and
-	/// the `should_show` property of this object is `true`
-	pub fn set_visible_if_should_show(&self, visible: bool) {
-		self.widget.set_visible(visible && self.should_show);
+	/// When entering fullscreen, the bottom bar is shown or hidden according to
+	/// `fullscreen_toolbar`, and it's restored to its windowed visibility when leaving fullscreen.
+	pub fn set_fullscreen(&self, fullscreen: bool) {
+		self.fullscreen.set(fullscreen);
+		self.reveal_hide_time.set(None);
+		let visible = if fullscreen {
+			self.fullscreen_toolbar == FullscreenToolbar::AlwaysShown
+		} else {
+			self.windowed_visible.get()
+		};
+		self.widget.set_visible(visible);
+	}
+
+	/// Reveals the bar in fullscreen when the cursor gets within `reveal_margin` of the bottom
+	/// edge, and schedules hiding it when the cursor leaves the bar. `picture_bottom` is the
+	/// bottom edge of the area above the bar.
+	///
+	/// Returns the time at which `update_reveal` has to be called, if hiding was scheduled.
+	pub fn cursor_moved(&self, cursor_y: f32, picture_bottom: f32) -> Option<Instant> {
+		if !self.fullscreen.get() || self.fullscreen_toolbar != FullscreenToolbar::EdgeReveal {
+			return None;
+		}
+		let visible = self.widget.visible();
+		let margin = if visible { 0.0 } else { self.reveal_margin };
+		if cursor_y >= picture_bottom - margin {
+			self.reveal_hide_time.set(None);
+			if !visible {
+				self.widget.set_visible(true);
+			}
+		} else if visible && self.reveal_hide_time.get().is_none() {
+			let hide_time = Instant::now() + REVEAL_HIDE_DELAY;
+			self.reveal_hide_time.set(Some(hide_time));
+			return Some(hide_time);
+		}
+		None
+	}
+
+	/// Hides the revealed bar once its delay is over. Returns the time at which this has to be
+	/// called again.
+	pub fn update_reveal(&self) -> Option<Instant> {
+		let hide_time = self.reveal_hide_time.get()?;
+		if Instant::now() < hide_time {
+			return Some(hide_time);
+		}
+		self.reveal_hide_time.set(None);
+		self.widget.set_visible(false);
+		None
+	}
+
+	/// Shows the bottom bar if it's hidden and hides it otherwise. Returns the new visibility.
+	///
+	/// This only changes the windowed visibility when the window is not fullscreen.
+	pub fn toggle_visible(&self) -> bool {
+		let visible = !self.widget.visible();
+		self.widget.set_visible(visible);
+		self.reveal_hide_time.set(None);
+		if !self.fullscreen.get() {
+			self.windowed_visible.set(visible);
+		}
+		visible
 	}
 
 	pub fn set_help_visible(&self, visible: bool) {
@@ -201,21 +311,22 @@ impl BottomBar {
 	}
 }
 
-fn make_icon_button(alignment: Alignment) -> Rc<Button> {
+/// The button is `size` wide and high, `top_gap` below the top of the bar
+fn make_icon_button(alignment: Alignment, (size, top_gap): (f32, f32)) -> Rc<Button> {
 	let button = Rc::new(Button::new());
-	button.set_margin_top(SMALL_BUTTON_GAP);
-	button.set_height(Length::Fixed(BUTTON_SIZE));
-	button.set_width(Length::Fixed(BUTTON_SIZE));
+	button.set_margin_top(top_gap);
+	button.set_height(Length::Fixed(size));
+	button.set_width(Length::Fixed(size));
 	button.set_horizontal_align(alignment);
 	button
 }
 
-fn make_slider() -> Rc<Slider> {
+fn make_slider((height, top_gap): (f32, f32)) -> Rc<Slider> {
 	let slider = Rc::new(Slider::new());
-	slider.set_margin_top(SMALL_BUTTON_GAP);
+	slider.set_margin_top(top_gap);
 	slider.set_margin_left(BIG_BUTTON_GAP);
 	slider.set_margin_right(BIG_BUTTON_GAP);
-	slider.set_height(Length::Fixed(BUTTON_SIZE));
+	slider.set_height(Length::Fixed(height));
 	slider.set_width(Length::Stretch { min: 0.0, max: f32::INFINITY });
 	slider.set_horizontal_align(Alignment::Center);
 	slider.set_steps(6, 1);
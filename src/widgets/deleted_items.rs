@@ -0,0 +1,208 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use gelatin::{
+	button::Button,
+	image::{imageops, RgbaImage},
+	line_layout_container::VerticalLayoutContainer,
+	misc::*,
+	picture::Picture,
+	Widget,
+};
+
+use super::text_notification::{render_sized_text, TEXT_RENDER_SCALE};
+use crate::image_cache::thumbnails::render_small;
+use crate::undo_stack::{trashed_paths, RESTORE_SUPPORTED};
+
+/// The longer side of the thumbnails in logical pixels
+const THUMBNAIL_SIZE: f32 = 48.0;
+const TEXT_SIZE: f32 = 13.0;
+const PANEL_MARGIN: f32 = 8.0;
+const PANEL_PADDING: f32 = 6.0;
+const ROW_GAP: f32 = 4.0;
+/// The oldest entries are dropped once there are more than this many
+const MAX_ENTRIES: usize = 10;
+const BG_COLOR: [f32; 4] = [0.08, 0.08, 0.08, 0.85];
+const TEXT_COLOR: [u8; 3] = [235, 235, 235];
+const HINT_COLOR: [u8; 3] = [160, 160, 160];
+const PURGED_COLOR: [u8; 3] = [100, 100, 100];
+
+struct DeletedItem {
+	path: PathBuf,
+	thumbnail: Option<RgbaImage>,
+	/// False once the file is gone from the trash, for example because the trash was emptied,
+	/// and on the platforms where files can't be restored from the trash
+	in_trash: bool,
+}
+
+/// Lists the files that were moved to the trash in this session, the most recent one first.
+/// Clicking an entry restores the file.
+pub struct DeletedItemsPanel {
+	pub widget: Rc<VerticalLayoutContainer>,
+	items: Vec<DeletedItem>,
+	/// The entry that was clicked, until it's taken by `take_restore_request`
+	clicked: Rc<RefCell<Option<PathBuf>>>,
+	size: LogicalVector,
+}
+
+impl DeletedItemsPanel {
+	pub fn new() -> DeletedItemsPanel {
+		let widget = Rc::new(VerticalLayoutContainer::new());
+		widget.set_ignore_layout(true);
+		widget.set_margin_all(PANEL_MARGIN);
+		widget.set_horizontal_align(Alignment::End);
+		widget.set_vertical_align(Alignment::Start);
+		widget.set_bg_color(BG_COLOR);
+		widget.set_visible(false);
+		let mut panel = DeletedItemsPanel {
+			widget,
+			items: Vec::new(),
+			clicked: Default::default(),
+			size: Default::default(),
+		};
+		panel.rebuild();
+		panel
+	}
+
+	/// Records that `path` is about to be moved to the trash. This must be called while the file
+	/// still exists so that its thumbnail can be made.
+	pub fn push(&mut self, path: &Path) {
+		let size = (THUMBNAIL_SIZE * TEXT_RENDER_SCALE) as u32;
+		let thumbnail = render_small(path, size).ok();
+		self.items.retain(|item| item.path != path);
+		self.items.insert(
+			0,
+			DeletedItem { path: path.to_owned(), thumbnail, in_trash: RESTORE_SUPPORTED },
+		);
+		self.items.truncate(MAX_ENTRIES);
+		self.rebuild();
+	}
+
+	/// Forgets the entry of `path` after it was restored
+	pub fn remove(&mut self, path: &Path) {
+		self.items.retain(|item| item.path != path);
+		self.rebuild();
+	}
+
+	/// Shows or hides the panel, and returns whether it's shown
+	pub fn toggle(&mut self) -> bool {
+		let visible = !self.widget.visible();
+		if visible {
+			self.refresh();
+		}
+		self.widget.set_visible(visible);
+		visible
+	}
+
+	/// Checks which of the files are still in the trash, the others are grayed out
+	pub fn refresh(&mut self) {
+		if let Some(trashed) = trashed_paths() {
+			for item in self.items.iter_mut() {
+				item.in_trash = trashed.contains(&item.path);
+			}
+		}
+		self.rebuild();
+	}
+
+	/// The original path of the entry that was clicked since the last call
+	pub fn take_restore_request(&self) -> Option<PathBuf> {
+		self.clicked.borrow_mut().take()
+	}
+
+	/// Whether the panel is shown over `cursor_pos` in `area`, which is the picture area that
+	/// the panel is placed in
+	pub fn covers(&self, cursor_pos: LogicalVector, area: &LogicalRect) -> bool {
+		if !self.widget.visible() {
+			return false;
+		}
+		let right = area.pos.vec.x + area.size.vec.x - PANEL_MARGIN;
+		let top = area.pos.vec.y + PANEL_MARGIN;
+		let (x, y) = (cursor_pos.vec.x, cursor_pos.vec.y);
+		x >= right - self.size.vec.x && x < right && y >= top && y < top + self.size.vec.y
+	}
+
+	fn rebuild(&mut self) {
+		let mut children = Vec::new();
+		self.widget.children(&mut children);
+		for child in children {
+			self.widget.remove_child(child);
+		}
+		let rows: Vec<_> = if self.items.is_empty() {
+			vec![(None, render_text_row("No files were deleted"))]
+		} else {
+			self.items.iter().map(|item| (Some(item), render_item_row(item))).collect()
+		};
+		let mut size = LogicalVector::new(0.0, 0.0);
+		for (i, (item, image)) in rows.into_iter().enumerate() {
+			let row_size = LogicalVector::new(
+				image.width() as f32 / TEXT_RENDER_SCALE,
+				image.height() as f32 / TEXT_RENDER_SCALE,
+			);
+			let margin_top = if i == 0 { PANEL_PADDING } else { ROW_GAP };
+			let button = Rc::new(Button::new());
+			button.set_icon(Some(Rc::new(Picture::from_image(image))));
+			button.set_fixed_size(row_size);
+			button.set_horizontal_align(Alignment::Start);
+			button.set_vertical_align(Alignment::Start);
+			button.set_margin_left(PANEL_PADDING);
+			button.set_margin_right(PANEL_PADDING);
+			button.set_margin_top(margin_top);
+			if let Some(item) = item.filter(|item| item.in_trash) {
+				let clicked = self.clicked.clone();
+				let path = item.path.clone();
+				button.set_on_click(move || *clicked.borrow_mut() = Some(path.clone()));
+			}
+			size.vec.x = size.vec.x.max(row_size.vec.x + PANEL_PADDING * 2.0);
+			size.vec.y += margin_top + row_size.vec.y;
+			self.widget.add_child(button);
+		}
+		size.vec.y += PANEL_PADDING;
+		self.widget.set_fixed_size(size);
+		self.size = size;
+	}
+}
+
+/// The thumbnail of the item next to its name, and whether it can be restored
+fn render_item_row(item: &DeletedItem) -> RgbaImage {
+	let name = item.path.file_name().unwrap_or(item.path.as_os_str()).to_string_lossy();
+	let (hint, thumbnail) = if item.in_trash {
+		("Click to restore", item.thumbnail.clone())
+	} else {
+		// Grayed out
+		let thumbnail = item.thumbnail.as_ref().map(|thumbnail| {
+			let mut thumbnail = imageops::grayscale_alpha(thumbnail);
+			for pixel in thumbnail.pixels_mut() {
+				pixel.0[1] /= 3;
+			}
+			gelatin::image::DynamicImage::ImageLumaA8(thumbnail).into_rgba8()
+		});
+		("Can't be restored", thumbnail)
+	};
+	let color = if item.in_trash { TEXT_COLOR } else { PURGED_COLOR };
+	let text = render_sized_text(&name, TEXT_SIZE, color, [0, 0, 0, 0]);
+	let hint_color = if item.in_trash { HINT_COLOR } else { PURGED_COLOR };
+	let hint = render_sized_text(hint, TEXT_SIZE * 0.85, hint_color, [0, 0, 0, 0]);
+
+	let box_size = (THUMBNAIL_SIZE * TEXT_RENDER_SCALE) as u32;
+	let text_w = text.iter().chain(hint.iter()).map(|t| t.width()).max().unwrap_or(0);
+	let text_h: u32 = text.iter().chain(hint.iter()).map(|t| t.height()).sum();
+	let height = box_size.max(text_h);
+	let mut row = RgbaImage::new(box_size + text_w, height);
+	if let Some(thumbnail) = thumbnail {
+		let x = (box_size - thumbnail.width().min(box_size)) / 2;
+		let y = (height - thumbnail.height().min(height)) / 2;
+		imageops::overlay(&mut row, &thumbnail, x as i64, y as i64);
+	}
+	let mut y = (height - text_h) / 2;
+	for line in text.iter().chain(hint.iter()) {
+		imageops::overlay(&mut row, line, box_size as i64, y as i64);
+		y += line.height();
+	}
+	row
+}
+
+fn render_text_row(text: &str) -> RgbaImage {
+	render_sized_text(text, TEXT_SIZE, HINT_COLOR, [0, 0, 0, 0])
+		.unwrap_or_else(|| RgbaImage::new(1, 1))
+}
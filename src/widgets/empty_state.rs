@@ -0,0 +1,129 @@
+use std::rc::{Rc, Weak};
+
+use gelatin::{
+	image::{imageops, RgbaImage},
+	label::Label,
+	misc::*,
+	picture::Picture,
+};
+
+use super::text_notification::{render_sized_text, TEXT_RENDER_SCALE};
+use crate::configuration::{Configuration, Theme};
+use crate::input_handling::*;
+
+const TITLE_SIZE: f32 = 20.0;
+const TEXT_SIZE: f32 = 14.0;
+/// The space between the title and the keys, and between the keys and their actions, in
+/// logical pixels
+const GAP: f32 = 16.0;
+
+/// Shown in the middle of the picture area while no image is open. It tells that images can be
+/// dropped onto the window and lists the keys of a few actions, as they are bound.
+pub struct EmptyState {
+	pub widget: Weak<Label>,
+	enabled: bool,
+	/// The keys of the actions next to what the actions do
+	hints: Vec<(String, &'static str)>,
+	/// False if the text couldn't be rendered, for example because there are no fonts
+	rendered: bool,
+}
+
+impl EmptyState {
+	pub fn new(widget: &Rc<Label>, config: &Configuration) -> EmptyState {
+		widget.set_icon(None);
+		widget.set_ignore_layout(true);
+		widget.set_horizontal_align(Alignment::Center);
+		widget.set_vertical_align(Alignment::Center);
+		widget.set_visible(false);
+
+		let actions = [
+			(IMG_NEXT_NAME, "Next image"),
+			(IMG_PREV_NAME, "Previous image"),
+			(TOGGLE_FULLSCREEN_NAME, "Fullscreen"),
+			(TOGGLE_TOOLBAR_NAME, "Toolbar"),
+			(TOGGLE_STATUS_BAR_NAME, "Status bar"),
+		];
+		let hints = actions
+			.iter()
+			.filter_map(|&(action, text)| Some((bound_keys(config, action)?, text)))
+			.collect();
+		let enabled = config.window.as_ref().and_then(|w| w.show_empty_state).unwrap_or(true);
+		EmptyState { widget: Rc::downgrade(widget), enabled, hints, rendered: false }
+	}
+
+	/// Renders the text in the colors of `theme`
+	pub fn set_theme(&mut self, theme: Theme) {
+		let (text_color, key_color) = match theme {
+			Theme::Light => ([40, 40, 40], [100, 100, 100]),
+			Theme::Dark => ([220, 220, 220], [140, 140, 140]),
+		};
+		let widget = self.widget.upgrade().unwrap();
+		match self.render(text_color, key_color) {
+			Some(image) => {
+				let size = LogicalVector::new(
+					image.width() as f32 / TEXT_RENDER_SCALE,
+					image.height() as f32 / TEXT_RENDER_SCALE,
+				);
+				widget.set_fixed_size(size);
+				widget.set_icon(Some(Rc::new(Picture::from_image(image))));
+				self.rendered = true;
+			}
+			None => self.rendered = false,
+		}
+	}
+
+	/// Shows the text while `nothing_open` is true, unless `show_empty_state` is off
+	pub fn set_shown(&self, nothing_open: bool) {
+		let widget = self.widget.upgrade().unwrap();
+		widget.set_visible(self.enabled && nothing_open && self.rendered);
+	}
+
+	fn render(&self, text_color: [u8; 3], key_color: [u8; 3]) -> Option<RgbaImage> {
+		let transparent = [0, 0, 0, 0];
+		let title = render_sized_text(
+			"Drop an image here to open it",
+			TITLE_SIZE,
+			text_color,
+			transparent,
+		)?;
+		let rows: Vec<_> = self
+			.hints
+			.iter()
+			.filter_map(|(keys, text)| {
+				let keys = render_sized_text(keys, TEXT_SIZE, key_color, transparent)?;
+				let text = render_sized_text(text, TEXT_SIZE, text_color, transparent)?;
+				Some((keys, text))
+			})
+			.collect();
+		// The keys are right aligned next to the left aligned actions
+		let gap = (GAP * TEXT_RENDER_SCALE) as u32;
+		let keys_w = rows.iter().map(|(keys, _)| keys.width()).max().unwrap_or(0);
+		let text_w = rows.iter().map(|(_, text)| text.width()).max().unwrap_or(0);
+		let rows_w = keys_w + gap + text_w;
+		let rows_h: u32 = rows.iter().map(|(keys, _)| keys.height()).sum();
+		let width = title.width().max(rows_w);
+		let mut image = RgbaImage::new(width, title.height() + gap + rows_h);
+		imageops::overlay(&mut image, &title, ((width - title.width()) / 2) as i64, 0);
+		let left = (width - rows_w) / 2;
+		let mut y = title.height() + gap;
+		for (keys, text) in rows.iter() {
+			let keys_x = left + keys_w - keys.width();
+			imageops::overlay(&mut image, keys, keys_x as i64, y as i64);
+			imageops::overlay(&mut image, text, (left + keys_w + gap) as i64, y as i64);
+			y += keys.height();
+		}
+		Some(image)
+	}
+}
+
+/// Like "D, Right", the keys that trigger `action`, `None` if it isn't bound
+fn bound_keys(config: &Configuration, action: &str) -> Option<String> {
+	let keys: Vec<&str> = match config.bindings.as_ref().and_then(|b| b.get(action)) {
+		Some(keys) => keys.iter().map(String::as_str).collect(),
+		None => DEFAULT_BINDINGS.get(action)?.clone(),
+	};
+	// The side buttons of the mouse and the rarer keys would only make the list longer
+	let keys: Vec<&str> =
+		keys.into_iter().filter(|key| !key.starts_with("Mouse")).take(2).collect();
+	(!keys.is_empty()).then(|| keys.join(", "))
+}
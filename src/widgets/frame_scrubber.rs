@@ -0,0 +1,115 @@
+use std::rc::Rc;
+
+use gelatin::{
+	label::Label, line_layout_container::VerticalLayoutContainer, misc::*, picture::Picture,
+	slider::Slider, Widget,
+};
+
+use super::text_notification::{render_sized_text, TEXT_RENDER_SCALE};
+use crate::configuration::Configuration;
+use crate::playback_manager::AnimationFrames;
+
+const TEXT_SIZE: f32 = 13.0;
+const SLIDER_HEIGHT: f32 = 16.0;
+const PANEL_MARGIN: f32 = 8.0;
+const PANEL_PADDING: f32 = 6.0;
+const BG_COLOR: [f32; 4] = [0.08, 0.08, 0.08, 0.85];
+const TEXT_COLOR: [u8; 3] = [235, 235, 235];
+
+/// A slider along the bottom of the picture with a step for every frame of the animation that
+/// is shown, and the number of the frame above it. The slider is wired up in `main`, like the
+/// slider of the toolbar.
+pub struct FrameScrubber {
+	pub widget: Rc<VerticalLayoutContainer>,
+	pub slider: Rc<Slider>,
+	label: Rc<Label>,
+	enabled: bool,
+	/// The frames that the label was rendered for
+	shown: Option<AnimationFrames>,
+	height: f32,
+}
+
+impl FrameScrubber {
+	pub fn new(config: &Configuration) -> FrameScrubber {
+		let widget = Rc::new(VerticalLayoutContainer::new());
+		widget.set_ignore_layout(true);
+		widget.set_margin_all(PANEL_MARGIN);
+		widget.set_width(Length::Stretch { min: 0.0, max: f32::INFINITY });
+		widget.set_vertical_align(Alignment::End);
+		widget.set_bg_color(BG_COLOR);
+		widget.set_visible(false);
+
+		let label = Rc::new(Label::new());
+		label.set_margin_top(PANEL_PADDING);
+		label.set_horizontal_align(Alignment::Center);
+		let slider = Rc::new(Slider::new());
+		slider.set_margin_left(PANEL_PADDING);
+		slider.set_margin_right(PANEL_PADDING);
+		slider.set_margin_top(PANEL_PADDING);
+		slider.set_height(Length::Fixed(SLIDER_HEIGHT));
+		slider.set_width(Length::Stretch { min: 0.0, max: f32::INFINITY });
+		widget.add_child(label.clone());
+		widget.add_child(slider.clone());
+
+		let enabled = config.window.as_ref().and_then(|w| w.show_frame_scrubber).unwrap_or(true);
+		FrameScrubber { widget, slider, label, enabled, shown: None, height: 0.0 }
+	}
+
+	/// Turns the scrubber on or off, and returns whether it's on
+	pub fn toggle(&mut self) -> bool {
+		self.enabled = !self.enabled;
+		if !self.enabled {
+			self.widget.set_visible(false);
+			self.shown = None;
+		}
+		self.enabled
+	}
+
+	/// Shows the frames of the animation, or hides the scrubber if there's only one frame
+	pub fn update(&mut self, frames: Option<AnimationFrames>) {
+		let frames = frames.filter(|frames| self.enabled && frames.count > 1);
+		self.widget.set_visible(frames.is_some());
+		let Some(frames) = frames else {
+			self.shown = None;
+			return;
+		};
+		if self.shown == Some(frames) {
+			return;
+		}
+		// While the slider is dragged, it stays where the cursor is until that frame is shown
+		let value = if self.slider.dragged() { self.slider.value() } else { frames.current as u32 };
+		self.slider.set_steps(frames.count as u32, value.min(frames.count as u32 - 1));
+		let text = if frames.complete {
+			format!("Frame {} / {}", frames.current + 1, frames.count)
+		} else {
+			format!("Frame {} / {} (decoding)", frames.current + 1, frames.count)
+		};
+		let label_height = match render_sized_text(&text, TEXT_SIZE, TEXT_COLOR, [0, 0, 0, 0]) {
+			Some(image) => {
+				let size = LogicalVector::new(
+					image.width() as f32 / TEXT_RENDER_SCALE,
+					image.height() as f32 / TEXT_RENDER_SCALE,
+				);
+				self.label.set_fixed_size(size);
+				self.label.set_icon(Some(Rc::new(Picture::from_image(image))));
+				size.vec.y
+			}
+			None => 0.0,
+		};
+		self.height = PANEL_PADDING * 3.0 + label_height + SLIDER_HEIGHT;
+		self.widget.set_height(Length::Fixed(self.height));
+		self.shown = Some(frames);
+	}
+
+	/// Whether the scrubber is shown over `cursor_pos` in `area`, which is the picture area that
+	/// the scrubber is placed in
+	pub fn covers(&self, cursor_pos: LogicalVector, area: &LogicalRect) -> bool {
+		if !self.widget.visible() {
+			return false;
+		}
+		let bottom = area.pos.vec.y + area.size.vec.y - PANEL_MARGIN;
+		let (left, right) = (area.pos.vec.x + PANEL_MARGIN, area.pos.vec.x + area.size.vec.x);
+		let (x, y) = (cursor_pos.vec.x, cursor_pos.vec.y);
+		x >= left && x < right - PANEL_MARGIN && y >= bottom - self.height && y < bottom
+	}
+}
@@ -0,0 +1,275 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use gelatin::{
+	image::RgbaImage,
+	label::Label,
+	misc::{Alignment, LogicalVector},
+	picture::Picture,
+	NextUpdate,
+};
+use log::debug;
+
+use crate::configuration::{AnimatedThumbnail, Configuration};
+use crate::headless::{decode_frames, shrink_to_fit};
+use crate::image_cache::thumbnails::render_small;
+
+pub const DEFAULT_HOVER_PREVIEW_DELAY_MS: u64 = 400;
+pub const DEFAULT_HOVER_PREVIEW_SIZE: u32 = 320;
+const MIN_PREVIEW_SIZE: u32 = 64;
+const MAX_PREVIEW_SIZE: u32 = 1024;
+/// The number of decoded previews that are kept around
+const PREVIEW_CACHE_SIZE: usize = 16;
+/// The number of frames of all the cached previews together, to bound the memory that animated
+/// previews take up
+const PREVIEW_CACHE_FRAMES: usize = 128;
+/// The frames after these are left out of animated previews
+const MAX_PREVIEW_FRAMES: usize = 64;
+/// Frames that are shown for a shorter time than this are shown for `DEFAULT_FRAME_DELAY`
+/// instead, like browsers do
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(10);
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+/// How often the decoding is checked for being done
+const POLL_INTERVAL: Duration = Duration::from_millis(30);
+const PREVIEW_MARGIN: f32 = 8.0;
+
+struct Request {
+	id: u64,
+	path: PathBuf,
+}
+
+/// The decoded frames of an image and how long each is shown. A still image has a single frame.
+type Frames<T> = Vec<(T, Duration)>;
+
+/// The id of the request, the path of the image and the preview, `None` if it couldn't be
+/// decoded
+type PreviewResult = (u64, PathBuf, Option<Frames<RgbaImage>>);
+
+/// The preview that the worker should decode next. Setting it replaces the previous request
+/// if the worker hasn't started on that one yet.
+struct Shared {
+	request: Mutex<Option<Request>>,
+	request_available: Condvar,
+}
+
+/// A larger preview of the image under the cursor on the slider of the bottom bar, which is
+/// shown once the cursor stayed on the same image for a while.
+///
+/// The previews are decoded on a background thread. Moving on to another image cancels the
+/// previous decode if it hasn't started yet. Animations are played if `animated_thumbnail` is
+/// `animate_playing`.
+pub struct HoverPreview {
+	pub widget: Weak<Label>,
+	delay: Duration,
+	size: u32,
+	animate: bool,
+	/// The hovered image and when the cursor got to it
+	hovered: Option<(PathBuf, Instant)>,
+	/// The id of the decode that is in progress
+	pending: Option<u64>,
+	next_id: u64,
+	cache: VecDeque<(PathBuf, Rc<Frames<Rc<Picture>>>)>,
+	shared: Arc<Shared>,
+	results: Receiver<PreviewResult>,
+	result_sender: Option<Sender<PreviewResult>>,
+}
+
+impl HoverPreview {
+	pub fn new(widget: &Rc<Label>, config: &Configuration) -> HoverPreview {
+		widget.set_icon(None);
+		widget.set_ignore_layout(true);
+		widget.set_margin_all(PREVIEW_MARGIN);
+		widget.set_horizontal_align(Alignment::Start);
+		widget.set_vertical_align(Alignment::End);
+		widget.set_visible(false);
+
+		let window = config.window.as_ref();
+		let delay = window.and_then(|w| w.hover_preview_delay_ms);
+		let size = window.and_then(|w| w.hover_preview_size);
+		let size = size.unwrap_or(DEFAULT_HOVER_PREVIEW_SIZE);
+		let size = if size == 0 { 0 } else { size.clamp(MIN_PREVIEW_SIZE, MAX_PREVIEW_SIZE) };
+		let animated_thumbnail = config.navigation.as_ref().and_then(|n| n.animated_thumbnail);
+		let (result_sender, results) = channel();
+		HoverPreview {
+			widget: Rc::downgrade(widget),
+			delay: Duration::from_millis(delay.unwrap_or(DEFAULT_HOVER_PREVIEW_DELAY_MS)),
+			size,
+			animate: animated_thumbnail == Some(AnimatedThumbnail::AnimatePlaying),
+			hovered: None,
+			pending: None,
+			next_id: 0,
+			cache: VecDeque::new(),
+			shared: Arc::new(Shared {
+				request: Mutex::new(None),
+				request_available: Condvar::new(),
+			}),
+			results,
+			result_sender: Some(result_sender),
+		}
+	}
+
+	/// Shows the preview of `hovered` once its delay is over, or hides the preview if nothing is
+	/// hovered. `hovered` is the path of the image and the horizontal position of the cursor.
+	pub fn update(&mut self, hovered: Option<(PathBuf, f32)>) -> NextUpdate {
+		if self.size == 0 {
+			return NextUpdate::Latest;
+		}
+		let Some((path, cursor_x)) = hovered else {
+			self.hovered = None;
+			self.cancel();
+			self.widget.upgrade().unwrap().set_visible(false);
+			return NextUpdate::Latest;
+		};
+		let now = Instant::now();
+		let since = match &self.hovered {
+			Some((hovered_path, since)) if *hovered_path == path => *since,
+			_ => {
+				self.cancel();
+				self.widget.upgrade().unwrap().set_visible(false);
+				self.hovered = Some((path.clone(), now));
+				now
+			}
+		};
+		let show_time = since + self.delay;
+		if now < show_time {
+			return NextUpdate::WaitUntil(show_time);
+		}
+		self.receive_results();
+		if let Some(frames) = self.cached(&path) {
+			let (picture, next_frame) = frame_at(&frames, now - show_time);
+			self.show(picture, cursor_x);
+			return match next_frame {
+				Some(wait) => NextUpdate::WaitUntil(now + wait),
+				None => NextUpdate::Latest,
+			};
+		}
+		if self.pending.is_none() {
+			self.request(path);
+		}
+		NextUpdate::WaitUntil(now + POLL_INTERVAL)
+	}
+
+	fn show(&self, picture: Rc<Picture>, cursor_x: f32) {
+		let widget = self.widget.upgrade().unwrap();
+		let metadata = match picture.get_metadata() {
+			Ok(metadata) if metadata.width > 0 => metadata,
+			_ => {
+				widget.set_visible(false);
+				return;
+			}
+		};
+		let size = LogicalVector::new(metadata.width as f32, metadata.height as f32);
+		widget.set_fixed_size(size);
+		// Centered on the cursor, but it stays in the window on the left
+		widget.set_margin_left((cursor_x - size.vec.x * 0.5).max(PREVIEW_MARGIN));
+		widget.set_icon(Some(picture));
+		widget.set_visible(true);
+	}
+
+	fn cached(&mut self, path: &Path) -> Option<Rc<Frames<Rc<Picture>>>> {
+		let index = self.cache.iter().position(|(cached, _)| cached == path)?;
+		// The most recently used is at the back
+		let entry = self.cache.remove(index).unwrap();
+		let frames = entry.1.clone();
+		self.cache.push_back(entry);
+		Some(frames)
+	}
+
+	fn receive_results(&mut self) {
+		while let Ok((id, path, frames)) = self.results.try_recv() {
+			if self.pending == Some(id) {
+				self.pending = None;
+			}
+			// A preview that was finished after the cursor moved on is kept as well, in case the
+			// cursor comes back. A failed decode is cached too, as an empty picture, so that it's not retried
+			let frames = frames.unwrap_or_else(|| vec![(RgbaImage::new(0, 0), Duration::ZERO)]);
+			let frames: Frames<_> = frames
+				.into_iter()
+				.map(|(image, delay)| (Rc::new(Picture::from_image(image)), delay))
+				.collect();
+			let cached_frames = |cache: &VecDeque<(_, Rc<Frames<_>>)>| {
+				cache.iter().map(|(_, frames)| frames.len()).sum::<usize>()
+			};
+			while !self.cache.is_empty()
+				&& (self.cache.len() >= PREVIEW_CACHE_SIZE
+					|| cached_frames(&self.cache) + frames.len() > PREVIEW_CACHE_FRAMES)
+			{
+				self.cache.pop_front();
+			}
+			self.cache.push_back((path, Rc::new(frames)));
+		}
+	}
+
+	fn request(&mut self, path: PathBuf) {
+		if let Some(sender) = self.result_sender.take() {
+			let shared = self.shared.clone();
+			let size = self.size;
+			let animate = self.animate;
+			std::thread::spawn(move || run_worker(&shared, &sender, size, animate));
+		}
+		self.next_id += 1;
+		self.pending = Some(self.next_id);
+		*self.shared.request.lock().unwrap() = Some(Request { id: self.next_id, path });
+		self.shared.request_available.notify_one();
+	}
+
+	fn cancel(&mut self) {
+		self.pending = None;
+		self.shared.request.lock().unwrap().take();
+	}
+}
+
+/// The frame to show once the preview has been shown for `elapsed`, and how long until the next
+/// frame. The animation loops, and a still image has no next frame.
+fn frame_at(frames: &Frames<Rc<Picture>>, elapsed: Duration) -> (Rc<Picture>, Option<Duration>) {
+	let delay = |delay: Duration| if delay < MIN_FRAME_DELAY { DEFAULT_FRAME_DELAY } else { delay };
+	if frames.len() < 2 {
+		return (frames[0].0.clone(), None);
+	}
+	let total: Duration = frames.iter().map(|(_, d)| delay(*d)).sum();
+	let mut time = Duration::from_nanos((elapsed.as_nanos() % total.as_nanos()) as u64);
+	for (picture, frame_delay) in frames.iter() {
+		let frame_delay = delay(*frame_delay);
+		if time < frame_delay {
+			return (picture.clone(), Some(frame_delay - time));
+		}
+		time -= frame_delay;
+	}
+	(frames[0].0.clone(), Some(delay(frames[0].1)))
+}
+
+fn run_worker(shared: &Shared, sender: &Sender<PreviewResult>, size: u32, animate: bool) {
+	loop {
+		let request = {
+			let mut request = shared.request.lock().unwrap();
+			loop {
+				if let Some(request) = request.take() {
+					break request;
+				}
+				request = shared.request_available.wait(request).unwrap();
+			}
+		};
+		let frames = if animate {
+			decode_frames(&request.path, MAX_PREVIEW_FRAMES).map(|frames| {
+				let shrink = |(image, delay)| (shrink_to_fit(image, size, size), delay);
+				frames.into_iter().map(shrink).collect()
+			})
+		} else {
+			render_small(&request.path, size).map(|image| vec![(image, Duration::ZERO)])
+		};
+		let frames = match frames {
+			Ok(frames) => Some(frames),
+			Err(e) => {
+				debug!("Could not decode the preview of {:?}: {}", request.path, e);
+				None
+			}
+		};
+		if sender.send((request.id, request.path, frames)).is_err() {
+			return;
+		}
+	}
+}
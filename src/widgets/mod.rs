@@ -1,4 +1,12 @@
+pub mod badge;
 pub mod bottom_bar;
 pub mod copy_notification;
+pub mod deleted_items;
+pub mod empty_state;
+pub mod frame_scrubber;
 pub mod help_screen;
+pub mod hover_preview;
 pub mod picture_widget;
+pub mod status_bar;
+pub mod text_notification;
+pub mod watermark;
@@ -1,18 +1,30 @@
 use std::{
-	cell::{Ref, RefCell},
-	path::PathBuf,
+	cell::RefCell,
+	f32::consts::LN_2,
+	path::{Path, PathBuf},
 	rc::{Rc, Weak},
 	sync::{Arc, Mutex},
-	time::{Duration, Instant},
+	time::{Duration, Instant, SystemTime},
 };
 
 use gelatin::{
-	cgmath::{Matrix4, Vector2, Vector3},
-	glium::{uniform, uniforms::MagnifySamplerFilter, Frame, Program, Surface},
+	cgmath::{Deg, InnerSpace, Matrix4, SquareMatrix, Vector2, Vector3, VectorSpace, Zero},
+	glium::{
+		framebuffer::SimpleFrameBuffer,
+		texture::{
+			MipmapsOption, RawImage2d, SrgbFormat, SrgbTexture2d, Texture2d,
+			UncompressedFloatFormat,
+		},
+		uniform,
+		uniforms::MagnifySamplerFilter,
+		Blend, BlitTarget, Frame, Program, Surface,
+	},
+	image::{imageops, Rgba, RgbaImage},
 	shaders::ShaderDescriptor,
 	winit::{
 		event::{ElementState, MouseButton},
 		platform::modifier_supplement::KeyEventExtModifierSupplement,
+		window::CursorIcon,
 	},
 };
 
@@ -27,19 +39,137 @@ use gelatin::{
 
 use crate::{
 	clipboard_handler::ClipboardHandler,
-	configuration::{Antialias, Cache, Configuration},
-	image_cache::{image_loader::Orientation, AnimationFrameTexture},
+	command_runner::CommandRunner,
+	configuration::{
+		format_title, AnimatedThumbnail, Antialias, AutoAdvanceDirection, AutoAdvanceTrigger,
+		Cache, CompositionOverlay, ConfigMouseSection, Configuration, ConfirmQuit, CopyViewArea,
+		CurrentDeleted, EndBehavior, FitPadding, FolderScaling, FolderViewSettings, IdleAction,
+		LetterboxColor, MagnifierShape, MeasureUnit, MirrorView, NamedLetterboxColor, OpenBehavior,
+		OpenFile, PanButton, PrecisionModifier, ReadingDirection, RotationFill, SmallImagePolicy,
+		SortOrder, StartupFit, TapZone, Theme, UnsupportedFileAction, TITLE_TOKENS,
+	},
+	image_cache::{
+		archive::is_archive,
+		capture_date::CaptureDate,
+		directory::image_after,
+		disk_cache::{self, DISK_CACHE_LIMIT},
+		image_loader::{decode_thread_count, is_file_supported, Orientation, MAKE_BACKDROPS},
+		motion_photo::{clip_file, find_motion_clip, MotionClip},
+		resolution::pixels_per_mm,
+		source, srgb_decode, srgb_to_linear,
+		thumbnails::REPRESENTATIVE_FRAME,
+		AnimationFrameTexture, GridTexture,
+	},
 	input_handling::*,
 	playback_manager::*,
 	shaders,
+	undo_stack::{move_to_folder, move_to_trash, restore_from_trash, UndoStack},
 	utils::virtual_keycode_to_string,
+	view_edits::{ViewEdit, ViewEdits},
 };
 
-use super::{bottom_bar::BottomBar, copy_notification::CopyNotifications, help_screen::HelpScreen};
+use super::{
+	badge::Badge,
+	bottom_bar::BottomBar,
+	copy_notification::CopyNotifications,
+	deleted_items::DeletedItemsPanel,
+	empty_state::EmptyState,
+	frame_scrubber::FrameScrubber,
+	help_screen::HelpScreen,
+	hover_preview::HoverPreview,
+	status_bar::{format_file_size, StatusBar, StatusInfo},
+	text_notification::TextNotification,
+	watermark::{Watermark, WatermarkStyle},
+};
 
 const MIN_ZOOM_FACTOR: f32 = 0.0001;
 const MAX_ZOOM_FACTOR: f32 = 10000.0;
 const AA_TEXEL_SIZE_THRESHOLD: f32 = 4f32;
+/// Large enough for favicons, icons and most sprites
+const DEFAULT_SMALL_IMAGE_THRESHOLD: u32 = 128;
+const DEFAULT_SMALL_IMAGE_POLICY: SmallImagePolicy = SmallImagePolicy::FitWithLimit(8.0);
+const DEFAULT_PAN_KEY_SPEED: f32 = 1000.0;
+const DEFAULT_PAN_KEY_ACCEL: f32 = 4000.0;
+/// The rates of the zoom keys in how many times the size doubles per second
+const ZOOM_KEY_START_RATE: f32 = 0.75;
+const DEFAULT_ZOOM_KEY_MAX_RATE: f32 = 4.0;
+/// In seconds
+const DEFAULT_ZOOM_KEY_ACCEL: f32 = 1.5;
+const DEFAULT_DOUBLE_CLICK_MS: u64 = 250;
+/// See `precision_zoom_factor` and `precision_pan_factor`
+const DEFAULT_PRECISION_ZOOM_FACTOR: f32 = 0.2;
+const DEFAULT_PRECISION_PAN_FACTOR: f32 = 0.25;
+/// In images per second
+const DEFAULT_NAV_MAX_RATE: f32 = 10.0;
+/// The distance in logical pixels that the cursor may move between pressing and releasing the
+/// mouse button, for it to still count as a click
+const CLICK_MAX_DRAG: f32 = 4.0;
+const NOTIFICATION_DURATION: Duration = Duration::from_secs(2);
+const ZOOM_SNAP_NOTIFICATION_DURATION: Duration = Duration::from_millis(700);
+/// Long enough to read the instructions, it's shown again while drawing the line
+const STRAIGHTEN_NOTIFICATION_DURATION: Duration = Duration::from_secs(5);
+/// The scale bar is the longest round length that fits into this many logical pixels
+const SCALE_BAR_MAX_LENGTH: f32 = 160.0;
+/// The distance of the scale bar from the bottom of the picture area, which leaves room for its
+/// label below it
+const SCALE_BAR_BOTTOM: f32 = 40.0;
+/// Half the width of the marks at the ends of the scale bar and at the points of `measure`
+const MARK_SIZE: f32 = 5.0;
+const DEFAULT_ROTATION_STEP: f32 = 1.0;
+const DEFAULT_GRID_CELLS: u32 = 4;
+const GOLDEN_RATIO: f32 = 1.618_034;
+const DEFAULT_ZOOM_SNAP_TOLERANCE: f32 = 3.0;
+const DEFAULT_ZOOM_SNAP_TARGETS: &[f32] = &[100.0];
+const DEFAULT_REFERENCE_OPACITY: f32 = 0.5;
+const REFERENCE_OPACITY_STEP: f32 = 0.1;
+const DEFAULT_PEAKING_SENSITIVITY: f32 = 0.5;
+const PEAKING_SENSITIVITY_STEP: f32 = 0.1;
+/// The range of the Sobel gradient of the brightness that focus peaking highlights from, as
+/// the sensitivity goes from 1 to 0
+const MIN_PEAKING_THRESHOLD: f32 = 0.1;
+const MAX_PEAKING_THRESHOLD: f32 = 1.0;
+const DEFAULT_MAGNIFIER_ZOOM: f32 = 200.0;
+const DEFAULT_MAGNIFIER_SIZE: f32 = 240.0;
+const MIN_MAGNIFIER_SIZE: f32 = 32.0;
+const MAX_MAGNIFIER_SIZE: f32 = 2048.0;
+const DEFAULT_EDGE_MARGIN: f32 = 40.0;
+/// The `loupe_cursor` image is shrunk to fit into this many pixels
+const MAX_CURSOR_SIZE: u32 = 128;
+/// Leaves room for the rounding of texture filtering, so that a pixel at the threshold counts
+/// as clipped
+const CLIP_TOLERANCE: f32 = 0.0001;
+/// The number of characters of a load error that the error card shows
+const MAX_ERROR_LENGTH: usize = 120;
+/// How often the shown file is checked for being deleted or replaced by another program
+const FILE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// The names of the triage folders in the notifications and the status bar
+const TRIAGE_LETTERS: [&str; 2] = ["A", "B"];
+/// How often the free memory of the system is checked
+const MEMORY_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+/// The cached images are dropped when less than this fraction of the memory of the system is
+/// available
+const MEMORY_PRESSURE_RATIO: f64 = 0.05;
+/// The low memory mode is turned on when the system has less memory than this, in KiB, unless
+/// `low_memory` is set
+const LOW_MEMORY_TOTAL_KIB: u64 = 4 * 1024 * 1024;
+/// Images are shrunk to this size in the low memory mode when the monitor size is unknown
+const DEFAULT_LOW_MEMORY_MAX_SIDE: u32 = 1920;
+const EDGE_FLASH_DURATION: Duration = Duration::from_millis(300);
+const EDGE_FLASH_WIDTH: f32 = 6.0;
+/// How long after the first quit request the second one exits, with `confirm_quit`
+const QUIT_CONFIRM_DURATION: Duration = Duration::from_secs(3);
+/// Without `continuous_fit`, images are fit again once the size of the window stayed the same
+/// for this long
+const FIT_SETTLE_DURATION: Duration = Duration::from_millis(150);
+/// How far a step of the wheel scrolls with `edge_scroll_navigate`, in logical pixels
+const SCROLL_LINE_HEIGHT: f32 = 60.0;
+/// How many steps of the wheel past the edge go to the next image, with `edge_scroll_navigate`
+const EDGE_SCROLL_THRESHOLD: f32 = 3.0;
+/// Scrolling past the edge starts over after a pause this long
+const EDGE_SCROLL_RESET: Duration = Duration::from_millis(800);
+pub const MIN_UI_SCALE: f32 = 0.5;
+pub const MAX_UI_SCALE: f32 = 4.0;
+const UI_SCALE_STEP: f32 = 0.25;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum ScalingMode {
@@ -48,6 +178,203 @@ pub enum ScalingMode {
 	FitMin,
 }
 
+/// The windows that the actions of the picture widget ask for
+#[derive(Debug)]
+pub enum WindowRequest {
+	/// A new window that shows this image
+	OpenInNewWindow(PathBuf),
+	ToggleMirrorDisplay,
+	/// Links all the windows, so that they go to the next and previous images together
+	LinkWindows,
+	UnlinkWindows,
+	/// The widget of a linked window went to the next or the previous image, which the other
+	/// linked windows do as well
+	LinkedStep {
+		forward: bool,
+	},
+}
+
+/// What the window of `toggle_mirror_display` shows, see `PictureWidget::mirror_state`. This is
+/// also the view that linked windows share, without the path.
+#[derive(Debug, Clone)]
+pub struct MirrorState {
+	path: Option<PathBuf>,
+	scaling: ScalingMode,
+	rotation: f32,
+	/// The zoom, and the offset of the image from the center of the widget, with
+	/// `MirrorView::Same`
+	view: Option<(f32, LogicalVector)>,
+}
+
+/// Briefly highlights the left or right edge of the image area to signal that the
+/// first or the last image of the folder was reached.
+#[derive(Clone, Copy)]
+struct EdgeFlash {
+	at_end: bool,
+	start: Instant,
+}
+
+/// The image that was scrolled past with `edge_scroll_navigate`. The next image is shown with
+/// the edge that it's entered from in view.
+#[derive(Clone)]
+struct EdgeScrollEntry {
+	from: Option<PathBuf>,
+	/// Whether the next image was entered from its top, otherwise from its bottom
+	top: bool,
+}
+
+/// The points that are clicked with the `measure` tool
+#[derive(Clone, Default)]
+struct Measurement {
+	/// In the pixels of the image, at most two. The next click starts over once there are two.
+	points: Vec<Vector2<f32>>,
+}
+
+/// The line that is drawn to straighten the image
+#[derive(Clone, Copy)]
+struct StraightenLine {
+	/// `None` until the left button is pressed
+	start: Option<LogicalVector>,
+	end: LogicalVector,
+}
+
+impl StraightenLine {
+	/// Returns the angle that the image has to be rotated by clockwise for the line to become
+	/// horizontal or vertical, whichever is closer
+	fn level_angle(&self) -> Option<f32> {
+		let delta = (self.end - self.start?).vec;
+		if delta.magnitude() < CLICK_MAX_DRAG {
+			return None;
+		}
+		let angle = delta.y.atan2(delta.x).to_degrees();
+		Some(-(angle - (angle / 90.0).round() * 90.0))
+	}
+}
+
+/// The settings of the magnifier, which shows the image around the cursor at a fixed zoom
+#[derive(Clone, Copy)]
+struct Magnifier {
+	/// The size of an image texel in physical display pixels inside the magnifier
+	texel_size: f32,
+	/// The width and height in logical pixels
+	size: f32,
+	shape: MagnifierShape,
+	toggle: bool,
+}
+
+/// The area around `center` that is drawn magnified by `zoom`. The center is relative to the
+/// widget, in logical pixels.
+#[derive(Clone, Copy)]
+struct Lens {
+	center: LogicalVector,
+	size: f32,
+	zoom: f32,
+	circle: bool,
+}
+
+impl Lens {
+	/// The square that the lens covers, relative to the window
+	fn bounds(&self, widget_bounds: &LogicalRect) -> LogicalRect {
+		let half = self.size * 0.5;
+		let corner = widget_bounds.pos.vec + self.center.vec - Vector2::new(half, half);
+		LogicalRect {
+			pos: LogicalVector { vec: corner },
+			size: LogicalVector::new(self.size, self.size),
+		}
+	}
+}
+
+/// How the current image is drawn over the reference image
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum ReferenceBlend {
+	Opacity,
+	/// Shows how much each pixel differs from the reference
+	Difference,
+}
+
+/// The temporary pan mode, while the pan key is held
+struct HandTool {
+	/// The key that started it, and the modifiers at the time
+	key: String,
+	modifiers: ModifiersState,
+	/// Whether the image was dragged while the key was held. If it wasn't, releasing the key
+	/// carries out the other actions bound to it.
+	used: bool,
+}
+
+/// Which channels of the image are shown, see `cycle_channel_view`. The values are the ones of
+/// `channel_view` in the fragment shader.
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum ChannelView {
+	Rgb = 0,
+	Red = 1,
+	Green = 2,
+	Blue = 3,
+	Alpha = 4,
+	Luminance = 5,
+}
+
+impl ChannelView {
+	fn name(self) -> &'static str {
+		match self {
+			ChannelView::Rgb => "RGB",
+			ChannelView::Red => "Red",
+			ChannelView::Green => "Green",
+			ChannelView::Blue => "Blue",
+			ChannelView::Alpha => "Alpha",
+			ChannelView::Luminance => "Luminance",
+		}
+	}
+}
+
+/// The cursors of the modes of the picture, see `mode_cursors`
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum CursorMode {
+	Default,
+	/// While the pan key is held
+	Grab,
+	/// While the image is dragged
+	Grabbing,
+	/// While straightening
+	Crosshair,
+	/// While the magnifier is shown
+	Loupe,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum GroupJump {
+	Next,
+	Prev,
+}
+
+/// Returns the index of the first image in the consecutive run of images that includes `index`
+/// and were taken on the same day.
+fn group_start(dates: &[Option<CaptureDate>], index: usize) -> usize {
+	let date = dates[index];
+	dates[..index].iter().rposition(|d| *d != date).map_or(0, |i| i + 1)
+}
+
+/// Returns the index of the first image of the group to jump to, and the position of that group
+/// in the form of (group number, group count). Returns `None` when jumping past the last group.
+fn group_jump_target(
+	dates: &[Option<CaptureDate>],
+	curr_index: usize,
+	jump: GroupJump,
+) -> Option<(usize, usize, usize)> {
+	let curr_start = group_start(dates, curr_index);
+	let target = match jump {
+		GroupJump::Next => {
+			let date = dates[curr_index];
+			curr_index + dates[curr_index..].iter().position(|d| *d != date)?
+		}
+		GroupJump::Prev if curr_start == 0 => 0,
+		GroupJump::Prev => group_start(dates, curr_start - 1),
+	};
+	let group_number = 1 + dates[..=target].windows(2).filter(|w| w[0] != w[1]).count();
+	let group_count = 1 + dates.windows(2).filter(|w| w[0] != w[1]).count();
+	Some((target, group_number, group_count))
+}
+
 #[derive(PartialEq, Eq, Clone, Copy)]
 pub enum MovementDir {
 	None,
@@ -67,6 +394,94 @@ enum HoverState {
 	ItemHovered { prev_path: LoadedImgPath },
 }
 
+/// Returns the action to carry out for a mouse gesture, or `None` if it shouldn't do anything.
+fn click_action(configured: Option<&str>, default: &str, option_name: &str) -> Option<String> {
+	let action = match configured.unwrap_or(default) {
+		action if action == "none" || is_action_name(action) => action,
+		action => {
+			eprintln!("Illegal configuration value {:?} for {}!", action, option_name);
+			eprintln!(r#"Allowed values are the names of the actions and "none"."#);
+			default
+		}
+	};
+	if action == "none" {
+		None
+	} else {
+		Some(action.to_owned())
+	}
+}
+
+/// The zones of the `tap_zones` setting, empty if it's turned off
+fn tap_zones(config: &ConfigMouseSection) -> Vec<TapZone> {
+	if !config.tap_zones.unwrap_or(false) {
+		return Vec::new();
+	}
+	let Some(zones) = config.tap_zone.clone() else {
+		return vec![
+			TapZone::new(0.0, 0.0, 1.0 / 3.0, 1.0, IMG_PREV_NAME),
+			TapZone::new(2.0 / 3.0, 0.0, 1.0, 1.0, IMG_NEXT_NAME),
+			TapZone::new(1.0 / 3.0, 0.0, 2.0 / 3.0, 1.0, TOGGLE_TOOLBAR_NAME),
+		];
+	};
+	zones
+		.into_iter()
+		.filter(|zone| {
+			if !is_action_name(&zone.action) {
+				eprintln!("Illegal configuration value {:?} for tap_zone action!", zone.action);
+				eprintln!("Allowed values are the names of the actions.");
+				return false;
+			}
+			let edges = [zone.left, zone.top, zone.right, zone.bottom];
+			if !edges.iter().all(|v| (0.0..=1.0).contains(v))
+				|| zone.left >= zone.right
+				|| zone.top >= zone.bottom
+			{
+				eprintln!("Illegal configuration value {:?} for tap_zone!", zone);
+				eprintln!(
+					"The edges have to be between 0 and 1, with left < right and top < bottom."
+				);
+				return false;
+			}
+			true
+		})
+		.collect()
+}
+
+/// The image of the cursor that is shown over the magnifier, and its hotspot. `path` is the
+/// `loupe_cursor` setting.
+fn loupe_cursor_image(path: Option<&str>) -> (RgbaImage, (u32, u32)) {
+	let custom = path.and_then(|path| match gelatin::image::open(path) {
+		Ok(image) => Some(image.thumbnail(MAX_CURSOR_SIZE, MAX_CURSOR_SIZE).into_rgba8()),
+		Err(e) => {
+			eprintln!("Could not load the loupe_cursor {:?}: {}", path, e);
+			None
+		}
+	});
+	if let Some(image) = custom {
+		let hotspot = (image.width() / 2, image.height() / 2);
+		return (image, hotspot);
+	}
+	// A magnifying glass with a dark outline, pointing with the center of the lens
+	let (center, radius) = (Vector2::new(12.0f32, 12.0), 8.0);
+	let (handle_start, handle_end) = (Vector2::new(18.0f32, 18.0), Vector2::new(29.0, 29.0));
+	let image = RgbaImage::from_fn(32, 32, |x, y| {
+		let p = Vector2::new(x as f32 + 0.5, y as f32 + 0.5);
+		let ring = ((p - center).magnitude() - radius).abs();
+		let along = (p - handle_start).dot(handle_end - handle_start)
+			/ (handle_end - handle_start).magnitude2();
+		let handle = (p - handle_start.lerp(handle_end, along.clamp(0.0, 1.0))).magnitude() - 0.75;
+		let dist = ring.min(handle);
+		if dist < 1.25 {
+			Rgba([255, 255, 255, 255])
+		} else if dist < 2.25 {
+			Rgba([0, 0, 0, 255])
+		} else {
+			Rgba([0, 0, 0, 0])
+		}
+	});
+	(image, (12, 12))
+}
+
 fn orientation_to_matrix(orientation: Orientation) -> Matrix4<f32> {
 	#[rustfmt::skip]
 	let result = match orientation {
@@ -117,6 +532,15 @@ fn orientation_to_matrix(orientation: Orientation) -> Matrix4<f32> {
 	result
 }
 
+/// The view settings that can be saved for a folder, see `FolderViewSettings`
+#[derive(Clone, Copy)]
+struct ViewSettings {
+	scaling: FolderScaling,
+	antialiasing: Antialias,
+	sort_order: SortOrder,
+	fit: Option<StartupFit>,
+}
+
 struct PictureWidgetData {
 	placement: WidgetPlacement,
 	drawn_bounds: LogicalRect,
@@ -133,26 +557,110 @@ struct PictureWidgetData {
 	// It's an option to allow manual destruction.
 	clipboard_handler: Option<ClipboardHandler>,
 	clipboard_request_was_pending: bool,
+	copy_view_area: CopyViewArea,
+	/// The view is copied to the clipboard in the next draw
+	copy_view_requested: bool,
+	command_runner: CommandRunner,
 
 	program: Program,
+	difference_program: Program,
 	bright_shade: f32,
 	/// Size of an image texel in physical display pixels
 	img_texel_size: f32,
+	/// The scale factor of the last draw
+	dpi_scale: f32,
 	scaling: ScalingMode,
 	img_pos: LogicalVector,
 	antialiasing: Antialias,
+	/// The folder of the currently shown image
+	curr_folder: Option<PathBuf>,
+	/// The global view settings from before entering a folder that has its own settings.
+	/// This is `None` when no folder settings are in effect.
+	global_view_settings: Option<ViewSettings>,
+	/// The fit of the folder settings in effect, or the global one
+	startup_fit: Option<StartupFit>,
+	sort_order: SortOrder,
+	no_upscale_on_fit: bool,
+	small_image_policy: SmallImagePolicy,
+	small_image_threshold: u32,
+	continuous_fit: bool,
+	/// When the fitted image is fit to the new size of the picture, while it's being resized and
+	/// `continuous_fit` is off
+	fit_settle_time: Option<Instant>,
+	/// The image that `startup_fit` was last applied to
+	startup_fit_path: Option<PathBuf>,
 
 	hor_pan_input: MovementDir,
 	ver_pan_input: MovementDir,
 	zoom_input: MovementDir,
-	/// The velocity of horizontal panning
-	hor_pan_vel: f32,
-	/// The velocity of vertical panning
-	ver_pan_vel: f32,
+	/// The velocity of panning with the keyboard in logical pixels per second
+	pan_vel: Vector2<f32>,
+	pan_key_speed: f32,
+	pan_key_accel: f32,
+	edge_autopan: bool,
+	edge_margin: f32,
+	edge_scroll_navigate: bool,
+	/// How many steps of the wheel went past the edge so far, negative past the bottom edge
+	edge_overscroll: f32,
+	edge_overscroll_time: Instant,
+	edge_scroll_entry: Option<EdgeScrollEntry>,
 	/// The velocity of zooming
 	zoom_vel: f32,
+	/// How long the zoom keys were held in the current direction, in seconds
+	zoom_hold_time: f32,
+	/// In seconds
+	zoom_key_accel: f32,
+	/// In natural logarithm of the zoom per second, like `zoom_vel`
+	zoom_key_max_rate: f32,
+	zoom_snap: bool,
+	zoom_snap_tolerance: f32,
+	/// In percent of the original size
+	zoom_snap_targets: Vec<f32>,
+	/// The texel size that the zoom last snapped to, while the zoom is within its tolerance
+	zoom_snapped_to: Option<f32>,
+	letterbox_color: LetterboxColor,
+	fit_padding: Option<FitPadding>,
+	/// The rotation of the view in degrees clockwise, on top of the orientation of the image
+	rotation: f32,
+	/// The image that `rotation` belongs to, the rotations of the others are in `view_edits`
+	rotation_path: Option<PathBuf>,
+	rotation_step: f32,
+	rotation_fill: RotationFill,
+	straighten: Option<StraightenLine>,
+	magnifier: Magnifier,
+	magnifier_shown: bool,
+	composition_overlay: CompositionOverlay,
+	/// The number of cells of the grid overlay in each direction
+	composition_grid_cells: u32,
+	/// The linear color of the composition guides
+	overlay_color: [f32; 4],
+	/// Whether `fit_padding` is applied, it can be turned off for a while with a key
+	fit_padding_enabled: bool,
+	confirm_quit: ConfirmQuit,
+	/// When quitting was first requested, while waiting for it to be confirmed
+	quit_requested_time: Option<Instant>,
 
 	last_click_time: Instant,
+	/// Where the cursor was when a mouse button was last pressed
+	click_start_pos: LogicalVector,
+	pan_button: MouseButton,
+	/// See `precision_modifier`
+	precision_modifier: PrecisionModifier,
+	precision_zoom_factor: f32,
+	precision_pan_factor: f32,
+	hand_tool: Option<HandTool>,
+	mode_cursors: bool,
+	/// The cursor that was shown last
+	cursor_mode: CursorMode,
+	double_click_time: Duration,
+	double_click_action: Option<String>,
+	middle_click_action: Option<String>,
+	tap_zones: Vec<TapZone>,
+	unsupported_file_action: UnsupportedFileAction,
+	auto_advance_on: Vec<AutoAdvanceTrigger>,
+	auto_advance_direction: AutoAdvanceDirection,
+	/// The image that is being copied to the clipboard, for auto-advancing once it's done
+	copy_advance_path: Option<PathBuf>,
 	last_mouse_pos: LogicalVector,
 	panning_2d: bool,
 	panning_vert: bool,
@@ -163,8 +671,109 @@ struct PictureWidgetData {
 	last_cam_move_time: Instant,
 	next_update: NextUpdate,
 	bottom_bar: Rc<BottomBar>,
+	status_bar: Rc<StatusBar>,
 	left_to_pan_hint: Rc<HelpScreen>,
 	copy_notifications: CopyNotifications,
+	notification: TextNotification,
+	pending_group_jump: Option<GroupJump>,
+	fast_nav: FastNavigation,
+	/// Set by `next_duplicate` even if `find_duplicates` is disabled in the configuration
+	find_duplicates: bool,
+	pending_duplicate_jump: bool,
+	/// Tells how many images look like the current one
+	duplicate_badge: Badge,
+	eager_thumbnail: bool,
+	/// The size that images are shrunk to in the low memory mode, the longer side of the monitor
+	low_memory_max_side: u32,
+	/// The image that was requested at its full size in the low memory mode, until it's shown
+	full_resolution_path: Option<PathBuf>,
+	next_memory_check: Instant,
+	/// Tells how many thumbnails of the folder are done
+	thumbnail_badge: Badge,
+	on_current_deleted: CurrentDeleted,
+	/// The shown file and its modification time when it was last checked
+	checked_file: Option<(PathBuf, Option<SystemTime>)>,
+	next_file_check: Instant,
+	/// See `idle_action` of the configuration
+	idle_action: IdleAction,
+	idle_timeout: Option<Duration>,
+	last_input: Instant,
+	/// The idle action was taken since the last input, it's only taken once
+	idle_action_taken: bool,
+	/// The shown image and its motion clip, if it's a motion photo
+	motion_clip: Option<(PathBuf, Option<MotionClip>)>,
+	/// The shown file, if it no longer exists
+	missing_file: Option<PathBuf>,
+	/// Tells that the shown file no longer exists or that it's truncated
+	warning_badge: Badge,
+	/// Tells why the shown file couldn't be opened
+	error_card: Badge,
+	/// Tells that the shown image has view edits
+	edited_badge: Badge,
+	/// The label of the scale bar
+	scale_badge: Badge,
+	scale_bar: bool,
+	/// The length of the scale bar in logical pixels, while it's shown
+	scale_bar_length: Option<f32>,
+	/// The unit of the scale bar and of `measure`
+	measure_unit: MeasureUnit,
+	/// The image that the resolution was read from, and its pixels per millimeter
+	resolution: Option<(PathBuf, Option<f64>)>,
+	/// Whether the status bar shows the pixel under the cursor
+	show_coordinates: bool,
+	/// While the `measure` tool is used
+	measure: Option<Measurement>,
+	hover_preview: HoverPreview,
+	watermark: Watermark,
+	deleted_items: DeletedItemsPanel,
+	empty_state: EmptyState,
+	frame_scrubber: FrameScrubber,
+	/// True while the animation is paused because the frame scrubber is dragged
+	scrubbing: bool,
+	/// Drawn onto the images of `copy_view` if `watermark_on_export` is set
+	export_watermark: Option<WatermarkStyle>,
+	edge_flash: Option<EdgeFlash>,
+
+	/// The image shown before the current one, for comparing the two with `ab_flicker`
+	previous_image: Option<(PathBuf, AnimationFrameTexture)>,
+	current_image: Option<(PathBuf, AnimationFrameTexture)>,
+	show_previous: bool,
+	/// The image that the current one is drawn over, to check whether the two line up
+	reference_image: Option<(PathBuf, AnimationFrameTexture)>,
+	reference_blend: ReferenceBlend,
+	/// The opacity of the current image over the reference
+	reference_opacity: f32,
+	clipping_warning: bool,
+	/// The linear highlight and shadow thresholds of the clipping warning
+	clip_thresholds: (f32, f32),
+	focus_peaking: bool,
+	/// Between 0 and 1
+	peaking_sensitivity: f32,
+	/// Linear
+	peaking_color: [f32; 3],
+	channel_view: ChannelView,
+	two_page: bool,
+	reading_direction: ReadingDirection,
+	cover_is_single: bool,
+	/// The image after the current one, which is shown beside it in two page mode
+	second_page: Option<AnimationFrameTexture>,
+	undo_stack: UndoStack,
+	view_edits: ViewEdits,
+	/// The folders of `triage_a` and `triage_b`, relative to the folder of the image if they
+	/// aren't absolute
+	triage_folders: [Option<PathBuf>; 2],
+	/// See `PictureWidget::set_on_window_request`
+	on_window_request: Option<Box<dyn Fn(WindowRequest)>>,
+	/// The image that the embedded preview of the last draw belongs to
+	preview_path: Option<PathBuf>,
+	/// The image that was last requested by `apply_mirror_state`
+	mirrored_path: Option<PathBuf>,
+	/// See `PictureWidget::set_linked`
+	linked: bool,
+	/// Set from `read_only`, refuses the actions that change the files
+	read_only: bool,
+	/// Set from `on_open_file`
+	open_file: OpenFile,
 	window: Weak<Window>,
 }
 impl WidgetData for PictureWidgetData {
@@ -179,32 +788,115 @@ impl WidgetData for PictureWidgetData {
 	}
 }
 impl PictureWidgetData {
-	fn fit_image_to_panel(&mut self, dpi_scale: f32, stretch: bool) {
+	/// The cursor of the mode that the picture is in
+	fn cursor_mode(&self) -> CursorMode {
+		if !self.mode_cursors {
+			CursorMode::Default
+		} else if self.panning_2d {
+			CursorMode::Grabbing
+		} else if self.hand_tool.is_some() {
+			CursorMode::Grab
+		} else if !self.hover {
+			CursorMode::Default
+		} else if self.straighten.is_some() || self.measure.is_some() {
+			CursorMode::Crosshair
+		} else if self.magnifier_shown {
+			CursorMode::Loupe
+		} else {
+			CursorMode::Default
+		}
+	}
+
+	/// Shows the cursor of the current mode if it changed. This is called after every event, so
+	/// that the default cursor comes back once a mode ends.
+	fn update_cursor(&mut self) {
+		let mode = self.cursor_mode();
+		if mode == self.cursor_mode {
+			return;
+		}
+		let Some(window) = self.window.upgrade() else {
+			return;
+		};
+		let icon = match mode {
+			CursorMode::Default => CursorIcon::Default,
+			CursorMode::Grab => CursorIcon::Grab,
+			CursorMode::Grabbing => CursorIcon::Grabbing,
+			CursorMode::Crosshair => CursorIcon::Crosshair,
+			CursorMode::Loupe => {
+				if window.set_custom_cursor() {
+					self.cursor_mode = mode;
+					return;
+				}
+				// The custom cursor isn't made yet
+				CursorIcon::ZoomIn
+			}
+		};
+		window.set_cursor_icon(icon);
+		self.cursor_mode = mode;
+	}
+
+	/// Returns the margin that is kept on each side of a fitted image
+	fn fit_padding(&self) -> LogicalVector {
 		let size = self.drawn_bounds.size.vec;
-		if let Some(texture) = self.get_texture() {
-			let panel_aspect = size.x / size.y;
-			let (img_phys_w, img_pyhs_h) = {
-				let (w, h) = texture.oriented_dimensions();
-				(w as f32, h as f32)
-			};
-			let img_aspect = img_phys_w / img_pyhs_h;
+		let padding = match self.fit_padding.filter(|_| self.fit_padding_enabled) {
+			None => Vector2::zero(),
+			Some(FitPadding::Pixels(pixels)) => Vector2::new(pixels, pixels),
+			Some(FitPadding::Percent(percent)) => size * (percent / 100.0),
+		};
+		// Leave at least a pixel for the image
+		let max = ((size - Vector2::new(1.0, 1.0)) * 0.5).map(|max| max.max(0.0));
+		LogicalVector::new(padding.x.min(max.x), padding.y.min(max.y))
+	}
 
-			let texel_size_to_fit_width = size.x / img_phys_w;
-			let img_texel_size = if img_aspect > panel_aspect {
-				// The image is relatively wider than the panel
-				texel_size_to_fit_width
-			} else {
-				texel_size_to_fit_width * (img_aspect / panel_aspect)
-			};
-			let widget_phys_size = size * dpi_scale;
+	/// The size of the area that a fitted image is fitted into
+	fn fit_area(&self) -> Vector2<f32> {
+		self.drawn_bounds.size.vec - self.fit_padding().vec * 2.0
+	}
+
+	fn toggle_fit_padding(&mut self) {
+		if self.fit_padding.is_none() {
+			self.notification.show("There is no fit_padding configured", NOTIFICATION_DURATION);
+			return;
+		}
+		self.fit_padding_enabled = !self.fit_padding_enabled;
+		let text = if self.fit_padding_enabled { "Fit padding on" } else { "Fit padding off" };
+		self.notification.show(text, NOTIFICATION_DURATION);
+		self.render_validity.invalidate();
+	}
+
+	/// Returns the texel size at which the image exactly fills the panel in one dimension
+	fn texel_size_to_fit(&self, (w, h): (u32, u32), dpi_scale: f32) -> f32 {
+		let size = self.fit_area();
+		let panel_aspect = size.x / size.y;
+		let (img_phys_w, img_pyhs_h) = (w as f32, h as f32);
+		let img_aspect = img_phys_w / img_pyhs_h;
+
+		let texel_size_to_fit_width = size.x / img_phys_w;
+		let img_texel_size = if img_aspect > panel_aspect {
+			// The image is relatively wider than the panel
+			texel_size_to_fit_width
+		} else {
+			texel_size_to_fit_width * (img_aspect / panel_aspect)
+		};
+		img_texel_size * dpi_scale
+	}
+
+	fn fit_image_to_panel(&mut self, dpi_scale: f32, stretch: bool) {
+		let size = self.drawn_bounds.size.vec;
+		if let Some(dimensions) = self.rotated_dimensions() {
+			let (img_phys_w, img_pyhs_h) = (dimensions.0 as f32, dimensions.1 as f32);
+			let widget_phys_size = self.fit_area() * dpi_scale;
 			let fits_in_widget =
 				widget_phys_size.x >= img_phys_w && widget_phys_size.y >= img_pyhs_h;
 			self.img_pos = LogicalVector::new(size.x * 0.5, size.y * 0.5);
-			if fits_in_widget && !stretch {
-				self.img_texel_size = 1.0;
-			} else {
-				self.img_texel_size = img_texel_size * dpi_scale;
-			}
+			let fit = self.texel_size_to_fit(dimensions, dpi_scale);
+			self.img_texel_size = match self.small_image_policy() {
+				Some(SmallImagePolicy::ShowActual) => fit.min(1.0),
+				Some(SmallImagePolicy::FitWithLimit(max_scale)) => fit.min(max_scale),
+				Some(SmallImagePolicy::FitNearest) => fit,
+				None if fits_in_widget && !stretch => 1.0,
+				None => fit,
+			};
 			if stretch {
 				self.scaling = ScalingMode::FitStretch;
 			} else {
@@ -213,14 +905,36 @@ impl PictureWidgetData {
 		}
 	}
 
+	/// The `small_image_policy` if the shown image is small enough for it
+	fn small_image_policy(&self) -> Option<SmallImagePolicy> {
+		let (w, h) = self.rotated_dimensions()?;
+		let threshold = self.small_image_threshold;
+		(w <= threshold && h <= threshold).then_some(self.small_image_policy)
+	}
+
+	/// Whether the automatic antialiasing draws the image with nearest neighbor sampling at
+	/// `texel_size`
+	fn auto_aa_nearest(&self, texel_size: f32) -> bool {
+		let sharp_pixels = self.small_image_policy() == Some(SmallImagePolicy::FitNearest);
+		texel_size >= AA_TEXEL_SIZE_THRESHOLD || (sharp_pixels && texel_size > 1.0)
+	}
+
+	/// Whether the `precision_modifier` is held, which slows zooming and panning down
+	fn precision_held(&self, modifiers: ModifiersState) -> bool {
+		match self.precision_modifier {
+			PrecisionModifier::Alt => modifiers.alt_key(),
+			PrecisionModifier::Ctrl => modifiers.control_key(),
+			PrecisionModifier::Shift => modifiers.shift_key(),
+			PrecisionModifier::Logo => modifiers.super_key(),
+			PrecisionModifier::None => false,
+		}
+	}
+
 	fn zoom_image(&mut self, anchor: LogicalVector, mut delta: f32) {
 		delta = if delta > 0.0 { delta + 1.0 } else { 1.0 / (delta.abs() + 1.0) };
-		let mut image_texel_size = (self.img_texel_size * delta).max(0.0);
-		if (image_texel_size - 1.0).abs() < 0.01 {
-			image_texel_size = 1.0;
-		} else {
-			image_texel_size = image_texel_size.clamp(MIN_ZOOM_FACTOR, MAX_ZOOM_FACTOR)
-		}
+		let image_texel_size =
+			(self.img_texel_size * delta).clamp(MIN_ZOOM_FACTOR, MAX_ZOOM_FACTOR);
+		let image_texel_size = self.snap_zoom(image_texel_size);
 		self.img_pos = (image_texel_size / self.img_texel_size) * (self.img_pos - anchor) + anchor;
 		self.img_texel_size = image_texel_size;
 		self.scaling = ScalingMode::Fixed;
@@ -228,13 +942,122 @@ impl PictureWidgetData {
 		self.render_validity.invalidate();
 	}
 
+	/// Returns the snap target if `texel_size` is within the tolerance of one. After snapping
+	/// to a target, zooming further moves away from it freely, and it only snaps again once
+	/// the zoom has left the tolerance range of the target.
+	fn snap_zoom(&mut self, texel_size: f32) -> f32 {
+		if !self.zoom_snap {
+			return texel_size;
+		}
+		let tolerance = self.zoom_snap_tolerance / 100.0;
+		let in_range = |target: f32| (texel_size / target - 1.0).abs() <= tolerance;
+		if let Some(snapped_to) = self.zoom_snapped_to {
+			if in_range(snapped_to) {
+				return texel_size;
+			}
+			self.zoom_snapped_to = None;
+		}
+		let fit =
+			self.rotated_dimensions().map(|dims| self.texel_size_to_fit(dims, self.dpi_scale));
+		let percent_targets = self.zoom_snap_targets.iter().map(|percent| {
+			let target = percent / 100.0;
+			(target, format!("{}%", percent))
+		});
+		let targets = percent_targets.chain(fit.map(|fit| (fit, "Fit".to_string())));
+		let closest = targets.filter(|(target, _)| in_range(*target)).min_by(|(a, _), (b, _)| {
+			let a = (texel_size / a - 1.0).abs();
+			let b = (texel_size / b - 1.0).abs();
+			a.total_cmp(&b)
+		});
+		match closest {
+			Some((target, name)) => {
+				self.zoom_snapped_to = Some(target);
+				self.notification.show(&name, ZOOM_SNAP_NOTIFICATION_DURATION);
+				target
+			}
+			None => texel_size,
+		}
+	}
+
+	/// Sets up the view configured by `startup_fit` when a new image is shown
+	fn apply_startup_fit(&mut self, dpi_scale: f32) {
+		let Some(fit) = self.startup_fit else {
+			return;
+		};
+		let LoadedImgPath::Loaded(path) = self.playback_manager.shown_file_path() else {
+			return;
+		};
+		if self.startup_fit_path.as_ref() == Some(path) {
+			return;
+		}
+		let Some((img_w, img_h)) = self.rotated_dimensions() else {
+			return;
+		};
+		self.startup_fit_path = Some(path.clone());
+
+		let size = self.drawn_bounds.size.vec;
+		let padding = self.fit_padding().vec;
+		let phys_size = self.fit_area() * dpi_scale;
+		let (img_phys_w, img_phys_h) = (img_w as f32, img_h as f32);
+		let no_upscale = self.no_upscale_on_fit;
+		let limit_upscale =
+			|texel_size: f32| if no_upscale { texel_size.min(1.0) } else { texel_size };
+		self.img_pos = LogicalVector::new(size.x * 0.5, size.y * 0.5);
+		match fit {
+			StartupFit::Fit => {
+				let stretch = !no_upscale;
+				self.scaling = if stretch { ScalingMode::FitStretch } else { ScalingMode::FitMin };
+			}
+			StartupFit::FitWidth => {
+				self.img_texel_size = limit_upscale(phys_size.x / img_phys_w);
+				self.scaling = ScalingMode::Fixed;
+				let img_h = img_phys_h * self.img_texel_size / dpi_scale;
+				self.img_pos.vec.y = self.img_pos.vec.y.max(img_h * 0.5 + padding.y);
+			}
+			StartupFit::FitHeight => {
+				self.img_texel_size = limit_upscale(phys_size.y / img_phys_h);
+				self.scaling = ScalingMode::Fixed;
+				let img_w = img_phys_w * self.img_texel_size / dpi_scale;
+				self.img_pos.vec.x = self.img_pos.vec.x.max(img_w * 0.5 + padding.x);
+			}
+			StartupFit::OneToOne => {
+				self.img_texel_size = 1.0;
+				self.scaling = ScalingMode::Fixed;
+			}
+			StartupFit::Percent(percent) => {
+				self.img_texel_size = (percent / 100.0).clamp(MIN_ZOOM_FACTOR, MAX_ZOOM_FACTOR);
+				self.scaling = ScalingMode::Fixed;
+			}
+		}
+		self.update_scaling_buttons();
+	}
+
 	fn update_image_transform(&mut self, dpi_scale: f32) {
+		self.dpi_scale = dpi_scale;
+		let shown_path = match self.playback_manager.shown_file_path() {
+			LoadedImgPath::Loaded(path) => Some(path),
+			_ => None,
+		};
+		if shown_path != self.rotation_path.as_ref() {
+			let edit = shown_path.map(|path| self.view_edits.get(path)).unwrap_or_default();
+			self.rotation = edit.rotation;
+			self.rotation_path = shown_path.cloned();
+		}
+		self.apply_startup_fit(dpi_scale);
+		self.apply_edge_scroll_entry(dpi_scale);
+		if self.drawn_bounds.size.vec != self.prev_draw_size.vec && !self.continuous_fit {
+			self.fit_settle_time = Some(Instant::now() + FIT_SETTLE_DURATION);
+		}
 		match self.scaling {
 			ScalingMode::Fixed => {
 				let center_offset = (self.drawn_bounds.size - self.prev_draw_size) * 0.5f32;
 				self.img_pos += center_offset;
 				self.apply_img_bounds(dpi_scale);
 			}
+			ScalingMode::FitStretch | ScalingMode::FitMin if self.fit_settle_time.is_some() => {
+				// Keeps the size from before the resize, see `update_fit_settle`
+				self.img_pos = self.drawn_bounds.size * 0.5;
+			}
 			ScalingMode::FitStretch => {
 				self.fit_image_to_panel(dpi_scale, true);
 			}
@@ -245,46 +1068,39 @@ impl PictureWidgetData {
 		self.prev_draw_size = self.drawn_bounds.size;
 	}
 
-	fn apply_camera_movement(&mut self, dpi_scale: f32) {
-		fn animate_value(v: &mut f32, dir: f32, dt: f32, next_update: &mut NextUpdate) {
-			#[allow(clippy::float_cmp)]
-			if v.signum() != dir {
-				*v = 0.0;
-			}
-			*v += dir * dt * (2.0 / (v.abs() + 1.0));
-			*next_update = NextUpdate::Soonest;
-		}
+	/// The zoom rate of the zoom keys after they were held for `hold_time` seconds. It eases
+	/// from `ZOOM_KEY_START_RATE` to `zoom_key_max_rate` over `zoom_key_accel` seconds.
+	fn zoom_key_rate(&self, hold_time: f32) -> f32 {
+		let start = ZOOM_KEY_START_RATE * LN_2;
+		let t = if self.zoom_key_accel > 0.0 {
+			(hold_time / self.zoom_key_accel).min(1.0)
+		} else {
+			1.0
+		};
+		let eased = t * t * (3.0 - 2.0 * t);
+		start + (self.zoom_key_max_rate - start) * eased
+	}
 
+	fn apply_camera_movement(&mut self) {
 		let now = Instant::now();
 		let dt_sec = now.duration_since(self.last_cam_move_time).as_secs_f32();
 		self.last_cam_move_time = now;
 
-		match self.hor_pan_input {
-			MovementDir::None => self.hor_pan_vel = 0.0,
-			MovementDir::Positive => {
-				animate_value(&mut self.hor_pan_vel, 1.0, dt_sec, &mut self.next_update)
-			}
-			MovementDir::Negative => {
-				animate_value(&mut self.hor_pan_vel, -1.0, dt_sec, &mut self.next_update)
-			}
-		}
-		match self.ver_pan_input {
-			MovementDir::None => self.ver_pan_vel = 0.0,
-			MovementDir::Positive => {
-				animate_value(&mut self.ver_pan_vel, 1.0, dt_sec, &mut self.next_update)
-			}
-			MovementDir::Negative => {
-				animate_value(&mut self.ver_pan_vel, -1.0, dt_sec, &mut self.next_update)
-			}
+		// The speed follows how long the key is held instead of the key repeats of the system,
+		// which come at different rates on different systems
+		let zoom_dir = match self.zoom_input {
+			MovementDir::None => 0.0,
+			MovementDir::Positive => 1.0,
+			MovementDir::Negative => -1.0,
+		};
+		if zoom_dir == 0.0 || self.zoom_vel * zoom_dir < 0.0 {
+			self.zoom_hold_time = 0.0;
+		} else {
+			self.zoom_hold_time += dt_sec;
 		}
-		match self.zoom_input {
-			MovementDir::None => self.zoom_vel = 0.0,
-			MovementDir::Positive => {
-				animate_value(&mut self.zoom_vel, 1.0, dt_sec, &mut self.next_update)
-			}
-			MovementDir::Negative => {
-				animate_value(&mut self.zoom_vel, -1.0, dt_sec, &mut self.next_update)
-			}
+		self.zoom_vel = zoom_dir * self.zoom_key_rate(self.zoom_hold_time);
+		if zoom_dir != 0.0 {
+			self.next_update = NextUpdate::Soonest;
 		}
 
 		if self.zoom_input.moving() {
@@ -292,26 +1108,203 @@ impl PictureWidgetData {
 			let anchor = LogicalVector::new(bounds_size.x * 0.5, bounds_size.y * 0.5);
 			self.zoom_image(anchor, self.zoom_vel * dt_sec);
 		}
-		if self.hor_pan_input.moving() || self.ver_pan_input.moving() {
-			let panning_speed = 400.0 * dpi_scale;
-			let pos_delta = Vector2::new(self.hor_pan_vel, self.ver_pan_vel) * dt_sec;
+		self.apply_key_panning(dt_sec);
+		self.apply_edge_panning(dt_sec);
+	}
+
+	/// Moves the pan velocity towards the direction of the held pan keys, or towards zero if none
+	/// are held. Diagonal movement has the same speed as horizontal or vertical movement.
+	fn apply_key_panning(&mut self, dt_sec: f32) {
+		let dir_value = |dir: MovementDir| match dir {
+			MovementDir::None => 0.0,
+			MovementDir::Positive => 1.0,
+			MovementDir::Negative => -1.0,
+		};
+		let mut target_vel =
+			Vector2::new(dir_value(self.hor_pan_input), dir_value(self.ver_pan_input));
+		if target_vel != Vector2::zero() {
+			target_vel = target_vel.normalize() * self.pan_key_speed;
+		}
+		let vel_diff = target_vel - self.pan_vel;
+		let max_change = self.pan_key_accel * dt_sec;
+		if vel_diff.magnitude() <= max_change {
+			self.pan_vel = target_vel;
+		} else {
+			self.pan_vel += vel_diff.normalize() * max_change;
+		}
+		if self.pan_vel != Vector2::zero() {
+			self.scaling = ScalingMode::Fixed;
+			self.update_scaling_buttons();
+			self.img_pos.vec += self.pan_vel * dt_sec;
+			self.next_update = NextUpdate::Soonest;
+		}
+	}
+
+	/// The velocity of `edge_autopan` for the current cursor position. It's zero along the axes
+	/// that the image fits into the picture along, and towards the edges of the image that are
+	/// already in view.
+	fn edge_pan_vel(&self) -> Vector2<f32> {
+		let Some((w, h)) = self.rotated_dimensions().filter(|_| self.edge_autopan && self.hover)
+		else {
+			return Vector2::zero();
+		};
+		if self.panning_2d {
+			return Vector2::zero();
+		}
+		let img_size = Vector2::new(w as f32, h as f32) * (self.img_texel_size / self.dpi_scale);
+		let size = self.drawn_bounds.size.vec;
+		let cursor = self.last_mouse_pos.vec - self.drawn_bounds.pos.vec;
+		let axis_vel = |cursor: f32, size: f32, img_size: f32, img_pos: f32| {
+			if img_size <= size {
+				return 0.0;
+			}
+			let margin = self.edge_margin.min(size * 0.5);
+			// Near the start the image moves towards the end to show its start, and the other way
+			let proximity = if cursor < margin {
+				(margin - cursor) / margin
+			} else if cursor > size - margin {
+				(size - margin - cursor) / margin
+			} else {
+				0.0
+			};
+			let at_bound = if proximity > 0.0 {
+				img_pos >= img_size * 0.5
+			} else {
+				img_pos <= size - img_size * 0.5
+			};
+			if at_bound {
+				0.0
+			} else {
+				proximity.clamp(-1.0, 1.0) * self.pan_key_speed
+			}
+		};
+		Vector2::new(
+			axis_vel(cursor.x, size.x, img_size.x, self.img_pos.vec.x),
+			axis_vel(cursor.y, size.y, img_size.y, self.img_pos.vec.y),
+		)
+	}
+
+	fn apply_edge_panning(&mut self, dt_sec: f32) {
+		let vel = self.edge_pan_vel();
+		if vel == Vector2::zero() {
+			return;
+		}
+		let img_size = self.rotated_dimensions().map_or(Vector2::zero(), |(w, h)| {
+			Vector2::new(w as f32, h as f32) * (self.img_texel_size / self.dpi_scale)
+		});
+		let size = self.drawn_bounds.size.vec;
+		let stop = |pos: f32, delta: f32, size: f32, img_size: f32| {
+			if delta > 0.0 {
+				(pos + delta).min((img_size * 0.5).max(pos))
+			} else {
+				(pos + delta).max((size - img_size * 0.5).min(pos))
+			}
+		};
+		let delta = vel * dt_sec;
+		let pos = self.img_pos.vec;
+		self.img_pos.vec.x = stop(pos.x, delta.x, size.x, img_size.x);
+		self.img_pos.vec.y = stop(pos.y, delta.y, size.y, img_size.y);
+		if self.scaling != ScalingMode::Fixed {
 			self.scaling = ScalingMode::Fixed;
 			self.update_scaling_buttons();
-			self.img_pos.vec += panning_speed * pos_delta;
 		}
+		self.next_update = NextUpdate::Soonest;
+	}
+
+	/// Scrolls the image by `delta` steps of the wheel for `edge_scroll_navigate`, and goes to
+	/// the next or the previous image after scrolling past the edge for a while. Returns false
+	/// if the image isn't scrolled, because it fits into the picture vertically.
+	fn scroll_image(&mut self, delta: f32) -> bool {
+		let Some((_, h)) = self.rotated_dimensions().filter(|_| self.edge_scroll_navigate) else {
+			return false;
+		};
+		let img_h = h as f32 * (self.img_texel_size / self.dpi_scale);
+		let size = self.drawn_bounds.size.vec.y;
+		if img_h <= size || delta == 0.0 {
+			return false;
+		}
+		// Scrolling up shows more of the top of the image
+		let pos = self.img_pos.vec.y;
+		let at_edge = if delta > 0.0 { pos >= img_h * 0.5 } else { pos <= size - img_h * 0.5 };
+		if !at_edge {
+			self.edge_overscroll = 0.0;
+			self.edge_scroll_entry = None;
+			let target = pos + delta * SCROLL_LINE_HEIGHT;
+			self.img_pos.vec.y =
+				if delta > 0.0 { target.min(img_h * 0.5) } else { target.max(size - img_h * 0.5) };
+			if self.scaling != ScalingMode::Fixed {
+				self.scaling = ScalingMode::Fixed;
+				self.update_scaling_buttons();
+			}
+			self.render_validity.invalidate();
+			return true;
+		}
+		// Only the steps that start at the edge count, so that the scrolling that reaches the
+		// edge never goes on to the next image
+		let now = Instant::now();
+		let paused = now.duration_since(self.edge_overscroll_time) > EDGE_SCROLL_RESET;
+		if paused || self.edge_overscroll * delta < 0.0 {
+			self.edge_overscroll = 0.0;
+		}
+		self.edge_overscroll += delta;
+		self.edge_overscroll_time = now;
+		if self.edge_overscroll.abs() >= EDGE_SCROLL_THRESHOLD {
+			let forward = delta < 0.0;
+			self.edge_overscroll = 0.0;
+			let from = match self.playback_manager.shown_file_path() {
+				LoadedImgPath::Loaded(path) => Some(path.clone()),
+				_ => None,
+			};
+			self.edge_scroll_entry = Some(EdgeScrollEntry { from, top: forward });
+			self.step_pages(forward);
+			if let (true, Some(callback)) = (self.linked, &self.on_window_request) {
+				callback(WindowRequest::LinkedStep { forward });
+			}
+		}
+		true
+	}
+
+	/// Shows the edge that the image was entered from after `scroll_image` went to it
+	fn apply_edge_scroll_entry(&mut self, dpi_scale: f32) {
+		let LoadedImgPath::Loaded(path) = self.playback_manager.shown_file_path() else {
+			return;
+		};
+		let Some(entry) = self.edge_scroll_entry.as_ref() else {
+			return;
+		};
+		if entry.from.as_ref() == Some(path) {
+			return;
+		}
+		let Some((_, h)) = self.rotated_dimensions() else {
+			return;
+		};
+		let top = entry.top;
+		self.edge_scroll_entry = None;
+		let img_h = h as f32 * (self.img_texel_size / dpi_scale);
+		let size = self.drawn_bounds.size.vec.y;
+		if self.scaling == ScalingMode::Fixed && img_h > size {
+			self.img_pos.vec.y = if top { img_h * 0.5 } else { size - img_h * 0.5 };
+		}
+	}
+
+	fn camera_moving(&self) -> bool {
+		self.hor_pan_input.moving()
+			|| self.ver_pan_input.moving()
+			|| self.zoom_input.moving()
+			|| self.pan_vel != Vector2::zero()
+			|| self.edge_pan_vel() != Vector2::zero()
 	}
 
 	fn camera_movement_will_start(&mut self) {
 		// If there hasn't been any movement in a while, then reset the last update time
 		// to avoid large jumps at the beggining of a move when the delta would be large.
-		if !self.hor_pan_input.moving() && !self.ver_pan_input.moving() && !self.zoom_input.moving()
-		{
+		if !self.camera_moving() {
 			self.last_cam_move_time = Instant::now();
 		}
 	}
 
 	fn set_window_title_filename(
-		&self,
+		&mut self,
 		window: &Window,
 		playback_state: PlaybackState,
 		file_path: &LoadedImgPath,
@@ -322,6 +1315,10 @@ impl PictureWidgetData {
 			PlaybackState::RandomPresent => " : Presenting Shuffled",
 			PlaybackState::Paused => "",
 		};
+		let playback = match self.read_only {
+			true => format!("{} : Read-only", playback),
+			false => playback.to_owned(),
+		};
 
 		let config = self.configuration.borrow();
 		let title_config = config.title.clone().unwrap_or_default();
@@ -333,55 +1330,1460 @@ impl PictureWidgetData {
 			}
 			LoadedImgPath::Loaded(path) => title_config.format_file_path(path),
 		};
-		let title = format!("{}{}{}", name, playback, title_config.format_program_name());
-		window.set_title(title);
+		// Only the deeper images are marked, most are 8-bit
+		let bit_depth = match (file_path, self.playback_manager.image_texture()) {
+			(LoadedImgPath::Loaded(_), Some(texture))
+				if texture.bit_depth > 8 && self.fast_nav.target().is_none() =>
+			{
+				format!(" : {}", texture.bit_depth_text())
+			}
+			(LoadedImgPath::Loaded(_), Some(texture)) if texture.preview => {
+				" : Preview".to_string()
+			}
+			_ => String::new(),
+		};
+		let format = match &title_config.format {
+			Some(format) => format,
+			None => {
+				let program_name = title_config.format_program_name();
+				window.set_title(format!("{}{}{}{}", name, bit_depth, playback, program_name));
+				return;
+			}
+		};
+		let path = match file_path {
+			LoadedImgPath::Loaded(path) | LoadedImgPath::ErrLoading(path) => Some(path),
+			LoadedImgPath::NotYetLoaded => None,
+		};
+		let dimensions = self.shown_dimensions().filter(|_| path.is_some());
+		let file_size = self.status_bar.file_size_of(path.map(|p| p.as_path()));
+		// The preview marker takes the place of the bit depth in the default title
+		let mode = match bit_depth.as_str() {
+			" : Preview" => format!("{}{}", bit_depth, playback),
+			_ => playback,
+		};
+		let depth = match file_path {
+			LoadedImgPath::Loaded(_) => {
+				self.playback_manager.image_texture().map(|t| t.bit_depth_text())
+			}
+			_ => None,
+		};
+		let values = [
+			("{name}", Some(name.into_owned())),
+			("{path}", path.map(|p| p.to_string_lossy().into_owned())),
+			("{index}", self.playback_manager.current_file_index().map(|i| (i + 1).to_string())),
+			("{count}", self.playback_manager.current_dir_len().map(|len| len.to_string())),
+			("{zoom}", dimensions.map(|_| format!("{:.0}%", self.img_texel_size * 100.0))),
+			("{dimensions}", dimensions.map(|(w, h)| format!("{}x{}", w, h))),
+			("{filesize}", file_size.map(format_file_size)),
+			("{depth}", depth),
+			("{mode}", Some(mode)),
+			("{app}", Some("Emulsion".to_owned())),
+		];
+		window.set_title(format_title(format, &values));
 	}
 
 	fn get_texture(&self) -> Option<AnimationFrameTexture> {
 		self.playback_manager.image_texture()
 	}
 
-	pub fn set_img_size_to_orig(&mut self) {
-		self.img_texel_size = 1.0;
-		self.scaling = ScalingMode::Fixed;
-		self.update_scaling_buttons();
+	/// The dimensions of the shown image, or of the spread of the two pages in two page mode
+	fn set_rotation(&mut self, degrees: f32) {
+		// Kept between -180 and 180
+		let mut rotation = degrees.rem_euclid(360.0);
+		if rotation > 180.0 {
+			rotation -= 360.0;
+		}
+		self.rotation = rotation;
+		if let LoadedImgPath::Loaded(path) = self.playback_manager.shown_file_path() {
+			self.rotation_path = Some(path.clone());
+			self.view_edits.set(path, ViewEdit { rotation });
+		}
+		self.notification.show(&format!("Rotation {:.1}°", rotation), NOTIFICATION_DURATION);
 		self.render_validity.invalidate();
 	}
 
-	pub fn set_img_size_to_fit(&mut self, stretch: bool) {
-		{
-			let mut cache = self.cache.lock().unwrap();
-			cache.image.fit_stretches = stretch;
+	/// Splits the rotation into a number of quarter turns and the angle between -45 and 45
+	/// degrees that remains
+	fn split_rotation(&self) -> (i32, f32) {
+		let quarters = (self.rotation / 90.0).round();
+		(quarters as i32, self.rotation - quarters * 90.0)
+	}
+
+	/// The dimensions of the image as it's fitted into the window, with the rotation applied
+	fn rotated_dimensions(&self) -> Option<(u32, u32)> {
+		let (w, h) = self.shown_dimensions()?;
+		let (quarters, angle) = self.split_rotation();
+		let (w, h) = if quarters % 2 != 0 { (h, w) } else { (w, h) };
+		if self.rotation_fill == RotationFill::Crop || angle == 0.0 {
+			return Some((w, h));
 		}
-		self.scaling = if stretch { ScalingMode::FitStretch } else { ScalingMode::FitMin };
-		self.update_scaling_buttons();
-		self.render_validity.invalidate();
+		let (sin, cos) = angle.abs().to_radians().sin_cos();
+		let (w, h) = (w as f32, h as f32);
+		Some(((w * cos + h * sin).round() as u32, (w * sin + h * cos).round() as u32))
 	}
 
-	pub fn toggle_antialias(&mut self) {
-		let aa = match self.antialiasing {
-			Antialias::Auto if self.img_texel_size < AA_TEXEL_SIZE_THRESHOLD => Antialias::Never,
-			Antialias::Auto | Antialias::Never => Antialias::Always,
-			Antialias::Always => Antialias::Never,
+	/// Returns the transform that rotates the image around its center, and when the corners
+	/// are cropped, the area of the window that the image is cut to
+	fn rotation_transform(&self) -> (Matrix4<f32>, Option<LogicalRect>) {
+		if self.rotation == 0.0 {
+			return (Matrix4::identity(), None);
+		}
+		let center = self.img_pos.vec;
+		let (quarters, angle) = self.split_rotation();
+		let mut scale = 1.0;
+		let mut crop = None;
+		if let (RotationFill::Crop, Some((w, h))) = (self.rotation_fill, self.shown_dimensions()) {
+			let (w, h) = if quarters % 2 != 0 { (h, w) } else { (w, h) };
+			let (w, h) = (w as f32, h as f32);
+			let (sin, cos) = angle.abs().to_radians().sin_cos();
+			// Enlarged just enough for the rotated image to cover its unrotated frame
+			scale = cos + sin * (w / h).max(h / w);
+			let size = Vector2::new(w, h) * (self.img_texel_size / self.dpi_scale);
+			let bounds = self.drawn_bounds;
+			let frame_min = bounds.pos.vec + center - size * 0.5;
+			let frame_max = frame_min + size;
+			let left = frame_min.x.max(bounds.left());
+			let top = frame_min.y.max(bounds.top());
+			let right = frame_max.x.min(bounds.right());
+			let bottom = frame_max.y.min(bounds.bottom());
+			crop = Some(LogicalRect {
+				pos: LogicalVector::new(left, top),
+				size: LogicalVector::new((right - left).max(0.0), (bottom - top).max(0.0)),
+			});
+		}
+		let to_origin = Matrix4::from_translation(-center.extend(0.0));
+		let rotate = Matrix4::from_angle_z(Deg(self.rotation)) * Matrix4::from_scale(scale);
+		let back = Matrix4::from_translation(center.extend(0.0));
+		(back * rotate * to_origin, crop)
+	}
+
+	fn cycle_composition_overlay(&mut self) {
+		let (overlay, name) = match self.composition_overlay {
+			CompositionOverlay::None => (CompositionOverlay::Thirds, "Rule of thirds".to_string()),
+			CompositionOverlay::Thirds => (CompositionOverlay::GoldenRatio, "Golden ratio".into()),
+			CompositionOverlay::GoldenRatio => (CompositionOverlay::Diagonals, "Diagonals".into()),
+			CompositionOverlay::Diagonals => {
+				let cells = self.composition_grid_cells;
+				(CompositionOverlay::Grid(cells), format!("{0}×{0} grid", cells))
+			}
+			CompositionOverlay::Grid(_) => (CompositionOverlay::None, "No guides".into()),
+		};
+		self.composition_overlay = overlay;
+		self.notification.show(&name, NOTIFICATION_DURATION);
+		self.render_validity.invalidate();
+	}
+
+	/// The magnified area, while the magnifier is shown and the cursor is over the image
+	fn lens(&self) -> Option<Lens> {
+		if !self.magnifier_shown || !self.hover {
+			return None;
+		}
+		Some(Lens {
+			center: self.last_mouse_pos - self.drawn_bounds.pos,
+			size: self.magnifier.size,
+			zoom: self.magnifier.texel_size / self.img_texel_size,
+			circle: self.magnifier.shape == MagnifierShape::Circle,
+		})
+	}
+
+	fn toggle_straighten(&mut self) {
+		if self.straighten.take().is_some() {
+			self.notification.show("Straightening cancelled", NOTIFICATION_DURATION);
+		} else {
+			self.straighten = Some(StraightenLine { start: None, end: Default::default() });
+			let text = "Drag along a line that should be level";
+			self.notification.show(text, STRAIGHTEN_NOTIFICATION_DURATION);
+		}
+		self.render_validity.invalidate();
+	}
+
+	fn toggle_measure(&mut self) {
+		if self.measure.take().is_some() {
+			self.notification.show("Measuring stopped", NOTIFICATION_DURATION);
+		} else {
+			self.measure = Some(Measurement::default());
+			let text = "Click the two ends of what to measure";
+			self.notification.show(text, STRAIGHTEN_NOTIFICATION_DURATION);
+		}
+		self.render_validity.invalidate();
+	}
+
+	/// Places a point of `measure` at `cursor_pos`, and shows the distance once there are two
+	fn add_measure_point(&mut self, cursor_pos: LogicalVector) {
+		let (Some(point), Some(measure)) = (self.view_to_image(cursor_pos), &mut self.measure)
+		else {
+			return;
+		};
+		if measure.points.len() >= 2 {
+			measure.points.clear();
+		}
+		measure.points.push(point);
+		let text = match measure.points[..] {
+			[start, end] => {
+				let delta = end - start;
+				let length = self.describe_length(delta.magnitude() as f64);
+				format!("{}    Δx {:.0}  Δy {:.0}", length, delta.x.abs(), delta.y.abs())
+			}
+			_ => "Click the other end".to_string(),
+		};
+		self.notification.show(&text, STRAIGHTEN_NOTIFICATION_DURATION);
+		self.render_validity.invalidate();
+	}
+
+	/// Like "120.5 px", followed by the length in `measure_unit` if the image names its
+	/// resolution
+	fn describe_length(&mut self, pixels: f64) -> String {
+		let per_mm = self.measure_unit.per_mm();
+		match per_mm.zip(per_mm.and_then(|_| self.pixels_per_mm())) {
+			Some((per_mm, pixels_per_mm)) => {
+				let length = pixels / pixels_per_mm * per_mm;
+				format!("{:.1} px = {:.2} {}", pixels, length, self.measure_unit.symbol())
+			}
+			None => format!("{:.1} px", pixels),
+		}
+	}
+
+	/// The resolution of the shown image, which is read from the file once
+	fn pixels_per_mm(&mut self) -> Option<f64> {
+		let LoadedImgPath::Loaded(path) = self.playback_manager.shown_file_path() else {
+			return None;
+		};
+		match &self.resolution {
+			Some((read_path, resolution)) if read_path == path => *resolution,
+			_ => {
+				let resolution = pixels_per_mm(path);
+				self.resolution = Some((path.clone(), resolution));
+				resolution
+			}
+		}
+	}
+
+	/// The number of pixels of the file per texel of the shown image, which is more than one
+	/// when the image was shrunk while decoding
+	fn original_scale(&self) -> f32 {
+		let Some(texture) = self.get_texture() else {
+			return 1.0;
+		};
+		match texture.downscaled_from {
+			Some((w, h)) => w.max(h) as f32 / texture.w.max(texture.h) as f32,
+			None => 1.0,
+		}
+	}
+
+	/// Maps a point of the window to the pixels of the image, undoing the zoom, the panning and
+	/// the rotation. The point may be outside of the image.
+	fn view_to_image(&self, pos: LogicalVector) -> Option<Vector2<f32>> {
+		let (w, h) = self.shown_dimensions()?;
+		let (rotation, _) = self.rotation_transform();
+		let local = (pos.vec - self.drawn_bounds.pos.vec).extend(0.0).extend(1.0);
+		let local = (rotation.invert()? * local).truncate().truncate();
+		let size = Vector2::new(w as f32, h as f32) * (self.img_texel_size / self.dpi_scale);
+		let corner = self.img_pos.vec - size * 0.5;
+		let texels = (local - corner) * (self.dpi_scale / self.img_texel_size);
+		Some(texels * self.original_scale())
+	}
+
+	/// The inverse of `view_to_image`
+	fn image_to_view(&self, point: Vector2<f32>) -> Option<LogicalVector> {
+		let (w, h) = self.shown_dimensions()?;
+		let (rotation, _) = self.rotation_transform();
+		let size = Vector2::new(w as f32, h as f32) * (self.img_texel_size / self.dpi_scale);
+		let corner = self.img_pos.vec - size * 0.5;
+		let texels = point / self.original_scale();
+		let local = corner + texels * (self.img_texel_size / self.dpi_scale);
+		let local = (rotation * local.extend(0.0).extend(1.0)).truncate().truncate();
+		Some(LogicalVector { vec: self.drawn_bounds.pos.vec + local })
+	}
+
+	/// The pixel of the image under the cursor, if `show_coordinates` is on
+	fn cursor_pixel(&self) -> Option<(u32, u32)> {
+		if !self.show_coordinates || !self.hover {
+			return None;
+		}
+		let point = self.view_to_image(self.last_mouse_pos)?;
+		let (w, h) = self.shown_dimensions()?;
+		let scale = self.original_scale();
+		let (w, h) = (w as f32 * scale, h as f32 * scale);
+		let inside = point.x >= 0.0 && point.y >= 0.0 && point.x < w && point.y < h;
+		inside.then_some((point.x as u32, point.y as u32))
+	}
+
+	fn update_scale_bar(&mut self) {
+		let bar = if self.scale_bar { self.scale_bar_size() } else { None };
+		self.scale_bar_length = bar.as_ref().map(|(length, _)| *length);
+		self.scale_badge.set_text(bar.map(|(_, label)| label));
+	}
+
+	/// The length of the scale bar in logical pixels, and its label
+	fn scale_bar_size(&mut self) -> Option<(f32, String)> {
+		let center = self.drawn_bounds.pos + self.drawn_bounds.size * 0.5;
+		let start = self.view_to_image(center)?;
+		let end = self.view_to_image(center + LogicalVector::new(1.0, 0.0))?;
+		let per_mm = self.measure_unit.per_mm();
+		let (units_per_pixel, unit) = match per_mm.zip(per_mm.and_then(|_| self.pixels_per_mm())) {
+			Some((per_mm, pixels_per_mm)) => (per_mm / pixels_per_mm, self.measure_unit),
+			None => (1.0, MeasureUnit::Pixels),
+		};
+		// The length of a logical pixel of the window in the unit
+		let units_per_logical = (end - start).magnitude() as f64 * units_per_pixel;
+		if !units_per_logical.is_normal() {
+			return None;
+		}
+		let (length, decimals) = round_length(SCALE_BAR_MAX_LENGTH as f64 * units_per_logical);
+		let label = format!("{:.*} {}", decimals, length, unit.symbol());
+		Some(((length / units_per_logical) as f32, label))
+	}
+
+	fn shown_dimensions(&self) -> Option<(u32, u32)> {
+		let (w, h) = self.get_texture()?.oriented_dimensions();
+		match &self.second_page {
+			Some(second) => {
+				let (second_w, second_h) = second.oriented_dimensions();
+				Some((w + second_w, h.max(second_h)))
+			}
+			None => Some((w, h)),
+		}
+	}
+
+	pub fn set_img_size_to_orig(&mut self) {
+		self.img_texel_size = 1.0;
+		self.scaling = ScalingMode::Fixed;
+		self.update_scaling_buttons();
+		self.render_validity.invalidate();
+	}
+
+	pub fn set_img_size_to_fit(&mut self, stretch: bool) {
+		if self.global_view_settings.is_none() {
+			let mut cache = self.cache.lock().unwrap();
+			cache.image.fit_stretches = stretch;
+		}
+		self.scaling = if stretch { ScalingMode::FitStretch } else { ScalingMode::FitMin };
+		self.update_scaling_buttons();
+		self.render_validity.invalidate();
+	}
+
+	pub fn toggle_antialias(&mut self) {
+		let aa = match self.antialiasing {
+			Antialias::Auto if !self.auto_aa_nearest(self.img_texel_size) => Antialias::Never,
+			Antialias::Auto | Antialias::Never => Antialias::Always,
+			Antialias::Always => Antialias::Never,
 		};
 		self.antialiasing = aa;
-		self.cache.lock().unwrap().image.antialiasing = aa;
+		if self.global_view_settings.is_none() {
+			self.cache.lock().unwrap().image.antialiasing = aa;
+		}
 		self.render_validity.invalidate();
 	}
 
 	pub fn set_automatic_antialias(&mut self) {
 		self.antialiasing = Antialias::Auto;
-		self.cache.lock().unwrap().image.antialiasing = Antialias::Auto;
+		if self.global_view_settings.is_none() {
+			self.cache.lock().unwrap().image.antialiasing = Antialias::Auto;
+		}
+		self.render_validity.invalidate();
+	}
+
+	fn view_settings(&self) -> ViewSettings {
+		let scaling = match self.scaling {
+			ScalingMode::Fixed => FolderScaling::Orig,
+			ScalingMode::FitStretch => FolderScaling::Fit,
+			ScalingMode::FitMin => FolderScaling::FitBest,
+		};
+		ViewSettings {
+			scaling,
+			antialiasing: self.antialiasing,
+			sort_order: self.sort_order,
+			fit: self.startup_fit,
+		}
+	}
+
+	/// Goes back to the view that a new image starts with, centered in the window: the saved
+	/// settings of the folder, `startup_fit`, or fitting the image like the last time
+	fn reset_view(&mut self) {
+		let folder_scaling = self.curr_folder.as_ref().and_then(|folder| {
+			let mut cache = self.cache.lock().unwrap();
+			cache.folders.get(folder).map(|s| s.scaling)
+		});
+		match (folder_scaling, self.startup_fit) {
+			// This also applies the fit of the folder again before the next draw
+			(Some(scaling), _) => {
+				self.apply_view_settings(ViewSettings { scaling, ..self.view_settings() })
+			}
+			// It's applied again before the next draw
+			(None, Some(_)) => self.startup_fit_path = None,
+			(None, None) => {
+				let stretch = self.cache.lock().unwrap().image.fit_stretches;
+				self.scaling = if stretch { ScalingMode::FitStretch } else { ScalingMode::FitMin };
+				self.update_scaling_buttons();
+			}
+		}
+		let size = self.drawn_bounds.size.vec;
+		self.img_pos = LogicalVector::new(size.x * 0.5, size.y * 0.5);
+		self.zoom_snapped_to = None;
+		self.notification.show("View reset", NOTIFICATION_DURATION);
+		self.render_validity.invalidate();
+	}
+
+	/// Unlike `set_img_size_to_fit` and the others, this doesn't store anything in the cache
+	fn apply_view_settings(&mut self, settings: ViewSettings) {
+		match settings.scaling {
+			FolderScaling::Orig => {
+				self.img_texel_size = 1.0;
+				self.scaling = ScalingMode::Fixed;
+			}
+			FolderScaling::Fit => self.scaling = ScalingMode::FitStretch,
+			FolderScaling::FitBest => self.scaling = ScalingMode::FitMin,
+		}
+		self.antialiasing = settings.antialiasing;
+		if self.sort_order != settings.sort_order {
+			self.sort_order = settings.sort_order;
+			self.playback_manager.set_sort_order(settings.sort_order);
+		}
+		self.startup_fit = settings.fit;
+		// So that the fit is applied to the image that is shown
+		self.startup_fit_path = None;
+		self.update_scaling_buttons();
+		self.render_validity.invalidate();
+	}
+
+	/// Applies the saved settings of the folder of the shown image when it's different
+	/// from the folder of the previous image.
+	fn update_folder_view_settings(&mut self) {
+		let folder = match self.playback_manager.shown_file_path() {
+			LoadedImgPath::Loaded(path) | LoadedImgPath::ErrLoading(path) => {
+				path.parent().map(Path::to_owned)
+			}
+			LoadedImgPath::NotYetLoaded => None,
+		};
+		if folder.is_none() || folder == self.curr_folder {
+			return;
+		}
+		self.curr_folder = folder;
+		let folder = self.curr_folder.as_deref().unwrap();
+		let global = self.global_view_settings.unwrap_or_else(|| self.view_settings());
+		let folder_settings = {
+			let mut cache = self.cache.lock().unwrap();
+			cache.folders.get(folder).map(|s| ViewSettings {
+				scaling: s.scaling,
+				antialiasing: s.antialiasing,
+				sort_order: s.sort_order.unwrap_or(global.sort_order),
+				fit: s.fit,
+			})
+		};
+		match folder_settings {
+			Some(settings) => {
+				self.global_view_settings = Some(global);
+				self.apply_view_settings(settings);
+			}
+			None => {
+				if let Some(global) = self.global_view_settings.take() {
+					self.apply_view_settings(global);
+				}
+			}
+		}
+	}
+
+	/// Keeps track of the image that was shown before the current one
+	fn track_shown_image(&mut self) {
+		let LoadedImgPath::Loaded(path) = self.playback_manager.shown_file_path() else {
+			return;
+		};
+		let Some(texture) = self.playback_manager.image_texture() else {
+			return;
+		};
+		match &mut self.current_image {
+			Some((curr_path, curr_texture)) if curr_path == path => *curr_texture = texture,
+			_ => {
+				self.previous_image = self.current_image.replace((path.clone(), texture));
+				self.show_previous = false;
+				if let Some(text) = self.reference_mismatch() {
+					self.notification.show(&text, NOTIFICATION_DURATION);
+				}
+			}
+		}
+	}
+
+	/// Tells the sizes of the reference and the current image if they differ
+	fn reference_mismatch(&self) -> Option<String> {
+		let (_, reference) = self.reference_image.as_ref()?;
+		let (_, current) = self.current_image.as_ref()?;
+		if (reference.w, reference.h) == (current.w, current.h) {
+			return None;
+		}
+		Some(format!(
+			"The reference is {}×{}, the current image is {}×{}",
+			reference.w, reference.h, current.w, current.h
+		))
+	}
+
+	fn set_reference(&mut self) {
+		let Some((path, texture)) = self.current_image.clone() else {
+			self.notification.show("There's no image to use as reference", NOTIFICATION_DURATION);
+			return;
+		};
+		let text = format!("Reference: {}", path.file_name().unwrap_or_default().to_string_lossy());
+		self.reference_image = Some((path, texture));
+		self.notification.show(&text, NOTIFICATION_DURATION);
+		self.render_validity.invalidate();
+	}
+
+	fn clear_reference(&mut self) {
+		let text = match self.reference_image.take() {
+			Some(_) => "Reference cleared",
+			None => "There's no reference image",
+		};
+		self.notification.show(text, NOTIFICATION_DURATION);
+		self.render_validity.invalidate();
+	}
+
+	fn toggle_reference_difference(&mut self) {
+		if self.reference_image.is_none() {
+			self.notification.show("There's no reference image", NOTIFICATION_DURATION);
+			return;
+		}
+		let text = match self.reference_blend {
+			ReferenceBlend::Opacity => {
+				self.reference_blend = ReferenceBlend::Difference;
+				"Difference to the reference"
+			}
+			ReferenceBlend::Difference => {
+				self.reference_blend = ReferenceBlend::Opacity;
+				"Over the reference"
+			}
+		};
+		self.notification.show(text, NOTIFICATION_DURATION);
+		self.render_validity.invalidate();
+	}
+
+	fn change_reference_opacity(&mut self, delta: f32) {
+		self.reference_opacity = ((self.reference_opacity + delta) * 10.0).round() / 10.0;
+		self.reference_opacity = self.reference_opacity.clamp(0.0, 1.0);
+		let text = format!("Opacity over the reference {:.0}%", self.reference_opacity * 100.0);
+		self.notification.show(&text, NOTIFICATION_DURATION);
+		self.render_validity.invalidate();
+	}
+
+	/// Shows the next of the channels, one at a time as grayscale, or all of them
+	fn cycle_channel_view(&mut self) {
+		self.channel_view = match self.channel_view {
+			ChannelView::Rgb => ChannelView::Red,
+			ChannelView::Red => ChannelView::Green,
+			ChannelView::Green => ChannelView::Blue,
+			ChannelView::Blue => ChannelView::Alpha,
+			ChannelView::Alpha => ChannelView::Luminance,
+			ChannelView::Luminance => ChannelView::Rgb,
+		};
+		let text = format!("Channel: {}", self.channel_view.name());
+		self.notification.show(&text, NOTIFICATION_DURATION);
+		self.render_validity.invalidate();
+	}
+
+	/// Also turns focus peaking on, so that the change can be seen
+	fn change_peaking_sensitivity(&mut self, delta: f32) {
+		self.peaking_sensitivity = ((self.peaking_sensitivity + delta) * 10.0).round() / 10.0;
+		self.peaking_sensitivity = self.peaking_sensitivity.clamp(0.0, 1.0);
+		self.focus_peaking = true;
+		let text = format!("Focus peaking sensitivity {:.0}%", self.peaking_sensitivity * 100.0);
+		self.notification.show(&text, NOTIFICATION_DURATION);
+		self.render_validity.invalidate();
+	}
+
+	/// The contrast above which focus peaking highlights a pixel
+	fn peaking_threshold(&self) -> f32 {
+		MAX_PEAKING_THRESHOLD
+			- self.peaking_sensitivity * (MAX_PEAKING_THRESHOLD - MIN_PEAKING_THRESHOLD)
+	}
+
+	fn toggle_ab_flicker(&mut self) {
+		let (Some((prev_path, prev_tex)), Some((_, curr_tex))) =
+			(&self.previous_image, &self.current_image)
+		else {
+			self.notification
+				.show("There's no previous image to compare with", NOTIFICATION_DURATION);
+			return;
+		};
+		self.show_previous = !self.show_previous;
+		let text = if self.show_previous {
+			let name = prev_path.file_name().unwrap_or_default().to_string_lossy();
+			if (prev_tex.w, prev_tex.h) == (curr_tex.w, curr_tex.h) {
+				format!("Previous: {}", name)
+			} else {
+				format!(
+					"Previous: {}  ({}×{}, the current is {}×{})",
+					name, prev_tex.w, prev_tex.h, curr_tex.w, curr_tex.h
+				)
+			}
+		} else {
+			"Current".to_string()
+		};
+		self.notification.show(&text, NOTIFICATION_DURATION);
+		self.render_validity.invalidate();
+	}
+
+	fn step_in_folder(&mut self, forward: bool) {
+		match self.playback_manager.request_step(forward) {
+			FolderStep::Moved => {}
+			FolderStep::Stopped => {
+				let text =
+					if forward { "Last image of the folder" } else { "First image of the folder" };
+				self.notification.show(text, NOTIFICATION_DURATION);
+				self.edge_flash = Some(EdgeFlash { at_end: forward, start: Instant::now() });
+			}
+			FolderStep::Bounced => {
+				self.edge_flash = Some(EdgeFlash { at_end: forward, start: Instant::now() });
+			}
+		}
+		self.render_validity.invalidate();
+	}
+
+	/// Steps to the next or the previous spread in two page mode, and to the next or the previous
+	/// image otherwise. Right-to-left reading swaps the directions in two page mode.
+	fn step_pages(&mut self, forward: bool) {
+		if !self.two_page {
+			if !self.step_fast(forward) {
+				self.step_in_folder(forward);
+			}
+			return;
+		}
+		let right_to_left = self.reading_direction == ReadingDirection::RightToLeft;
+		let forward = forward != right_to_left;
+		let (Some(index), Some(len)) =
+			(self.playback_manager.current_file_index(), self.playback_manager.current_dir_len())
+		else {
+			return;
+		};
+		let target = if forward {
+			let step = if self.cover_is_single && index == 0 { 1 } else { 2 };
+			Some(index + step).filter(|&target| target < len)
+		} else if index == 0 {
+			None
+		} else if self.cover_is_single && index <= 2 {
+			Some(0)
+		} else {
+			Some(index.saturating_sub(2))
+		};
+		// Bouncing doesn't make sense for pages, so it stops at the ends like `Stop`
+		let wrap = self.playback_manager.end_behavior() == EndBehavior::Wrap;
+		let target = match target {
+			Some(target) => target,
+			None if wrap && forward => 0,
+			None if wrap => len.saturating_sub(2),
+			None => {
+				let text = if forward { "Last page" } else { "First page" };
+				self.notification.show(text, NOTIFICATION_DURATION);
+				let at_end = forward != right_to_left;
+				self.edge_flash = Some(EdgeFlash { at_end, start: Instant::now() });
+				self.render_validity.invalidate();
+				return;
+			}
+		};
+		self.playback_manager.request_load(LoadRequest::LoadAtIndex(target));
+		self.render_validity.invalidate();
+	}
+
+	/// Handles a step when the steps come faster than `nav_max_rate`, by only moving the slider
+	/// and showing the file name of the image that was stepped to. Returns false if the step
+	/// wasn't fast, and the image should be loaded as usual.
+	fn step_fast(&mut self, forward: bool) -> bool {
+		let now = Instant::now();
+		let (Some(curr_index), Some(len)) =
+			(self.playback_manager.current_file_index(), self.playback_manager.current_dir_len())
+		else {
+			return false;
+		};
+		let end_behavior = self.playback_manager.end_behavior();
+		let playback_manager = &mut self.playback_manager;
+		let path_at = |index| playback_manager.file_path_at_index(index);
+		let Some(step) = self.fast_nav.step(now, forward, (curr_index, len), end_behavior, path_at)
+		else {
+			return false;
+		};
+		if step != FolderStep::Moved {
+			self.edge_flash = Some(EdgeFlash { at_end: forward, start: now });
+		}
+		if let Some((target, path)) = self.fast_nav.target() {
+			let name = path.file_name().unwrap_or_default().to_string_lossy();
+			let text = format!("{}  ({} of {})", name, target + 1, len);
+			self.notification.show(&text, NOTIFICATION_DURATION);
+		}
+		self.render_validity.invalidate();
+		true
+	}
+
+	/// Loads the image that rapid stepping arrived at, once the stepping has slowed down
+	fn update_fast_nav(&mut self) {
+		let Some(load_time) = self.fast_nav.settle_time() else {
+			return;
+		};
+		match self.fast_nav.take_settled(Instant::now()) {
+			Some(target) => {
+				self.playback_manager.request_load(LoadRequest::LoadAtIndex(target));
+				self.next_update = NextUpdate::Soonest;
+				self.render_validity.invalidate();
+			}
+			None => self.next_update = self.next_update.aggregate(NextUpdate::WaitUntil(load_time)),
+		}
+	}
+
+	fn toggle_two_page(&mut self) {
+		self.two_page = !self.two_page;
+		if !self.two_page {
+			self.second_page = None;
+		}
+		let text = if self.two_page { "Two page mode" } else { "Single page mode" };
+		self.notification.show(text, NOTIFICATION_DURATION);
+		self.render_validity.invalidate();
+	}
+
+	/// Returns the index of the image that is shown beside the current one
+	fn second_page_index(&mut self) -> Option<usize> {
+		if !self.two_page {
+			return None;
+		}
+		let index = self.playback_manager.current_file_index()?;
+		let len = self.playback_manager.current_dir_len()?;
+		if (self.cover_is_single && index == 0) || index + 1 >= len {
+			return None;
+		}
+		Some(index + 1)
+	}
+
+	fn update_second_page(&mut self, window: &Window) {
+		let second_page = match self.second_page_index() {
+			Some(index) => {
+				let texture = self.playback_manager.texture_at_index(window, index);
+				if texture.is_none() {
+					let poll_time = Instant::now() + Duration::from_millis(50);
+					self.next_update = self.next_update.aggregate(NextUpdate::WaitUntil(poll_time));
+				}
+				texture
+			}
+			None => None,
+		};
+		let changed = match (&self.second_page, &second_page) {
+			(Some(prev), Some(new)) => !Rc::ptr_eq(&prev.tex_grid, &new.tex_grid),
+			(prev, new) => prev.is_some() != new.is_some(),
+		};
+		if changed {
+			self.render_validity.invalidate();
+		}
+		self.second_page = second_page;
+	}
+
+	/// Whether the files can't be changed, which is shown as a notification
+	fn refuse_read_only(&mut self) -> bool {
+		if self.read_only {
+			self.notification.show("Read-only mode", NOTIFICATION_DURATION);
+		}
+		self.read_only
+	}
+
+	/// Reverses the last view edit of the shown image, or the last file operation if the image
+	/// has no edits to undo, and shows what was undone
+	fn undo(&mut self) {
+		if let LoadedImgPath::Loaded(path) = self.playback_manager.shown_file_path() {
+			if let Some(edit) = self.view_edits.undo(path) {
+				self.apply_view_edit(edit, "Undo");
+				return;
+			}
+		}
+		let Some(action) = self.undo_stack.pop() else {
+			self.notification.show("Nothing to undo", NOTIFICATION_DURATION);
+			return;
+		};
+		let description = action.description.clone();
+		let text = match action.undo() {
+			Ok(path) => {
+				if let Err(e) = self.playback_manager.update_directory() {
+					eprintln!("Error while updating directory {:?}", e);
+				}
+				self.playback_manager.request_load(LoadRequest::FilePath(path));
+				format!("Undo: {}", description)
+			}
+			Err(e) => {
+				eprintln!("Could not undo \"{}\": {}", description, e);
+				format!("Could not undo: {}", description)
+			}
+		};
+		self.notification.show(&text, NOTIFICATION_DURATION);
 		self.render_validity.invalidate();
 	}
 
+	/// Reapplies the last view edit of the shown image that was undone
+	fn redo(&mut self) {
+		let edit = match self.playback_manager.shown_file_path() {
+			LoadedImgPath::Loaded(path) => self.view_edits.redo(path),
+			_ => None,
+		};
+		match edit {
+			Some(edit) => self.apply_view_edit(edit, "Redo"),
+			None => self.notification.show("Nothing to redo", NOTIFICATION_DURATION),
+		}
+	}
+
+	/// Shows the shown image with `edit`, which was taken from its history
+	fn apply_view_edit(&mut self, edit: ViewEdit, verb: &str) {
+		self.rotation = edit.rotation;
+		let text = format!("{}: rotation {:.1}°", verb, edit.rotation);
+		self.notification.show(&text, NOTIFICATION_DURATION);
+		self.render_validity.invalidate();
+	}
+
+	/// Shows the next or the previous image after `trigger` succeeded on the image at `path`, if
+	/// `auto_advance_on` includes it and the image is still shown
+	fn auto_advance(&mut self, trigger: AutoAdvanceTrigger, path: &Path) {
+		if !self.auto_advance_on.contains(&trigger) {
+			return;
+		}
+		let shown = self.playback_manager.shown_file_path();
+		if !matches!(shown, LoadedImgPath::Loaded(shown) if shown == path) {
+			return;
+		}
+		let forward = self.auto_advance_direction == AutoAdvanceDirection::Next;
+		if path.exists() {
+			self.step_pages(forward);
+			return;
+		}
+		// Once the image is gone from the folder, the one after it takes its place
+		if let Err(e) = self.playback_manager.update_directory() {
+			eprintln!("Error while updating directory {:?}", e);
+		}
+		if !forward {
+			self.step_pages(false);
+		}
+	}
+
+	/// Moves the shown image into the triage folder `index`, which shows the next image
+	fn triage(&mut self, index: usize) {
+		let LoadedImgPath::Loaded(path) = self.playback_manager.shown_file_path().clone() else {
+			return;
+		};
+		if self.refuse_read_only() {
+			return;
+		}
+		let letter = TRIAGE_LETTERS[index];
+		let Some(folder) = &self.triage_folders[index] else {
+			let text = format!("No triage folder {} is set", letter);
+			self.notification.show(&text, NOTIFICATION_DURATION);
+			return;
+		};
+		let folder = path.parent().map_or_else(|| folder.clone(), |parent| parent.join(folder));
+		match move_to_folder(&path, &folder) {
+			Ok(undo_action) => {
+				self.notification.show(&undo_action.description, NOTIFICATION_DURATION);
+				self.undo_stack.push(undo_action);
+				// The image after the moved one takes its place
+				if let Err(e) = self.playback_manager.update_directory() {
+					eprintln!("Error while updating directory {:?}", e);
+				}
+			}
+			Err(e) => {
+				eprintln!("Could not move {:?} to {:?}: {}", path, folder, e);
+				let text = format!("Could not move the image to {}", letter);
+				self.notification.show(&text, NOTIFICATION_DURATION);
+			}
+		}
+		self.render_validity.invalidate();
+	}
+
+	/// Makes the folder of the shown image the triage folder `index`, for the rest of the
+	/// session
+	fn set_triage_folder(&mut self, index: usize) {
+		let LoadedImgPath::Loaded(path) = self.playback_manager.shown_file_path() else {
+			return;
+		};
+		let Some(folder) = path.parent().map(Path::to_owned) else {
+			return;
+		};
+		let text = format!("Triage folder {}: {}", TRIAGE_LETTERS[index], folder.display());
+		self.triage_folders[index] = Some(folder);
+		self.notification.show(&text, NOTIFICATION_DURATION);
+		self.render_validity.invalidate();
+	}
+
+	/// Like "A: keep    B: reject", or `None` if there are no triage folders
+	fn triage_text(&self) -> Option<String> {
+		let names: Vec<_> = self
+			.triage_folders
+			.iter()
+			.zip(TRIAGE_LETTERS.iter())
+			.filter_map(|(folder, letter)| {
+				let folder = folder.as_ref()?;
+				let name = folder.file_name().unwrap_or(folder.as_os_str()).to_string_lossy();
+				Some(format!("{}: {}", letter, name))
+			})
+			.collect();
+		(!names.is_empty()).then(|| names.join("    "))
+	}
+
+	/// Applies `unsupported_file_action` if `path` is a file that is not an image. Returns the
+	/// path to open instead, or `None` if nothing should be opened.
+	fn unsupported_file_target(&mut self, path: PathBuf) -> Option<PathBuf> {
+		if !path.is_file() || is_file_supported(&path) {
+			return Some(path);
+		}
+		let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned();
+		match self.unsupported_file_action {
+			UnsupportedFileAction::ShowError => Some(path),
+			UnsupportedFileAction::SkipInList => match image_after(&path) {
+				Ok(Some(image)) => Some(image),
+				// The error is shown for the file if there are no images next to it
+				_ => Some(path),
+			},
+			UnsupportedFileAction::OpenExternally => {
+				let text = match open::that_detached(&path) {
+					Ok(()) => format!("Opened {:?} with the default program", name),
+					Err(e) => {
+						eprintln!("Could not open {:?} with the default program: {}", path, e);
+						format!("Could not open {:?}", name)
+					}
+				};
+				self.notification.show(&text, NOTIFICATION_DURATION);
+				self.render_validity.invalidate();
+				None
+			}
+		}
+	}
+
+	/// Shows the empty state while no image is open, including when the folder has no images
+	fn update_empty_state(&mut self) {
+		let shown = self.playback_manager.shown_file_path();
+		let nothing_open = matches!(shown, LoadedImgPath::NotYetLoaded)
+			&& self.playback_manager.current_dir_len() == Some(0);
+		self.empty_state.set_shown(nothing_open);
+	}
+
+	/// Fits the image again once the picture stopped being resized, when `continuous_fit` is off
+	fn update_fit_settle(&mut self) {
+		let Some(time) = self.fit_settle_time else {
+			return;
+		};
+		if Instant::now() < time {
+			self.next_update = self.next_update.aggregate(NextUpdate::WaitUntil(time));
+		} else {
+			self.fit_settle_time = None;
+			self.render_validity.invalidate();
+		}
+	}
+
+	/// Keeps the frame scrubber at the frame that is shown
+	fn update_frame_scrubber(&mut self) {
+		let frames = self.playback_manager.animation_frames();
+		self.frame_scrubber.update(frames);
+	}
+
+	/// Restores the file whose entry was clicked in the panel of deleted files
+	fn update_deleted_items(&mut self) {
+		let Some(path) = self.deleted_items.take_restore_request() else {
+			return;
+		};
+		let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned();
+		let text = match restore_from_trash(&path) {
+			Ok(restored) => {
+				self.deleted_items.remove(&path);
+				if let Err(e) = self.playback_manager.update_directory() {
+					eprintln!("Error while updating directory {:?}", e);
+				}
+				self.playback_manager.request_load(LoadRequest::FilePath(restored));
+				format!("Restored {:?}", name)
+			}
+			Err(e) => {
+				eprintln!("Could not restore {:?}: {}", path, e);
+				self.deleted_items.refresh();
+				format!("Could not restore {:?}", name)
+			}
+		};
+		self.notification.show(&text, NOTIFICATION_DURATION);
+		self.render_validity.invalidate();
+	}
+
+	/// Shows whether the finished custom commands succeeded, and reloads the image if a command
+	/// asks for it.
+	fn update_commands(&mut self) {
+		while let Some(outcome) = self.command_runner.try_get_outcome() {
+			let text = match &outcome.error {
+				None => format!("{} finished", outcome.name),
+				Some(error) => format!("{} failed, {}", outcome.name, error),
+			};
+			self.notification.show(&text, NOTIFICATION_DURATION);
+			if outcome.error.is_none() {
+				self.auto_advance(AutoAdvanceTrigger::Command, &outcome.img_path);
+			}
+			let shown = self.playback_manager.shown_file_path();
+			let is_shown =
+				matches!(shown, LoadedImgPath::Loaded(path) if *path == outcome.img_path);
+			if outcome.reload && is_shown {
+				if let Err(e) = self.playback_manager.update_directory() {
+					eprintln!("Error while updating directory {:?}", e);
+				}
+			}
+			self.render_validity.invalidate();
+		}
+		if self.command_runner.running() {
+			let poll_time = Instant::now() + Duration::from_millis(100);
+			self.next_update = self.next_update.aggregate(NextUpdate::WaitUntil(poll_time));
+		}
+	}
+
+	/// Returns the opacity of the edge flash, or `None` if it has faded out.
+	fn edge_flash_opacity(&self) -> Option<f32> {
+		let elapsed = self.edge_flash?.start.elapsed();
+		if elapsed >= EDGE_FLASH_DURATION {
+			return None;
+		}
+		Some(1.0 - elapsed.as_secs_f32() / EDGE_FLASH_DURATION.as_secs_f32())
+	}
+
+	/// Carries out the requested jump to the next or previous capture date group,
+	/// once the capture dates are available.
+	fn update_group_jump(&mut self) {
+		let Some(jump) = self.pending_group_jump else {
+			return;
+		};
+		let Some(curr_index) = self.playback_manager.current_file_index() else {
+			return;
+		};
+		let Some(dates) = self.playback_manager.capture_dates() else {
+			let poll_time = Instant::now() + Duration::from_millis(50);
+			self.next_update = self.next_update.aggregate(NextUpdate::WaitUntil(poll_time));
+			return;
+		};
+		self.pending_group_jump = None;
+		if curr_index >= dates.len() {
+			return;
+		}
+		let Some((target, group_number, group_count)) = group_jump_target(dates, curr_index, jump)
+		else {
+			self.notification.show("Already at the last group", NOTIFICATION_DURATION);
+			self.render_validity.invalidate();
+			return;
+		};
+		let date = match dates[target] {
+			Some(date) => date.to_string(),
+			None => "Unknown date".into(),
+		};
+		let text = format!("{}  ({} of {})", date, group_number, group_count);
+		if target != curr_index {
+			self.playback_manager.request_load(LoadRequest::LoadAtIndex(target));
+		}
+		self.notification.show(&text, NOTIFICATION_DURATION);
+		self.render_validity.invalidate();
+	}
+
+	/// Shows how many thumbnails of the folder are done. Generating them starts only after the
+	/// current image is shown, like comparing the images in `update_duplicates`.
+	fn update_thumbnails(&mut self) {
+		if !self.eager_thumbnail || self.playback_manager.low_memory() {
+			return;
+		}
+		if !matches!(self.playback_manager.shown_file_path(), LoadedImgPath::Loaded(_)) {
+			return;
+		}
+		let progress = self.playback_manager.thumbnail_progress();
+		let text = progress.map(|(done, total)| format!("Thumbnailing {}/{}", done, total));
+		self.thumbnail_badge.set_text(text);
+		if progress.is_some() {
+			let poll_time = Instant::now() + Duration::from_millis(250);
+			self.next_update = self.next_update.aggregate(NextUpdate::WaitUntil(poll_time));
+		}
+	}
+
+	fn update_watermark(&mut self) {
+		let bounds = self.image_bounds();
+		let path = match self.playback_manager.shown_file_path() {
+			LoadedImgPath::Loaded(path) => Some(path.as_path()),
+			_ => None,
+		};
+		self.watermark.update(path, bounds);
+	}
+
+	/// The part of the picture area that the image covers, relative to the picture area
+	/// The action of the tap zone under `cursor_pos`, if `tap_zones` is on
+	fn tap_zone_action(&self, cursor_pos: LogicalVector) -> Option<String> {
+		let bounds = &self.drawn_bounds;
+		let x = (cursor_pos.vec.x - bounds.pos.vec.x) / bounds.size.vec.x;
+		let y = (cursor_pos.vec.y - bounds.pos.vec.y) / bounds.size.vec.y;
+		let zone = self.tap_zones.iter().find(|zone| zone.contains(x, y))?;
+		Some(zone.action.clone())
+	}
+
+	fn image_bounds(&self) -> Option<LogicalRect> {
+		let (w, h) = self.rotated_dimensions()?;
+		let size = Vector2::new(w as f32, h as f32) * (self.img_texel_size / self.dpi_scale);
+		let image_bounds = LogicalRect {
+			pos: LogicalVector { vec: self.img_pos.vec - size * 0.5 },
+			size: LogicalVector { vec: size },
+		};
+		let view = LogicalRect { pos: LogicalVector::new(0.0, 0.0), size: self.drawn_bounds.size };
+		Some(clip_rect(&image_bounds, &view))
+	}
+
+	fn update_hover_preview(&mut self) {
+		let hovered = self.bottom_bar.slider.hovered();
+		let hovered = hovered.and_then(|(index, cursor_x)| {
+			let path = self.playback_manager.file_path_at_index(index as usize)?;
+			Some((path, cursor_x))
+		});
+		let next_update = self.hover_preview.update(hovered);
+		self.next_update = self.next_update.aggregate(next_update);
+	}
+
+	fn shown_file_missing(&self) -> bool {
+		match self.playback_manager.shown_file_path() {
+			LoadedImgPath::Loaded(path) => {
+				matches!(source::metadata(path), Err(e) if e.kind() == std::io::ErrorKind::NotFound)
+			}
+			_ => false,
+		}
+	}
+
+	/// Notices when the shown file is deleted or replaced by another program. There is no
+	/// folder watcher, so the file is checked from time to time.
+	/// Loads the shown image at its full size when it was shrunk while it was decoded and it's
+	/// zoomed into. Also drops the cached images when the system is running out of memory.
+	fn update_low_memory(&mut self, prev_texture: Option<&AnimationFrameTexture>) {
+		let now = Instant::now();
+		if now >= self.next_memory_check {
+			self.next_memory_check = now + MEMORY_CHECK_INTERVAL;
+			let low = sys_info::mem_info()
+				.is_ok_and(|mem| (mem.avail as f64) < mem.total as f64 * MEMORY_PRESSURE_RATIO);
+			if low {
+				log::debug!("The system is low on memory, dropping the cached images");
+				self.playback_manager.release_memory();
+			}
+		}
+		let next_check = NextUpdate::WaitUntil(self.next_memory_check);
+		self.next_update = self.next_update.aggregate(next_check);
+
+		let LoadedImgPath::Loaded(path) = self.playback_manager.shown_file_path() else {
+			return;
+		};
+		let Some(texture) = self.get_texture() else {
+			return;
+		};
+		let requested = self.full_resolution_path.as_ref() == Some(path);
+		if requested && texture.downscaled_from.is_none() {
+			let prev =
+				prev_texture.filter(|prev| prev.downscaled_from == Some((texture.w, texture.h)));
+			if let (Some(prev), ScalingMode::Fixed) = (prev, self.scaling) {
+				// The image stays the same size on the screen
+				self.img_texel_size *= prev.w as f32 / texture.w as f32;
+			}
+			self.full_resolution_path = None;
+		} else if !requested && texture.downscaled_from.is_some() && self.img_texel_size > 1.0 {
+			self.full_resolution_path = Some(path.clone());
+			self.playback_manager.request_full_resolution();
+		}
+	}
+
+	/// Keeps a zoomed image the same size on the screen when the decoded image takes the place of
+	/// its embedded preview
+	fn keep_preview_size(
+		&mut self,
+		prev_texture: Option<&AnimationFrameTexture>,
+		new_texture: Option<&AnimationFrameTexture>,
+	) {
+		let shown_path = match self.playback_manager.shown_file_path() {
+			LoadedImgPath::Loaded(path) => Some(path),
+			_ => None,
+		};
+		let prev_path = self.preview_path.take();
+		let (Some(prev), Some(new)) = (prev_texture, new_texture) else {
+			return;
+		};
+		if new.preview {
+			self.preview_path = shown_path.cloned();
+		} else if prev.preview
+			&& prev_path.as_ref() == shown_path
+			&& self.scaling == ScalingMode::Fixed
+		{
+			self.img_texel_size *=
+				prev.oriented_dimensions().0 as f32 / new.oriented_dimensions().0 as f32;
+		}
+	}
+
+	/// Takes the `idle_action` once there was no input for `idle_timeout_seconds`
+	fn update_idle(&mut self) {
+		let Some(timeout) = self.idle_timeout.filter(|_| self.idle_action != IdleAction::None)
+		else {
+			return;
+		};
+		if self.idle_action_taken {
+			return;
+		}
+		let deadline = self.last_input + timeout;
+		if Instant::now() < deadline {
+			self.next_update = self.next_update.aggregate(NextUpdate::WaitUntil(deadline));
+			return;
+		}
+		self.idle_action_taken = true;
+		match self.idle_action.clone() {
+			IdleAction::None => {}
+			IdleAction::RestartSlideshow => {
+				self.playback_manager.request_load(LoadRequest::LoadAtIndex(0));
+				self.playback_manager.start_presentation();
+			}
+			IdleAction::GotoImage(path) => {
+				self.playback_manager.pause_playback();
+				self.playback_manager.request_load(LoadRequest::FilePath(path));
+			}
+			IdleAction::Quit => {
+				self.clipboard_handler.take();
+				request_exit();
+			}
+		}
+		self.render_validity.invalidate();
+	}
+
+	fn check_current_file(&mut self) {
+		if self.on_current_deleted == CurrentDeleted::KeepStale {
+			return;
+		}
+		let now = Instant::now();
+		if now >= self.next_file_check {
+			self.next_file_check = now + FILE_CHECK_INTERVAL;
+			self.check_shown_file();
+		}
+		let next_check = NextUpdate::WaitUntil(self.next_file_check);
+		self.next_update = self.next_update.aggregate(next_check);
+	}
+
+	/// Shows what's wrong with the shown file, if anything
+	fn update_warnings(&mut self) {
+		let motion = self.update_motion_clip().is_some();
+		let shown_path = self.playback_manager.shown_file_path();
+		let missing = matches!(
+			(shown_path, &self.missing_file),
+			(LoadedImgPath::Loaded(path), Some(missing)) if path == missing
+		);
+		let truncated = self.playback_manager.image_texture().is_some_and(|t| t.truncated);
+		let warning = if missing {
+			Some("The file no longer exists".to_string())
+		} else if truncated {
+			Some("Truncated".to_string())
+		} else if self.playback_manager.image_texture().is_some_and(|t| t.preview) {
+			Some("Preview, loading the full image…".to_string())
+		} else {
+			None
+		};
+		self.warning_badge.set_text(warning);
+		let edited = match shown_path {
+			LoadedImgPath::Loaded(path) => !self.view_edits.get(path).is_unedited(),
+			_ => false,
+		};
+		let info = match (edited, motion) {
+			(true, true) => Some("Edited · Motion photo"),
+			(true, false) => Some("Edited"),
+			(false, true) => Some("Motion photo"),
+			(false, false) => None,
+		};
+		self.edited_badge.set_text(info.map(str::to_string));
+		let error = match (shown_path, self.playback_manager.load_error()) {
+			(LoadedImgPath::ErrLoading(path), Some(error)) => {
+				let name = path.file_name().unwrap_or_default().to_string_lossy();
+				let mut error: String = error.lines().next().unwrap_or_default().to_string();
+				if error.chars().count() > MAX_ERROR_LENGTH {
+					error = error.chars().take(MAX_ERROR_LENGTH).collect();
+					error.push('…');
+				}
+				Some(format!("Could not open {}: {}", name, error))
+			}
+			_ => None,
+		};
+		self.error_card.set_text(error);
+	}
+
+	/// Looks for the motion clip of the shown image when another image is shown, and returns it
+	fn update_motion_clip(&mut self) -> Option<&MotionClip> {
+		let LoadedImgPath::Loaded(path) = self.playback_manager.shown_file_path() else {
+			self.motion_clip = None;
+			return None;
+		};
+		if self.motion_clip.as_ref().map(|(checked, _)| checked) != Some(path) {
+			self.motion_clip = Some((path.clone(), find_motion_clip(path)));
+		}
+		self.motion_clip.as_ref().and_then(|(_, clip)| clip.as_ref())
+	}
+
+	/// Opens the clip of the shown motion photo in the default video player
+	fn play_motion_photo(&mut self) {
+		let Some((path, clip)) = self.motion_clip.clone() else {
+			return;
+		};
+		let Some(clip) = clip else {
+			self.notification.show("This is not a motion photo", NOTIFICATION_DURATION);
+			return;
+		};
+		let result = clip_file(&path, &clip).and_then(open::that_detached);
+		let text = match result {
+			Ok(()) => "Playing the motion clip in the video player".to_string(),
+			Err(e) => {
+				eprintln!("Could not play the motion clip of {:?}: {}", path, e);
+				format!("Could not play the motion clip: {}", e)
+			}
+		};
+		self.notification.show(&text, NOTIFICATION_DURATION);
+	}
+
+	fn check_shown_file(&mut self) {
+		let path = match self.playback_manager.shown_file_path() {
+			LoadedImgPath::Loaded(path) => path.clone(),
+			_ => {
+				self.checked_file = None;
+				return;
+			}
+		};
+		let modified = match source::metadata(&path) {
+			Ok(metadata) => metadata.modified().ok(),
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+				match self.on_current_deleted {
+					CurrentDeleted::ShowMissing => self.missing_file = Some(path),
+					CurrentDeleted::AdvanceNext => {
+						// Drops the file from the folder and loads the next one
+						if let Err(e) = self.playback_manager.update_directory() {
+							eprintln!("Error while updating directory {:?}", e);
+						}
+					}
+					CurrentDeleted::KeepStale => {}
+				}
+				self.render_validity.invalidate();
+				return;
+			}
+			Err(_) => return,
+		};
+		self.missing_file = None;
+		// A file with the same name but new contents is loaded again
+		let replaced = matches!(
+			&self.checked_file,
+			Some((checked_path, checked_modified))
+				if *checked_path == path && *checked_modified != modified
+		);
+		if replaced {
+			if let Err(e) = self.playback_manager.update_directory() {
+				eprintln!("Error while updating directory {:?}", e);
+			}
+			self.render_validity.invalidate();
+		}
+		self.checked_file = Some((path, modified));
+	}
+
+	/// Shows the number of images that are similar to the current one, and carries out the
+	/// requested jump to a similar image once the images are compared. The comparison starts
+	/// only after the current image is shown, so that it doesn't delay showing it.
+	/// Keeps polling while the folder is being sorted, so that the new order is shown once
+	/// it's done
+	fn update_sorting(&mut self) {
+		if self.playback_manager.sorting() {
+			let poll_time = Instant::now() + Duration::from_millis(100);
+			self.next_update = self.next_update.aggregate(NextUpdate::WaitUntil(poll_time));
+		}
+	}
+
+	fn update_duplicates(&mut self) {
+		if !self.find_duplicates {
+			return;
+		}
+		if !matches!(self.playback_manager.shown_file_path(), LoadedImgPath::Loaded(_)) {
+			return;
+		}
+		let Some(curr_index) = self.playback_manager.current_file_index() else {
+			return;
+		};
+		let Some(duplicates) = self.playback_manager.duplicates() else {
+			let poll_time = Instant::now() + Duration::from_millis(100);
+			self.next_update = self.next_update.aggregate(NextUpdate::WaitUntil(poll_time));
+			return;
+		};
+		let group_size = duplicates.group_of(curr_index).map(|group| group.len());
+		let target = duplicates.next_duplicate(curr_index);
+		let target_group = target.and_then(|target| duplicates.group_of(target));
+		let target_position = target.and_then(|target| {
+			let group = target_group?;
+			Some((group.iter().position(|&i| i == target)? + 1, group.len()))
+		});
+		let similar_count = group_size.map(|size| size - 1);
+		self.duplicate_badge.set_text(similar_count.map(|count| match count {
+			1 => "1 similar image".to_string(),
+			count => format!("{} similar images", count),
+		}));
+		if !self.pending_duplicate_jump {
+			return;
+		}
+		self.pending_duplicate_jump = false;
+		let text = match (target, target_position) {
+			(Some(target), Some((position, size))) => {
+				self.playback_manager.request_load(LoadRequest::LoadAtIndex(target));
+				format!("Similar image {} of {}", position, size)
+			}
+			_ => "There are no similar images in the folder".to_string(),
+		};
+		self.notification.show(&text, NOTIFICATION_DURATION);
+		self.render_validity.invalidate();
+	}
+
+	pub fn save_folder_view_settings(&mut self) {
+		if let Some(folder) = &self.curr_folder {
+			let view = self.view_settings();
+			let settings = FolderViewSettings {
+				scaling: view.scaling,
+				antialiasing: view.antialiasing,
+				sort_order: Some(view.sort_order),
+				fit: view.fit,
+				last_used: 0,
+			};
+			self.cache.lock().unwrap().folders.insert(folder, settings);
+			if self.global_view_settings.is_none() {
+				self.global_view_settings = Some(view);
+			}
+		}
+	}
+
+	pub fn clear_folder_view_settings(&mut self) {
+		if let Some(folder) = &self.curr_folder {
+			self.cache.lock().unwrap().folders.remove(folder);
+			if let Some(global) = self.global_view_settings.take() {
+				self.apply_view_settings(global);
+			}
+		}
+	}
+
 	/// Ensures that the image is within the widget, or at least touches an edge of the widget
+	/// Exits, unless `confirm_quit` asks for quitting a second time first
+	fn request_quit(&mut self) {
+		let confirm = match self.confirm_quit {
+			ConfirmQuit::Never | ConfirmQuit::IfUnsaved => false,
+			ConfirmQuit::Always => true,
+		};
+		let now = Instant::now();
+		let confirmed =
+			self.quit_requested_time.is_some_and(|time| now - time < QUIT_CONFIRM_DURATION);
+		if !confirm || confirmed {
+			// Just let it drop.
+			self.clipboard_handler.take();
+			request_exit();
+			return;
+		}
+		self.quit_requested_time = Some(now);
+		self.notification.show("Quit again to exit", QUIT_CONFIRM_DURATION);
+	}
+
+	/// Overrides the scale factor of the window, starting from the current one
+	fn step_ui_scale(&mut self, step: f32) {
+		let Some(window) = self.window.upgrade() else {
+			return;
+		};
+		// Round to the step, so that stepping from an odd reported scale factor ends up on the
+		// usual ones
+		let scale = ((window.scale_factor() + step) / UI_SCALE_STEP).round() * UI_SCALE_STEP;
+		let scale = scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE);
+		window.set_ui_scale_override(Some(scale));
+		self.notification.show(&format!("UI scale {:.0}%", scale * 100.0), NOTIFICATION_DURATION);
+	}
+
 	fn apply_img_bounds(&mut self, dpi_scale: f32) {
-		if let Some(texture) = self.get_texture() {
-			let (img_phys_w, img_phys_h) = {
-				let (w, h) = texture.oriented_dimensions();
-				(w as f32 * self.img_texel_size, h as f32 * self.img_texel_size)
-			};
+		if let Some((w, h)) = self.rotated_dimensions() {
+			let (img_phys_w, img_phys_h) =
+				(w as f32 * self.img_texel_size, h as f32 * self.img_texel_size);
 			let img_w = img_phys_w / dpi_scale;
 			let img_h = img_phys_h / dpi_scale;
 
@@ -404,6 +2806,43 @@ impl PictureWidgetData {
 		}
 	}
 
+	fn update_status_bar(&mut self) {
+		let index = match (
+			self.playback_manager.current_file_index(),
+			self.playback_manager.current_dir_len(),
+		) {
+			(Some(index), Some(len)) => Some((index, len)),
+			_ => None,
+		};
+		let path = match self.playback_manager.shown_file_path() {
+			LoadedImgPath::Loaded(path) => Some(path.clone()),
+			_ => None,
+		};
+		let dimensions = self.shown_dimensions();
+		let bit_depth = match path {
+			Some(_) => self.playback_manager.image_texture().map(|t| t.bit_depth_text()),
+			None => None,
+		};
+		let sort_key = path.as_deref().and_then(|p| self.playback_manager.sort_key_text(p));
+		let triage = self.triage_text();
+		let info = StatusInfo {
+			path: path.as_deref(),
+			sort_key,
+			triage,
+			dimensions,
+			downscaled_from: path.as_ref().and_then(|_| self.get_texture()?.downscaled_from),
+			decoder: path.as_ref().and_then(|_| Some(self.get_texture()?.decoder)),
+			read_only: self.read_only,
+			cursor: self.cursor_pixel(),
+			channel: Some(self.channel_view.name())
+				.filter(|_| self.channel_view != ChannelView::Rgb),
+			bit_depth,
+			zoom: dimensions.map(|_| self.img_texel_size * 100.0),
+			index,
+		};
+		self.status_bar.update(&info);
+	}
+
 	fn update_scaling_buttons(&mut self) {
 		self.bottom_bar.update_scaling_buttons(self.scaling, self.img_texel_size);
 	}
@@ -413,12 +2852,35 @@ pub struct PictureWidget {
 	data: RefCell<PictureWidgetData>,
 }
 impl PictureWidget {
+	/// Exits, or asks for confirmation first. See `ConfirmQuit`
+	pub fn request_quit(&self) {
+		self.data.borrow_mut().request_quit();
+	}
+
+	pub fn two_page(&self) -> bool {
+		self.data.borrow().two_page
+	}
+
+	#[allow(clippy::too_many_arguments)]
 	pub fn new(
 		display: &Display,
 		window: &Rc<Window>,
 		bottom_bar: Rc<BottomBar>,
+		status_bar: Rc<StatusBar>,
 		left_to_pan_hint: Rc<HelpScreen>,
 		copy_notifications: CopyNotifications,
+		notification: TextNotification,
+		duplicate_badge: Badge,
+		thumbnail_badge: Badge,
+		warning_badge: Badge,
+		error_card: Badge,
+		edited_badge: Badge,
+		scale_badge: Badge,
+		hover_preview: HoverPreview,
+		watermark: Watermark,
+		deleted_items: DeletedItemsPanel,
+		empty_state: EmptyState,
+		frame_scrubber: FrameScrubber,
 		configuration: Rc<RefCell<Configuration>>,
 		cache: Arc<Mutex<Cache>>,
 	) -> PictureWidget {
@@ -444,6 +2906,16 @@ impl PictureWidget {
 			},
 		)
 		.unwrap();
+		let difference_program = gelatin::shaders::shader_from_source(
+			display,
+			ShaderDescriptor {
+				vertex_shader: shaders::VERTEX_140,
+				fragment_shader: shaders::DIFFERENCE_140,
+				outputs_srgb: false,
+				..Default::default()
+			},
+		)
+		.unwrap();
 
 		let scaling;
 		{
@@ -474,6 +2946,405 @@ impl PictureWidget {
 			}
 		};
 
+		let (pan_key_speed, pan_key_accel) = {
+			let config = configuration.borrow();
+			let image_config = config.image.as_ref();
+			let positive = |value: Option<f32>, default: f32, option_name: &str| match value {
+				Some(value) if value > 0.0 && value.is_finite() => value,
+				Some(value) => {
+					eprintln!("Illegal configuration value {} for {}!", value, option_name);
+					eprintln!("It has to be a positive number.");
+					default
+				}
+				None => default,
+			};
+			(
+				positive(
+					image_config.and_then(|s| s.pan_key_speed),
+					DEFAULT_PAN_KEY_SPEED,
+					"pan_key_speed",
+				),
+				positive(
+					image_config.and_then(|s| s.pan_key_accel),
+					DEFAULT_PAN_KEY_ACCEL,
+					"pan_key_accel",
+				),
+			)
+		};
+
+		let (zoom_key_accel, zoom_key_max_rate) = {
+			let config = configuration.borrow();
+			let image_config = config.image.as_ref();
+			let accel = image_config.and_then(|s| s.zoom_key_accel);
+			let max_rate = image_config.and_then(|s| s.zoom_key_max_rate);
+			let max_rate = max_rate.unwrap_or(DEFAULT_ZOOM_KEY_MAX_RATE).max(ZOOM_KEY_START_RATE);
+			(accel.unwrap_or(DEFAULT_ZOOM_KEY_ACCEL).max(0.0), max_rate * LN_2)
+		};
+
+		let (startup_fit, no_upscale_on_fit) = {
+			let config = configuration.borrow();
+			let image_config = config.image.as_ref();
+			(
+				image_config.and_then(|s| s.startup_fit),
+				image_config.and_then(|s| s.no_upscale_on_fit).unwrap_or(false),
+			)
+		};
+
+		let (small_image_policy, small_image_threshold) = {
+			let config = configuration.borrow();
+			let image_config = config.image.as_ref();
+			let policy = match image_config.and_then(|s| s.small_image_policy) {
+				Some(SmallImagePolicy::FitWithLimit(max_scale)) if max_scale < 1.0 => {
+					eprintln!(
+						"Illegal configuration value {} for the fit_with_limit of small_image_policy!",
+						max_scale
+					);
+					eprintln!("It has to be at least 1.");
+					DEFAULT_SMALL_IMAGE_POLICY
+				}
+				Some(policy) => policy,
+				None => DEFAULT_SMALL_IMAGE_POLICY,
+			};
+			let threshold = image_config.and_then(|s| s.small_image_threshold);
+			(policy, threshold.unwrap_or(DEFAULT_SMALL_IMAGE_THRESHOLD))
+		};
+		let continuous_fit = {
+			let config = configuration.borrow();
+			config.image.as_ref().and_then(|s| s.continuous_fit).unwrap_or(true)
+		};
+
+		let (zoom_snap, zoom_snap_tolerance, zoom_snap_targets) = {
+			let config = configuration.borrow();
+			let image_config = config.image.as_ref();
+			(
+				image_config.and_then(|s| s.zoom_snap).unwrap_or(true),
+				image_config
+					.and_then(|s| s.zoom_snap_tolerance)
+					.unwrap_or(DEFAULT_ZOOM_SNAP_TOLERANCE),
+				image_config
+					.and_then(|s| s.zoom_snap_targets.clone())
+					.unwrap_or_else(|| DEFAULT_ZOOM_SNAP_TARGETS.to_vec()),
+			)
+		};
+
+		let letterbox_color = configuration
+			.borrow()
+			.image
+			.as_ref()
+			.and_then(|s| s.letterbox_color)
+			.unwrap_or_default();
+		let reference_opacity =
+			match configuration.borrow().image.as_ref().and_then(|s| s.reference_opacity) {
+				Some(opacity) if (0.0..=1.0).contains(&opacity) => opacity,
+				Some(opacity) => {
+					eprintln!("Illegal configuration value {} for reference_opacity!", opacity);
+					eprintln!("It has to be between 0 and 1.");
+					DEFAULT_REFERENCE_OPACITY
+				}
+				None => DEFAULT_REFERENCE_OPACITY,
+			};
+		let clip_thresholds = {
+			let configuration = configuration.borrow();
+			let image_config = configuration.image.as_ref();
+			let threshold = |value: Option<f32>, name: &str, default: f32| match value {
+				Some(value) if (0.0..=1.0).contains(&value) => value,
+				Some(value) => {
+					eprintln!("Illegal configuration value {} for {}!", value, name);
+					eprintln!("It has to be between 0 and 1.");
+					default
+				}
+				None => default,
+			};
+			let highlight =
+				threshold(image_config.and_then(|s| s.clip_highlight), "clip_highlight", 1.0);
+			let shadow = threshold(image_config.and_then(|s| s.clip_shadow), "clip_shadow", 0.0);
+			(srgb_decode(highlight) - CLIP_TOLERANCE, srgb_decode(shadow) + CLIP_TOLERANCE)
+		};
+		let (peaking_sensitivity, peaking_color) = {
+			let configuration = configuration.borrow();
+			let image_config = configuration.image.as_ref();
+			let sensitivity = match image_config.and_then(|s| s.focus_peaking_sensitivity) {
+				Some(value) if (0.0..=1.0).contains(&value) => value,
+				Some(value) => {
+					eprintln!(
+						"Illegal configuration value {} for focus_peaking_sensitivity!",
+						value
+					);
+					eprintln!("It has to be between 0 and 1.");
+					DEFAULT_PEAKING_SENSITIVITY
+				}
+				None => DEFAULT_PEAKING_SENSITIVITY,
+			};
+			let [r, g, b] = image_config.and_then(|s| s.focus_peaking_color).unwrap_or([255, 0, 0]);
+			(sensitivity, [srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b)])
+		};
+		let (composition_overlay, composition_grid_cells, overlay_color) = {
+			let configuration = configuration.borrow();
+			let image_config = configuration.image.as_ref();
+			let overlay = image_config.and_then(|s| s.composition_overlay).unwrap_or_default();
+			let grid_cells = match overlay {
+				CompositionOverlay::Grid(cells) => cells.max(1),
+				_ => DEFAULT_GRID_CELLS,
+			};
+			let [r, g, b] = image_config.and_then(|s| s.overlay_color).unwrap_or([255, 255, 255]);
+			let opacity = image_config.and_then(|s| s.overlay_opacity).unwrap_or(0.6);
+			let color =
+				[srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), opacity.clamp(0.0, 1.0)];
+			(overlay, grid_cells, color)
+		};
+		let export_watermark = WatermarkStyle::for_export(&configuration.borrow());
+		let (show_coordinates, scale_bar, measure_unit) = {
+			let configuration = configuration.borrow();
+			let image_config = configuration.image.as_ref();
+			(
+				image_config.and_then(|s| s.show_coordinates).unwrap_or(false),
+				image_config.and_then(|s| s.scale_bar).unwrap_or(false),
+				image_config.and_then(|s| s.measure_unit).unwrap_or_default(),
+			)
+		};
+		let copy_view_area = configuration
+			.borrow()
+			.image
+			.as_ref()
+			.and_then(|s| s.copy_view_area)
+			.unwrap_or_default();
+		let magnifier = {
+			let configuration = configuration.borrow();
+			let image_config = configuration.image.as_ref();
+			let zoom = match image_config.and_then(|s| s.magnifier_zoom) {
+				Some(zoom) if zoom > 0.0 => zoom,
+				Some(zoom) => {
+					eprintln!("Illegal configuration value {} for magnifier_zoom!", zoom);
+					DEFAULT_MAGNIFIER_ZOOM
+				}
+				None => DEFAULT_MAGNIFIER_ZOOM,
+			};
+			let size = image_config.and_then(|s| s.magnifier_size);
+			Magnifier {
+				texel_size: zoom / 100.0,
+				size: size
+					.unwrap_or(DEFAULT_MAGNIFIER_SIZE)
+					.clamp(MIN_MAGNIFIER_SIZE, MAX_MAGNIFIER_SIZE),
+				shape: image_config.and_then(|s| s.magnifier_shape).unwrap_or_default(),
+				toggle: image_config.and_then(|s| s.magnifier_toggle).unwrap_or(false),
+			}
+		};
+		let (rotation_step, rotation_fill) = {
+			let configuration = configuration.borrow();
+			let image_config = configuration.image.as_ref();
+			let rotation_step = match image_config.and_then(|s| s.rotation_step) {
+				Some(step) if step > 0.0 && step <= 90.0 => step,
+				Some(_) => {
+					eprintln!(
+						"rotation_step must be more than 0 and at most 90, using the default"
+					);
+					DEFAULT_ROTATION_STEP
+				}
+				None => DEFAULT_ROTATION_STEP,
+			};
+			(rotation_step, image_config.and_then(|s| s.rotation_fill).unwrap_or_default())
+		};
+		let fit_padding =
+			configuration.borrow().image.as_ref().and_then(|s| s.fit_padding).filter(|padding| {
+				let valid = padding.is_valid();
+				if !valid {
+					eprintln!("fit_padding must not be negative, it's ignored");
+				}
+				valid
+			});
+		MAKE_BACKDROPS.store(
+			letterbox_color == LetterboxColor::Named(NamedLetterboxColor::Blur),
+			std::sync::atomic::Ordering::Relaxed,
+		);
+		let animated_thumbnail =
+			configuration.borrow().navigation.as_ref().and_then(|n| n.animated_thumbnail);
+		let disk_cache_size_mb =
+			configuration.borrow().navigation.as_ref().and_then(|n| n.disk_cache_size_mb);
+		DISK_CACHE_LIMIT.store(
+			disk_cache_size_mb.unwrap_or(0).saturating_mul(1024 * 1024),
+			std::sync::atomic::Ordering::Relaxed,
+		);
+		REPRESENTATIVE_FRAME.store(
+			animated_thumbnail.unwrap_or_default() != AnimatedThumbnail::FirstFrame,
+			std::sync::atomic::Ordering::Relaxed,
+		);
+
+		let confirm_quit =
+			configuration.borrow().window.as_ref().and_then(|w| w.confirm_quit).unwrap_or_default();
+
+		let (end_behavior, nav_min_interval) = {
+			let config = configuration.borrow();
+			let nav_config = config.navigation.as_ref();
+			let nav_max_rate =
+				nav_config.and_then(|s| s.nav_max_rate).unwrap_or(DEFAULT_NAV_MAX_RATE);
+			(
+				nav_config.and_then(|s| s.end_behavior).unwrap_or_default(),
+				Duration::from_secs_f32(1.0 / nav_max_rate.max(0.1)),
+			)
+		};
+		let (two_page, reading_direction, cover_is_single, find_duplicates) = {
+			let config = configuration.borrow();
+			let nav_config = config.navigation.as_ref();
+			let restore_session = config.window.as_ref().and_then(|w| w.restore_session);
+			let saved_two_page =
+				restore_session == Some(true) && cache.lock().unwrap().window.two_page;
+			(
+				nav_config.and_then(|s| s.two_page).unwrap_or(saved_two_page),
+				nav_config.and_then(|s| s.reading_direction).unwrap_or_default(),
+				nav_config.and_then(|s| s.cover_is_single).unwrap_or(false),
+				nav_config.and_then(|s| s.find_duplicates).unwrap_or(false),
+			)
+		};
+		let (
+			preload_count,
+			eager_thumbnail,
+			thumbnail_threads,
+			decode_threads,
+			on_current_deleted,
+			sort_order,
+			unsupported_file_action,
+			auto_advance_on,
+			auto_advance_direction,
+			read_only,
+			open_file,
+			low_memory,
+			embedded_preview,
+			triage_folders,
+			idle_action,
+			idle_timeout,
+		) = {
+			let config = configuration.borrow();
+			let nav_config = config.navigation.as_ref();
+			(
+				nav_config.and_then(|s| s.preload_count),
+				nav_config.and_then(|s| s.eager_thumbnail).unwrap_or(false),
+				nav_config.and_then(|s| s.thumbnail_threads),
+				decode_thread_count(nav_config.and_then(|s| s.decode_threads)),
+				nav_config.and_then(|s| s.on_current_deleted).unwrap_or_default(),
+				nav_config.and_then(|s| s.sort_order).unwrap_or_default(),
+				nav_config.and_then(|s| s.unsupported_file_action).unwrap_or_default(),
+				nav_config.and_then(|s| s.auto_advance_on.clone()).unwrap_or_default(),
+				nav_config.and_then(|s| s.auto_advance_direction).unwrap_or_default(),
+				nav_config.and_then(|s| s.read_only).unwrap_or(false),
+				nav_config.and_then(|s| s.on_open_file).unwrap_or_default(),
+				nav_config.and_then(|s| s.low_memory).unwrap_or_else(|| {
+					sys_info::mem_info().is_ok_and(|mem| mem.total < LOW_MEMORY_TOTAL_KIB)
+				}),
+				nav_config.and_then(|s| s.embedded_preview).unwrap_or(false),
+				[
+					nav_config.and_then(|s| s.triage_folder_a.as_ref()).map(PathBuf::from),
+					nav_config.and_then(|s| s.triage_folder_b.as_ref()).map(PathBuf::from),
+				],
+				nav_config.and_then(|s| s.idle_action.clone()).unwrap_or_default(),
+				match nav_config.and_then(|s| s.idle_timeout_seconds) {
+					Some(secs) if secs > 0.0 && secs.is_finite() => {
+						Some(Duration::from_secs_f32(secs))
+					}
+					Some(secs) => {
+						eprintln!("Illegal configuration value {} for idle_timeout_seconds!", secs);
+						eprintln!("It has to be a positive number.");
+						None
+					}
+					None => None,
+				},
+			)
+		};
+		if let Some(title_config) = &configuration.borrow().title {
+			for token in title_config.unknown_format_tokens() {
+				eprintln!("Unknown token {} in the title format, it's shown as it is.", token);
+				eprintln!("The known tokens are {}.", TITLE_TOKENS.join(", "));
+			}
+		}
+		let low_memory_max_side = window
+			.window_mut()
+			.current_monitor()
+			.map(|monitor| monitor.size().width.max(monitor.size().height))
+			.filter(|&side| side > 0)
+			.unwrap_or(DEFAULT_LOW_MEMORY_MAX_SIDE);
+		let mut playback_manager = PlaybackManager::new(decode_threads);
+		if low_memory {
+			playback_manager.set_low_memory(Some(low_memory_max_side));
+		}
+		{
+			let config = configuration.borrow();
+			let max_megapixels = config.navigation.as_ref().and_then(|s| s.max_decode_megapixels);
+			let max_pixels = match max_megapixels {
+				Some(mp) if mp > 0.0 && mp.is_finite() => Some((mp as f64 * 1_000_000.0) as u64),
+				Some(mp) => {
+					eprintln!("Illegal configuration value {} for max_decode_megapixels!", mp);
+					eprintln!("It has to be a positive number.");
+					None
+				}
+				None => None,
+			};
+			playback_manager.set_max_decode_pixels(max_pixels);
+		}
+		playback_manager.set_embedded_preview(embedded_preview);
+		playback_manager.set_end_behavior(end_behavior);
+		playback_manager.set_preload_count(preload_count);
+		playback_manager.set_thumbnail_threads(
+			thumbnail_threads.unwrap_or_else(|| (decode_threads / 2).max(1)),
+		);
+		playback_manager.set_sort_order(sort_order);
+		{
+			let config = configuration.borrow();
+			let nav_config = config.navigation.as_ref();
+			let patterns = nav_config.and_then(|s| s.navigation_ignore.clone());
+			let show_hidden = nav_config.and_then(|s| s.show_hidden_files).unwrap_or(true);
+			playback_manager.set_ignore(patterns.unwrap_or_default(), show_hidden);
+		}
+		{
+			let config = configuration.borrow();
+			let image_config = config.image.as_ref();
+			let min = image_config.and_then(|s| s.min_frame_delay_ms);
+			let max = image_config.and_then(|s| s.max_frame_delay_ms);
+			let min = Duration::from_millis(min.unwrap_or(DEFAULT_MIN_FRAME_DELAY_MS));
+			playback_manager.set_frame_delay_limits(min, max.map(Duration::from_millis));
+			let remember = image_config.and_then(|s| s.remember_animation_position);
+			playback_manager.set_remember_animation_position(remember.unwrap_or(false));
+		}
+
+		let mouse_config = configuration.borrow().mouse.clone().unwrap_or_default();
+		let double_click_time =
+			Duration::from_millis(mouse_config.double_click_ms.unwrap_or(DEFAULT_DOUBLE_CLICK_MS));
+		let double_click_action = click_action(
+			mouse_config.double_click_action.as_deref(),
+			TOGGLE_FULLSCREEN_NAME,
+			"double_click_action",
+		);
+		let middle_click_action = click_action(
+			mouse_config.middle_click_action.as_deref(),
+			"none",
+			"middle_click_action",
+		);
+		let (loupe_cursor, loupe_hotspot) =
+			loupe_cursor_image(mouse_config.loupe_cursor.as_deref());
+		window.set_custom_cursor_image(&loupe_cursor, loupe_hotspot);
+		let pan_button = match mouse_config.pan_button.unwrap_or_default() {
+			PanButton::Left => MouseButton::Left,
+			PanButton::Middle => MouseButton::Middle,
+			PanButton::Right => MouseButton::Right,
+		};
+		let precision_factor = |factor: Option<f32>, default: f32, option_name: &str| match factor {
+			Some(factor) if factor > 0.0 && factor.is_finite() => factor,
+			Some(factor) => {
+				eprintln!("Illegal configuration value {} for {}!", factor, option_name);
+				eprintln!("It has to be a positive number.");
+				default
+			}
+			None => default,
+		};
+		let precision_zoom_factor = precision_factor(
+			mouse_config.precision_zoom_factor,
+			DEFAULT_PRECISION_ZOOM_FACTOR,
+			"precision_zoom_factor",
+		);
+		let precision_pan_factor = precision_factor(
+			mouse_config.precision_pan_factor,
+			DEFAULT_PRECISION_PAN_FACTOR,
+			"precision_pan_factor",
+		);
+
 		let mut data = PictureWidgetData {
 			placement: Default::default(),
 			drawn_bounds: Default::default(),
@@ -483,24 +3354,84 @@ impl PictureWidget {
 			hover: false,
 			configuration,
 			cache,
-			playback_manager: PlaybackManager::new(),
+			playback_manager,
 			clipboard_handler: Some(ClipboardHandler::new()),
 			clipboard_request_was_pending: false,
+			copy_view_area,
+			copy_view_requested: false,
+			command_runner: CommandRunner::new(),
 			render_validity: Default::default(),
 
 			program,
+			difference_program,
 			bright_shade: 0.95,
 			img_texel_size: 0.0,
+			dpi_scale: 1.0,
 			scaling,
 			img_pos: Default::default(),
 			antialiasing,
+			curr_folder: None,
+			global_view_settings: None,
+			startup_fit,
+			sort_order,
+			no_upscale_on_fit,
+			small_image_policy,
+			small_image_threshold,
+			continuous_fit,
+			fit_settle_time: None,
+			startup_fit_path: None,
 			hor_pan_input: MovementDir::None,
 			ver_pan_input: MovementDir::None,
 			zoom_input: MovementDir::None,
-			hor_pan_vel: 0.0,
-			ver_pan_vel: 0.0,
+			pan_vel: Vector2::zero(),
+			edge_autopan: mouse_config.edge_autopan.unwrap_or(false),
+			edge_margin: mouse_config.edge_margin.unwrap_or(DEFAULT_EDGE_MARGIN).max(1.0),
+			edge_scroll_navigate: mouse_config.edge_scroll_navigate.unwrap_or(false),
+			edge_overscroll: 0.0,
+			edge_overscroll_time: Instant::now(),
+			edge_scroll_entry: None,
+			pan_key_speed,
+			pan_key_accel,
 			zoom_vel: 0.0,
+			zoom_hold_time: 0.0,
+			zoom_key_accel,
+			zoom_key_max_rate,
+			zoom_snap,
+			zoom_snap_tolerance,
+			zoom_snap_targets,
+			letterbox_color,
+			fit_padding,
+			fit_padding_enabled: true,
+			rotation: 0.0,
+			rotation_path: None,
+			rotation_step,
+			rotation_fill,
+			straighten: None,
+			magnifier,
+			magnifier_shown: false,
+			composition_overlay,
+			composition_grid_cells,
+			overlay_color,
+			confirm_quit,
+			quit_requested_time: None,
+			zoom_snapped_to: None,
 			last_click_time: Instant::now() - Duration::from_secs(10),
+			click_start_pos: Default::default(),
+			pan_button,
+			precision_modifier: mouse_config.precision_modifier.unwrap_or_default(),
+			precision_zoom_factor,
+			precision_pan_factor,
+			hand_tool: None,
+			mode_cursors: mouse_config.mode_cursors.unwrap_or(true),
+			cursor_mode: CursorMode::Default,
+			double_click_time,
+			double_click_action,
+			middle_click_action,
+			tap_zones: tap_zones(&mouse_config),
+			unsupported_file_action,
+			auto_advance_on,
+			auto_advance_direction,
+			copy_advance_path: None,
 			last_mouse_pos: Default::default(),
 			panning_2d: false,
 			panning_vert: false,
@@ -510,8 +3441,72 @@ impl PictureWidget {
 			first_draw: true,
 			next_update: NextUpdate::Latest,
 			bottom_bar,
+			status_bar,
 			left_to_pan_hint,
 			copy_notifications,
+			notification,
+			pending_group_jump: None,
+			fast_nav: FastNavigation::new(nav_min_interval),
+			find_duplicates,
+			pending_duplicate_jump: false,
+			duplicate_badge,
+			eager_thumbnail,
+			low_memory_max_side,
+			full_resolution_path: None,
+			next_memory_check: Instant::now(),
+			thumbnail_badge,
+			on_current_deleted,
+			checked_file: None,
+			next_file_check: Instant::now(),
+			idle_action,
+			idle_timeout,
+			last_input: Instant::now(),
+			idle_action_taken: false,
+			motion_clip: None,
+			missing_file: None,
+			warning_badge,
+			error_card,
+			edited_badge,
+			scale_badge,
+			scale_bar,
+			scale_bar_length: None,
+			measure_unit,
+			resolution: None,
+			show_coordinates,
+			measure: None,
+			hover_preview,
+			watermark,
+			deleted_items,
+			empty_state,
+			frame_scrubber,
+			scrubbing: false,
+			export_watermark,
+			edge_flash: None,
+			previous_image: None,
+			current_image: None,
+			show_previous: false,
+			reference_image: None,
+			reference_blend: ReferenceBlend::Opacity,
+			reference_opacity,
+			clipping_warning: false,
+			clip_thresholds,
+			focus_peaking: false,
+			peaking_sensitivity,
+			peaking_color,
+			channel_view: ChannelView::Rgb,
+			two_page,
+			reading_direction,
+			cover_is_single,
+			second_page: None,
+			undo_stack: Default::default(),
+			view_edits: Default::default(),
+			triage_folders,
+			on_window_request: None,
+			preview_path: None,
+			mirrored_path: None,
+			linked: false,
+			read_only,
+			open_file,
 			window: Rc::downgrade(window),
 		};
 		data.update_scaling_buttons();
@@ -520,6 +3515,84 @@ impl PictureWidget {
 
 	add_common_widget_functions!(data);
 
+	/// Called when an action asks for a window, like `open_in_new_window`
+	pub fn set_on_window_request<F: Fn(WindowRequest) + 'static>(&self, callback: F) {
+		self.data.borrow_mut().on_window_request = Some(Box::new(callback));
+	}
+
+	/// The image and the view that a mirror of this widget shows
+	pub fn mirror_state(&self, mirror_view: MirrorView) -> MirrorState {
+		let borrowed = self.data.borrow();
+		let path = match borrowed.playback_manager.shown_file_path() {
+			LoadedImgPath::Loaded(path) => Some(path.clone()),
+			_ => None,
+		};
+		let (scaling, view) = match (mirror_view, borrowed.scaling) {
+			(MirrorView::Same, scaling) => {
+				let offset = borrowed.img_pos - borrowed.drawn_bounds.size * 0.5f32;
+				(scaling, Some((borrowed.img_texel_size, offset)))
+			}
+			(MirrorView::Fit, ScalingMode::Fixed) => (ScalingMode::FitMin, None),
+			(MirrorView::Fit, scaling) => (scaling, None),
+		};
+		MirrorState { path, scaling, rotation: borrowed.rotation, view }
+	}
+
+	/// Shows what `state` describes, which comes from `mirror_state` of another widget
+	pub fn apply_mirror_state(&self, state: &MirrorState) {
+		let mut borrowed = self.data.borrow_mut();
+		if state.path.is_some() && state.path != borrowed.mirrored_path {
+			borrowed.mirrored_path = state.path.clone();
+			let path = state.path.clone().unwrap();
+			borrowed.playback_manager.request_load(LoadRequest::FilePath(path));
+			borrowed.render_validity.invalidate();
+		}
+		let img_pos = state.view.map(|(_, offset)| borrowed.drawn_bounds.size * 0.5f32 + offset);
+		let texel_size = state.view.map(|(texel_size, _)| texel_size);
+		let changed = borrowed.scaling != state.scaling
+			|| borrowed.rotation != state.rotation
+			|| texel_size.is_some_and(|texel_size| texel_size != borrowed.img_texel_size)
+			|| img_pos.is_some_and(|img_pos| img_pos.vec != borrowed.img_pos.vec);
+		if !changed {
+			return;
+		}
+		borrowed.scaling = state.scaling;
+		borrowed.rotation = state.rotation;
+		if let (Some(texel_size), Some(img_pos)) = (texel_size, img_pos) {
+			borrowed.img_texel_size = texel_size;
+			borrowed.img_pos = img_pos;
+		}
+		borrowed.render_validity.invalidate();
+	}
+
+	/// A linked widget asks for `WindowRequest::LinkedStep` whenever it goes to the next or the
+	/// previous image
+	pub fn set_linked(&self, linked: bool) {
+		self.data.borrow_mut().linked = linked;
+	}
+
+	/// Goes to the next or the previous image, like another widget that this one is linked to
+	pub fn step_linked(&self, forward: bool) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.step_pages(forward);
+		borrowed.render_validity.invalidate();
+	}
+
+	/// The zoom and the position of the image that linked windows share, see `link_view`
+	pub fn linked_view(&self) -> MirrorState {
+		MirrorState { path: None, ..self.mirror_state(MirrorView::Same) }
+	}
+
+	pub fn show_notification(&self, text: &str) {
+		self.data.borrow_mut().notification.show(text, NOTIFICATION_DURATION);
+	}
+
+	pub fn set_theme(&self, theme: Theme) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.empty_state.set_theme(theme);
+		borrowed.render_validity.invalidate();
+	}
+
 	pub fn set_bright_shade(&self, shade: f32) {
 		let mut borrowed = self.data.borrow_mut();
 		borrowed.bright_shade = shade;
@@ -542,50 +3615,430 @@ impl PictureWidget {
 		borrowed.render_validity.invalidate();
 	}
 
-	pub fn jump_to_path<P: Into<PathBuf>>(&self, path: P) {
+	/// Shows the frame at `index` of the animation, which is paused until the frame scrubber is
+	/// let go
+	pub fn seek_frame(&self, index: u32) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.playback_manager.seek_frame(index as usize);
+		borrowed.scrubbing = true;
+		borrowed.render_validity.invalidate();
+	}
+
+	/// Loads the image at `path`, and starts the presentation if `behavior` says so.
+	pub fn jump_to_path<P: Into<PathBuf>>(&self, path: P, behavior: OpenBehavior) {
 		let mut borrowed = self.data.borrow_mut();
-		borrowed.playback_manager.request_load(LoadRequest::FilePath(path.into()));
+		let Some(path) = borrowed.unsupported_file_target(path.into()) else {
+			return;
+		};
+		let is_file = !path.is_dir() && !is_archive(&path);
+		let single_image = is_file && borrowed.open_file == OpenFile::SingleImage;
+		borrowed.playback_manager.set_single_image(single_image);
+		borrowed.playback_manager.request_load(LoadRequest::FilePath(path));
+		if behavior == OpenBehavior::Slideshow {
+			borrowed.playback_manager.start_presentation();
+		}
 		borrowed.render_validity.invalidate();
 	}
 
 	fn handle_key_input(&self, input_key: &str, modifiers: ModifiersState) {
+		let configuration = self.data.borrow().configuration.clone();
+		self.handle_actions(|action| {
+			action_triggered(&configuration, action, input_key, modifiers)
+		});
+
+		let mut borrowed = self.data.borrow_mut();
+		let commands = triggered_commands(&borrowed.configuration.borrow(), input_key, modifiers);
+		// The commands can do anything with the files
+		if commands.is_empty() || borrowed.refuse_read_only() {
+			return;
+		}
+		if let LoadedImgPath::Loaded(img_path) = borrowed.playback_manager.shown_file_path().clone()
+		{
+			if let Some(folder_path) = img_path.parent() {
+				let img_and_folder = (img_path.to_str(), folder_path.to_str());
+				if let (Some(img_path), Some(folder_path)) = img_and_folder {
+					for command in commands.iter() {
+						borrowed.command_runner.run(command, img_path, folder_path);
+					}
+				} else {
+					log::error!("Could not convert the image path to utf8. Path: '{:?}'", img_path);
+				}
+			} else {
+				log::error!("Could not get parent folder for the image path {:?}", img_path);
+			}
+		}
+	}
+
+	/// Dragging with the pan button pans the image, and so does dragging with the left button
+	/// while the pan key is held
+	fn start_or_stop_drag_pan(
+		&self,
+		state: ElementState,
+		button: MouseButton,
+		cursor_pos: LogicalVector,
+	) {
+		let mut borrowed = self.data.borrow_mut();
+		let pressed = state == ElementState::Pressed;
+		if pressed {
+			borrowed.click_start_pos = cursor_pos;
+		}
+		let hand_tool = borrowed.hand_tool.is_some();
+		if button != borrowed.pan_button && !(hand_tool && button == MouseButton::Left) {
+			return;
+		}
+		// The left button draws the line while straightening or measuring
+		let drawing = borrowed.straighten.is_some() || borrowed.measure.is_some();
+		if button == MouseButton::Left && drawing {
+			return;
+		}
+		if pressed && borrowed.hover {
+			borrowed.panning_2d = true;
+			if let Some(hand_tool) = borrowed.hand_tool.as_mut() {
+				hand_tool.used = true;
+			}
+		} else if !pressed {
+			borrowed.panning_2d = false;
+		}
+	}
+
+	fn handle_mouse_button(
+		&self,
+		state: ElementState,
+		button: MouseButton,
+		cursor_pos: LogicalVector,
+	) {
+		// Releasing a button after dragging the image doesn't count as a click.
+		let was_dragged = |borrowed: &PictureWidgetData| {
+			(cursor_pos - borrowed.click_start_pos).vec.magnitude() >= CLICK_MAX_DRAG
+		};
+		if button == MouseButton::Left {
+			let mut borrowed = self.data.borrow_mut();
+			if state == ElementState::Released && borrowed.scrubbing {
+				borrowed.scrubbing = false;
+				borrowed.playback_manager.resume_animation();
+			}
+			// The frame scrubber takes the clicks on it
+			let on_scrubber = borrowed.frame_scrubber.covers(cursor_pos, &borrowed.drawn_bounds);
+			if state == ElementState::Pressed && on_scrubber {
+				return;
+			}
+		}
+		match button {
+			MouseButton::Left if self.data.borrow().straighten.is_some() => {
+				let mut borrowed = self.data.borrow_mut();
+				let pressed = state == ElementState::Pressed;
+				let line = borrowed.straighten.unwrap();
+				if pressed && borrowed.hover {
+					let line = StraightenLine { start: Some(cursor_pos), end: cursor_pos };
+					borrowed.straighten = Some(line);
+				} else if !pressed && line.start.is_some() {
+					let angle = StraightenLine { end: cursor_pos, ..line }.level_angle();
+					borrowed.straighten = None;
+					match angle {
+						Some(angle) => {
+							let rotation = borrowed.rotation + angle;
+							borrowed.set_rotation(rotation);
+						}
+						None => borrowed
+							.notification
+							.show("Straightening cancelled", NOTIFICATION_DURATION),
+					}
+				}
+				borrowed.render_validity.invalidate();
+			}
+			MouseButton::Left if self.data.borrow().measure.is_some() => {
+				let mut borrowed = self.data.borrow_mut();
+				if state == ElementState::Pressed && borrowed.hover {
+					borrowed.add_measure_point(cursor_pos);
+				}
+			}
+			MouseButton::Left => {
+				let mut borrowed = self.data.borrow_mut();
+				let mut double_clicked = false;
+				let mut tapped = None;
+				if state == ElementState::Pressed {
+					// While the pan key is held, the left button only pans
+					let on_panel =
+						borrowed.deleted_items.covers(cursor_pos, &borrowed.drawn_bounds);
+					if borrowed.hover && borrowed.hand_tool.is_none() && !on_panel {
+						borrowed.click = true;
+					}
+				} else {
+					let was_click = borrowed.click;
+					borrowed.click = false;
+					if was_click && borrowed.hover && !was_dragged(&borrowed) {
+						tapped = borrowed.tap_zone_action(cursor_pos);
+					}
+					// Every click in a tap zone is a tap, so a quick second tap doesn't count as a
+					// double-click
+					if was_click && borrowed.hover && !was_dragged(&borrowed) && tapped.is_none() {
+						let now = Instant::now();
+						let duration_since_last_click =
+							now.duration_since(borrowed.last_click_time);
+						if duration_since_last_click < borrowed.double_click_time {
+							double_clicked = true;
+							// So that a third click doesn't count as another double-click
+							borrowed.last_click_time = now - Duration::from_secs(10);
+						} else {
+							borrowed.last_click_time = now;
+						}
+					}
+				}
+				borrowed.render_validity.invalidate();
+				let action = borrowed.double_click_action.clone();
+				drop(borrowed);
+				if let (true, Some(action)) = (double_clicked, action) {
+					self.handle_actions(|name| name == action);
+				}
+				if let Some(action) = tapped {
+					self.handle_actions(|name| name == action);
+				}
+			}
+			MouseButton::Middle => {
+				let borrowed = self.data.borrow();
+				let dragged = borrowed.pan_button == MouseButton::Middle && was_dragged(&borrowed);
+				if state == ElementState::Released && borrowed.hover && !dragged {
+					let action = borrowed.middle_click_action.clone();
+					drop(borrowed);
+					if let Some(action) = action {
+						self.handle_actions(|name| name == action);
+					}
+				}
+			}
+			MouseButton::Right => {
+				let borrowed = self.data.borrow();
+				// The hint is only right when the left button pans
+				if borrowed.pan_button == MouseButton::Left {
+					let pressed = state == ElementState::Pressed;
+					borrowed.left_to_pan_hint.set_visible(pressed);
+				}
+			}
+			_ => {}
+		}
+	}
+
+	/// Handles the pan key, and returns true if the key press or release was used up by it.
+	///
+	/// Holding the key turns the left button into a pan button. Tapping the key without
+	/// dragging carries out the other actions bound to it once the key is released.
+	fn handle_pan_key(&self, input_key: &str, modifiers: ModifiersState, pressed: bool) -> bool {
+		let mut borrowed = self.data.borrow_mut();
+		if !pressed {
+			match borrowed.hand_tool.take() {
+				Some(hand_tool) if hand_tool.key == input_key => {
+					drop(borrowed);
+					if !hand_tool.used {
+						self.handle_key_input(&hand_tool.key, hand_tool.modifiers);
+					}
+					return true;
+				}
+				other => borrowed.hand_tool = other,
+			}
+			return false;
+		}
+		if borrowed.hand_tool.is_some() {
+			// A repeat of the held key
+			return borrowed.hand_tool.as_ref().is_some_and(|hand_tool| hand_tool.key == input_key);
+		}
+		if !action_triggered(&borrowed.configuration, PAN_NAME, input_key, modifiers) {
+			return false;
+		}
+		borrowed.hand_tool =
+			Some(HandTool { key: input_key.to_owned(), modifiers, used: borrowed.panning_2d });
+		true
+	}
+
+	/// Carries out every action for which `is_triggered` returns true.
+	/// The argument of `is_triggered` is the name of an action.
+	fn handle_actions(&self, is_triggered: impl Fn(&str) -> bool) {
 		let mut borrowed = self.data.borrow_mut();
 		macro_rules! triggered {
 			($action_name:ident) => {
-				action_triggered(&borrowed.configuration, $action_name, input_key, modifiers)
+				is_triggered($action_name)
 			};
 		}
+		if triggered!(QUIT_NAME) {
+			borrowed.request_quit();
+		}
+		if triggered!(NEXT_DUPLICATE_NAME) {
+			borrowed.find_duplicates = true;
+			borrowed.pending_duplicate_jump = true;
+			if borrowed.playback_manager.duplicates().is_none() {
+				borrowed
+					.notification
+					.show("Looking for similar images...", Duration::from_secs(30));
+			}
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(NEXT_GROUP_NAME) || triggered!(PREV_GROUP_NAME) {
+			let jump = if triggered!(NEXT_GROUP_NAME) { GroupJump::Next } else { GroupJump::Prev };
+			borrowed.pending_group_jump = Some(jump);
+			if borrowed.playback_manager.capture_dates().is_none() {
+				borrowed.notification.show("Reading capture dates...", Duration::from_secs(30));
+			}
+			borrowed.render_validity.invalidate();
+		}
 		if triggered!(TOGGLE_FULLSCREEN_NAME) {
 			if let Some(window) = borrowed.window.upgrade() {
 				let fullscreen = !window.fullscreen();
 				window.set_fullscreen(fullscreen);
-				borrowed.bottom_bar.set_visible_if_should_show(!fullscreen);
+				borrowed.bottom_bar.set_fullscreen(fullscreen);
 			}
 		}
-		if triggered!(ESCAPE_NAME) {
+		if triggered!(FULLSCREEN_NEXT_MONITOR_NAME) {
 			if let Some(window) = borrowed.window.upgrade() {
+				match window.fullscreen_next_monitor() {
+					Some(name) => {
+						borrowed.bottom_bar.set_fullscreen(true);
+						borrowed.notification.show(&name, NOTIFICATION_DURATION);
+					}
+					None => borrowed.notification.show("No monitors found", NOTIFICATION_DURATION),
+				}
+			}
+		}
+		if triggered!(TOGGLE_STATUS_BAR_NAME) {
+			borrowed.status_bar.toggle_visible();
+		}
+		if triggered!(TOGGLE_TOOLBAR_NAME) {
+			let visible = borrowed.bottom_bar.toggle_visible();
+			let fullscreen = borrowed.window.upgrade().is_some_and(|w| w.fullscreen());
+			if !fullscreen {
+				borrowed.cache.lock().unwrap().window.toolbar_visible = visible;
+			}
+		}
+		if triggered!(ESCAPE_NAME) {
+			if borrowed.straighten.is_some() {
+				borrowed.toggle_straighten();
+			} else if borrowed.measure.is_some() {
+				borrowed.toggle_measure();
+			} else if let Some(window) = borrowed.window.upgrade() {
 				if window.fullscreen() {
 					window.set_fullscreen(false);
-					borrowed.bottom_bar.set_visible_if_should_show(true);
+					borrowed.bottom_bar.set_fullscreen(false);
 				} else {
-					request_exit();
+					borrowed.request_quit();
 				}
 			}
 		}
-		if triggered!(PLAY_ANIM_NAME) {
-			match borrowed.playback_manager.playback_state() {
-				PlaybackState::Forward => borrowed.playback_manager.pause_playback(),
-				_ => borrowed.playback_manager.start_playback_forward(),
-			}
+		if triggered!(PLAY_ANIM_NAME) {
+			match borrowed.playback_manager.playback_state() {
+				PlaybackState::Forward => borrowed.playback_manager.pause_playback(),
+				_ => borrowed.playback_manager.start_playback_forward(),
+			}
+		}
+		for (action, forward) in [(IMG_PREV_NAME, false), (IMG_NEXT_NAME, true)] {
+			if !triggered!(action) {
+				continue;
+			}
+			borrowed.step_pages(forward);
+			if let (true, Some(callback)) = (borrowed.linked, &borrowed.on_window_request) {
+				callback(WindowRequest::LinkedStep { forward });
+			}
+		}
+		if triggered!(TOGGLE_TWO_PAGE_NAME) {
+			borrowed.toggle_two_page();
+		}
+		if triggered!(UI_SCALE_UP_NAME) {
+			borrowed.step_ui_scale(UI_SCALE_STEP);
+		}
+		if triggered!(UI_SCALE_DOWN_NAME) {
+			borrowed.step_ui_scale(-UI_SCALE_STEP);
+		}
+		if triggered!(ROTATE_RIGHT_NAME) {
+			let rotation = borrowed.rotation + 90.0;
+			borrowed.set_rotation(rotation);
+		}
+		if triggered!(ROTATE_LEFT_NAME) {
+			let rotation = borrowed.rotation - 90.0;
+			borrowed.set_rotation(rotation);
+		}
+		if triggered!(ROTATE_STEP_CW_NAME) {
+			let rotation = borrowed.rotation + borrowed.rotation_step;
+			borrowed.set_rotation(rotation);
+		}
+		if triggered!(ROTATE_STEP_CCW_NAME) {
+			let rotation = borrowed.rotation - borrowed.rotation_step;
+			borrowed.set_rotation(rotation);
+		}
+		if triggered!(RESET_ROTATION_NAME) {
+			borrowed.set_rotation(0.0);
+		}
+		if triggered!(COMPOSITION_OVERLAY_NAME) {
+			borrowed.cycle_composition_overlay();
+		}
+		if triggered!(STRAIGHTEN_NAME) {
+			borrowed.toggle_straighten();
+		}
+		if triggered!(MEASURE_NAME) {
+			borrowed.toggle_measure();
+		}
+		if triggered!(TOGGLE_COORDINATES_NAME) {
+			borrowed.show_coordinates = !borrowed.show_coordinates;
+			let text = if borrowed.show_coordinates { "Coordinates on" } else { "Coordinates off" };
+			borrowed.notification.show(text, NOTIFICATION_DURATION);
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(TOGGLE_SCALE_BAR_NAME) {
+			borrowed.scale_bar = !borrowed.scale_bar;
+			let text = if borrowed.scale_bar { "Scale bar on" } else { "Scale bar off" };
+			borrowed.notification.show(text, NOTIFICATION_DURATION);
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(TOGGLE_FRAME_SCRUBBER_NAME) {
+			let text = if borrowed.frame_scrubber.toggle() {
+				"Frame scrubber on"
+			} else {
+				"Frame scrubber off"
+			};
+			borrowed.notification.show(text, NOTIFICATION_DURATION);
+		}
+		if triggered!(TOGGLE_FIT_PADDING_NAME) {
+			borrowed.toggle_fit_padding();
+		}
+		if triggered!(AB_FLICKER_NAME) {
+			borrowed.toggle_ab_flicker();
 		}
-		if triggered!(IMG_PREV_NAME) {
-			borrowed.playback_manager.request_load(LoadRequest::LoadPrevious);
+		if triggered!(TOGGLE_CLIPPING_WARNING_NAME) {
+			borrowed.clipping_warning = !borrowed.clipping_warning;
+			let text = if borrowed.clipping_warning {
+				"Clipping warning on"
+			} else {
+				"Clipping warning off"
+			};
+			borrowed.notification.show(text, NOTIFICATION_DURATION);
 			borrowed.render_validity.invalidate();
 		}
-		if triggered!(IMG_NEXT_NAME) {
-			borrowed.playback_manager.request_load(LoadRequest::LoadNext);
+		if triggered!(CYCLE_CHANNEL_VIEW_NAME) {
+			borrowed.cycle_channel_view();
+		}
+		if triggered!(TOGGLE_FOCUS_PEAKING_NAME) {
+			borrowed.focus_peaking = !borrowed.focus_peaking;
+			let text =
+				if borrowed.focus_peaking { "Focus peaking on" } else { "Focus peaking off" };
+			borrowed.notification.show(text, NOTIFICATION_DURATION);
 			borrowed.render_validity.invalidate();
 		}
+		if triggered!(PEAKING_SENSITIVITY_UP_NAME) {
+			borrowed.change_peaking_sensitivity(PEAKING_SENSITIVITY_STEP);
+		}
+		if triggered!(PEAKING_SENSITIVITY_DOWN_NAME) {
+			borrowed.change_peaking_sensitivity(-PEAKING_SENSITIVITY_STEP);
+		}
+		if triggered!(SET_REFERENCE_NAME) {
+			borrowed.set_reference();
+		}
+		if triggered!(CLEAR_REFERENCE_NAME) {
+			borrowed.clear_reference();
+		}
+		if triggered!(REFERENCE_DIFFERENCE_NAME) {
+			borrowed.toggle_reference_difference();
+		}
+		if triggered!(REFERENCE_OPACITY_UP_NAME) {
+			borrowed.change_reference_opacity(REFERENCE_OPACITY_STEP);
+		}
+		if triggered!(REFERENCE_OPACITY_DOWN_NAME) {
+			borrowed.change_reference_opacity(-REFERENCE_OPACITY_STEP);
+		}
 		if triggered!(IMG_FIT_NAME) {
 			borrowed.set_img_size_to_fit(true);
 		}
@@ -595,12 +4048,21 @@ impl PictureWidget {
 		if triggered!(IMG_ORIG_NAME) {
 			borrowed.set_img_size_to_orig();
 		}
+		if triggered!(RESET_VIEW_NAME) {
+			borrowed.reset_view();
+		}
 		if triggered!(TOGGLE_ANTIALIAS_NAME) {
 			borrowed.toggle_antialias();
 		}
 		if triggered!(SET_AUTOMATIC_ANTIALIAS_NAME) {
 			borrowed.set_automatic_antialias();
 		}
+		if triggered!(SAVE_FOLDER_SETTINGS_NAME) {
+			borrowed.save_folder_view_settings();
+		}
+		if triggered!(CLEAR_FOLDER_SETTINGS_NAME) {
+			borrowed.clear_folder_view_settings();
+		}
 		if triggered!(PLAY_PRESENT_NAME) {
 			match borrowed.playback_manager.playback_state() {
 				PlaybackState::Present => borrowed.playback_manager.pause_playback(),
@@ -615,25 +4077,118 @@ impl PictureWidget {
 			}
 			borrowed.render_validity.invalidate();
 		}
-		if triggered!(IMG_DEL_NAME) {
-			if let LoadedImgPath::Loaded(path) = borrowed.playback_manager.shown_file_path() {
-				if let Err(e) = trash::delete(path) {
-					eprintln!("Error while moving file '{:?}' to trash: {:?}", path, e);
-				}
+		if triggered!(IMG_DEL_NAME) && !borrowed.refuse_read_only() {
+			if let LoadedImgPath::Loaded(path) = borrowed.playback_manager.shown_file_path().clone()
+			{
+				borrowed.deleted_items.push(&path);
+				let deleted = match move_to_trash(&path) {
+					Ok(undo_action) => {
+						if let Some(undo_action) = undo_action {
+							borrowed.undo_stack.push(undo_action);
+						}
+						true
+					}
+					Err(e) => {
+						eprintln!("Error while moving file '{:?}' to trash: {:?}", path, e);
+						borrowed.deleted_items.remove(&path);
+						false
+					}
+				};
 				if let Err(e) = borrowed.playback_manager.update_directory() {
 					eprintln!("Error while updating directory {:?}", e);
 				}
+				if deleted {
+					borrowed.auto_advance(AutoAdvanceTrigger::Delete, &path);
+				}
 				borrowed.render_validity.invalidate();
 			}
 		}
+		if triggered!(UNDO_NAME) {
+			borrowed.undo();
+		}
+		if triggered!(REDO_NAME) {
+			borrowed.redo();
+		}
+		if triggered!(CLEAR_DISK_CACHE_NAME) {
+			let freed = disk_cache::clear();
+			let text = format!("Cleared the image cache, {:.1} MB freed", freed as f64 / 1e6);
+			borrowed.notification.show(&text, NOTIFICATION_DURATION);
+		}
+		if triggered!(TOGGLE_LOW_MEMORY_NAME) {
+			let low_memory = !borrowed.playback_manager.low_memory();
+			let max_side = low_memory.then_some(borrowed.low_memory_max_side);
+			borrowed.playback_manager.set_low_memory(max_side);
+			borrowed.full_resolution_path = None;
+			let text = if low_memory { "Low memory mode on" } else { "Low memory mode off" };
+			borrowed.notification.show(text, NOTIFICATION_DURATION);
+		}
+		if triggered!(TOGGLE_IGNORED_FILES_NAME) {
+			let show = !borrowed.playback_manager.show_ignored();
+			if let Err(e) = borrowed.playback_manager.set_show_ignored(show) {
+				eprintln!("Error while updating directory {:?}", e);
+			}
+			let text = if show { "Showing the ignored files" } else { "Hiding the ignored files" };
+			borrowed.notification.show(text, NOTIFICATION_DURATION);
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(PLAY_MOTION_PHOTO_NAME) {
+			borrowed.play_motion_photo();
+		}
+		if triggered!(TOGGLE_EMBEDDED_PREVIEW_NAME) {
+			let enabled = !borrowed.playback_manager.embedded_preview();
+			borrowed.playback_manager.set_embedded_preview(enabled);
+			let text = if enabled { "Embedded previews on" } else { "Embedded previews off" };
+			borrowed.notification.show(text, NOTIFICATION_DURATION);
+		}
+		for (index, (action, set_action)) in
+			[(TRIAGE_A_NAME, SET_TRIAGE_A_NAME), (TRIAGE_B_NAME, SET_TRIAGE_B_NAME)]
+				.iter()
+				.enumerate()
+		{
+			if triggered!(action) {
+				borrowed.triage(index);
+			}
+			if triggered!(set_action) {
+				borrowed.set_triage_folder(index);
+			}
+		}
+		if let Some(callback) = &borrowed.on_window_request {
+			if let (LoadedImgPath::Loaded(path), true) =
+				(borrowed.playback_manager.shown_file_path(), triggered!(OPEN_IN_NEW_WINDOW_NAME))
+			{
+				callback(WindowRequest::OpenInNewWindow(path.clone()));
+			}
+			if triggered!(TOGGLE_MIRROR_DISPLAY_NAME) {
+				callback(WindowRequest::ToggleMirrorDisplay);
+			}
+			if triggered!(LINK_WINDOWS_NAME) {
+				callback(WindowRequest::LinkWindows);
+			}
+			if triggered!(UNLINK_WINDOWS_NAME) {
+				callback(WindowRequest::UnlinkWindows);
+			}
+		}
+		if triggered!(TOGGLE_DELETED_ITEMS_NAME) {
+			borrowed.deleted_items.toggle();
+			borrowed.render_validity.invalidate();
+		}
+		if triggered!(TOGGLE_WATERMARK_NAME) && borrowed.watermark.toggle() {
+			let text = if borrowed.watermark.enabled() { "Watermark on" } else { "Watermark off" };
+			borrowed.notification.show(text, NOTIFICATION_DURATION);
+		}
+		if triggered!(COPY_VIEW_NAME) && borrowed.clipboard_handler.is_some() {
+			borrowed.copy_view_requested = true;
+			borrowed.render_validity.invalidate();
+		}
 		if triggered!(IMG_COPY_NAME) {
 			if let LoadedImgPath::Loaded(path) = borrowed.playback_manager.shown_file_path().clone()
 			{
 				let request_started;
 				if let Some(clipboard_handler) = &mut borrowed.clipboard_handler {
 					request_started = true;
-					clipboard_handler.request_copy(path);
+					clipboard_handler.request_copy(path.clone());
 					borrowed.copy_notifications.set_started();
+					borrowed.copy_advance_path = Some(path);
 				} else {
 					request_started = false;
 				}
@@ -642,24 +4197,6 @@ impl PictureWidget {
 				}
 			}
 		}
-		if let LoadedImgPath::Loaded(img_path) = borrowed.playback_manager.shown_file_path() {
-			if let Some(folder_path) = img_path.parent() {
-				let img_and_folder = (img_path.to_str(), folder_path.to_str());
-				if let (Some(img_path), Some(folder_path)) = img_and_folder {
-					execute_triggered_commands(
-						borrowed.configuration.clone(),
-						input_key,
-						modifiers,
-						img_path,
-						folder_path,
-					);
-				} else {
-					log::error!("Could not convert the image path to utf8. Path: '{:?}'", img_path);
-				}
-			} else {
-				log::error!("Could not get parent folder for the image path {:?}", img_path);
-			}
-		}
 	}
 }
 
@@ -681,7 +4218,15 @@ impl Widget for PictureWidget {
 		let prev_texture = data.playback_manager.image_texture();
 		data.next_update = data.playback_manager.update_image(window);
 		let new_texture = data.playback_manager.image_texture();
-		let curr_file_index = data.playback_manager.current_file_index();
+		data.update_folder_view_settings();
+		data.track_shown_image();
+		data.update_warnings();
+		data.update_second_page(window);
+		data.update_fast_nav();
+		let curr_file_index = match data.fast_nav.target() {
+			Some((target, _)) => Some(*target),
+			None => data.playback_manager.current_file_index(),
+		};
 		let curr_dir_len = data.playback_manager.current_dir_len();
 		if let (Some(curr_file_index), Some(curr_dir_len)) = (curr_file_index, curr_dir_len) {
 			// dbg!(curr_file_index);
@@ -690,11 +4235,13 @@ impl Widget for PictureWidget {
 		}
 		//data.slider.set_step_bg(data.playback_manager.cached_from_dir());
 		let playback_state = data.playback_manager.playback_state();
-		data.set_window_title_filename(
-			window,
-			playback_state,
-			data.playback_manager.shown_file_path(),
-		);
+		let title_path = match data.fast_nav.target() {
+			Some((_, path)) => LoadedImgPath::Loaded(path.clone()),
+			None => data.playback_manager.shown_file_path().clone(),
+		};
+		data.set_window_title_filename(window, playback_state, &title_path);
+		data.update_low_memory(prev_texture.as_ref());
+		data.keep_preview_size(prev_texture.as_ref(), new_texture.as_ref());
 		if prev_texture.is_none() != new_texture.is_none() {
 			data.render_validity.invalidate();
 		} else if let (Some(prev_tex), Some(new_tex)) = (prev_texture, new_texture) {
@@ -707,7 +4254,12 @@ impl Widget for PictureWidget {
 			let request_pending = clipboard_result.is_none();
 			if data.clipboard_request_was_pending != request_pending {
 				match clipboard_result {
-					Some(succeeded) => data.copy_notifications.set_finished(succeeded),
+					Some(succeeded) => {
+						data.copy_notifications.set_finished(succeeded);
+						if let Some(path) = data.copy_advance_path.take().filter(|_| succeeded) {
+							data.auto_advance(AutoAdvanceTrigger::Copy, &path);
+						}
+					}
 					None => data.copy_notifications.set_started(),
 				}
 				data.clipboard_request_was_pending = request_pending;
@@ -716,31 +4268,98 @@ impl Widget for PictureWidget {
 				data.next_update = data.next_update.aggregate(NextUpdate::WaitUntil(next_update));
 			}
 		}
-		if data.zoom_input.moving() || data.hor_pan_input.moving() || data.ver_pan_input.moving() {
+		if data.camera_moving() {
 			data.render_validity.invalidate();
 			data.next_update = NextUpdate::Soonest;
 		}
+		data.update_group_jump();
+		data.update_duplicates();
+		data.update_sorting();
+		data.update_thumbnails();
+		data.update_hover_preview();
+		data.update_watermark();
+		data.update_deleted_items();
+		data.update_empty_state();
+		data.update_frame_scrubber();
+		data.update_fit_settle();
+		data.check_current_file();
+		data.update_idle();
+		data.update_commands();
+		if let Some(hide_time) = data.bottom_bar.update_reveal() {
+			data.next_update = data.next_update.aggregate(NextUpdate::WaitUntil(hide_time));
+		}
+		if data.edge_flash.is_some() {
+			if data.edge_flash_opacity().is_some() {
+				data.next_update = NextUpdate::Soonest;
+			} else {
+				data.edge_flash = None;
+			}
+			data.render_validity.invalidate();
+		}
 		let next_copy_noti_update = data.copy_notifications.update();
 		data.next_update = data.next_update.aggregate(next_copy_noti_update);
+		let next_noti_update = data.notification.update();
+		data.next_update = data.next_update.aggregate(next_noti_update);
 		data.next_update
 	}
 
 	fn draw(&self, target: &mut Frame, context: &DrawContext) -> Result<NextUpdate, WidgetError> {
 		let texture;
+		let copy_view;
+		let mut view_image = None;
 		{
 			let mut data = self.data.borrow_mut();
 			if !data.visible {
 				return Ok(data.next_update);
 			}
+			copy_view = std::mem::take(&mut data.copy_view_requested);
 			data.update_image_transform(context.dpi_scale_factor);
-			data.apply_camera_movement(context.dpi_scale_factor);
+			data.apply_camera_movement();
+			// The status bar is drawn after the picture, so it shows the zoom of this frame
+			data.update_status_bar();
+			data.update_scale_bar();
 			texture = data.get_texture();
 		}
 		if let Some(texture) = texture {
 			let data = self.data.borrow();
-			draw_tex_grid(data, target, context, texture);
+			draw_letterbox(&data, target, context, &texture);
+			draw_images(&data, target, context, &texture, None);
+			if copy_view {
+				view_image = read_view(&data, target, context);
+			}
+			draw_composition_overlay(&data, target, context);
+			if let Some(StraightenLine { start: Some(start), end }) = data.straighten {
+				let (bounds, color) = (&data.drawn_bounds, context.focus_color);
+				draw_line(target, context, bounds, (start, end), 2.0, color);
+			}
+			draw_measurement(&data, target, context);
+			draw_scale_bar(&data, target, context);
+			if let Some(lens) = data.lens() {
+				draw_magnifier(&data, target, context, &texture, lens);
+			}
+		}
+		if let Some(image) = view_image {
+			let mut borrowed = self.data.borrow_mut();
+			if let Some(clipboard_handler) = &mut borrowed.clipboard_handler {
+				clipboard_handler.request_copy_image(image);
+				borrowed.copy_notifications.set_started();
+				borrowed.copy_advance_path = None;
+				borrowed.clipboard_request_was_pending = true;
+				borrowed.next_update = NextUpdate::Soonest;
+			}
 		}
 		let borrowed = self.data.borrow();
+		if let (Some(flash), Some(opacity)) = (borrowed.edge_flash, borrowed.edge_flash_opacity()) {
+			let bounds = borrowed.drawn_bounds;
+			let mut rect = LogicalRect {
+				pos: bounds.pos,
+				size: LogicalVector::new(EDGE_FLASH_WIDTH, bounds.size.vec.y),
+			};
+			if flash.at_end {
+				rect.pos.vec.x += bounds.size.vec.x - EDGE_FLASH_WIDTH;
+			}
+			context.clear_color(target, [1.0, 1.0, 1.0, 0.4 * opacity], Some(rect));
+		}
 		Ok(borrowed.next_update)
 	}
 
@@ -754,10 +4373,31 @@ impl Widget for PictureWidget {
 		if !self.data.borrow().visible {
 			return;
 		}
+		if let EventKind::MouseMove
+		| EventKind::MouseButton { .. }
+		| EventKind::MouseScroll { .. }
+		| EventKind::KeyInput { .. } = event.kind
+		{
+			let mut borrowed = self.data.borrow_mut();
+			borrowed.last_input = Instant::now();
+			borrowed.idle_action_taken = false;
+		}
 		match event.kind {
 			EventKind::MouseMove => {
 				let mut borrowed = self.data.borrow_mut();
+				// Reaching the edge starts `edge_autopan`
+				borrowed.camera_movement_will_start();
 				borrowed.hover = borrowed.drawn_bounds.contains(event.cursor_pos);
+				if let Some(line) = borrowed.straighten.as_mut().filter(|line| line.start.is_some())
+				{
+					line.end = event.cursor_pos;
+					let text = match line.level_angle() {
+						Some(angle) => format!("Straighten by {:.1}°", angle),
+						None => "Straighten".to_string(),
+					};
+					borrowed.notification.show(&text, STRAIGHTEN_NOTIFICATION_DURATION);
+					borrowed.render_validity.invalidate();
+				}
 				if borrowed.panning_2d || borrowed.panning_hor || borrowed.panning_vert {
 					let mut delta = event.cursor_pos - borrowed.last_mouse_pos;
 					if !borrowed.panning_2d {
@@ -770,54 +4410,62 @@ impl Widget for PictureWidget {
 							delta.vec.y = 0.0;
 						}
 					}
+					if borrowed.precision_held(event.modifiers) {
+						delta = delta * borrowed.precision_pan_factor;
+					}
 					borrowed.scaling = ScalingMode::Fixed;
 					borrowed.update_scaling_buttons();
 					borrowed.img_pos += delta;
 					borrowed.render_validity.invalidate();
 				}
 				borrowed.last_mouse_pos = event.cursor_pos;
-			}
-			EventKind::MouseButton { state, button, .. } => match button {
-				MouseButton::Left => {
-					let mut borrowed = self.data.borrow_mut();
-					if state == ElementState::Pressed {
-						if borrowed.hover {
-							borrowed.click = true;
-							borrowed.panning_2d = true
-						}
-					} else {
-						borrowed.panning_2d = false;
-						borrowed.click = false;
-						if borrowed.hover {
-							let now = Instant::now();
-							let duration_since_last_click =
-								now.duration_since(borrowed.last_click_time);
-							borrowed.last_click_time = now;
-							if duration_since_last_click < Duration::from_millis(250) {
-								match borrowed.window.upgrade() {
-									Some(window) => {
-										let fullscreen = !window.fullscreen();
-										window.set_fullscreen(fullscreen);
-										borrowed.bottom_bar.set_visible_if_should_show(!fullscreen);
-									}
-									None => unreachable!(),
-								}
-							}
-						}
-					}
+				// The magnifier, the coordinates and the line of `measure` follow the cursor
+				let measuring = borrowed.measure.as_ref().is_some_and(|m| m.points.len() == 1);
+				if borrowed.magnifier_shown || borrowed.show_coordinates || measuring {
 					borrowed.render_validity.invalidate();
 				}
-				MouseButton::Right => {
-					let borrowed = self.data.borrow();
-					let pressed = state == ElementState::Pressed;
-					borrowed.left_to_pan_hint.set_visible(pressed);
+				let picture_bottom =
+					borrowed.drawn_bounds.pos.vec.y + borrowed.drawn_bounds.size.vec.y;
+				let hide_time =
+					borrowed.bottom_bar.cursor_moved(event.cursor_pos.vec.y, picture_bottom);
+				if let Some(hide_time) = hide_time {
+					borrowed.next_update =
+						borrowed.next_update.aggregate(NextUpdate::WaitUntil(hide_time));
+					borrowed.render_validity.invalidate();
 				}
-				_ => {}
-			},
+			}
+			EventKind::MouseButton { state, button, .. } => {
+				if *LOG_INPUT {
+					let input_key = mouse_button_input_key(button);
+					let input_key = input_key.filter(|_| state == ElementState::Pressed);
+					let description = format!("mouse button {:?} {:?}", button, state);
+					let configuration = self.data.borrow().configuration.clone();
+					log_input(&configuration, &description, input_key, event.modifiers);
+				}
+				self.start_or_stop_drag_pan(state, button, event.cursor_pos);
+				self.handle_mouse_button(state, button, event.cursor_pos);
+				if let (ElementState::Pressed, Some(key)) = (state, mouse_button_input_key(button))
+				{
+					self.handle_key_input(key, event.modifiers);
+				}
+			}
+			// The slider of the toolbar scrolls through the images itself
+			EventKind::MouseScroll { .. }
+				if self.data.borrow().bottom_bar.slider.hovered().is_some() => {}
 			EventKind::MouseScroll { delta } => {
 				let mut borrowed = self.data.borrow_mut();
-				let delta = delta.vec.y * 0.375;
-				borrowed.zoom_image(event.cursor_pos, delta);
+				if *LOG_INPUT {
+					let description = format!("mouse scroll by {:.2}", delta.vec.y);
+					log_input(&borrowed.configuration, &description, None, event.modifiers);
+				}
+				let scrolled = !event.modifiers.control_key() && borrowed.scroll_image(delta.vec.y);
+				if !scrolled {
+					let mut delta = delta.vec.y * 0.375;
+					if borrowed.precision_held(event.modifiers) {
+						delta *= borrowed.precision_zoom_factor;
+					}
+					borrowed.zoom_image(event.cursor_pos, delta);
+				}
 			}
 			EventKind::KeyInput { ref input } => {
 				let key = input.key_without_modifiers();
@@ -825,38 +4473,57 @@ impl Widget for PictureWidget {
 				//println!("Got input for {:?}", key);
 				let input_key_str = virtual_keycode_to_string(&key).to_lowercase();
 				let input_key_str = char_to_input_key(&input_key_str);
-				if is_pressed {
+				if *LOG_INPUT {
+					// The actions are only triggered by pressing the keys
+					let input_key = Some(input_key_str.as_str()).filter(|_| is_pressed);
+					let description = format!(
+						"key {:?}, logical key: {:?}, physical key: {:?}",
+						input.state, input.logical_key, input.physical_key
+					);
+					let configuration = self.data.borrow().configuration.clone();
+					log_input(&configuration, &description, input_key, event.modifiers);
+				}
+				let used_by_pan = self.handle_pan_key(&input_key_str, event.modifiers, is_pressed);
+				if is_pressed && !used_by_pan {
 					self.handle_key_input(input_key_str.as_str(), event.modifiers);
 				}
 				// Panning is a special snowflake
 				let mut borrowed = self.data.borrow_mut();
 				if action_triggered(
 					&borrowed.configuration,
-					PAN_NAME,
+					PAN_VERT_NAME,
 					input_key_str.as_str(),
 					event.modifiers,
 				) {
-					borrowed.panning_2d = is_pressed;
+					borrowed.panning_vert = is_pressed;
 				}
 				if action_triggered(
 					&borrowed.configuration,
-					PAN_VERT_NAME,
+					PAN_HOR_NAME,
 					input_key_str.as_str(),
 					event.modifiers,
 				) {
-					borrowed.panning_vert = is_pressed;
+					borrowed.panning_hor = is_pressed;
 				}
 				if action_triggered(
 					&borrowed.configuration,
-					PAN_HOR_NAME,
+					MAGNIFIER_NAME,
 					input_key_str.as_str(),
 					event.modifiers,
 				) {
-					borrowed.panning_hor = is_pressed;
+					let shown = borrowed.magnifier_shown;
+					if !borrowed.magnifier.toggle {
+						borrowed.magnifier_shown = is_pressed;
+					} else if is_pressed && !input.repeat {
+						borrowed.magnifier_shown = !shown;
+					}
+					if borrowed.magnifier_shown != shown {
+						borrowed.render_validity.invalidate();
+					}
 				}
 
 				macro_rules! movement_trigger {
-					($input:expr, $vel:expr, $name:expr, $dir:expr) => {
+					($input:expr, $name:expr, $dir:expr) => {
 						if action_triggered(
 							&borrowed.configuration,
 							$name,
@@ -865,7 +4532,6 @@ impl Widget for PictureWidget {
 						) {
 							if $input == $dir && !is_pressed {
 								$input = MovementDir::None;
-								$vel = 0.0;
 							}
 							if $input != $dir && is_pressed {
 								borrowed.camera_movement_will_start();
@@ -875,50 +4541,19 @@ impl Widget for PictureWidget {
 					};
 				}
 
-				movement_trigger!(
-					borrowed.zoom_input,
-					borrowed.zoom_vel,
-					ZOOM_IN_NAME,
-					MovementDir::Positive
-				);
-				movement_trigger!(
-					borrowed.zoom_input,
-					borrowed.zoom_vel,
-					ZOOM_OUT_NAME,
-					MovementDir::Negative
-				);
+				movement_trigger!(borrowed.zoom_input, ZOOM_IN_NAME, MovementDir::Positive);
+				movement_trigger!(borrowed.zoom_input, ZOOM_OUT_NAME, MovementDir::Negative);
 
-				movement_trigger!(
-					borrowed.hor_pan_input,
-					borrowed.hor_pan_vel,
-					PAN_LEFT_NAME,
-					MovementDir::Positive
-				);
-				movement_trigger!(
-					borrowed.hor_pan_input,
-					borrowed.hor_pan_vel,
-					PAN_RIGHT_NAME,
-					MovementDir::Negative
-				);
+				movement_trigger!(borrowed.hor_pan_input, PAN_LEFT_NAME, MovementDir::Positive);
+				movement_trigger!(borrowed.hor_pan_input, PAN_RIGHT_NAME, MovementDir::Negative);
 
-				movement_trigger!(
-					borrowed.ver_pan_input,
-					borrowed.ver_pan_vel,
-					PAN_UP_NAME,
-					MovementDir::Positive
-				);
-				movement_trigger!(
-					borrowed.ver_pan_input,
-					borrowed.ver_pan_vel,
-					PAN_DOWN_NAME,
-					MovementDir::Negative
-				);
+				movement_trigger!(borrowed.ver_pan_input, PAN_UP_NAME, MovementDir::Positive);
+				movement_trigger!(borrowed.ver_pan_input, PAN_DOWN_NAME, MovementDir::Negative);
 			}
 			EventKind::DroppedFile(ref path) => {
-				let mut borrowed = self.data.borrow_mut();
-				borrowed.playback_manager.request_load(LoadRequest::FilePath(path.clone()));
-				borrowed.hover_state = HoverState::None;
-				borrowed.render_validity.invalidate();
+				let behavior = self.data.borrow().configuration.borrow().open_behavior(path);
+				self.jump_to_path(path.clone(), behavior);
+				self.data.borrow_mut().hover_state = HoverState::None;
 			}
 			EventKind::HoveredFile(ref path) => {
 				let mut borrowed = self.data.borrow_mut();
@@ -950,20 +4585,26 @@ impl Widget for PictureWidget {
 				borrowed.render_validity.invalidate();
 			}
 			EventKind::Focused(focused) => {
+				let mut borrowed = self.data.borrow_mut();
 				if focused {
-					let mut borrowed = self.data.borrow_mut();
-					if let Err(e) = borrowed.playback_manager.update_directory() {
-						eprintln!("{}", e);
+					// Updating the folder would move away from a deleted file
+					let keep_deleted = borrowed.on_current_deleted != CurrentDeleted::AdvanceNext
+						&& borrowed.shown_file_missing();
+					if !keep_deleted {
+						if let Err(e) = borrowed.playback_manager.update_directory() {
+							eprintln!("{}", e);
+						}
 					}
 					borrowed.render_validity.invalidate();
+				} else {
+					// The release of the pan key won't arrive
+					borrowed.hand_tool = None;
 				}
 			}
-			EventKind::CloseRequested => {
-				let mut borrowed = self.data.borrow_mut();
-				// Just let it drop.
-				borrowed.clipboard_handler.take();
-			}
+			// Closing the window doesn't always exit, see `request_quit`
+			EventKind::CloseRequested => {}
 		}
+		self.data.borrow_mut().update_cursor();
 	}
 
 	// No children for a button
@@ -990,35 +4631,505 @@ impl Drop for PictureWidget {
 	}
 }
 
+/// Draws a line between two points in window coordinates, cut to `clip`
+fn draw_line(
+	target: &mut Frame,
+	context: &DrawContext,
+	clip: &LogicalRect,
+	(start, end): (LogicalVector, LogicalVector),
+	width: f32,
+	color: [f32; 4],
+) {
+	let delta = (end - start).vec;
+	let angle = Deg::from(gelatin::cgmath::Rad(delta.y.atan2(delta.x)));
+	let transform = context.projection_transform
+		* Matrix4::from_translation(start.vec.extend(0.0))
+		* Matrix4::from_angle_z(angle)
+		* Matrix4::from_nonuniform_scale(delta.magnitude(), width, 1.0)
+		* Matrix4::from_translation(Vector3::new(0.0, -0.5, 0.0));
+	let uniforms = uniform! {
+		matrix: Into::<[[f32; 4]; 4]>::into(transform),
+		color: color,
+	};
+	let draw_params = gelatin::glium::DrawParameters {
+		blend: gelatin::glium::Blend::alpha_blending(),
+		scissor: Some(context.logical_rect_to_viewport(clip)),
+		..Default::default()
+	};
+	target
+		.draw(
+			context.unit_quad_vertices,
+			context.unit_quad_indices,
+			context.colored_program,
+			&uniforms,
+			&draw_params,
+		)
+		.unwrap();
+}
+
+/// Draws a line with a dark outline, so that it's visible on light and dark images alike
+fn draw_outlined_line(
+	target: &mut Frame,
+	context: &DrawContext,
+	clip: &LogicalRect,
+	line: (LogicalVector, LogicalVector),
+	width: f32,
+	color: [f32; 4],
+) {
+	draw_line(target, context, clip, line, width + 2.0, [0.0, 0.0, 0.0, 0.5]);
+	draw_line(target, context, clip, line, width, color);
+}
+
+/// Draws the line of the `measure` tool, which ends at the cursor until the second point is
+/// placed, with a cross at each point
+fn draw_measurement(data: &PictureWidgetData, target: &mut Frame, context: &DrawContext) {
+	let Some(measure) = &data.measure else {
+		return;
+	};
+	let points: Vec<_> = measure.points.iter().filter_map(|&p| data.image_to_view(p)).collect();
+	let (bounds, color) = (&data.drawn_bounds, context.focus_color);
+	let line = match points[..] {
+		[start] => Some((start, data.last_mouse_pos)),
+		[start, end] => Some((start, end)),
+		_ => None,
+	};
+	if let Some(line) = line {
+		draw_outlined_line(target, context, bounds, line, 1.0, color);
+	}
+	let horizontal = LogicalVector::new(MARK_SIZE, 0.0);
+	let vertical = LogicalVector::new(0.0, MARK_SIZE);
+	for &point in &points {
+		for &mark in [horizontal, vertical].iter() {
+			draw_outlined_line(target, context, bounds, (point - mark, point + mark), 1.0, color);
+		}
+	}
+}
+
+/// Draws the scale bar at the bottom of the picture area, above its label
+fn draw_scale_bar(data: &PictureWidgetData, target: &mut Frame, context: &DrawContext) {
+	let Some(length) = data.scale_bar_length else {
+		return;
+	};
+	let bounds = &data.drawn_bounds;
+	let center = bounds.pos.vec.x + bounds.size.vec.x * 0.5;
+	let y = bounds.pos.vec.y + bounds.size.vec.y - SCALE_BAR_BOTTOM;
+	let start = LogicalVector::new(center - length * 0.5, y);
+	let end = LogicalVector::new(center + length * 0.5, y);
+	let color = [1.0, 1.0, 1.0, 0.9];
+	draw_outlined_line(target, context, bounds, (start, end), 2.0, color);
+	let mark = LogicalVector::new(0.0, MARK_SIZE);
+	for &point in [start, end].iter() {
+		draw_outlined_line(target, context, bounds, (point - mark, point + mark), 2.0, color);
+	}
+}
+
+/// The largest length that is 1, 2 or 5 times a power of ten and at most `max`, and the number
+/// of decimals to print it with
+fn round_length(max: f64) -> (f64, usize) {
+	let exponent = max.log10().floor();
+	let power = 10f64.powf(exponent);
+	let step = [5.0, 2.0, 1.0].iter().copied().find(|&step| step * power <= max).unwrap_or(1.0);
+	(step * power, (-exponent).max(0.0) as usize)
+}
+
+/// Draws the current image, together with the previous image, the second page or the reference
+/// image when they are shown
+fn draw_images(
+	data: &PictureWidgetData,
+	target: &mut Frame,
+	context: &DrawContext,
+	texture: &AnimationFrameTexture,
+	lens: Option<Lens>,
+) {
+	// The previous image is placed where the current one is, aligned at the top left
+	// corner, so that the two can be compared exactly.
+	let align_size = (texture.w, texture.h);
+	match (&data.previous_image, &data.second_page, &data.reference_image) {
+		(Some((_, prev_texture)), _, _) if data.show_previous => {
+			draw_tex_grid(data, target, context, prev_texture, (align_size, (0, 0)), lens);
+		}
+		(_, Some(second_page), _) => {
+			// The pages are placed next to each other, centered vertically
+			let spread = data.shown_dimensions().unwrap_or(align_size);
+			let (left, right) = match data.reading_direction {
+				ReadingDirection::LeftToRight => (texture, second_page),
+				ReadingDirection::RightToLeft => (second_page, texture),
+			};
+			let (left_w, left_h) = left.oriented_dimensions();
+			let (_, right_h) = right.oriented_dimensions();
+			let left_offset = (0, (spread.1 - left_h) / 2);
+			let right_offset = (left_w, (spread.1 - right_h) / 2);
+			draw_tex_grid(data, target, context, left, (spread, left_offset), lens);
+			draw_tex_grid(data, target, context, right, (spread, right_offset), lens);
+		}
+		(_, _, Some((_, reference))) => {
+			draw_over_reference(data, target, context, (texture, reference), lens);
+		}
+		_ => draw_tex_grid(data, target, context, texture, (align_size, (0, 0)), lens),
+	}
+}
+
+/// Draws the images again inside the lens, magnified around the cursor, with an outline
+fn draw_magnifier(
+	data: &PictureWidgetData,
+	target: &mut Frame,
+	context: &DrawContext,
+	texture: &AnimationFrameTexture,
+	lens: Lens,
+) {
+	let bounds = lens.bounds(&data.drawn_bounds);
+	if !lens.circle {
+		context.clear_color(
+			target,
+			texture.edge_color,
+			Some(clip_rect(&bounds, &data.drawn_bounds)),
+		);
+	}
+	draw_images(data, target, context, texture, Some(lens));
+	let center = data.drawn_bounds.pos + lens.center;
+	let radius = lens.size * 0.5;
+	let outline: Vec<LogicalVector> = if lens.circle {
+		const SEGMENTS: usize = 64;
+		(0..=SEGMENTS)
+			.map(|i| {
+				let angle = i as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+				center + LogicalVector::new(angle.cos() * radius, angle.sin() * radius)
+			})
+			.collect()
+	} else {
+		let (min, max) = (bounds.pos, bounds.pos + bounds.size);
+		let (left, top, right, bottom) = (min.vec.x, min.vec.y, max.vec.x, max.vec.y);
+		let corners = [(left, top), (right, top), (right, bottom), (left, bottom), (left, top)];
+		corners.iter().map(|&(x, y)| LogicalVector::new(x, y)).collect()
+	};
+	let clip = &data.drawn_bounds;
+	for line in outline.windows(2) {
+		draw_line(target, context, clip, (line[0], line[1]), 3.0, [0.0, 0.0, 0.0, 0.5]);
+	}
+	for line in outline.windows(2) {
+		draw_line(target, context, clip, (line[0], line[1]), 1.0, [1.0, 1.0, 1.0, 0.8]);
+	}
+}
+
+/// Reads the pixels of the area that `copy_view_area` selects back from the framebuffer. This
+/// is called before anything is drawn over the images.
+fn read_view(data: &PictureWidgetData, target: &Frame, context: &DrawContext) -> Option<RgbaImage> {
+	let image_bounds = data.image_bounds()?;
+	let local_area = match data.copy_view_area {
+		CopyViewArea::View => {
+			LogicalRect { pos: LogicalVector::new(0.0, 0.0), size: data.drawn_bounds.size }
+		}
+		CopyViewArea::Image => image_bounds,
+	};
+	let area = LogicalRect {
+		pos: LogicalVector { vec: data.drawn_bounds.pos.vec + local_area.pos.vec },
+		size: local_area.size,
+	};
+	let rect = context.logical_rect_to_viewport(&area);
+	if rect.width == 0 || rect.height == 0 {
+		return None;
+	}
+	let copy = SrgbTexture2d::empty_with_format(
+		context.display,
+		SrgbFormat::U8U8U8U8,
+		MipmapsOption::NoMipmap,
+		rect.width,
+		rect.height,
+	)
+	.ok()?;
+	let whole_copy =
+		BlitTarget { left: 0, bottom: 0, width: rect.width as i32, height: rect.height as i32 };
+	let copy_target = SimpleFrameBuffer::new(context.display, &copy).ok()?;
+	target.blit_color(&rect, &copy_target, &whole_copy, MagnifySamplerFilter::Nearest);
+	drop(copy_target);
+	let pixels: RawImage2d<u8> = copy.read();
+	let mut image = RgbaImage::from_raw(rect.width, rect.height, pixels.data.into_owned())?;
+	// The rows of the framebuffer go from the bottom to the top
+	imageops::flip_vertical_in_place(&mut image);
+	for pixel in image.pixels_mut() {
+		pixel.0[3] = 255;
+	}
+	if let (Some(watermark), LoadedImgPath::Loaded(path)) =
+		(&data.export_watermark, data.playback_manager.shown_file_path())
+	{
+		let scale = data.dpi_scale;
+		let pos = (image_bounds.pos.vec - local_area.pos.vec) * scale;
+		let size = image_bounds.size.vec * scale;
+		watermark.stamp(path, &mut image, (pos.x, pos.y, size.x, size.y), scale);
+	}
+	Some(image)
+}
+
+/// The part of `rect` that is inside `bounds`
+fn clip_rect(rect: &LogicalRect, bounds: &LogicalRect) -> LogicalRect {
+	let left = rect.left().max(bounds.left());
+	let top = rect.top().max(bounds.top());
+	let right = rect.right().min(bounds.right());
+	let bottom = rect.bottom().min(bounds.bottom());
+	LogicalRect {
+		pos: LogicalVector::new(left, top),
+		size: LogicalVector::new((right - left).max(0.0), (bottom - top).max(0.0)),
+	}
+}
+
+/// Draws the guides of `composition_overlay` over the image
+fn draw_composition_overlay(data: &PictureWidgetData, target: &mut Frame, context: &DrawContext) {
+	let Some((w, h)) = data.rotated_dimensions() else {
+		return;
+	};
+	let size = Vector2::new(w as f32, h as f32) * (data.img_texel_size / data.dpi_scale);
+	let corner = data.drawn_bounds.pos.vec + data.img_pos.vec - size * 0.5;
+	let point =
+		|x: f32, y: f32| LogicalVector { vec: corner + Vector2::new(x * size.x, y * size.y) };
+	let mut lines = Vec::new();
+	let mut add_grid = |fractions: &[f32]| {
+		for &f in fractions {
+			lines.push((point(f, 0.0), point(f, 1.0)));
+			lines.push((point(0.0, f), point(1.0, f)));
+		}
+	};
+	match data.composition_overlay {
+		CompositionOverlay::None => return,
+		CompositionOverlay::Thirds => add_grid(&[1.0 / 3.0, 2.0 / 3.0]),
+		CompositionOverlay::GoldenRatio => {
+			let minor = 1.0 - 1.0 / GOLDEN_RATIO;
+			add_grid(&[minor, 1.0 - minor]);
+		}
+		CompositionOverlay::Grid(cells) => {
+			let cells = cells.max(1);
+			let fractions: Vec<f32> = (1..cells).map(|i| i as f32 / cells as f32).collect();
+			add_grid(&fractions);
+		}
+		CompositionOverlay::Diagonals => {
+			lines.push((point(0.0, 0.0), point(1.0, 1.0)));
+			lines.push((point(1.0, 0.0), point(0.0, 1.0)));
+		}
+	}
+	// A dark outline keeps the guides visible on light and dark images alike
+	let [r, g, b, a] = data.overlay_color;
+	let shadow = [0.0, 0.0, 0.0, a * 0.5];
+	for &line in &lines {
+		draw_line(target, context, &data.drawn_bounds, line, 3.0, shadow);
+	}
+	for &line in &lines {
+		draw_line(target, context, &data.drawn_bounds, line, 1.0, [r, g, b, a]);
+	}
+}
+
+/// Fills the area of the widget behind the image according to `letterbox_color`
+fn draw_letterbox(
+	data: &PictureWidgetData,
+	target: &mut Frame,
+	context: &DrawContext,
+	texture: &AnimationFrameTexture,
+) {
+	let color = match data.letterbox_color {
+		LetterboxColor::Named(NamedLetterboxColor::Theme) => return,
+		LetterboxColor::Rgb(rgb) => {
+			[srgb_to_linear(rgb[0]), srgb_to_linear(rgb[1]), srgb_to_linear(rgb[2]), 1.0]
+		}
+		LetterboxColor::Named(NamedLetterboxColor::AverageEdge) => texture.edge_color,
+		LetterboxColor::Named(NamedLetterboxColor::Blur) => match &texture.backdrop {
+			Some(backdrop) => return draw_backdrop(data, target, context, texture, backdrop),
+			// Loaded before the option took effect
+			None => texture.edge_color,
+		},
+	};
+	context.clear_color(target, color, Some(data.drawn_bounds));
+}
+
+/// Stretches the backdrop of the image so that it covers the whole widget
+fn draw_backdrop(
+	data: &PictureWidgetData,
+	target: &mut Frame,
+	context: &DrawContext,
+	texture: &AnimationFrameTexture,
+	backdrop: &SrgbTexture2d,
+) {
+	let size = data.drawn_bounds.size.vec;
+	let projection_transform = gelatin::cgmath::ortho(0.0, size.x, size.y, 0.0, -1.0, 1.0);
+	let viewport_rect = context.logical_rect_to_viewport(&data.drawn_bounds);
+	// Only the UI is antialiased, see `msaa_samples`
+	let draw_params = gelatin::glium::DrawParameters {
+		viewport: Some(viewport_rect),
+		multisampling: false,
+		..Default::default()
+	};
+
+	let (oriented_w, oriented_h) = texture.oriented_dimensions();
+	let scale = (size.x / oriented_w as f32).max(size.y / oriented_h as f32);
+	let w = texture.w as f32 * scale;
+	let h = texture.h as f32 * scale;
+	let to_center = Matrix4::from_translation(Vector3::new(-0.5 * w, -0.5 * h, 0.0));
+	let orient = orientation_to_matrix(texture.orientation);
+	let to_widget_center = Matrix4::from_translation(Vector3::new(0.5 * size.x, 0.5 * size.y, 0.0));
+	let scaling = Matrix4::from_nonuniform_scale(w, h, 1.0);
+	let transform = projection_transform * to_widget_center * orient * to_center * scaling;
+
+	let sampler = backdrop
+		.sampled()
+		.minify_filter(gelatin::glium::uniforms::MinifySamplerFilter::Linear)
+		.wrap_function(gelatin::glium::uniforms::SamplerWrapFunction::Clamp)
+		.magnify_filter(MagnifySamplerFilter::Linear);
+	let uniforms = uniform! {
+		matrix: Into::<[[f32; 4]; 4]>::into(transform),
+		bright_shade: data.bright_shade,
+		tex: sampler,
+		lod_level: 0.0f32,
+		opacity: 1.0f32,
+		clipping_warning: false,
+		clip_highlight: 0.0f32,
+		clip_shadow: 0.0f32,
+		focus_peaking: false,
+		peaking_threshold: 0.0f32,
+		peaking_color: [0.0f32; 3],
+		channel_view: ChannelView::Rgb as i32,
+		lens_center: [0.0f32; 2],
+		lens_radius: 0.0f32,
+	};
+	target
+		.draw(
+			context.unit_quad_vertices,
+			context.unit_quad_indices,
+			&data.program,
+			&uniforms,
+			&draw_params,
+		)
+		.unwrap();
+}
+
+/// How the cells of a texture are combined with what's under them
+#[derive(Clone, Copy)]
+enum CellBlend<'a> {
+	Opaque,
+	Opacity(f32),
+	/// The absolute difference to the image that was drawn into the texture, which has the
+	/// size of the window
+	Difference(&'a Texture2d),
+}
+
+/// Draws the current image over the reference image. The two are aligned at the top left
+/// corner, like the previous image of `ab_flicker`.
+fn draw_over_reference(
+	data: &PictureWidgetData,
+	target: &mut Frame,
+	context: &DrawContext,
+	(texture, reference): (&AnimationFrameTexture, &AnimationFrameTexture),
+	lens: Option<Lens>,
+) {
+	let placement = ((texture.w, texture.h), (0, 0));
+	match data.reference_blend {
+		ReferenceBlend::Opacity => {
+			draw_tex_grid(data, target, context, reference, placement, lens);
+			let blend = CellBlend::Opacity(data.reference_opacity);
+			draw_tex_cells(data, target, context, texture, placement, blend, lens);
+		}
+		ReferenceBlend::Difference => {
+			let (w, h) = target.get_dimensions();
+			let reference_target = Texture2d::empty_with_format(
+				context.display,
+				UncompressedFloatFormat::F16F16F16F16,
+				MipmapsOption::NoMipmap,
+				w,
+				h,
+			)
+			.unwrap();
+			let mut framebuffer =
+				SimpleFrameBuffer::new(context.display, &reference_target).unwrap();
+			framebuffer.clear_color(0.0, 0.0, 0.0, 0.0);
+			draw_tex_cells(
+				data,
+				&mut framebuffer,
+				context,
+				reference,
+				placement,
+				CellBlend::Opaque,
+				lens,
+			);
+			let blend = CellBlend::Difference(&reference_target);
+			draw_tex_cells(data, target, context, texture, placement, blend, lens);
+		}
+	}
+}
+
+/// Draws the texture `offset` texels from the top left corner of an area of `align_size` texels,
+/// that is centered on the image position. With a lens, only the area of the lens is drawn,
+/// magnified around its center.
 fn draw_tex_grid(
-	data: Ref<PictureWidgetData>,
+	data: &PictureWidgetData,
 	target: &mut Frame,
 	context: &DrawContext,
-	texture: AnimationFrameTexture,
+	texture: &AnimationFrameTexture,
+	placement: ((u32, u32), (u32, u32)),
+	lens: Option<Lens>,
+) {
+	draw_tex_cells(data, target, context, texture, placement, CellBlend::Opaque, lens);
+}
+
+fn draw_tex_cells<S: Surface>(
+	data: &PictureWidgetData,
+	target: &mut S,
+	context: &DrawContext,
+	texture: &AnimationFrameTexture,
+	(align_size, offset): ((u32, u32), (u32, u32)),
+	blend: CellBlend,
+	lens: Option<Lens>,
 ) {
 	let size = data.drawn_bounds.size.vec;
 	let projection_transform = gelatin::cgmath::ortho(0.0, size.x, size.y, 0.0, -1.0, 1.0);
 
 	let viewport_rect = context.logical_rect_to_viewport(&data.drawn_bounds);
-	let image_draw_params =
-		gelatin::glium::DrawParameters { viewport: Some(viewport_rect), ..Default::default() };
+	let (rotation, mut crop) = data.rotation_transform();
+	// The lens magnifies the whole view around its center
+	let mut magnification = Matrix4::identity();
+	let (mut lens_center, mut lens_radius) = ([0.0f32; 2], 0.0f32);
+	let mut texel_size = data.img_texel_size;
+	if let Some(lens) = lens {
+		let center = lens.center.vec.extend(0.0);
+		magnification = Matrix4::from_translation(center)
+			* Matrix4::from_scale(lens.zoom)
+			* Matrix4::from_translation(-center);
+		let bounds = lens.bounds(&data.drawn_bounds);
+		crop = Some(clip_rect(&bounds, &data.drawn_bounds));
+		if lens.circle {
+			let center = (data.drawn_bounds.pos + lens.center).vec * context.dpi_scale_factor;
+			lens_center = [center.x, context.viewport.height as f32 - center.y];
+			lens_radius = lens.size * 0.5 * context.dpi_scale_factor;
+		}
+		texel_size *= lens.zoom;
+	}
+	let image_draw_params = gelatin::glium::DrawParameters {
+		viewport: Some(viewport_rect),
+		scissor: crop.map(|crop| context.logical_rect_to_viewport(&crop)),
+		blend: match blend {
+			CellBlend::Opacity(_) => Blend::alpha_blending(),
+			_ => Default::default(),
+		},
+		// The image is sampled from its texture already, see `msaa_samples`
+		multisampling: false,
+		..Default::default()
+	};
 
 	let img_phys_w = texture.w as f32;
 	let img_phys_h = texture.h as f32;
-	let img_height_over_width = img_phys_h / img_phys_w;
-	let image_display_width = data.img_texel_size * img_phys_w / context.dpi_scale_factor;
-	let image_display_height = image_display_width * img_height_over_width;
-	// Model tranform
-	let img_pyhs_pos = data.img_pos.vec * context.dpi_scale_factor;
-	let img_phys_siz = {
+	let phys_size_of = |(w, h): (u32, u32)| {
+		let image_display_width = data.img_texel_size * w as f32 / context.dpi_scale_factor;
+		let image_display_height = image_display_width * h as f32 / w as f32;
 		let img_phys_w = image_display_width * context.dpi_scale_factor;
 		let img_phys_h = image_display_height * context.dpi_scale_factor;
 		LogicalVector::new(img_phys_w.ceil(), img_phys_h.ceil())
 	};
-	let img_logical_corner_x =
-		(img_pyhs_pos.x - img_phys_siz.vec.x * 0.5).ceil() / context.dpi_scale_factor;
-	let img_logical_corner_y =
-		(img_pyhs_pos.y - img_phys_siz.vec.y * 0.5).ceil() / context.dpi_scale_factor;
+	// Model tranform
+	let img_pyhs_pos = data.img_pos.vec * context.dpi_scale_factor;
+	let img_phys_siz = phys_size_of((texture.w, texture.h));
+	// The position of the image is determined by `align_size` and `offset`
+	let align_phys_siz = phys_size_of(align_size);
+	let offset_phys_x = offset.0 as f32 * data.img_texel_size;
+	let offset_phys_y = offset.1 as f32 * data.img_texel_size;
+	let img_logical_corner_x = (img_pyhs_pos.x - align_phys_siz.vec.x * 0.5 + offset_phys_x).ceil()
+		/ context.dpi_scale_factor;
+	let img_logical_corner_y = (img_pyhs_pos.y - align_phys_siz.vec.y * 0.5 + offset_phys_y).ceil()
+		/ context.dpi_scale_factor;
 
 	// This is the display width of the image in logical pixel units
 	let img_adjusted_w = img_phys_siz.vec.x / context.dpi_scale_factor;
@@ -1070,39 +5181,94 @@ fn draw_tex_grid(
 		let transform =
 			img_translation * orientation * img_scaling * cell_translation * cell_scaling;
 		// Projection tranform
-		let transform = projection_transform * transform;
-
-		let sampler = cell_tex
-			.tex
-			.sampled()
-			.minify_filter(gelatin::glium::uniforms::MinifySamplerFilter::LinearMipmapLinear)
-			.wrap_function(gelatin::glium::uniforms::SamplerWrapFunction::Clamp);
+		let transform = projection_transform * magnification * rotation * transform;
 
 		let filter = match data.antialiasing {
-			Antialias::Auto if data.img_texel_size < AA_TEXEL_SIZE_THRESHOLD => {
-				MagnifySamplerFilter::Linear
-			}
+			Antialias::Auto if !data.auto_aa_nearest(texel_size) => MagnifySamplerFilter::Linear,
 			Antialias::Auto | Antialias::Never => MagnifySamplerFilter::Nearest,
 			Antialias::Always => MagnifySamplerFilter::Linear,
 		};
-		let sampler = sampler.magnify_filter(filter);
 
 		// building the uniforms
-		let lod_level = ((1.0 / data.img_texel_size).log2().max(0.0) + 0.125).floor();
-		let uniforms = uniform! {
-			matrix: Into::<[[f32; 4]; 4]>::into(transform),
-			bright_shade: data.bright_shade,
-			tex: sampler,
-			lod_level: lod_level,
-		};
-		target
-			.draw(
-				context.unit_quad_vertices,
-				context.unit_quad_indices,
-				&data.program,
-				&uniforms,
-				&image_draw_params,
-			)
-			.unwrap();
+		let lod_level = ((1.0 / texel_size).log2().max(0.0) + 0.125).floor();
+		// The two kinds of textures have different sampler types
+		macro_rules! draw_cell {
+			($tex:expr) => {{
+				let sampler = $tex
+					.sampled()
+					.minify_filter(
+						gelatin::glium::uniforms::MinifySamplerFilter::LinearMipmapLinear,
+					)
+					.wrap_function(gelatin::glium::uniforms::SamplerWrapFunction::Clamp)
+					.magnify_filter(filter);
+				let matrix: [[f32; 4]; 4] = transform.into();
+				let (vertices, indices) = (context.unit_quad_vertices, context.unit_quad_indices);
+				match blend {
+					CellBlend::Opaque | CellBlend::Opacity(_) => {
+						let opacity = match blend {
+							CellBlend::Opacity(opacity) => opacity,
+							_ => 1.0,
+						};
+						let uniforms = uniform! {
+							matrix: matrix,
+							bright_shade: data.bright_shade,
+							tex: sampler,
+							lod_level: lod_level,
+							opacity: opacity,
+							clipping_warning: data.clipping_warning,
+							clip_highlight: data.clip_thresholds.0,
+							clip_shadow: data.clip_thresholds.1,
+							focus_peaking: data.focus_peaking,
+							peaking_threshold: data.peaking_threshold(),
+							peaking_color: data.peaking_color,
+							channel_view: data.channel_view as i32,
+							lens_center: lens_center,
+							lens_radius: lens_radius,
+						};
+						target
+							.draw(vertices, indices, &data.program, &uniforms, &image_draw_params)
+							.unwrap();
+					}
+					CellBlend::Difference(reference) => {
+						let uniforms = uniform! {
+							matrix: matrix,
+							bright_shade: data.bright_shade,
+							tex: sampler,
+							reference: reference,
+							lod_level: lod_level,
+							lens_center: lens_center,
+							lens_radius: lens_radius,
+						};
+						let program = &data.difference_program;
+						target
+							.draw(vertices, indices, program, &uniforms, &image_draw_params)
+							.unwrap();
+					}
+				}
+			}};
+		}
+		match &cell_tex.tex {
+			GridTexture::Srgb(tex) => draw_cell!(tex),
+			GridTexture::Float(tex) => draw_cell!(tex),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn jumps_between_the_capture_date_groups() {
+		let date = |day| Some(CaptureDate { year: 2024, month: 5, day });
+		let dates = [date(1), date(1), None, date(2), date(2), date(2)];
+
+		assert_eq!(group_jump_target(&dates, 1, GroupJump::Next), Some((2, 2, 3)));
+		assert_eq!(group_jump_target(&dates, 2, GroupJump::Next), Some((3, 3, 3)));
+		assert_eq!(group_jump_target(&dates, 4, GroupJump::Prev), Some((2, 2, 3)));
+		assert_eq!(group_jump_target(&dates, 1, GroupJump::Prev), Some((0, 1, 3)));
+		// There is nothing after the last group, not even its own start
+		assert_eq!(group_jump_target(&dates, 4, GroupJump::Next), None);
+		assert_eq!(group_jump_target(&dates, 3, GroupJump::Next), None);
 	}
 }
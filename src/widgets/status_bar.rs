@@ -0,0 +1,228 @@
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use gelatin::{
+	label::Label,
+	line_layout_container::HorizontalLayoutContainer,
+	misc::{Alignment, Length, LogicalVector},
+	picture::Picture,
+	Widget,
+};
+
+use super::text_notification::{line_height, render_sized_text, FONT_SIZE, TEXT_RENDER_SCALE};
+use crate::image_cache::source;
+use crate::{ConfigWindowSection, Configuration, Theme};
+
+pub const DEFAULT_STATUS_FORMAT: &str = "%name    %dim    %depth    %size    %zoom    %index";
+const MIN_FONT_SIZE: f32 = 8.0;
+const MAX_FONT_SIZE: f32 = 48.0;
+
+/// What the status bar describes
+pub struct StatusInfo<'a> {
+	pub path: Option<&'a Path>,
+	pub dimensions: Option<(u32, u32)>,
+	/// The size of the image in the file, when a smaller copy of it is shown
+	pub downscaled_from: Option<(u32, u32)>,
+	/// Like "16-bit"
+	pub bit_depth: Option<String>,
+	/// In percent of the original size
+	pub zoom: Option<f32>,
+	/// The index of the image and the number of images in the folder
+	pub index: Option<(usize, usize)>,
+	/// The value that the folder is sorted by, when it's sorted by EXIF data
+	pub sort_key: Option<String>,
+	/// The folders that the triage actions move the image into, like "A: keep    B: reject"
+	pub triage: Option<String>,
+	/// The name of the decoder that decoded the image, see `Decoder::name`
+	pub decoder: Option<&'static str>,
+	/// Whether the files can't be changed, see `read_only` in the configuration
+	pub read_only: bool,
+	/// The pixel of the image under the cursor, while `show_coordinates` is on
+	pub cursor: Option<(u32, u32)>,
+	/// The channel that is shown on its own, like "Alpha"
+	pub channel: Option<&'static str>,
+}
+
+/// A thin bar below the picture that describes the current image.
+///
+/// The text is given by a format string, in which `%name`, `%path`, `%dim`, `%depth`, `%size`,
+/// `%zoom` and `%index` are replaced by the file name, the whole path, the pixel dimensions, the
+/// bits per channel, the file size, the zoom and the position of the image in the folder.
+/// `%sort` is the EXIF value that the folder is sorted by, and `%triage` names the folders of
+/// `triage_a` and `triage_b`. Both are added to the end when the format doesn't have them.
+/// `%decoder` names the decoder that decoded the image, which is useful with the `[decoders]`
+/// section of the configuration. "Read-only" is added to the end in the read-only mode.
+/// `%pos` is the pixel under the cursor, which is added to the end while the coordinates are
+/// shown. `%channel` is the channel that `cycle_channel_view` shows on its own, which is also
+/// added to the end.
+pub struct StatusBar {
+	pub widget: Rc<HorizontalLayoutContainer>,
+	label: Rc<Label>,
+	format: String,
+	font_size: f32,
+	text_color: Cell<[u8; 3]>,
+	shown_text: RefCell<String>,
+	/// The size of the last described file, so that the file isn't queried on every update
+	file_size: RefCell<Option<(PathBuf, Option<u64>)>>,
+}
+
+impl StatusBar {
+	pub fn new(config: &Configuration) -> StatusBar {
+		let font_size = config.window.as_ref().and_then(|w| w.panel_font_size);
+		let font_size = font_size.unwrap_or(FONT_SIZE).clamp(MIN_FONT_SIZE, MAX_FONT_SIZE);
+		let widget = Rc::new(HorizontalLayoutContainer::new());
+		widget.set_margin_all(0.0);
+		widget.set_height(Length::Fixed(line_height(font_size)));
+		widget.set_width(Length::Stretch { min: 0.0, max: f32::INFINITY });
+
+		let label = Rc::new(Label::new());
+		label.set_margin_left(4.0);
+		label.set_horizontal_align(Alignment::Start);
+		label.set_vertical_align(Alignment::Center);
+		label.set_visible(false);
+		widget.add_child(label.clone());
+
+		let (visible, format) = match &config.window {
+			Some(ConfigWindowSection { show_status_bar, status_format, .. }) => {
+				(show_status_bar.unwrap_or(false), status_format.clone())
+			}
+			None => (false, None),
+		};
+		widget.set_visible(visible);
+
+		StatusBar {
+			widget,
+			label,
+			format: format.unwrap_or_else(|| DEFAULT_STATUS_FORMAT.to_string()),
+			font_size,
+			text_color: Cell::new([255, 255, 255]),
+			shown_text: RefCell::new(String::new()),
+			file_size: RefCell::new(None),
+		}
+	}
+
+	pub fn set_theme(&self, theme: Theme) {
+		match theme {
+			Theme::Light => {
+				self.widget.set_bg_color([0.92, 0.92, 0.92, 1.0]);
+				self.text_color.set([40, 40, 40]);
+			}
+			Theme::Dark => {
+				self.widget.set_bg_color([0.05, 0.05, 0.05, 1.0]);
+				self.text_color.set([200, 200, 200]);
+			}
+		}
+		let text = self.shown_text.borrow().clone();
+		self.render(&text);
+	}
+
+	pub fn toggle_visible(&self) {
+		self.widget.set_visible(!self.widget.visible());
+	}
+
+	/// Changes the text if the described image or the view has changed
+	pub fn update(&self, info: &StatusInfo) {
+		if !self.widget.visible() {
+			return;
+		}
+		let file_size = self.file_size_of(info.path);
+		let mut text = format_status(&self.format, info, file_size);
+		if let (Some(sort_key), false) = (&info.sort_key, self.format.contains("%sort")) {
+			text.push_str("    ");
+			text.push_str(sort_key);
+		}
+		if let (Some(triage), false) = (&info.triage, self.format.contains("%triage")) {
+			text.push_str("    ");
+			text.push_str(triage);
+		}
+		if let (Some(channel), false) = (info.channel, self.format.contains("%channel")) {
+			text.push_str("    Channel: ");
+			text.push_str(channel);
+		}
+		if let (Some((x, y)), false) = (info.cursor, self.format.contains("%pos")) {
+			text.push_str(&format!("    x {}  y {}", x, y));
+		}
+		if info.read_only {
+			text.push_str("    Read-only");
+		}
+		if *self.shown_text.borrow() != text {
+			self.render(&text);
+			*self.shown_text.borrow_mut() = text;
+		}
+	}
+
+	/// The size of the file at `path`, cached for the last path that was asked for
+	pub fn file_size_of(&self, path: Option<&Path>) -> Option<u64> {
+		let path = path?;
+		let mut file_size = self.file_size.borrow_mut();
+		match &*file_size {
+			Some((cached_path, size)) if cached_path == path => *size,
+			_ => {
+				let size = source::metadata(path).ok().map(|m| m.len());
+				*file_size = Some((path.to_owned(), size));
+				size
+			}
+		}
+	}
+
+	fn render(&self, text: &str) {
+		match render_sized_text(text, self.font_size, self.text_color.get(), [0, 0, 0, 0]) {
+			Some(image) => {
+				let size = LogicalVector::new(
+					image.width() as f32 / TEXT_RENDER_SCALE,
+					image.height() as f32 / TEXT_RENDER_SCALE,
+				);
+				self.label.set_fixed_size(size);
+				self.label.set_icon(Some(Rc::new(Picture::from_image(image))));
+				self.label.set_visible(true);
+			}
+			None => self.label.set_visible(false),
+		}
+	}
+}
+
+pub fn format_file_size(bytes: u64) -> String {
+	const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+	if bytes < 1000 {
+		return format!("{} B", bytes);
+	}
+	let mut size = bytes as f64 / 1000.0;
+	let mut unit = 0;
+	while size >= 1000.0 && unit + 1 < UNITS.len() {
+		size /= 1000.0;
+		unit += 1;
+	}
+	format!("{:.1} {}", size, UNITS[unit])
+}
+
+fn format_status(format: &str, info: &StatusInfo, file_size: Option<u64>) -> String {
+	let name = info.path.and_then(|p| p.file_name()).map(|n| n.to_string_lossy().into_owned());
+	let path = info.path.map(|p| p.to_string_lossy().into_owned());
+	let dim = info.dimensions.map(|(w, h)| match info.downscaled_from {
+		Some((orig_w, orig_h)) => format!("{} × {} (shrunk from {} × {})", w, h, orig_w, orig_h),
+		None => format!("{} × {}", w, h),
+	});
+	let size = file_size.map(format_file_size);
+	let zoom = info.zoom.map(|zoom| format!("{:.0}%", zoom));
+	let index = info.index.map(|(index, count)| format!("{} / {}", index + 1, count));
+	let replacements = [
+		("%index", index),
+		("%name", name),
+		("%path", path),
+		("%size", size),
+		("%zoom", zoom),
+		("%dim", dim),
+		("%depth", info.bit_depth.clone()),
+		("%sort", info.sort_key.clone()),
+		("%triage", info.triage.clone()),
+		("%decoder", info.decoder.map(str::to_owned)),
+		("%channel", info.channel.map(str::to_owned)),
+		("%pos", info.cursor.map(|(x, y)| format!("x {}  y {}", x, y))),
+	];
+	let mut text = format.to_string();
+	for (pattern, value) in replacements.iter() {
+		text = text.replace(pattern, value.as_deref().unwrap_or("-"));
+	}
+	text.trim().to_string()
+}
@@ -0,0 +1,143 @@
+use std::rc::{Rc, Weak};
+use std::time::{Duration, Instant};
+
+use gelatin::{image::RgbaImage, label::Label, misc::*, picture::Picture, NextUpdate, Widget};
+use lazy_static::lazy_static;
+use usvg::fontdb;
+
+/// The text is rendered at this multiple of its logical size so that it stays sharp on high-dpi
+/// displays.
+pub const TEXT_RENDER_SCALE: f32 = 2.0;
+pub const FONT_SIZE: f32 = 14.0;
+const PADDING: f32 = 6.0;
+
+lazy_static! {
+	static ref FONT_DB: fontdb::Database = {
+		let mut fontdb = fontdb::Database::new();
+		fontdb.load_system_fonts();
+		fontdb
+	};
+}
+
+fn escape_xml(text: &str) -> String {
+	text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Renders a single line of white text on a translucent dark background.
+///
+/// The size of the returned image is `TEXT_RENDER_SCALE` times the logical size of the text.
+/// Returns `None` if the text couldn't be rendered, for example because there are no fonts
+/// available on the system.
+pub fn render_text(text: &str) -> Option<RgbaImage> {
+	render_colored_text(text, [255, 255, 255], [0, 0, 0, 160])
+}
+
+/// The logical height of the text images that `render_sized_text` renders with `font_size`
+pub fn line_height(font_size: f32) -> f32 {
+	font_size + 2.0 * PADDING
+}
+
+/// Like `render_text`, but with the given text and background colors
+pub fn render_colored_text(text: &str, color: [u8; 3], background: [u8; 4]) -> Option<RgbaImage> {
+	render_sized_text(text, FONT_SIZE, color, background)
+}
+
+/// Like `render_colored_text`, but with a font size in logical pixels
+pub fn render_sized_text(
+	text: &str,
+	font_size: f32,
+	color: [u8; 3],
+	background: [u8; 4],
+) -> Option<RgbaImage> {
+	let scale = TEXT_RENDER_SCALE;
+	let font_size = font_size * scale;
+	let svg = format!(
+		r#"<svg xmlns="http://www.w3.org/2000/svg" width="8192" height="{h}">
+			<text x="0" y="{y}" font-family="sans-serif" font-size="{font_size}" fill="rgb({r},{g},{b})">{text}</text>
+		</svg>"#,
+		h = font_size * 2.0,
+		y = font_size * 1.5,
+		r = color[0],
+		g = color[1],
+		b = color[2],
+		text = escape_xml(text),
+	);
+	let tree = usvg::Tree::from_str(&svg, &usvg::Options::default(), &FONT_DB).ok()?;
+	let bounds = tree.root().abs_bounding_box();
+	if bounds.width() <= 0.0 || bounds.height() <= 0.0 {
+		return None;
+	}
+	let padding = PADDING * scale;
+	let width = (bounds.width() + 2.0 * padding).ceil() as u32;
+	let height = (font_size + 2.0 * padding).ceil() as u32;
+	let mut pixmap = tiny_skia::Pixmap::new(width, height)?;
+	let [bg_r, bg_g, bg_b, bg_a] = background;
+	pixmap.fill(tiny_skia::Color::from_rgba8(bg_r, bg_g, bg_b, bg_a));
+	// Center the text vertically based on the font size instead of the bounds of the glyphs,
+	// so that texts with and without descenders are aligned the same way.
+	let baseline = padding + font_size * 0.8;
+	let transform =
+		tiny_skia::Transform::from_translate(padding - bounds.x(), baseline - font_size * 1.5);
+	resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+	let mut image = RgbaImage::new(width, height);
+	for (dst, src) in image.pixels_mut().zip(pixmap.pixels()) {
+		let color = src.demultiply();
+		dst.0 = [color.red(), color.green(), color.blue(), color.alpha()];
+	}
+	Some(image)
+}
+
+/// A label that shows a short text for a while, then hides itself.
+pub struct TextNotification {
+	pub widget: Weak<Label>,
+	hide_time: Instant,
+}
+
+impl TextNotification {
+	pub fn new(widget: &Rc<Label>) -> TextNotification {
+		widget.set_icon(None);
+		widget.set_ignore_layout(true);
+		widget.set_margin_all(8.0);
+		widget.set_horizontal_align(Alignment::Center);
+		widget.set_vertical_align(Alignment::Start);
+		widget.set_visible(false);
+
+		TextNotification { widget: Rc::downgrade(widget), hide_time: Instant::now() }
+	}
+
+	/// Shows `text` for the given duration
+	pub fn show(&mut self, text: &str, duration: Duration) {
+		let widget = self.widget.upgrade().unwrap();
+		match render_text(text) {
+			Some(image) => {
+				let size = LogicalVector::new(
+					image.width() as f32 / TEXT_RENDER_SCALE,
+					image.height() as f32 / TEXT_RENDER_SCALE,
+				);
+				widget.set_fixed_size(size);
+				widget.set_icon(Some(Rc::new(Picture::from_image(image))));
+				widget.set_visible(true);
+				self.hide_time = Instant::now() + duration;
+			}
+			None => {
+				log::warn!("Could not render the notification text: {:?}", text);
+				widget.set_visible(false);
+			}
+		}
+	}
+
+	pub fn update(&mut self) -> NextUpdate {
+		let widget = self.widget.upgrade().unwrap();
+		if widget.visible() {
+			if Instant::now() >= self.hide_time {
+				widget.set_visible(false);
+				NextUpdate::Latest
+			} else {
+				NextUpdate::WaitUntil(self.hide_time)
+			}
+		} else {
+			NextUpdate::Latest
+		}
+	}
+}
@@ -0,0 +1,188 @@
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+
+use gelatin::{
+	image::{imageops, RgbaImage},
+	label::Label,
+	misc::*,
+	picture::Picture,
+};
+
+use super::text_notification::{render_sized_text, TEXT_RENDER_SCALE};
+use crate::configuration::{Configuration, OverlayPosition};
+use crate::image_cache::capture_date::capture_date;
+
+pub const DEFAULT_OVERLAY_SIZE: f32 = 24.0;
+pub const DEFAULT_OVERLAY_OPACITY: f32 = 0.5;
+const MIN_OVERLAY_SIZE: f32 = 6.0;
+const MAX_OVERLAY_SIZE: f32 = 256.0;
+/// The distance between the text and the edges of the image, in logical pixels
+const OVERLAY_MARGIN: f32 = 12.0;
+const OVERLAY_COLOR: [u8; 3] = [255, 255, 255];
+
+/// How the `overlay_text` of the configuration is drawn
+#[derive(Clone)]
+pub struct WatermarkStyle {
+	template: String,
+	position: OverlayPosition,
+	font_size: f32,
+	opacity: f32,
+}
+
+impl WatermarkStyle {
+	/// Returns `None` if there's no `overlay_text` in the configuration
+	pub fn from_config(config: &Configuration) -> Option<WatermarkStyle> {
+		let image = config.image.as_ref()?;
+		let template = image.overlay_text.clone().filter(|text| !text.is_empty())?;
+		let font_size = image.overlay_text_size.unwrap_or(DEFAULT_OVERLAY_SIZE);
+		let opacity = image.overlay_text_opacity.unwrap_or(DEFAULT_OVERLAY_OPACITY);
+		Some(WatermarkStyle {
+			template,
+			position: image.overlay_text_position.unwrap_or_default(),
+			font_size: font_size.clamp(MIN_OVERLAY_SIZE, MAX_OVERLAY_SIZE),
+			opacity: opacity.clamp(0.0, 1.0),
+		})
+	}
+
+	/// The style to use for `copy_view` and the exports, if `watermark_on_export` is set
+	pub fn for_export(config: &Configuration) -> Option<WatermarkStyle> {
+		let on_export = config.image.as_ref().and_then(|i| i.watermark_on_export);
+		on_export.unwrap_or(false).then(|| WatermarkStyle::from_config(config)).flatten()
+	}
+
+	/// The text of the watermark for the image at `path`, with `{name}` replaced by the file
+	/// name and `{date}` by the capture date
+	pub fn text(&self, path: &Path) -> String {
+		let mut text = self.template.clone();
+		if text.contains("{name}") {
+			let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+			text = text.replace("{name}", &name);
+		}
+		if text.contains("{date}") {
+			let date = capture_date(path).map(|d| d.to_string()).unwrap_or_default();
+			text = text.replace("{date}", &date);
+		}
+		text
+	}
+
+	/// Renders `text` at `TEXT_RENDER_SCALE` times its logical size, with the opacity of the
+	/// style
+	fn render(&self, text: &str) -> Option<RgbaImage> {
+		let mut image = render_sized_text(text, self.font_size, OVERLAY_COLOR, [0, 0, 0, 0])?;
+		for pixel in image.pixels_mut() {
+			pixel.0[3] = (pixel.0[3] as f32 * self.opacity).round() as u8;
+		}
+		Some(image)
+	}
+
+	/// The top left corner of a text of `size` in `bounds`
+	fn place(&self, size: (f32, f32), bounds: (f32, f32, f32, f32), margin: f32) -> (f32, f32) {
+		let (x, y, w, h) = bounds;
+		let left = x + margin;
+		let right = x + w - size.0 - margin;
+		let top = y + margin;
+		let bottom = y + h - size.1 - margin;
+		match self.position {
+			OverlayPosition::TopLeft => (left, top),
+			OverlayPosition::TopRight => (right, top),
+			OverlayPosition::BottomLeft => (left, bottom),
+			OverlayPosition::BottomRight => (right, bottom),
+			OverlayPosition::Center => (x + (w - size.0) * 0.5, y + (h - size.1) * 0.5),
+		}
+	}
+
+	/// Draws the watermark of the image at `path` onto `image`, in the `bounds` that the image
+	/// covers. `bounds` is the left, top, width and height in pixels, and `scale` is the number
+	/// of pixels per logical pixel.
+	pub fn stamp(
+		&self,
+		path: &Path,
+		image: &mut RgbaImage,
+		bounds: (f32, f32, f32, f32),
+		scale: f32,
+	) {
+		let Some(text) = self.render(&self.text(path)) else {
+			return;
+		};
+		let text_scale = scale / TEXT_RENDER_SCALE;
+		let w = ((text.width() as f32 * text_scale).round() as u32).max(1);
+		let h = ((text.height() as f32 * text_scale).round() as u32).max(1);
+		let text = imageops::resize(&text, w, h, imageops::FilterType::Triangle);
+		let (x, y) = self.place((w as f32, h as f32), bounds, OVERLAY_MARGIN * scale);
+		imageops::overlay(image, &text, x.round() as i64, y.round() as i64);
+	}
+}
+
+/// The `overlay_text` over the shown image. It follows the image when it's panned and zoomed,
+/// and can be turned off with the `toggle_watermark` action.
+pub struct Watermark {
+	pub widget: Weak<Label>,
+	style: Option<WatermarkStyle>,
+	enabled: bool,
+	/// The image that the watermark was rendered for, and its logical size
+	rendered: Option<(PathBuf, LogicalVector)>,
+}
+
+impl Watermark {
+	pub fn new(widget: &Rc<Label>, config: &Configuration) -> Watermark {
+		widget.set_icon(None);
+		widget.set_ignore_layout(true);
+		widget.set_horizontal_align(Alignment::Start);
+		widget.set_vertical_align(Alignment::Start);
+		widget.set_visible(false);
+
+		Watermark {
+			widget: Rc::downgrade(widget),
+			style: WatermarkStyle::from_config(config),
+			enabled: true,
+			rendered: None,
+		}
+	}
+
+	/// Returns false if there's no `overlay_text` to toggle
+	pub fn toggle(&mut self) -> bool {
+		if self.style.is_none() {
+			return false;
+		}
+		self.enabled = !self.enabled;
+		true
+	}
+
+	pub fn enabled(&self) -> bool {
+		self.enabled
+	}
+
+	/// Places the watermark of `path` in `image_bounds`, which is the part of the picture area
+	/// that the image covers in logical pixels. The watermark is hidden if either is `None`.
+	pub fn update(&mut self, path: Option<&Path>, image_bounds: Option<LogicalRect>) {
+		let widget = self.widget.upgrade().unwrap();
+		let (Some(style), Some(path), Some(bounds), true) =
+			(&self.style, path, image_bounds, self.enabled)
+		else {
+			widget.set_visible(false);
+			return;
+		};
+		let size = match &self.rendered {
+			Some((rendered_path, size)) if rendered_path == path => *size,
+			_ => {
+				let Some(image) = style.render(&style.text(path)) else {
+					widget.set_visible(false);
+					return;
+				};
+				let size = LogicalVector::new(
+					image.width() as f32 / TEXT_RENDER_SCALE,
+					image.height() as f32 / TEXT_RENDER_SCALE,
+				);
+				widget.set_fixed_size(size);
+				widget.set_icon(Some(Rc::new(Picture::from_image(image))));
+				self.rendered = Some((path.to_owned(), size));
+				size
+			}
+		};
+		let bounds = (bounds.pos.vec.x, bounds.pos.vec.y, bounds.size.vec.x, bounds.size.vec.y);
+		let (x, y) = style.place((size.vec.x, size.vec.y), bounds, OVERLAY_MARGIN);
+		widget.set_margin_left(x);
+		widget.set_margin_top(y);
+		widget.set_visible(true);
+	}
+}
@@ -21,11 +21,19 @@ use crate::{
 
 // const MAX_SLEEP_DURATION: std::time::Duration = std::time::Duration::from_millis(4);
 static EXIT_REQUESTED: AtomicBool = AtomicBool::new(false);
+static EXIT_ON_CLOSE: AtomicBool = AtomicBool::new(true);
 
 pub fn request_exit() {
 	EXIT_REQUESTED.store(true, Ordering::Relaxed);
 }
 
+/// Sets whether closing a window exits the application. When this is off, the
+/// `CloseRequested` event is only passed on to the handlers, which decide what to do
+/// with the window.
+pub fn set_exit_on_close(exit_on_close: bool) {
+	EXIT_ON_CLOSE.store(exit_on_close, Ordering::Relaxed);
+}
+
 fn set_control_flow(event_loop: &WinitActiveEventLoop, control_flow: ControlFlow) {
 	if let ControlFlow::WaitUntil(time) = control_flow {
 		let very_short_time_from_now = Instant::now() + Duration::from_micros(100);
@@ -48,11 +56,9 @@ fn aggregate_control_flow(event_loop: &WinitActiveEventLoop, new: ControlFlow) -
 			return true;
 		}
 		ControlFlow::WaitUntil(new_time) => match original {
-			ControlFlow::WaitUntil(orig_time) => {
-				if new_time < orig_time {
-					set_control_flow(event_loop, new);
-					return true;
-				}
+			ControlFlow::WaitUntil(orig_time) if new_time < orig_time => {
+				set_control_flow(event_loop, new);
+				return true;
 			}
 			ControlFlow::Wait => {
 				set_control_flow(event_loop, new);
@@ -78,7 +84,7 @@ fn sanitize_control_flow(event_loop: &WinitActiveEventLoop) {
 // pub type EventHandler<UserEvent> = dyn FnMut(&Event<UserEvent>) -> NextUpdate;
 
 pub struct Application {
-	windows: HashMap<WindowId, Rc<Window>>,
+	pub(crate) windows: HashMap<WindowId, Rc<Window>>,
 	first_resume_done: bool,
 }
 
@@ -93,35 +99,16 @@ impl Application {
 
 	pub fn start_event_loop<UserEvent: Debug + 'static>(
 		&mut self,
-		application_handler: impl ApplicationHandler,
+		application_handler: impl ApplicationHandler<UserEvent>,
 		event_loop: EventLoop<UserEvent>,
 	) {
-		#[cfg(feature = "benchmark")]
-		let mut update_draw_dt = {
-			let mut last_draw_time = std::time::Instant::now();
-			let mut prev_draw_dts = vec![0f32; 64];
-			let mut prev_draw_dt_index = 0;
-
-			move || {
-				let now = std::time::Instant::now();
-				let delta_time = now.duration_since(last_draw_time).as_secs_f32();
-				last_draw_time = now;
-				prev_draw_dts[prev_draw_dt_index] = delta_time;
-				prev_draw_dt_index = (prev_draw_dt_index + 1) % prev_draw_dts.len();
-				if prev_draw_dt_index == 0 {
-					let max_dt = prev_draw_dts.iter().fold(0.0f32, |a, &b| a.max(b));
-					println!(
-						"{} redraws finsished, max delta time in that duration was: {}ms, {} FPS",
-						prev_draw_dts.len(),
-						(max_dt * 1000.0).round() as i32,
-						(1.0 / max_dt).round() as i32
-					);
-				}
-			}
+		let mut app_with_app_handler = AppWithAppHandler {
+			application: self,
+			application_handler,
+			#[cfg(feature = "benchmark")]
+			draw_timer: crate::benchmark::DrawTimer::from_env(),
 		};
 
-		let mut app_with_app_handler = AppWithAppHandler { application: self, application_handler };
-
 		event_loop.inner.run_app(&mut app_with_app_handler).unwrap();
 	}
 }
@@ -132,23 +119,31 @@ impl Default for Application {
 	}
 }
 
-struct AppWithAppHandler<'a, AppHandler>
-where
-	AppHandler: ApplicationHandler,
-{
+struct AppWithAppHandler<'a, AppHandler> {
 	application: &'a mut Application,
 	application_handler: AppHandler,
+	#[cfg(feature = "benchmark")]
+	draw_timer: crate::benchmark::DrawTimer,
 }
 
-pub trait ApplicationHandler {
+pub trait ApplicationHandler<UserEvent = ()> {
 	fn handle_can_create_surface(&mut self, event_loop: &mut ActiveEventLoop);
 	fn handle_window_event(
 		&mut self,
-		event_loop: &ActiveEventLoop,
+		event_loop: &mut ActiveEventLoop,
 		window_id: WindowId,
 		event: &WindowEvent,
 	) -> NextUpdate;
 
+	/// Called with the events sent through an `EventLoopProxy`
+	fn handle_user_event(
+		&mut self,
+		_event_loop: &mut ActiveEventLoop,
+		_event: UserEvent,
+	) -> NextUpdate {
+		NextUpdate::Latest
+	}
+
 	// fn resumed(&mut self, event_loop: &ActiveEventLoop<UserEvent>);
 	// fn about_to_wait(&mut self, event_loop: &ActiveEventLoop<UserEvent>);
 
@@ -159,7 +154,7 @@ impl<'a, UserEvent, AppHandler> WinitApplicationHandler<UserEvent>
 	for AppWithAppHandler<'a, AppHandler>
 where
 	UserEvent: Debug + 'static,
-	AppHandler: ApplicationHandler,
+	AppHandler: ApplicationHandler<UserEvent>,
 {
 	fn resumed(&mut self, event_loop: &WinitActiveEventLoop) {
 		if !self.application.first_resume_done {
@@ -187,6 +182,14 @@ where
 		self.application_handler.exiting();
 	}
 
+	fn user_event(&mut self, event_loop: &WinitActiveEventLoop, event: UserEvent) {
+		let handler_next_update = self.application_handler.handle_user_event(
+			&mut ActiveEventLoop { inner: event_loop, application: self.application },
+			event,
+		);
+		aggregate_control_flow(event_loop, handler_next_update.into());
+	}
+
 	fn new_events(&mut self, event_loop: &WinitActiveEventLoop, _cause: event::StartCause) {
 		event_loop.set_control_flow(ControlFlow::Wait);
 		for window in self.application.windows.values() {
@@ -204,23 +207,28 @@ where
 		sanitize_control_flow(event_loop);
 
 		let handler_next_update = self.application_handler.handle_window_event(
-			&ActiveEventLoop { inner: event_loop, application: self.application },
+			&mut ActiveEventLoop { inner: event_loop, application: self.application },
 			window_id,
 			&event,
 		);
 		aggregate_control_flow(event_loop, handler_next_update.into());
 
+		// The handler may have closed the window
+		let Some(window) = self.application.windows.get(&window_id).cloned() else {
+			return;
+		};
 		if let WindowEvent::RedrawRequested = event {
-			let new_control_flow =
-				self.application.windows.get(&window_id).unwrap().redraw().into();
+			let new_control_flow = window.redraw().into();
 			aggregate_control_flow(event_loop, new_control_flow);
 			#[cfg(feature = "benchmark")]
-			update_draw_dt();
+			self.draw_timer.update();
 		}
 		if let WindowEvent::CloseRequested = event {
 			// This actually wouldn't be okay for a general pupose ui toolkit,
 			// but gelatin is specifically made for emulsion so this is fine hehe
-			request_exit();
+			if EXIT_ON_CLOSE.load(Ordering::Relaxed) {
+				request_exit();
+			}
 		}
 		let destroyed;
 		if let WindowEvent::Destroyed = event {
@@ -228,11 +236,7 @@ where
 		} else {
 			destroyed = false;
 		}
-		self.application
-			.windows
-			.get(&window_id)
-			.unwrap()
-			.process_event::<UserEvent>(event, event_loop);
+		window.process_event::<UserEvent>(event, event_loop);
 		if destroyed {
 			self.application.windows.remove(&window_id);
 		}
@@ -0,0 +1,123 @@
+//! Reports how long the redraws take, when built with the `benchmark` feature.
+//!
+//! After every `GELATIN_BENCHMARK_WINDOW` redraws (64 by default), the longest time between two
+//! redraws is reported. `GELATIN_BENCHMARK_FORMAT` is `human` (the default), `csv` or `json`,
+//! and `GELATIN_BENCHMARK_OUTPUT` is a file to append the reports to instead of stdout.
+
+use std::{
+	env,
+	fs::{File, OpenOptions},
+	io::{self, Write},
+	time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+const DEFAULT_WINDOW: usize = 64;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Format {
+	Human,
+	/// `timestamp,frame_count,max_dt_ms,fps`, where the timestamp is in milliseconds since the
+	/// Unix epoch
+	Csv,
+	/// One object per line, with the same fields as `Csv`
+	Json,
+}
+
+pub(crate) struct DrawTimer {
+	format: Format,
+	output: Option<File>,
+	last_draw_time: Instant,
+	prev_draw_dts: Vec<f32>,
+	prev_draw_dt_index: usize,
+	/// The number of redraws since the start
+	frame_count: u64,
+}
+
+impl DrawTimer {
+	pub fn from_env() -> DrawTimer {
+		let format = match env::var("GELATIN_BENCHMARK_FORMAT").as_deref() {
+			Err(_) | Ok("human") => Format::Human,
+			Ok("csv") => Format::Csv,
+			Ok("json") => Format::Json,
+			Ok(val) => {
+				eprintln!("Illegal value {:?} for GELATIN_BENCHMARK_FORMAT!", val);
+				eprintln!(r#"Allowed values are "human", "csv" and "json"."#);
+				Format::Human
+			}
+		};
+		let window = match env::var("GELATIN_BENCHMARK_WINDOW") {
+			Err(_) => DEFAULT_WINDOW,
+			Ok(val) => match val.parse() {
+				Ok(window) if window > 0 => window,
+				_ => {
+					eprintln!("Illegal value {:?} for GELATIN_BENCHMARK_WINDOW!", val);
+					eprintln!("It has to be a positive number of redraws.");
+					DEFAULT_WINDOW
+				}
+			},
+		};
+		let output =
+			env::var_os("GELATIN_BENCHMARK_OUTPUT").and_then(|path| {
+				match OpenOptions::new().create(true).append(true).open(&path) {
+					Ok(file) => Some(file),
+					Err(e) => {
+						eprintln!("Could not open {:?}, writing to stdout instead: {}", path, e);
+						None
+					}
+				}
+			});
+		let mut timer = DrawTimer {
+			format,
+			output,
+			last_draw_time: Instant::now(),
+			prev_draw_dts: vec![0.0; window],
+			prev_draw_dt_index: 0,
+			frame_count: 0,
+		};
+		if format == Format::Csv {
+			timer.write_line("timestamp,frame_count,max_dt_ms,fps");
+		}
+		timer
+	}
+
+	/// Called after every redraw
+	pub fn update(&mut self) {
+		let now = Instant::now();
+		let delta_time = now.duration_since(self.last_draw_time).as_secs_f32();
+		self.last_draw_time = now;
+		self.frame_count += 1;
+		self.prev_draw_dts[self.prev_draw_dt_index] = delta_time;
+		self.prev_draw_dt_index = (self.prev_draw_dt_index + 1) % self.prev_draw_dts.len();
+		if self.prev_draw_dt_index != 0 {
+			return;
+		}
+		let max_dt = self.prev_draw_dts.iter().fold(0.0f32, |a, &b| a.max(b));
+		let max_dt_ms = (max_dt * 1000.0).round() as i32;
+		let fps = (1.0 / max_dt).round() as i32;
+		let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis());
+		let line = match self.format {
+			Format::Human => format!(
+				"{} redraws finsished, max delta time in that duration was: {}ms, {} FPS",
+				self.prev_draw_dts.len(),
+				max_dt_ms,
+				fps
+			),
+			Format::Csv => format!("{},{},{},{}", timestamp, self.frame_count, max_dt_ms, fps),
+			Format::Json => format!(
+				r#"{{"timestamp":{},"frame_count":{},"max_dt_ms":{},"fps":{}}}"#,
+				timestamp, self.frame_count, max_dt_ms, fps
+			),
+		};
+		self.write_line(&line);
+	}
+
+	fn write_line(&mut self, line: &str) {
+		let result = match &mut self.output {
+			Some(file) => writeln!(file, "{}", line),
+			None => writeln!(io::stdout(), "{}", line),
+		};
+		if let Err(e) = result {
+			eprintln!("Could not write the benchmark results: {}", e);
+		}
+	}
+}
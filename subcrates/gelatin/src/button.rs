@@ -22,6 +22,8 @@ struct ButtonData {
 	icon: Option<Rc<Picture>>,
 	bg_color: [f32; 4],
 	on_click: Option<Rc<dyn Fn()>>,
+	focus_order: Option<i32>,
+	focused: bool,
 
 	render_validity: RenderValidity,
 }
@@ -51,6 +53,8 @@ impl Button {
 				click: false,
 				hover: false,
 				on_click: None,
+				focus_order: None,
+				focused: false,
 				bg_color: [0.0; 4],
 				icon: None,
 				render_validity: Default::default(),
@@ -67,6 +71,11 @@ impl Button {
 		borrowed.on_click = Some(Rc::new(callback));
 	}
 
+	/// Makes the button reachable with Tab. See `Widget::focus_order`
+	pub fn set_focus_order(&self, order: Option<i32>) {
+		self.data.borrow_mut().focus_order = order;
+	}
+
 	pub fn set_icon(&self, img: Option<Rc<Picture>>) {
 		let mut borrowed = self.data.borrow_mut();
 		borrowed.icon = img;
@@ -167,6 +176,9 @@ impl Widget for Button {
 					)
 					.unwrap();
 			}
+			if borrowed.focused {
+				context.draw_focus_ring(target, aligned_bounds);
+			}
 		}
 		Ok(NextUpdate::Latest)
 	}
@@ -227,4 +239,21 @@ impl Widget for Button {
 	fn set_valid_ref(&self, render_validity: RenderValidity) {
 		self.data.borrow_mut().render_validity = render_validity;
 	}
+
+	fn focus_order(&self) -> Option<i32> {
+		self.data.borrow().focus_order
+	}
+
+	fn set_focused(&self, focused: bool) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.focused = focused;
+		borrowed.render_validity.invalidate();
+	}
+
+	fn activate(&self) {
+		let on_click = self.data.borrow().on_click.clone();
+		if let Some(callback) = on_click {
+			callback();
+		}
+	}
 }
@@ -45,4 +45,10 @@ impl<'a> ActiveEventLoop<'a> {
 	) -> Result<Rc<Window>, Box<dyn std::error::Error>> {
 		Window::new(self.application, desc, self.inner)
 	}
+
+	/// Stops sending events to the window. The window is destroyed once the last reference to it
+	/// is dropped.
+	pub fn close_window(&mut self, window_id: winit::window::WindowId) {
+		self.application.windows.remove(&window_id);
+	}
 }
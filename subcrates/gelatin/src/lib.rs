@@ -15,7 +15,7 @@ use winit::{
 	keyboard::ModifiersState,
 };
 
-use cgmath::{Matrix4, Vector3};
+use cgmath::{Matrix4, Vector2, Vector3};
 use glium::{
 	glutin::surface::WindowSurface, implement_vertex, uniform, Blend, BlendingFunction, Frame,
 	IndexBuffer, LinearBlendingFactor, Program, Rect, Surface, VertexBuffer,
@@ -24,6 +24,8 @@ use glium::{
 use misc::*;
 
 pub mod application;
+#[cfg(feature = "benchmark")]
+mod benchmark;
 pub mod button;
 pub mod event_loop;
 pub mod label;
@@ -242,6 +244,19 @@ pub trait Widget: Any {
 	/// Containers must call this function for all of their children
 	/// immediately and pass a clone of the provided object.
 	fn set_valid_ref(&self, rendered_valid: window::RenderValidity);
+
+	/// The position of this widget in the order in which Tab moves the keyboard focus, or `None`
+	/// if the widget can't have the focus. Widgets at the same position are visited in the order
+	/// of the widget tree.
+	fn focus_order(&self) -> Option<i32> {
+		None
+	}
+
+	/// Called when the widget gets or loses the keyboard focus
+	fn set_focused(&self, _focused: bool) {}
+
+	/// Called when Enter or Space is pressed while the widget has the keyboard focus
+	fn activate(&self) {}
 }
 
 /// This function can be used to avoid comparing fat trait pointers as those can be
@@ -361,6 +376,8 @@ pub struct DrawContext<'a> {
 	pub colored_program: &'a Program,
 	pub viewport: &'a Rect,
 	pub projection_transform: &'a Matrix4<f32>,
+	/// The color of the ring drawn around the widget that has the keyboard focus
+	pub focus_color: [f32; 4],
 }
 impl<'a> DrawContext<'a> {
 	pub fn logical_rect_to_viewport(&self, rect: &LogicalRect) -> Rect {
@@ -413,4 +430,21 @@ impl<'a> DrawContext<'a> {
 			)
 			.unwrap();
 	}
+
+	/// Draws the focus ring along the inside of `rect`
+	pub fn draw_focus_ring(&self, target: &mut Frame, rect: LogicalRect) {
+		const WIDTH: f32 = 2.0;
+		let pos = rect.pos.vec;
+		let size = rect.size.vec;
+		let edges = [
+			(pos, LogicalVector::new(size.x, WIDTH)),
+			(pos + Vector2::new(0.0, size.y - WIDTH), LogicalVector::new(size.x, WIDTH)),
+			(pos, LogicalVector::new(WIDTH, size.y)),
+			(pos + Vector2::new(size.x - WIDTH, 0.0), LogicalVector::new(WIDTH, size.y)),
+		];
+		for (edge_pos, edge_size) in edges {
+			let edge = LogicalRect { pos: LogicalVector { vec: edge_pos }, size: edge_size };
+			self.clear_color(target, self.focus_color, Some(edge));
+		}
+	}
 }
@@ -1,4 +1,4 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, rc::Rc, time::Instant};
 
 use cgmath::{Matrix4, Vector3};
 use glium::{uniform, Frame, Surface};
@@ -6,10 +6,61 @@ use winit::event::{ElementState, MouseButton};
 
 use crate::add_common_widget_functions;
 use crate::misc::{Alignment, Length, LogicalRect, LogicalVector, WidgetPlacement};
-use crate::window::RenderValidity;
+use crate::window::{RenderValidity, Window};
 use crate::NextUpdate;
 use crate::{DrawContext, Event, EventKind, Widget, WidgetData, WidgetError};
 
+/// The shortest glide in seconds, which stands for a glide that should end right away
+const MIN_INERTIA: f32 = 0.001;
+/// A glide stops once it has less than this many steps to go
+const MIN_GLIDE_DISTANCE: f32 = 0.05;
+/// Only the movement of a drag within this many seconds before the release flings the slider
+const FLING_WINDOW: f32 = 0.1;
+
+/// How the mouse wheel moves the slider, see `Slider::set_scrolling`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scrolling {
+	/// The number of steps that one notch of the wheel moves the slider by
+	pub speed: f32,
+	/// How long the slider glides after a notch of the wheel or a fling, in seconds. The speed of
+	/// the glide drops to a third within this time, and zero stops it right away.
+	pub inertia: f32,
+}
+
+/// The slider moving on its own, with a velocity that decays exponentially
+#[derive(Debug, Clone, Copy)]
+struct Glide {
+	position: f32,
+	/// In steps per second
+	velocity: f32,
+	updated: Instant,
+}
+
+impl Glide {
+	/// Moves the glide forward to `now`, stopping it at the ends. Returns false once it came to
+	/// rest.
+	fn advance(&mut self, now: Instant, inertia: f32, last_step: f32) -> bool {
+		let inertia = inertia.max(MIN_INERTIA);
+		let elapsed = now.saturating_duration_since(self.updated).as_secs_f32();
+		self.updated = now;
+		let decay = (-elapsed / inertia).exp();
+		// The distance that the velocity covers while it decays, so that the glide doesn't depend
+		// on the frame rate. In total, that's `velocity * inertia`.
+		self.position += self.velocity * inertia * (1.0 - decay);
+		self.velocity *= decay;
+		if self.position <= 0.0 || self.position >= last_step {
+			self.position = self.position.clamp(0.0, last_step);
+			self.velocity = 0.0;
+		}
+		(self.velocity * inertia).abs() >= MIN_GLIDE_DISTANCE
+	}
+
+	/// Adds the velocity that takes the glide `steps` further
+	fn push(&mut self, steps: f32, inertia: f32) {
+		self.velocity += steps / inertia.max(MIN_INERTIA);
+	}
+}
+
 struct SliderData {
 	placement: WidgetPlacement,
 	drawn_bounds: LogicalRect,
@@ -19,9 +70,20 @@ struct SliderData {
 	value: u32,
 	click: bool,
 	hover: bool,
+	/// The step under the cursor and the horizontal position of the cursor
+	hovered: Option<(u32, f32)>,
 	on_value_change: Option<Rc<dyn Fn()>>,
 	shadow_color: [f32; 3],
 
+	/// The wheel only moves the slider when this is set
+	scrolling: Option<Scrolling>,
+	glide: Option<Glide>,
+	/// Where the line is drawn. It follows `value` smoothly when `scrolling` is set.
+	drawn_value: f32,
+	drawn_at: Instant,
+	/// The value and the time the fling of a drag is measured from
+	fling_from: Option<(u32, Instant)>,
+
 	render_validity: RenderValidity,
 	//rendered_valid: bool,
 }
@@ -52,8 +114,14 @@ impl Slider {
 				value: 0,
 				click: false,
 				hover: false,
+				hovered: None,
 				on_value_change: None,
 				shadow_color: [0.0, 0.0, 0.0],
+				scrolling: None,
+				glide: None,
+				drawn_value: 0.0,
+				drawn_at: Instant::now(),
+				fling_from: None,
 				render_validity: Default::default(),
 				//rendered_valid: false,
 			}),
@@ -70,13 +138,20 @@ impl Slider {
 		self.data.borrow().value
 	}
 
+	/// While the slider glides, it keeps its own value, like while it's dragged
 	pub fn set_steps(&self, steps: u32, value: u32) {
 		let mut borrowed = self.data.borrow_mut();
 		let prev_steps = borrowed.steps;
 		let prev_value = borrowed.value;
 		borrowed.steps = steps;
-		borrowed.value = value;
-		if prev_steps != steps || prev_value != value {
+		if borrowed.glide.is_none() || prev_steps != steps {
+			borrowed.glide = None;
+			borrowed.value = value;
+		}
+		if prev_steps != steps {
+			borrowed.drawn_value = borrowed.value as f32;
+		}
+		if prev_steps != steps || prev_value != borrowed.value {
 			borrowed.render_validity.invalidate();
 		}
 	}
@@ -87,6 +162,17 @@ impl Slider {
 		borrowed.render_validity.invalidate();
 	}
 
+	/// Whether the slider is being dragged with the left button
+	pub fn dragged(&self) -> bool {
+		self.data.borrow().click
+	}
+
+	/// The step under the cursor and the horizontal position of the cursor, while the cursor is
+	/// over the slider
+	pub fn hovered(&self) -> Option<(u32, f32)> {
+		self.data.borrow().hovered
+	}
+
 	/// Feel free to use `RefCell`s within the callback to satisfy the apparent constnes
 	/// of the callback.
 	pub fn set_on_value_change<T: Fn() + 'static>(&self, callback: T) {
@@ -99,9 +185,87 @@ impl Slider {
 		borrowed.shadow_color = color;
 		borrowed.render_validity.invalidate();
 	}
+
+	/// Lets the mouse wheel and flinging a drag move the slider. The slider glides to a stop, and
+	/// its line also glides when the value is set.
+	pub fn set_scrolling(&self, scrolling: Option<Scrolling>) {
+		self.data.borrow_mut().scrolling = scrolling;
+	}
+}
+
+impl SliderData {
+	fn last_step(&self) -> f32 {
+		self.steps.saturating_sub(1) as f32
+	}
+
+	/// Starts a glide from the current value, or speeds up the one in progress
+	fn push_glide(&mut self, steps: f32, inertia: f32) {
+		let value = self.value as f32;
+		let glide = self.glide.get_or_insert(Glide {
+			position: value,
+			velocity: 0.0,
+			updated: Instant::now(),
+		});
+		glide.push(steps, inertia);
+		self.render_validity.invalidate();
+	}
+
+	fn value_at(&self, cursor_x: f32) -> u32 {
+		let relative_cursor_x = cursor_x - self.drawn_bounds.pos.vec.x;
+		let proportion = (relative_cursor_x / self.drawn_bounds.size.vec.x).clamp(0.0, 1.0);
+		let stepsf = self.steps as f32;
+		(proportion * (1.0 + 1.0 / stepsf) * (stepsf - 1.0)).floor() as u32
+	}
 }
 
 impl Widget for Slider {
+	fn before_draw(&self, _window: &Window) -> NextUpdate {
+		let on_value_change;
+		let next_update;
+		{
+			let mut borrowed = self.data.borrow_mut();
+			let data = &mut *borrowed;
+			let Some(scrolling) = data.scrolling else {
+				return NextUpdate::Latest;
+			};
+			let now = Instant::now();
+			let prev_value = data.value;
+			let last_step = data.last_step();
+			match &mut data.glide {
+				Some(glide) => {
+					let moving = glide.advance(now, scrolling.inertia, last_step);
+					data.value = glide.position.round() as u32;
+					data.drawn_value = glide.position;
+					if !moving {
+						data.glide = None;
+						data.drawn_value = data.value as f32;
+					}
+				}
+				None => {
+					let elapsed = now.saturating_duration_since(data.drawn_at).as_secs_f32();
+					let decay = (-elapsed / scrolling.inertia.max(MIN_INERTIA)).exp();
+					let target = data.value as f32;
+					data.drawn_value = target + (data.drawn_value - target) * decay;
+					if (data.drawn_value - target).abs() < MIN_GLIDE_DISTANCE {
+						data.drawn_value = target;
+					}
+				}
+			}
+			data.drawn_at = now;
+			let animating = data.glide.is_some() || data.drawn_value != data.value as f32;
+			if animating {
+				data.render_validity.invalidate();
+			}
+			next_update = if animating { NextUpdate::Soonest } else { NextUpdate::Latest };
+			on_value_change =
+				if data.value != prev_value { data.on_value_change.clone() } else { None };
+		}
+		if let Some(callback) = on_value_change {
+			callback();
+		}
+		next_update
+	}
+
 	fn draw(&self, target: &mut Frame, context: &DrawContext) -> Result<NextUpdate, WidgetError> {
 		use glium::{Blend, BlendingFunction, LinearBlendingFactor};
 		{
@@ -130,7 +294,11 @@ impl Widget for Slider {
 			// -----------------------
 			// Draw vertical line at slider value
 			// Do this before the shadow so the shadow we draw later will cover this line as well
-			let value_ratio = (borrowed.value as f32 + 0.5) / (borrowed.steps as f32);
+			let drawn_value = match borrowed.scrolling {
+				Some(_) => borrowed.drawn_value,
+				None => borrowed.value as f32,
+			};
+			let value_ratio = (drawn_value + 0.5) / (borrowed.steps as f32);
 			let slider_pos = Vector3::new(position.x + value_ratio * size.x, position.y, 0.0);
 			let color = [0.4, 0.4, 0.4, 1.0f32];
 
@@ -197,15 +365,23 @@ impl Widget for Slider {
 			{
 				let mut borrowed = self.data.borrow_mut();
 				borrowed.hover = borrowed.drawn_bounds.contains(event.cursor_pos);
+				let cursor_x = event.cursor_pos.vec.x;
+				let hovered = if borrowed.hover { Some(borrowed.value_at(cursor_x)) } else { None };
+				if hovered != borrowed.hovered.map(|(value, _)| value) {
+					// So that the widgets that show something for the hovered step get updated
+					borrowed.render_validity.invalidate();
+				}
+				borrowed.hovered = hovered.map(|value| (value, cursor_x));
 				if borrowed.click {
 					let prev_value = borrowed.value;
-					let relative_cursor_x =
-						event.cursor_pos.vec.x - borrowed.drawn_bounds.pos.vec.x;
-					let proportion =
-						(relative_cursor_x / borrowed.drawn_bounds.size.vec.x).clamp(0.0, 1.0);
-					let stepsf = borrowed.steps as f32;
-					borrowed.value =
-						(proportion * (1.0 + 1.0 / stepsf) * (stepsf - 1.0)).floor() as u32;
+					borrowed.value = borrowed.value_at(cursor_x);
+					borrowed.drawn_value = borrowed.value as f32;
+					let now = Instant::now();
+					match borrowed.fling_from {
+						Some((_, time))
+							if now.duration_since(time).as_secs_f32() < FLING_WINDOW => {}
+						_ => borrowed.fling_from = Some((prev_value, now)),
+					}
 					if borrowed.value != prev_value {
 						borrowed.render_validity.invalidate();
 						on_value_change = borrowed.on_value_change.clone();
@@ -229,14 +405,40 @@ impl Widget for Slider {
 					{
 						let mut borrowed = self.data.borrow_mut();
 						borrowed.click = borrowed.hover;
+						if borrowed.click {
+							borrowed.glide = None;
+						}
 					}
 					check_value_change();
+					// Jumping to the clicked step isn't a fling
+					let mut borrowed = self.data.borrow_mut();
+					borrowed.fling_from = Some((borrowed.value, Instant::now()));
 				}
 				ElementState::Released => {
 					let mut borrowed = self.data.borrow_mut();
-					borrowed.click = false;
+					let was_dragged = std::mem::take(&mut borrowed.click);
+					let fling_from = borrowed.fling_from.take();
+					if let (true, Some(scrolling), Some((from, time))) =
+						(was_dragged, borrowed.scrolling, fling_from)
+					{
+						let elapsed = time.elapsed().as_secs_f32();
+						if elapsed < FLING_WINDOW * 2.0 && borrowed.value != from {
+							// A short measurement, right after the start of the window, would
+							// exaggerate the speed
+							let velocity =
+								(borrowed.value as f32 - from as f32) / elapsed.max(FLING_WINDOW);
+							borrowed.push_glide(velocity * scrolling.inertia, scrolling.inertia);
+						}
+					}
 				}
 			},
+			EventKind::MouseScroll { delta } => {
+				let mut borrowed = self.data.borrow_mut();
+				if let (true, Some(scrolling)) = (borrowed.hover, borrowed.scrolling) {
+					// Scrolling down moves to the right, like the next image
+					borrowed.push_glide(-delta.vec.y * scrolling.speed, scrolling.inertia);
+				}
+			}
 			_ => (),
 		}
 	}
@@ -262,3 +464,43 @@ impl Default for Slider {
 		Self::new()
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::time::Duration;
+
+	/// Advances a glide that was pushed by `steps` in frames of `frame_time` until it stops, and
+	/// returns where it stopped and after how many frames
+	fn glide(steps: f32, inertia: f32, frame_time: Duration) -> (f32, usize) {
+		let start = Instant::now();
+		let mut glide = Glide { position: 5.0, velocity: 0.0, updated: start };
+		glide.push(steps, inertia);
+		let mut frames = 1;
+		while glide.advance(start + frame_time * frames as u32, inertia, 10.0) {
+			frames += 1;
+		}
+		(glide.position, frames)
+	}
+
+	#[test]
+	fn a_glide_goes_as_far_at_any_frame_rate() {
+		let (smooth, smooth_frames) = glide(3.0, 0.15, Duration::from_millis(8));
+		let (choppy, choppy_frames) = glide(3.0, 0.15, Duration::from_millis(50));
+		assert_eq!((smooth.round(), choppy.round()), (8.0, 8.0));
+		assert!((smooth - choppy).abs() < MIN_GLIDE_DISTANCE);
+		assert!(smooth_frames > choppy_frames && choppy_frames > 1);
+	}
+
+	#[test]
+	fn a_glide_stops_at_the_ends() {
+		assert_eq!(glide(20.0, 0.15, Duration::from_millis(16)).0, 10.0);
+		assert_eq!(glide(-20.0, 0.15, Duration::from_millis(16)).0, 0.0);
+	}
+
+	#[test]
+	fn without_inertia_the_slider_moves_right_away() {
+		let (position, frames) = glide(-2.0, 0.0, Duration::from_millis(16));
+		assert_eq!((position.round(), frames), (3.0, 1));
+	}
+}
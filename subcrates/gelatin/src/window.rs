@@ -12,26 +12,29 @@ use glium::{
 	uniform, Blend, BlendingFunction, Display, Frame, IndexBuffer, Program, Rect, Surface,
 	VertexBuffer,
 };
-use log::{debug, error, warn};
+use image::RgbaImage;
+use log::{debug, error, info, warn};
 use raw_window_handle::HasWindowHandle;
 use winit::{
 	dpi::{PhysicalPosition, PhysicalSize},
-	event::WindowEvent,
+	event::{ElementState, KeyEvent, WindowEvent},
 	event_loop::ActiveEventLoop,
-	keyboard::ModifiersState,
-	window::{CursorIcon, Fullscreen, Icon, WindowAttributes, WindowId},
+	keyboard::{Key, ModifiersState, NamedKey},
+	window::{
+		CursorIcon, CustomCursor, CustomCursorSource, Fullscreen, Icon, WindowAttributes, WindowId,
+	},
 };
 
 #[cfg(x11_platform)]
 use winit::platform::x11::WindowBuilderExtX11;
 
 #[cfg(wayland_platform)]
-use winit::platform::wayland::{EventLoopWindowTargetExtWayland, WindowBuilderExtWayland};
+use winit::platform::wayland::WindowBuilderExtWayland;
 
 use std::{
 	cell::{Cell, RefCell, RefMut},
 	cmp::Eq,
-	fmt::Debug,
+	fmt::{self, Debug},
 	hash::{Hash, Hasher},
 	num::NonZeroU32,
 	ops::{Deref, DerefMut},
@@ -48,7 +51,7 @@ use crate::{
 };
 use crate::{
 	misc::{FromPhysical, LogicalRect, LogicalVector},
-	DrawContext, Event, EventKind, NextUpdate, Vertex, Widget,
+	widget_data_ptr, DrawContext, Event, EventKind, NextUpdate, Vertex, Widget,
 };
 
 const EVENT_UPDATE_DELTA: std::time::Duration = std::time::Duration::from_millis(2);
@@ -124,6 +127,71 @@ impl<'a> DerefMut for WinitWindowRefMut<'a> {
 	}
 }
 
+/// Selects between the GPU driver and a software implementation of OpenGL
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum Renderer {
+	/// Use a hardware accelerated configuration, and fall back to any other configuration of the
+	/// driver if that fails
+	#[default]
+	Auto,
+	Hardware,
+	/// Always render on the CPU. This relies on Mesa (llvmpipe), and `use_software_rendering`
+	/// has to be called at startup
+	Software,
+}
+
+impl Renderer {
+	/// Calls `build` with the value for `prefer_hardware_accelerated` of the OpenGL
+	/// configuration, once more if the first attempt of `Auto` fails
+	fn build<T>(
+		self,
+		mut build: impl FnMut(Option<bool>) -> Result<T, WindowCreationError>,
+	) -> Result<T, WindowCreationError> {
+		match self {
+			Renderer::Hardware => build(Some(true)),
+			// Mesa only offers its software configurations, which don't have to be marked slow
+			Renderer::Software => build(None),
+			Renderer::Auto => build(Some(true)).or_else(|err| {
+				warn!("{err}. Falling back to software rendering.");
+				build(None)
+			}),
+		}
+	}
+}
+
+/// Asks Mesa to use its software rasterizer for the OpenGL displays. This changes the
+/// environment of the process, so it has to be called before any other thread is started.
+pub fn use_software_rendering() {
+	std::env::set_var("LIBGL_ALWAYS_SOFTWARE", "1");
+}
+
+/// The reasons for which creating a window can fail
+#[derive(Debug)]
+pub enum WindowCreationError {
+	/// No suitable OpenGL configuration was found for the display
+	AdapterNotFound(String),
+	/// The surface to draw on couldn't be created for the window
+	SurfaceUnavailable(String),
+	/// The OpenGL context couldn't be created or couldn't be made current
+	ContextUnavailable(String),
+}
+impl fmt::Display for WindowCreationError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			WindowCreationError::AdapterNotFound(err) => {
+				write!(f, "Could not find a suitable OpenGL configuration ({})", err)
+			}
+			WindowCreationError::SurfaceUnavailable(err) => {
+				write!(f, "Could not create the window surface ({})", err)
+			}
+			WindowCreationError::ContextUnavailable(err) => {
+				write!(f, "Could not create the OpenGL context ({})", err)
+			}
+		}
+	}
+}
+impl std::error::Error for WindowCreationError {}
+
 #[derive(Builder, Clone)]
 #[builder(setter(into))]
 pub struct WindowDescriptor {
@@ -145,6 +213,25 @@ pub struct WindowDescriptor {
 	#[builder(default)]
 	#[allow(dead_code)]
 	pub app_id: Option<String>,
+
+	/// The number of samples per pixel to use for multisample antialiasing.
+	/// This is only a request, the number of samples actually used will be the highest supported
+	/// value that doesn't exceed this. See `Window::msaa_samples`
+	///
+	/// The samples belong to the surface of the window, as OpenGL can't multisample only a part of
+	/// the default framebuffer. Draws that don't need antialiasing can set `multisampling` to
+	/// false in their `DrawParameters`, which shades them once per pixel like without MSAA.
+	#[builder(default = "1")]
+	pub msaa_samples: u8,
+
+	#[builder(default)]
+	pub renderer: Renderer,
+
+	/// Request a floating point framebuffer, which allows displaying colors brighter than the
+	/// SDR white on HDR monitors. If that's not available, a regular framebuffer is used.
+	/// See `Window::hdr_output`
+	#[builder(default)]
+	pub hdr: bool,
 }
 
 pub type EventHandler = dyn FnMut(&Window, &WindowEvent);
@@ -153,8 +240,14 @@ struct WindowData {
 	display: glium::Display<WindowSurface>,
 	window: winit::window::Window,
 
-	size_before_fullscreen: PhysicalSize<u32>,
+	/// The size and the position of the window before it became fullscreen, which are restored
+	/// when it leaves fullscreen
+	geometry_before_fullscreen: (PhysicalSize<u32>, Option<PhysicalPosition<i32>>),
 	fullscreen: bool,
+	msaa_samples: u8,
+	hdr_output: bool,
+	/// Replaces the scale factor reported by the window system
+	ui_scale_override: Option<f32>,
 	last_mouse_move_update_time: std::time::Instant,
 	unprocessed_move_event: Option<Event>,
 	last_event_invalidated: bool,
@@ -166,6 +259,17 @@ struct WindowData {
 	modifiers: ModifiersState,
 	root_widget: Rc<dyn Widget>,
 	bg_color: [f32; 4],
+	focus_color: [f32; 4],
+	/// Whether the window has the keyboard focus of the system
+	window_focused: bool,
+	/// How much the contents are darkened while the window isn't focused, between 0 and 1
+	unfocused_dim: f32,
+	/// The widget that has the keyboard focus. See `Widget::focus_order`
+	focused_widget: Option<Rc<dyn Widget>>,
+	/// The image of the custom cursor until the event loop turns it into a cursor, see
+	/// `set_custom_cursor_image`
+	pending_custom_cursor: Option<CustomCursorSource>,
+	custom_cursor: Option<CustomCursor>,
 
 	global_event_handlers: Vec<Box<EventHandler>>,
 
@@ -177,6 +281,12 @@ struct WindowData {
 	colored_program: Program,
 }
 
+impl WindowData {
+	fn scale_factor(&self) -> f32 {
+		self.ui_scale_override.unwrap_or_else(|| self.window.scale_factor() as f32)
+	}
+}
+
 pub struct Window {
 	data: RefCell<WindowData>,
 }
@@ -267,7 +377,27 @@ impl Window {
 		};
 
 		// let window = window.build(&application.event_loop).unwrap();
-		let (window, display) = Self::build_winit_window(window_attributes, event_loop);
+		let samples = desc.msaa_samples;
+		let hdr = desc.hdr;
+		let build = |attributes: WindowAttributes, hardware_accelerated: Option<bool>| {
+			if hdr {
+				let result = Self::build_winit_window(
+					attributes.clone(),
+					samples,
+					hardware_accelerated,
+					true,
+					event_loop,
+				);
+				match result {
+					Ok(result) => return Ok(result),
+					Err(err) => info!("{err}. HDR output is not available."),
+				}
+			}
+			Self::build_winit_window(attributes, samples, hardware_accelerated, false, event_loop)
+		};
+		let (window, display, msaa_samples, hdr_output) = desc
+			.renderer
+			.build(|hardware_accelerated| build(window_attributes.clone(), hardware_accelerated))?;
 
 		window.set_cursor(CursorIcon::Default);
 
@@ -328,8 +458,11 @@ impl Window {
 			data: RefCell::new(WindowData {
 				display,
 				window,
-				size_before_fullscreen: desc.size,
+				geometry_before_fullscreen: (desc.size, None),
 				fullscreen: false,
+				msaa_samples,
+				hdr_output,
+				ui_scale_override: None,
 				last_mouse_move_update_time: std::time::Instant::now(),
 				unprocessed_move_event: None,
 				last_event_invalidated: true,
@@ -339,6 +472,12 @@ impl Window {
 				render_validity: RenderValidity { validity: Rc::new(Cell::new(false)) },
 				root_widget: Rc::new(crate::line_layout_container::VerticalLayoutContainer::new()),
 				bg_color: [0.85, 0.85, 0.85, 1.0],
+				focus_color: [0.2, 0.45, 0.9, 1.0],
+				window_focused: true,
+				unfocused_dim: 0.0,
+				focused_widget: None,
+				pending_custom_cursor: None,
+				custom_cursor: None,
 
 				global_event_handlers: Vec::new(),
 
@@ -358,41 +497,64 @@ impl Window {
 
 	/// This is mostly copy-pasted from `glutin::SimpleWindowBuilder::build`
 	/// but I use some custom configuration settings here
+	///
+	/// Returns the number of MSAA samples of the selected config, and whether it has a floating
+	/// point framebuffer as the last two elements
 	fn build_winit_window(
 		attributes: WindowAttributes,
+		requested_samples: u8,
+		hardware_accelerated: Option<bool>,
+		float_pixels: bool,
 		event_loop: &ActiveEventLoop,
-	) -> (winit::window::Window, Display<WindowSurface>) {
+	) -> Result<(winit::window::Window, Display<WindowSurface>, u8, bool), WindowCreationError> {
+		use WindowCreationError::*;
+
 		// let is_maximized = builder.m
 		// First we start by opening a new Window
 		let display_builder =
 			glutin_winit::DisplayBuilder::new().with_window_attributes(Some(attributes));
 
 		let config_template_builder = glutin::config::ConfigTemplateBuilder::new()
-			.prefer_hardware_accelerated(Some(true))
+			.prefer_hardware_accelerated(hardware_accelerated)
+			.with_float_pixels(float_pixels)
 			.with_surface_type(ConfigSurfaceTypes::WINDOW)
 			.with_api(Api::OPENGL);
 		let (window, gl_config) = display_builder
 			.build(event_loop, config_template_builder, |mut configs| {
-				// Pick the first srgb capable config
+				// Pick the first srgb capable config, that has the most samples without exceeding
+				// the requested amount.
+				let score = |cfg: &glutin::config::Config| {
+					let samples = cfg.num_samples().max(1);
+					let samples = if samples <= requested_samples { samples } else { 0 };
+					(cfg.srgb_capable(), samples)
+				};
 				let mut target = configs.next().unwrap();
 				for cfg in configs {
-					if target.srgb_capable() {
-						break;
+					if score(&cfg) > score(&target) {
+						target = cfg;
 					}
-					target = cfg;
 				}
 				target
 			})
-			.unwrap();
-		let window = window.unwrap();
+			.map_err(|e| AdapterNotFound(e.to_string()))?;
+		let window = window.ok_or_else(|| SurfaceUnavailable("no window was created".into()))?;
+
+		let msaa_samples = gl_config.num_samples().max(1);
+		if msaa_samples < requested_samples {
+			warn!(
+				"{requested_samples} MSAA samples were requested but that's not supported. Using {msaa_samples} instead."
+			);
+		}
 
-		let raw_window_handle = window.window_handle().unwrap().as_raw();
+		let raw_window_handle =
+			window.window_handle().map_err(|e| SurfaceUnavailable(e.to_string()))?.as_raw();
 
 		// Now we get the window size to use as the initial size of the Surface
 		let (width, height): (u32, u32) = window.inner_size().into();
 		let attrs =
 			glutin::surface::SurfaceAttributesBuilder::<glutin::surface::WindowSurface>::new()
-				.with_srgb(Some(true))
+				// Floating point framebuffers store linear values that are displayed as is
+				.with_srgb(if float_pixels { None } else { Some(true) })
 				.build(
 					raw_window_handle,
 					NonZeroU32::new(width).unwrap(),
@@ -400,22 +562,18 @@ impl Window {
 				);
 
 		// Finally we can create a Surface, use it to make a PossiblyCurrentContext and create the glium Display
-		let surface =
-			unsafe { gl_config.display().create_window_surface(&gl_config, &attrs).unwrap() };
+		let surface = unsafe { gl_config.display().create_window_surface(&gl_config, &attrs) }
+			.map_err(|e| SurfaceUnavailable(e.to_string()))?;
 
 		let context_attributes = glutin::context::ContextAttributesBuilder::new()
 			.with_profile(GlProfile::Core) // requires OpenGL 3.3
 			.with_context_api(ContextApi::OpenGl(Some(Version::new(3, 3))))
 			.with_release_behavior(glutin::context::ReleaseBehavior::None)
 			.build(Some(raw_window_handle));
-		let current_context = unsafe {
-			gl_config
-				.display()
-				.create_context(&gl_config, &context_attributes)
-				.expect("failed to create context")
-		}
-		.make_current(&surface)
-		.unwrap();
+		let current_context =
+			unsafe { gl_config.display().create_context(&gl_config, &context_attributes) }
+				.and_then(|context| context.make_current(&surface))
+				.map_err(|e| ContextUnavailable(e.to_string()))?;
 
 		// Set up vsync
 		let swap_interval = glutin::surface::SwapInterval::Wait(NonZeroU32::new(1).unwrap());
@@ -423,9 +581,10 @@ impl Window {
 			error!("Failed to set vsync, error returned by set_swap_interval: {}", error);
 		}
 
-		let display = Display::from_context_surface(current_context, surface).unwrap();
+		let display = Display::from_context_surface(current_context, surface)
+			.map_err(|e| ContextUnavailable(e.to_string()))?;
 
-		(window, display)
+		Ok((window, display, msaa_samples, gl_config.float_pixels()))
 	}
 
 	pub fn add_global_event_handler<F: FnMut(&Window, &WindowEvent) + 'static>(&self, fun: F) {
@@ -445,6 +604,97 @@ impl Window {
 		borrowed.bg_color = color;
 	}
 
+	pub fn set_focus_color(&self, color: [f32; 4]) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.focus_color = color;
+		borrowed.render_validity.invalidate();
+	}
+
+	/// Darkens the whole window by `dim` while it isn't focused. 0 turns this off and 1 makes
+	/// the window black.
+	pub fn set_unfocused_dim(&self, dim: f32) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.unfocused_dim = dim.clamp(0.0, 1.0);
+		borrowed.render_validity.invalidate();
+	}
+
+	/// Gives the keyboard focus to `widget`, or takes it away from the focused widget
+	pub fn set_focused_widget(&self, widget: Option<Rc<dyn Widget>>) {
+		let prev = std::mem::replace(&mut self.data.borrow_mut().focused_widget, widget.clone());
+		if let Some(prev) = prev {
+			prev.set_focused(false);
+		}
+		if let Some(widget) = widget {
+			widget.set_focused(true);
+		}
+		self.data.borrow().render_validity.invalidate();
+	}
+
+	/// Moves the keyboard focus to the next or the previous visible widget that can have the
+	/// focus. Returns false if there is no such widget.
+	fn move_focus(&self, forward: bool) -> bool {
+		fn collect_focusable(widget: &Rc<dyn Widget>, focusable: &mut Vec<Rc<dyn Widget>>) {
+			if !widget.visible() {
+				return;
+			}
+			if widget.focus_order().is_some() {
+				focusable.push(widget.clone());
+			}
+			let mut children = Vec::new();
+			widget.children(&mut children);
+			for child in children.iter() {
+				collect_focusable(child, focusable);
+			}
+		}
+		let root_widget = self.data.borrow().root_widget.clone();
+		let mut focusable = Vec::new();
+		collect_focusable(&root_widget, &mut focusable);
+		if focusable.is_empty() {
+			return false;
+		}
+		// The sort is stable, so that widgets at the same position keep the order of the tree
+		focusable.sort_by_key(|widget| widget.focus_order());
+		let focused = self.data.borrow().focused_widget.as_ref().map(widget_data_ptr);
+		let current =
+			focused.and_then(|ptr| focusable.iter().position(|w| widget_data_ptr(w) == ptr));
+		let count = focusable.len();
+		let next = match (current, forward) {
+			(Some(current), true) => (current + 1) % count,
+			(Some(current), false) => (current + count - 1) % count,
+			(None, true) => 0,
+			(None, false) => count - 1,
+		};
+		self.set_focused_widget(Some(focusable[next].clone()));
+		true
+	}
+
+	/// Tab and Shift+Tab move the keyboard focus, Enter and Space activate the focused widget,
+	/// and Escape takes the focus away. Returns true if the key was used up by this.
+	fn handle_focus_key(&self, input: &KeyEvent, modifiers: ModifiersState) -> bool {
+		if input.state != ElementState::Pressed
+			|| modifiers.control_key()
+			|| modifiers.alt_key()
+			|| modifiers.super_key()
+		{
+			return false;
+		}
+		let focused = self.data.borrow().focused_widget.clone();
+		match (&input.logical_key, focused) {
+			(Key::Named(NamedKey::Tab), _) => self.move_focus(!modifiers.shift_key()),
+			(Key::Named(NamedKey::Enter | NamedKey::Space), Some(focused)) => {
+				if !input.repeat {
+					focused.activate();
+				}
+				true
+			}
+			(Key::Named(NamedKey::Escape), Some(_)) => {
+				self.set_focused_widget(None);
+				true
+			}
+			_ => false,
+		}
+	}
+
 	/// This is called when a NewEvents event is received in the application
 	pub fn handle_loop_wake_up(&self) -> NextUpdate {
 		let root_widget = self.data.borrow().root_widget.clone();
@@ -454,11 +704,15 @@ impl Window {
 	pub fn process_event<UserEvent>(
 		&self,
 		native_event: WindowEvent,
-		// allowing event_loop to be unused because it's only used on some platforms
-		#[allow(unused_variables)] event_loop: &ActiveEventLoop,
+		event_loop: &ActiveEventLoop,
 	) {
 		use winit::event::MouseScrollDelta;
 
+		let pending_cursor = self.data.borrow_mut().pending_custom_cursor.take();
+		if let Some(source) = pending_cursor {
+			self.data.borrow_mut().custom_cursor = Some(event_loop.create_custom_cursor(source));
+		}
+
 		let event;
 		{
 			let mut event_handlers = Vec::new();
@@ -477,11 +731,11 @@ impl Window {
 						return;
 					}
 
-					#[cfg(wayland_platform)]
-					if event_loop.is_wayland() {
-						// We need to set the framebuffer size explicitly on Wayland (god knows why)
-						borrowed.display.resize((size.width, size.height));
-					}
+					// Wayland needs the size of the framebuffer to be set explicitly, and other
+					// platforms only update it lazily, which leaves the next frame at the old
+					// size. Many of these events can arrive before that frame is drawn, the
+					// redraws they request are merged into one.
+					borrowed.display.resize((size.width, size.height));
 					borrowed.window.request_redraw();
 				}
 				WindowEvent::CloseRequested => {
@@ -501,7 +755,7 @@ impl Window {
 				WindowEvent::CursorMoved { position, .. } => {
 					let logical_pos;
 					{
-						let scaling = borrowed.window.scale_factor() as f32;
+						let scaling = borrowed.scale_factor();
 
 						logical_pos = LogicalVector::from_physical(position, scaling);
 						//logical_pos.vec.y = logical_dimensions.vec.y - logical_pos.vec.y;
@@ -564,6 +818,10 @@ impl Window {
 					});
 				}
 				WindowEvent::Focused(focused) => {
+					borrowed.window_focused = focused;
+					if borrowed.unfocused_dim > 0.0 {
+						borrowed.render_validity.invalidate();
+					}
 					event = Some(Event {
 						cursor_pos: borrowed.cursor_pos,
 						modifiers: borrowed.modifiers,
@@ -579,6 +837,18 @@ impl Window {
 		}
 
 		if let Some(event) = event {
+			match &event.kind {
+				EventKind::KeyInput { input } if self.handle_focus_key(input, event.modifiers) => {
+					return;
+				}
+				// Going back to using the mouse
+				EventKind::MouseButton { state: ElementState::Pressed, .. }
+					if self.data.borrow().focused_widget.is_some() =>
+				{
+					self.set_focused_widget(None);
+				}
+				_ => {}
+			}
 			let cloned = self.data.borrow().root_widget.clone();
 			cloned.handle_event(&event);
 			let mut borrowed = self.data.borrow_mut();
@@ -638,7 +908,7 @@ impl Window {
 			borrowed.last_event_invalidated = false;
 		}
 		// this way self.data is not borrowed while before draw is running.
-		let dpi_scaling = self.data.borrow().window.scale_factor();
+		let dpi_scaling = self.scale_factor();
 		let mut target = self.data.borrow().display.draw();
 
 		// Can't change the window during drawing phase. Deal with it.
@@ -647,7 +917,7 @@ impl Window {
 		let phys_dimensions = PhysicalSize::new(dimensions.0 as f32, dimensions.1 as f32);
 		let phys_width = phys_dimensions.width;
 		let phys_height = phys_dimensions.height;
-		let logical_dimensions = LogicalVector::from_physical(phys_dimensions, dpi_scaling as f32);
+		let logical_dimensions = LogicalVector::from_physical(phys_dimensions, dpi_scaling);
 
 		// Invoke the layout functions
 		let available_widget_space =
@@ -669,7 +939,7 @@ impl Window {
 
 		let draw_context = DrawContext {
 			display: &borrowed.display,
-			dpi_scale_factor: dpi_scaling as f32,
+			dpi_scale_factor: dpi_scaling,
 			unit_quad_vertices: &borrowed.unit_quad_vertices,
 			unit_quad_indices: &borrowed.unit_quad_indices,
 			textured_program: &borrowed.textured_program,
@@ -677,6 +947,7 @@ impl Window {
 			colored_program: &borrowed.colored_program,
 			viewport: &viewport,
 			projection_transform: &projection_transform,
+			focus_color: borrowed.focus_color,
 		};
 
 		// Clearing the framebuffer with fully black
@@ -689,6 +960,10 @@ impl Window {
 		// because self is being borrowed by through the draw_context anyways but it's fine.
 		let next_update = borrowed.root_widget.draw(&mut target, &draw_context).unwrap();
 
+		if !borrowed.window_focused && borrowed.unfocused_dim > 0.0 {
+			draw_context.clear_color(&mut target, [0.0, 0.0, 0.0, borrowed.unfocused_dim], None);
+		}
+
 		// After all widgets are drawn, let's set the alpha values of all the pixels to 1.
 		// This is required on Wayland because the Wayland compositor very kindly takes
 		// the alpha values into account and blends the framebuffer set by applications
@@ -704,26 +979,208 @@ impl Window {
 		self.data.borrow().fullscreen
 	}
 
+	/// The number of MSAA samples that the framebuffer of this window uses. This is fixed for the
+	/// lifetime of the window, so resizing or moving the window to a monitor with a different
+	/// scale factor doesn't change it.
+	pub fn msaa_samples(&self) -> u8 {
+		self.data.borrow().msaa_samples
+	}
+
+	/// True if the framebuffer of this window uses floating point pixels, in which case
+	/// values above 1.0 are displayed brighter than white on HDR monitors.
+	pub fn hdr_output(&self) -> bool {
+		self.data.borrow().hdr_output
+	}
+
 	pub fn set_fullscreen(&self, fullscreen: bool) {
-		let mut borrowed = self.data.borrow_mut();
-		borrowed.fullscreen = fullscreen;
-		let monitor = if fullscreen {
-			let curr_mon;
-			borrowed.size_before_fullscreen = {
-				curr_mon = borrowed.window.current_monitor();
-				borrowed.window.inner_size()
-			};
-			Some(Fullscreen::Borderless(curr_mon))
+		if fullscreen == self.fullscreen() {
+			return;
+		}
+		let monitor = self.data.borrow().window.current_monitor();
+		if fullscreen {
+			self.enter_fullscreen(monitor);
 		} else {
-			None
+			self.leave_fullscreen();
+		}
+	}
+
+	/// Moves the fullscreen window to the next monitor, or makes the window fullscreen on the
+	/// monitor after the one that it's on. Returns the name of the monitor, or `None` if the
+	/// monitors can't be listed.
+	///
+	/// The monitor is looked up every time, so this works even if the window was moved to
+	/// another monitor while it was fullscreen.
+	pub fn fullscreen_next_monitor(&self) -> Option<String> {
+		let next = {
+			let borrowed = self.data.borrow();
+			let monitors: Vec<_> = borrowed.window.available_monitors().collect();
+			let current = borrowed.window.current_monitor();
+			let index = monitors.iter().position(|monitor| Some(monitor) == current.as_ref());
+			let next = match index {
+				Some(index) if borrowed.fullscreen => (index + 1) % monitors.len(),
+				Some(index) => index,
+				None => 0,
+			};
+			monitors.into_iter().nth(next)?
 		};
-		borrowed.window.set_fullscreen(monitor);
+		let name = next.name().unwrap_or_else(|| "Unknown monitor".to_string());
+		self.enter_fullscreen(Some(next));
+		Some(name)
+	}
+
+	/// The name of the monitor that the window is on, or `None` if it's unknown
+	pub fn monitor_name(&self) -> Option<String> {
+		self.data.borrow().window.current_monitor()?.name()
+	}
+
+	/// Makes the window fullscreen on the monitor called `name`. Returns false and leaves the
+	/// window as it is if there's no such monitor.
+	pub fn set_fullscreen_on(&self, name: &str) -> bool {
+		let monitor = self
+			.data
+			.borrow()
+			.window
+			.available_monitors()
+			.find(|monitor| monitor.name().as_deref() == Some(name));
+		match monitor {
+			Some(monitor) => {
+				self.enter_fullscreen(Some(monitor));
+				true
+			}
+			None => false,
+		}
+	}
+
+	/// The number of monitors that the system lists
+	pub fn monitor_count(&self) -> usize {
+		self.data.borrow().window.available_monitors().count()
+	}
+
+	/// The position of the monitor that the window is on in the list of monitors, or `None` if
+	/// it can't be told
+	pub fn monitor_index(&self) -> Option<usize> {
+		let borrowed = self.data.borrow();
+		let current = borrowed.window.current_monitor()?;
+		borrowed.window.available_monitors().position(|monitor| monitor == current)
+	}
+
+	/// Makes the window fullscreen on the monitor at `index` of the list of monitors. Returns
+	/// the name of the monitor, or `None` if there's no such monitor.
+	pub fn fullscreen_on_monitor(&self, index: usize) -> Option<String> {
+		let monitor = self.data.borrow().window.available_monitors().nth(index)?;
+		let name = monitor.name().unwrap_or_else(|| "Unknown monitor".to_string());
+		self.enter_fullscreen(Some(monitor));
+		Some(name)
+	}
+
+	fn enter_fullscreen(&self, monitor: Option<winit::monitor::MonitorHandle>) {
+		let mut borrowed = self.data.borrow_mut();
+		if !borrowed.fullscreen {
+			let size = borrowed.window.inner_size();
+			let position = borrowed.window.outer_position().ok();
+			borrowed.geometry_before_fullscreen = (size, position);
+		}
+		borrowed.fullscreen = true;
+		borrowed.window.set_fullscreen(Some(Fullscreen::Borderless(monitor)));
+	}
+
+	fn leave_fullscreen(&self) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.fullscreen = false;
+		borrowed.window.set_fullscreen(None);
+		let (size, position) = borrowed.geometry_before_fullscreen;
+		let _ = borrowed.window.request_inner_size(size);
+		// The monitor that the window was on may have been disconnected in the meantime
+		if let Some(position) = position {
+			let in_bounds = borrowed
+				.window
+				.available_monitors()
+				.any(|monitor| is_in_bounds(monitor.position(), monitor.size(), position));
+			if in_bounds {
+				borrowed.window.set_outer_position(position);
+			}
+		}
 	}
 
 	pub fn set_maximized(&self, maximized: bool) {
 		self.data.borrow_mut().window.set_maximized(maximized);
 	}
 
+	/// The number of physical pixels per logical pixel. This is the scale factor reported by the
+	/// window system, unless it's overridden with `set_ui_scale_override`.
+	pub fn scale_factor(&self) -> f32 {
+		self.data.borrow().scale_factor()
+	}
+
+	pub fn ui_scale_override(&self) -> Option<f32> {
+		self.data.borrow().ui_scale_override
+	}
+
+	/// Lays out and draws the widgets with this scale factor instead of the one reported by the
+	/// window system. `None` goes back to the reported one.
+	pub fn set_ui_scale_override(&self, scale: Option<f32>) {
+		let mut borrowed = self.data.borrow_mut();
+		borrowed.ui_scale_override = scale;
+		borrowed.render_validity.invalidate();
+		borrowed.window.request_redraw();
+	}
+
+	pub fn set_cursor_icon(&self, icon: CursorIcon) {
+		self.data.borrow().window.set_cursor(icon);
+	}
+
+	/// Sets the image that `set_custom_cursor` shows. `hotspot` is the pixel of the image that
+	/// points at the cursor position. The cursor can only be made by the event loop, so it's
+	/// available from the next event on.
+	pub fn set_custom_cursor_image(&self, image: &RgbaImage, hotspot: (u32, u32)) {
+		let (w, h) = image.dimensions();
+		let (x, y) = (hotspot.0.min(w.saturating_sub(1)), hotspot.1.min(h.saturating_sub(1)));
+		if w > u16::MAX as u32 || h > u16::MAX as u32 {
+			warn!("The cursor image of {}x{} is too large", w, h);
+			return;
+		}
+		match CustomCursor::from_rgba(
+			image.as_raw().clone(),
+			w as u16,
+			h as u16,
+			x as u16,
+			y as u16,
+		) {
+			Ok(source) => self.data.borrow_mut().pending_custom_cursor = Some(source),
+			Err(e) => warn!("Could not make the cursor: {}", e),
+		}
+	}
+
+	/// Shows the cursor of `set_custom_cursor_image`. Returns false if there's none yet.
+	pub fn set_custom_cursor(&self) -> bool {
+		let borrowed = self.data.borrow();
+		match &borrowed.custom_cursor {
+			Some(cursor) => {
+				borrowed.window.set_cursor(cursor.clone());
+				true
+			}
+			None => false,
+		}
+	}
+
+	pub fn set_minimized(&self, minimized: bool) {
+		self.data.borrow().window.set_minimized(minimized);
+	}
+
+	/// Hiding the window keeps it alive, it can be shown again with `focus`
+	pub fn set_visible(&self, visible: bool) {
+		self.data.borrow().window.set_visible(visible);
+	}
+
+	/// Shows the window if it's hidden, restores it if it's minimized and asks the window
+	/// manager to bring it to the front and give it the input focus.
+	pub fn focus(&self) {
+		let borrowed = self.data.borrow();
+		borrowed.window.set_visible(true);
+		borrowed.window.set_minimized(false);
+		borrowed.window.focus_window();
+	}
+
 	/// Sets the alpha values by drawing a quad covering the entire framebuffer
 	/// with a blending mode set to max and a shader that draws (0,0,0,1) values
 	fn set_alpha_to_1(&self, target: &mut Frame, context: &DrawContext) {
@@ -752,3 +1209,38 @@ impl Window {
 			.unwrap();
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// The `prefer_hardware_accelerated` values that `renderer` tries, when the attempts fail
+	/// until `succeeding_attempt`
+	fn attempts(renderer: Renderer, succeeding_attempt: usize) -> (Vec<Option<bool>>, bool) {
+		let mut attempts = Vec::new();
+		let result = renderer.build(|hardware_accelerated| {
+			attempts.push(hardware_accelerated);
+			if attempts.len() == succeeding_attempt {
+				Ok(())
+			} else {
+				Err(WindowCreationError::ContextUnavailable("test".into()))
+			}
+		});
+		(attempts, result.is_ok())
+	}
+
+	#[test]
+	fn auto_falls_back_to_any_configuration_once() {
+		assert_eq!(attempts(Renderer::Auto, 1), (vec![Some(true)], true));
+		assert_eq!(attempts(Renderer::Auto, 2), (vec![Some(true), None], true));
+		assert_eq!(attempts(Renderer::Auto, 3), (vec![Some(true), None], false));
+	}
+
+	#[test]
+	fn hardware_and_software_are_not_retried() {
+		assert_eq!(attempts(Renderer::Hardware, 1), (vec![Some(true)], true));
+		assert_eq!(attempts(Renderer::Hardware, 2), (vec![Some(true)], false));
+		assert_eq!(attempts(Renderer::Software, 1), (vec![None], true));
+		assert_eq!(attempts(Renderer::Software, 2), (vec![None], false));
+	}
+}